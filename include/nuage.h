@@ -0,0 +1,41 @@
+/* C API of the nuage library (libnuage.so, built with `cargo build --release`). */
+#ifndef NUAGE_H
+#define NUAGE_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#define NUAGE_OK 0
+#define NUAGE_ERROR -1
+#define NUAGE_INVALID_ARGUMENT -2
+#define NUAGE_BUFFER_TOO_SMALL -3
+#define NUAGE_NOT_FOUND -4
+
+/* Write the unix timestamps of the frames the viewer would show now, most
+ * recent first, in `timestamps` (at most `capacity` of them) and return how
+ * many there are, or a negative error code. */
+int64_t nuage_timeline(int64_t *timestamps, size_t capacity);
+
+/* Fetch (or read from the cache) the most recent available frame and write
+ * its unix timestamp in `timestamp`. */
+int32_t nuage_fetch_latest(int64_t *timestamp);
+
+/* Write the frame at the unix `timestamp` in `buffer` as RGBA and its
+ * dimensions in `width` and `height`. With a null or too small buffer, only
+ * the dimensions are written and NUAGE_BUFFER_TOO_SMALL is returned. */
+int32_t nuage_render_frame_rgba(int64_t timestamp, uint8_t *buffer, size_t buffer_len,
+                                uint32_t *width, uint32_t *height);
+
+/* Write the freshest picture of the frames in `buffer` as RGBA, like
+ * nuage_render_frame_rgba: the latest whole frame with the tiles published
+ * since laid over it, older tiles framed and labeled with their time. The
+ * unix timestamps of the newest and oldest tiles are written in `newest` and
+ * `oldest`. */
+int32_t nuage_render_best_rgba(uint8_t *buffer, size_t buffer_len, uint32_t *width, uint32_t *height,
+                               int64_t *newest, int64_t *oldest);
+
+/* Copy the last error message of the calling thread, nul terminated, in
+ * `buffer`. Returns the length of the full message. */
+size_t nuage_last_error(char *buffer, size_t buffer_len);
+
+#endif /* NUAGE_H */
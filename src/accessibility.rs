@@ -0,0 +1,111 @@
+//! Announcements of the frame shown to screen readers, through AccessKit,
+//! rate-limited while the animation plays not to flood them.
+
+use chrono::{DateTime, Utc};
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+/// When the frame shown is announced.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub enum Announcements {
+    Off,
+    /// Every frame, as it is shown
+    EveryFrame,
+    /// Every `n`th frame while playing, with the number of frames since the
+    /// last one announced, and every frame when paused
+    Every(u32),
+    /// Only when the animation is paused or stepped through
+    OnPause,
+}
+
+impl Default for Announcements {
+    fn default() -> Self {
+        Announcements::Every(5)
+    }
+}
+
+impl Announcements {
+    /// The next setting, to cycle through them.
+    pub fn next(self) -> Self {
+        match self {
+            Announcements::Off => Announcements::EveryFrame,
+            Announcements::EveryFrame => Announcements::Every(5),
+            Announcements::Every(_) => Announcements::OnPause,
+            Announcements::OnPause => Announcements::Off,
+        }
+    }
+}
+
+/// The announcement of the frames shown.
+#[derive(Default)]
+pub struct Announcer {
+    // The frame last shown and the last one announced
+    shown: Option<DateTime<Utc>>,
+    announced: Option<DateTime<Utc>>,
+    // The frames shown since the last announcement
+    skipped: u32,
+    playing: bool,
+    message: String,
+}
+
+impl Announcer {
+    /// Follow the frame shown, at `timestamp`, `position` (number, count) in
+    /// the loop, and announce it if `announcements` say so.
+    pub fn update(
+        &mut self,
+        announcements: Announcements,
+        timestamp: DateTime<Utc>,
+        forecast: bool,
+        position: (usize, usize),
+        playing: bool,
+    ) {
+        let paused = self.playing && !playing;
+        self.playing = playing;
+        if self.shown != Some(timestamp) {
+            self.shown = Some(timestamp);
+            self.skipped += 1;
+        } else if !paused {
+            return;
+        }
+        let due = match announcements {
+            Announcements::Off => false,
+            Announcements::EveryFrame => true,
+            Announcements::Every(n) => !playing || self.skipped >= n.max(1),
+            Announcements::OnPause => !playing,
+        };
+        if !due {
+            return;
+        }
+        let mut message = format!("{}, frame {} of {}", timestamp.format("%H:%M UTC"), position.0, position.1);
+        if forecast {
+            message.push_str(", forecast");
+        }
+        if !playing {
+            message.push_str(", paused");
+        }
+        if let Some(announced) = self.announced
+            && self.skipped > 1
+        {
+            message.push_str(&format!(", {} frames since {}", self.skipped, announced.format("%H:%M")));
+        }
+        self.message = message;
+        self.announced = Some(timestamp);
+        self.skipped = 0;
+    }
+
+    /// The last announcement.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Expose the last announcement as a polite live region, read out by
+    /// screen readers when it changes. Does nothing when no screen reader is
+    /// active.
+    pub fn publish(&self, ctx: &egui::Context) {
+        ctx.accesskit_node_builder(egui::Id::new("frame_announcement"), |node| {
+            node.set_role(egui::accesskit::Role::Status);
+            node.set_live(egui::accesskit::Live::Polite);
+            node.set_label(self.message.clone());
+        });
+    }
+}
@@ -0,0 +1,351 @@
+//! Image analysis of the frames: bright cloud cells and their motion.
+
+use image::{GrayImage, Pixel, RgbImage};
+
+use crate::projection::Georeference;
+
+/// A connected region of bright pixels, likely a convective cell.
+#[derive(Clone, Debug)]
+pub struct Cell {
+    /// Barycenter, in pixels
+    pub centroid: (f32, f32),
+    /// Bounding box, in pixels, both corners included
+    pub min: (u32, u32),
+    pub max: (u32, u32),
+    /// Number of pixels
+    pub area: u32,
+    /// Mean luminance (0-255)
+    pub mean: f32,
+}
+
+/// The motion of a block of pixels between two frames.
+#[derive(Clone, Copy, Debug)]
+pub struct MotionVector {
+    /// Center of the block, in pixels
+    pub x: f32,
+    pub y: f32,
+    /// Displacement, in pixels per minute
+    pub dx: f32,
+    pub dy: f32,
+}
+
+pub fn luminance(image: &RgbImage) -> GrayImage {
+    image::imageops::grayscale(image)
+}
+
+/// The number of pixels of each luminance.
+pub fn luminance_histogram(image: &RgbImage) -> [u32; 256] {
+    let mut histogram = [0; 256];
+    for pixel in luminance(image).pixels() {
+        histogram[pixel.0[0] as usize] += 1;
+    }
+    histogram
+}
+
+/// The regions of at least `min_area` pixels brighter than `threshold`.
+pub fn detect_cells(image: &RgbImage, threshold: u8, min_area: u32) -> Vec<Cell> {
+    let gray = luminance(image);
+    let (width, height) = gray.dimensions();
+    let mut visited = vec![false; (width * height) as usize];
+    let mut cells = vec![];
+    let mut stack = vec![];
+    for start_y in 0..height {
+        for start_x in 0..width {
+            let start = (start_y * width + start_x) as usize;
+            if visited[start] || gray.get_pixel(start_x, start_y).0[0] < threshold {
+                continue;
+            }
+            // Flood fill the region
+            visited[start] = true;
+            stack.push((start_x, start_y));
+            let (mut sum_x, mut sum_y, mut sum) = (0u64, 0u64, 0u64);
+            let mut area = 0;
+            let (mut min, mut max) = ((start_x, start_y), (start_x, start_y));
+            while let Some((x, y)) = stack.pop() {
+                area += 1;
+                sum_x += x as u64;
+                sum_y += y as u64;
+                sum += gray.get_pixel(x, y).0[0] as u64;
+                min = (min.0.min(x), min.1.min(y));
+                max = (max.0.max(x), max.1.max(y));
+                let neighbours = [
+                    (x.wrapping_sub(1), y),
+                    (x + 1, y),
+                    (x, y.wrapping_sub(1)),
+                    (x, y + 1),
+                ];
+                for (nx, ny) in neighbours {
+                    if nx >= width || ny >= height {
+                        continue;
+                    }
+                    let index = (ny * width + nx) as usize;
+                    if !visited[index] && gray.get_pixel(nx, ny).0[0] >= threshold {
+                        visited[index] = true;
+                        stack.push((nx, ny));
+                    }
+                }
+            }
+            if area >= min_area {
+                cells.push(Cell {
+                    centroid: (sum_x as f32 / area as f32, sum_y as f32 / area as f32),
+                    min,
+                    max,
+                    area,
+                    mean: sum as f32 / area as f32,
+                });
+            }
+        }
+    }
+    cells
+}
+
+/// Downscale factor applied before block matching, for speed.
+const MOTION_SCALE: u32 = 4;
+/// Size of the matched blocks and search radius, in downscaled pixels.
+const MOTION_BLOCK: u32 = 8;
+const MOTION_SEARCH: i32 = 6;
+/// Blocks with less contrast than this (luminance standard deviation) are
+/// featureless and would give random vectors.
+const MOTION_MIN_CONTRAST: f32 = 6.0;
+
+/// Estimate the motion between two frames taken `minutes` apart by block
+/// matching (sum of absolute differences) on downscaled luminance.
+pub fn motion_vectors(previous: &RgbImage, current: &RgbImage, minutes: f32) -> Vec<MotionVector> {
+    let (width, height) = current.dimensions();
+    let (small_width, small_height) = ((width / MOTION_SCALE).max(1), (height / MOTION_SCALE).max(1));
+    let filter = image::imageops::FilterType::Triangle;
+    let previous = image::imageops::resize(&luminance(previous), small_width, small_height, filter);
+    let current = image::imageops::resize(&luminance(current), small_width, small_height, filter);
+
+    let block = MOTION_BLOCK as i32;
+    let mut vectors = vec![];
+    let mut block_y = MOTION_SEARCH;
+    while block_y + block + MOTION_SEARCH <= small_height as i32 {
+        let mut block_x = MOTION_SEARCH;
+        while block_x + block + MOTION_SEARCH <= small_width as i32 {
+            let pixel = |image: &GrayImage, x: i32, y: i32| image.get_pixel(x as u32, y as u32).0[0] as i32;
+            // Skip featureless blocks
+            let values: Vec<f32> = (0..block * block)
+                .map(|i| pixel(&current, block_x + i % block, block_y + i / block) as f32)
+                .collect();
+            let mean = values.iter().sum::<f32>() / values.len() as f32;
+            let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f32>() / values.len() as f32;
+            if variance.sqrt() >= MOTION_MIN_CONTRAST {
+                // Where was this block of the current frame in the previous one?
+                let mut best: (i32, i32, i32) = (i32::MAX, 0, 0);
+                for dy in -MOTION_SEARCH..=MOTION_SEARCH {
+                    for dx in -MOTION_SEARCH..=MOTION_SEARCH {
+                        let mut sad = 0;
+                        for y in 0..block {
+                            for x in 0..block {
+                                sad += (pixel(&current, block_x + x, block_y + y)
+                                    - pixel(&previous, block_x + x + dx, block_y + y + dy))
+                                    .abs();
+                            }
+                        }
+                        // Prefer the smallest displacement on ties
+                        if (sad, dx.abs() + dy.abs()) < (best.0, best.1.abs() + best.2.abs()) {
+                            best = (sad, dx, dy);
+                        }
+                    }
+                }
+                let scale = MOTION_SCALE as f32;
+                vectors.push(MotionVector {
+                    x: (block_x as f32 + block as f32 / 2.) * scale,
+                    y: (block_y as f32 + block as f32 / 2.) * scale,
+                    // The block moved from (x + dx, y + dy) to (x, y)
+                    dx: -best.1 as f32 * scale / minutes,
+                    dy: -best.2 as f32 * scale / minutes,
+                });
+            }
+            block_x += block;
+        }
+        block_y += block;
+    }
+    vectors
+}
+
+/// The median displacement of a set of motion vectors, robust to the odd
+/// mismatched block. (0, 0) without vectors.
+pub fn median_motion(vectors: &[MotionVector]) -> (f32, f32) {
+    if vectors.is_empty() {
+        return (0., 0.);
+    }
+    let median = |mut values: Vec<f32>| {
+        values.sort_by(|a, b| a.total_cmp(b));
+        values[values.len() / 2]
+    };
+    (
+        median(vectors.iter().map(|v| v.dx).collect()),
+        median(vectors.iter().map(|v| v.dy).collect()),
+    )
+}
+
+/// A dense motion field, in pixels per minute: the median of the vectors
+/// found in each block, the median of the scene where no block matched.
+pub struct MotionField {
+    /// Size of a block, in pixels
+    block: f32,
+    columns: usize,
+    rows: usize,
+    vectors: Vec<(f32, f32)>,
+}
+
+impl MotionField {
+    pub fn new(vectors: &[MotionVector], (width, height): (u32, u32)) -> Self {
+        let block = (MOTION_BLOCK * MOTION_SCALE) as f32;
+        let columns = (width as f32 / block).ceil().max(1.) as usize;
+        let rows = (height as f32 / block).ceil().max(1.) as usize;
+        let mut blocks: Vec<Vec<MotionVector>> = vec![vec![]; columns * rows];
+        for vector in vectors {
+            let column = ((vector.x / block) as usize).min(columns - 1);
+            let row = ((vector.y / block) as usize).min(rows - 1);
+            blocks[row * columns + column].push(*vector);
+        }
+        let scene = median_motion(vectors);
+        let vectors = blocks
+            .iter()
+            .map(|block| if block.is_empty() { scene } else { median_motion(block) })
+            .collect();
+        MotionField { block, columns, rows, vectors }
+    }
+
+    /// The motion from `previous` to `current`, taken `minutes` apart.
+    pub fn between(previous: &RgbImage, current: &RgbImage, minutes: f32) -> Self {
+        MotionField::new(&motion_vectors(previous, current, minutes), current.dimensions())
+    }
+
+    /// Size of a block, in pixels.
+    pub fn block(&self) -> f32 {
+        self.block
+    }
+
+    /// Number of blocks, horizontally and vertically.
+    pub fn dimensions(&self) -> (usize, usize) {
+        (self.columns, self.rows)
+    }
+
+    /// The motion of each block, row by row from the top-left one.
+    pub fn vectors(&self) -> &[(f32, f32)] {
+        &self.vectors
+    }
+
+    /// The motion at a pixel, interpolated between the blocks centers.
+    pub fn at(&self, x: f32, y: f32) -> (f32, f32) {
+        let fx = (x / self.block - 0.5).clamp(0., (self.columns - 1) as f32);
+        let fy = (y / self.block - 0.5).clamp(0., (self.rows - 1) as f32);
+        let (column, row) = (fx as usize, fy as usize);
+        let (next_column, next_row) = ((column + 1).min(self.columns - 1), (row + 1).min(self.rows - 1));
+        let (tx, ty) = (fx - column as f32, fy - row as f32);
+        let vector = |column: usize, row: usize| self.vectors[row * self.columns + column];
+        let lerp = |a: (f32, f32), b: (f32, f32), t: f32| (a.0 + (b.0 - a.0) * t, a.1 + (b.1 - a.1) * t);
+        lerp(
+            lerp(vector(column, row), vector(next_column, row), tx),
+            lerp(vector(column, next_row), vector(next_column, next_row), tx),
+            ty,
+        )
+    }
+}
+
+/// `current` moved `minutes` ahead along `field`: each pixel takes the value
+/// found upstream, the edges are extended.
+pub fn extrapolate(current: &RgbImage, field: &MotionField, minutes: f32) -> RgbImage {
+    let (width, height) = current.dimensions();
+    RgbImage::from_fn(width, height, |x, y| {
+        let (dx, dy) = field.at(x as f32, y as f32);
+        let source_x = (x as f32 - dx * minutes).round().clamp(0., (width - 1) as f32);
+        let source_y = (y as f32 - dy * minutes).round().clamp(0., (height - 1) as f32);
+        *current.get_pixel(source_x as u32, source_y as u32)
+    })
+}
+
+/// How far ahead the nowcast extrapolates, in minutes.
+pub const NOWCAST_MINUTES: [u32; 3] = [15, 30, 45];
+
+/// Cells and motion derived from the two most recent frames.
+pub struct Analysis {
+    pub cells: Vec<Cell>,
+    pub vectors: Vec<MotionVector>,
+    /// Median motion of the scene, in pixels per minute
+    pub motion: (f32, f32),
+}
+
+/// Fraction (0-1) of the pixels within `radius_km` of a (longitude,
+/// latitude) brighter than `threshold`. `None` if the area is outside of the
+/// frame.
+pub fn cover_around(
+    image: &RgbImage,
+    georeference: &Georeference,
+    (lon, lat): (f64, f64),
+    radius_km: f64,
+    threshold: u8,
+) -> Option<f32> {
+    // Kilometers per degree of latitude, and of longitude at `lat`
+    const KM_PER_DEGREE: f64 = 111.2;
+    let (cx, cy) = georeference.lonlat_to_pixel(lon, lat);
+    let (ex, _) = georeference.lonlat_to_pixel(lon + radius_km / (KM_PER_DEGREE * lat.to_radians().cos()), lat);
+    let (_, ny) = georeference.lonlat_to_pixel(lon, lat + radius_km / KM_PER_DEGREE);
+    let (rx, ry) = ((ex - cx).abs().max(1.), (ny - cy).abs().max(1.));
+    let (mut inside, mut bright) = (0u32, 0u32);
+    let x_range = (cx - rx).floor().max(0.) as u32..((cx + rx).ceil() as u32).min(image.width());
+    for y in (cy - ry).floor().max(0.) as u32..((cy + ry).ceil() as u32).min(image.height()) {
+        for x in x_range.clone() {
+            let (dx, dy) = ((x as f64 - cx) / rx, (y as f64 - cy) / ry);
+            if dx * dx + dy * dy > 1. {
+                continue;
+            }
+            inside += 1;
+            if image.get_pixel(x, y).to_luma().0[0] >= threshold {
+                bright += 1;
+            }
+        }
+    }
+    (inside > 0).then(|| bright as f32 / inside as f32)
+}
+
+/// Luminance from which a pixel is counted as cloud in the cloudiness of
+/// the markers.
+pub const CLOUD_THRESHOLD: u8 = 110;
+/// Radius around the markers their cloudiness is computed in, in kilometers.
+pub const CLOUDINESS_RADIUS_KM: f64 = 10.;
+
+/// Minutes until bright clouds cover at least `cover` of the area within
+/// `radius_km` of `home` (longitude, latitude), assuming `current` keeps
+/// moving with `motion` (pixels per minute). 0 if it already does, `None` if
+/// not within `horizon_minutes` or if home is outside of the frame.
+pub fn minutes_until_cover(
+    current: &RgbImage,
+    motion: (f32, f32),
+    georeference: &Georeference,
+    home: (f64, f64),
+    radius_km: f64,
+    cover: f32,
+    horizon_minutes: u32,
+) -> Option<u32> {
+    let (x, y) = georeference.lonlat_to_pixel(home.0, home.1);
+    // The clouds over home in t minutes are upstream today
+    (0..=horizon_minutes).step_by(5).find(|&minutes| {
+        let upstream = georeference.pixel_to_lonlat(
+            x - (motion.0 * minutes as f32) as f64,
+            y - (motion.1 * minutes as f32) as f64,
+        );
+        cover_around(current, georeference, upstream, radius_km, CELL_THRESHOLD).is_some_and(|c| c >= cover)
+    })
+}
+
+/// Luminance above which a pixel is considered part of a cell.
+pub const CELL_THRESHOLD: u8 = 200;
+/// Minimal area of a cell, in pixels.
+pub const CELL_MIN_AREA: u32 = 64;
+
+impl Analysis {
+    /// Analyze `current`, taken `minutes` after `previous`.
+    pub fn new(previous: &RgbImage, current: &RgbImage, minutes: f32) -> Self {
+        let vectors = motion_vectors(previous, current, minutes);
+        Analysis {
+            cells: detect_cells(current, CELL_THRESHOLD, CELL_MIN_AREA),
+            motion: median_motion(&vectors),
+            vectors,
+        }
+    }
+}
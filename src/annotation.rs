@@ -0,0 +1,219 @@
+//! Arrows, circles and text drawn by hand over the frames, e.g. to mark a
+//! developing cell before saving the frame to share it. They are placed by
+//! longitude and latitude, so that they stay on what they mark whatever the
+//! size of the window, and are saved with the session, or on their own as
+//! GeoJSON to be reopened later or sent to others.
+
+use chrono::{DateTime, Utc};
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::error::{NuageError, Result};
+use crate::geo::{self, Feature, Geometry, LonLat, Value};
+use crate::overlay::screen_position;
+use crate::projection::{Georeference, distance_km};
+use crate::provider::Tiles;
+use crate::render;
+
+/// The color of the annotations and the width of their lines, in points on
+/// screen and in pixels in the frames saved.
+pub const COLOR: egui::Color32 = egui::Color32::from_rgb(255, 64, 64);
+pub const WIDTH: f32 = 3.;
+
+/// The length of the head of the arrows, in points or pixels, and the angle
+/// of its sides with the shaft, in degrees.
+const HEAD_LENGTH: f32 = 14.;
+const HEAD_ANGLE: f32 = 25.;
+
+/// An annotation, by the (longitude, latitude) of its points.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub enum Annotation {
+    /// From its tail to its head
+    Arrow(LonLat, LonLat),
+    /// Its center and a point of the circle
+    Circle(LonLat, LonLat),
+    /// Its bottom-left corner and the text
+    Text(LonLat, String),
+}
+
+/// What dragging or clicking on the frames draws.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Tool {
+    Arrow,
+    Circle,
+    Text,
+}
+
+impl Tool {
+    /// The tool after this one, none after the last.
+    pub fn next(tool: Option<Tool>) -> Option<Tool> {
+        match tool {
+            None => Some(Tool::Arrow),
+            Some(Tool::Arrow) => Some(Tool::Circle),
+            Some(Tool::Circle) => Some(Tool::Text),
+            Some(Tool::Text) => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Tool::Arrow => "Arrows: drag from the tail to the head",
+            Tool::Circle => "Circles: drag from the center",
+            Tool::Text => "Text: click where it goes, Enter to add it",
+        }
+    }
+}
+
+/// The two sides of the head of an arrow from `from` to `to`.
+fn arrow_head((x1, y1): (f32, f32), (x2, y2): (f32, f32)) -> [(f32, f32); 2] {
+    let angle = (y1 - y2).atan2(x1 - x2);
+    [-HEAD_ANGLE, HEAD_ANGLE].map(|side| {
+        let side = angle + side.to_radians();
+        (x2 + HEAD_LENGTH * side.cos(), y2 + HEAD_LENGTH * side.sin())
+    })
+}
+
+impl Annotation {
+    /// Draw the annotation on the frame displayed in `image_rect`.
+    pub fn paint(&self, painter: &egui::Painter, georeference: &Georeference, image_rect: egui::Rect) {
+        let to_screen = |lonlat: LonLat| screen_position(georeference, image_rect, lonlat);
+        let stroke = egui::Stroke::new(WIDTH, COLOR);
+        let painter = painter.with_clip_rect(image_rect);
+        match self {
+            Annotation::Arrow(from, to) => {
+                let (from, to) = (to_screen(*from), to_screen(*to));
+                painter.line_segment([from, to], stroke);
+                for (x, y) in arrow_head((from.x, from.y), (to.x, to.y)) {
+                    painter.line_segment([egui::pos2(x, y), to], stroke);
+                }
+            }
+            Annotation::Circle(center, edge) => {
+                let (center, edge) = (to_screen(*center), to_screen(*edge));
+                painter.circle_stroke(center, center.distance(edge), stroke);
+            }
+            Annotation::Text(at, text) => {
+                let galley = painter.layout_no_wrap(text.clone(), egui::FontId::proportional(18.0), COLOR);
+                // Readable over bright clouds
+                let rect = egui::Align2::LEFT_BOTTOM.anchor_size(to_screen(*at), galley.size());
+                painter.rect_filled(rect.expand(3.), 3., egui::Color32::from_black_alpha(160));
+                painter.galley(rect.min, galley, COLOR);
+            }
+        }
+    }
+
+    /// Draw the annotation in the pixels of `canvas`, a frame of the window
+    /// of `georeference`. Text is written in white, with the label font.
+    pub fn burn(&self, canvas: &mut image::RgbImage, georeference: &Georeference) {
+        let to_pixel = |(lon, lat): LonLat| {
+            let (x, y) = georeference.lonlat_to_pixel(lon, lat);
+            (x as f32, y as f32)
+        };
+        let color = [COLOR.r(), COLOR.g(), COLOR.b()];
+        match self {
+            Annotation::Arrow(from, to) => {
+                let (from, to) = (to_pixel(*from), to_pixel(*to));
+                render::draw_line(canvas, from, to, WIDTH, color);
+                for side in arrow_head(from, to) {
+                    render::draw_line(canvas, side, to, WIDTH, color);
+                }
+            }
+            Annotation::Circle(center, edge) => {
+                let (center, edge) = (to_pixel(*center), to_pixel(*edge));
+                let radius = (edge.0 - center.0).hypot(edge.1 - center.1);
+                let steps = (radius.ceil() as u32).clamp(16, 720);
+                let point = |step: u32| {
+                    let angle = step as f32 / steps as f32 * std::f32::consts::TAU;
+                    (center.0 + radius * angle.cos(), center.1 + radius * angle.sin())
+                };
+                for step in 0..steps {
+                    render::draw_line(canvas, point(step), point(step + 1), WIDTH, color);
+                }
+            }
+            Annotation::Text(at, text) => render::draw_label(canvas, text, to_pixel(*at), 20.),
+        }
+    }
+}
+
+/// The annotations of a frame of a region, saved as a GeoJSON
+/// FeatureCollection: arrows as lines from the tail to the head, circles as
+/// their center with a `radius_km` property and text as its position with a
+/// `text` property. Each feature has the kind of annotation, the time of the
+/// frame and the tile window as properties too.
+#[derive(Clone, Debug)]
+pub struct AnnotationLayer {
+    pub timestamp: DateTime<Utc>,
+    pub region: (Tiles, u16),
+    pub annotations: Vec<Annotation>,
+}
+
+impl AnnotationLayer {
+    /// The annotations as GeoJSON features.
+    pub fn features(&self) -> Vec<Feature> {
+        let (((x1, y1), (x2, y2)), zoom) = self.region;
+        self.annotations
+            .iter()
+            .map(|annotation| {
+                let (feature, kind) = match annotation {
+                    Annotation::Arrow(from, to) => (Feature::new(Geometry::LineString(vec![*from, *to])), "arrow"),
+                    Annotation::Circle(center, edge) => (
+                        Feature::new(Geometry::Point(*center)).with("radius_km", Value::Number(distance_km(*center, *edge))),
+                        "circle",
+                    ),
+                    Annotation::Text(at, text) => (Feature::new(Geometry::Point(*at)).with("text", Value::Text(text.clone())), "text"),
+                };
+                feature
+                    .with("annotation", Value::Text(kind.into()))
+                    .with("time", Value::Text(self.timestamp.to_rfc3339()))
+                    .with("tiles", Value::Text(format!("{},{},{},{}", x1, y1, x2, y2)))
+                    .with("zoom", Value::Number(zoom as f64))
+            })
+            .collect()
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        geo::write_features(path, &self.features())
+    }
+
+    /// The annotations of a GeoJSON file, `None` if it has other features
+    /// than annotations, e.g. an overlay.
+    pub fn load(path: &Path) -> Result<Option<AnnotationLayer>> {
+        let features = geo::read_geojson(path)?;
+        let invalid = |message: &str| NuageError::Config(format!("{}: {}", path.display(), message));
+        let Some(first) = features.first().filter(|_| features.iter().all(|feature| feature.property("annotation").is_some())) else {
+            return Ok(None);
+        };
+        let timestamp = first
+            .property("time")
+            .and_then(|time| DateTime::parse_from_rfc3339(&time).ok())
+            .ok_or_else(|| invalid("annotations without a valid time"))?
+            .with_timezone(&Utc);
+        let tiles: Vec<u16> = first
+            .property("tiles")
+            .unwrap_or_default()
+            .split(',')
+            .filter_map(|index| index.trim().parse().ok())
+            .collect();
+        let zoom = first.property("zoom").and_then(|zoom| zoom.parse().ok());
+        let region = match (&tiles[..], zoom) {
+            (&[x1, y1, x2, y2], Some(zoom)) => (((x1, y1), (x2, y2)), zoom),
+            _ => return Err(invalid("annotations without a valid tile window")),
+        };
+        let mut annotations = vec![];
+        for feature in &features {
+            let annotation = match (feature.property("annotation").as_deref(), &feature.geometry) {
+                (Some("arrow"), Geometry::LineString(points)) if points.len() == 2 => Annotation::Arrow(points[0], points[1]),
+                (Some("circle"), Geometry::Point(center)) => {
+                    let radius_km: f64 = feature.property("radius_km").and_then(|radius| radius.parse().ok()).unwrap_or(0.);
+                    // The edge due east, along the parallel
+                    let degrees = (radius_km / distance_km(*center, (center.0 + 1., center.1))).min(180.);
+                    Annotation::Circle(*center, (center.0 + degrees, center.1))
+                }
+                (Some("text"), Geometry::Point(at)) => Annotation::Text(*at, feature.property("text").unwrap_or_default()),
+                (kind, _) => return Err(invalid(&format!("invalid {} annotation", kind.unwrap_or_default()))),
+            };
+            annotations.push(annotation);
+        }
+        Ok(Some(AnnotationLayer { timestamp, region, annotations }))
+    }
+}
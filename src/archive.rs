@@ -0,0 +1,115 @@
+//! Queries over the frames kept in the cache, for analyses over months of
+//! imagery outside of nuage.
+
+use chrono::{DateTime, Utc};
+use std::fmt::Write;
+use std::path::PathBuf;
+
+use crate::cache::{self, Cache};
+use crate::error::Result;
+use crate::geo::json_string;
+use crate::projection::{self, Bounds};
+use crate::provider::{Channel, Tiles};
+
+/// A cached frame and what its name tells about it.
+#[derive(Clone, Debug)]
+pub struct ArchivedFrame {
+    pub name: String,
+    pub timestamp: DateTime<Utc>,
+    pub zoom: u16,
+    pub tiles: Tiles,
+    pub channel: Channel,
+    /// The area covered by the frame
+    pub bounds: Bounds,
+    /// The file holding the frame and its size in bytes
+    pub path: PathBuf,
+    pub bytes: u64,
+}
+
+/// Which frames to return, all of them by default. Bounds select the frames
+/// covering part of them, the time range is inclusive.
+#[derive(Clone, Debug, Default)]
+pub struct Query {
+    pub bbox: Option<Bounds>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub channel: Option<Channel>,
+    /// Channels left out of the archive, see [`crate::cache::CachePolicy::archive`]
+    pub unarchived: Vec<Channel>,
+}
+
+impl Query {
+    fn matches(&self, frame: &ArchivedFrame) -> bool {
+        self.bbox.is_none_or(|bbox| bbox.intersects(&frame.bounds))
+            && self.from.is_none_or(|from| frame.timestamp >= from)
+            && self.to.is_none_or(|to| frame.timestamp <= to)
+            && self.channel.is_none_or(|channel| frame.channel == channel)
+            && !self.unarchived.contains(&frame.channel)
+    }
+}
+
+/// The cached frames matching `query`, from the oldest to the most recent.
+pub fn query(cache: &Cache, query: &Query) -> Result<Vec<ArchivedFrame>> {
+    let mut frames = vec![];
+    for name in cache.frame_names()? {
+        let Some((timestamp, zoom, tiles, channel)) = cache::parse_frame_name(&name) else {
+            continue;
+        };
+        let Some(path) = cache.locate(&name)? else {
+            continue;
+        };
+        let frame = ArchivedFrame {
+            bounds: projection::tiles_bounds(tiles, zoom),
+            bytes: std::fs::metadata(&path).map_or(0, |metadata| metadata.len()),
+            name,
+            timestamp,
+            zoom,
+            tiles,
+            channel,
+            path,
+        };
+        if query.matches(&frame) {
+            frames.push(frame);
+        }
+    }
+    frames.sort_by(|a, b| a.timestamp.cmp(&b.timestamp).then_with(|| a.name.cmp(&b.name)));
+    Ok(frames)
+}
+
+/// Serialize frames as a JSON document: `{"frames": [...]}`, each with its
+/// name, its time (RFC 3339), zoom, tiles `[x1, y1, x2, y2]`, channel, bounds
+/// `[west, south, east, north]`, path and size.
+pub fn to_json(frames: &[ArchivedFrame]) -> String {
+    let mut json = String::from("{\"frames\":[");
+    for (index, frame) in frames.iter().enumerate() {
+        if index > 0 {
+            json.push(',');
+        }
+        let ((x1, y1), (x2, y2)) = frame.tiles;
+        let Bounds { west, south, east, north } = frame.bounds;
+        write!(
+            json,
+            concat!(
+                "{{\"name\":{},\"time\":{},\"zoom\":{},\"tiles\":[{},{},{},{}],\"channel\":{},",
+                "\"bounds\":[{},{},{},{}],\"path\":{},\"bytes\":{}}}",
+            ),
+            json_string(&frame.name),
+            json_string(&frame.timestamp.to_rfc3339_opts(chrono::SecondsFormat::Secs, true)),
+            frame.zoom,
+            x1,
+            y1,
+            x2,
+            y2,
+            json_string(frame.channel.name()),
+            west,
+            south,
+            east,
+            north,
+            json_string(&frame.path.to_string_lossy()),
+            frame.bytes,
+        )
+        .unwrap();
+    }
+    json.push_str("]}");
+    json
+}
@@ -0,0 +1,85 @@
+//! A map drawn under the frames, OpenStreetMap by default, for the geography
+//! to show through thin cloud: the tiles of the window are stitched once and
+//! kept in the cache, as the tile usage policy of OpenStreetMap asks.
+
+use chrono::Utc;
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+
+use crate::cache::{Cache, sha256_hex};
+use crate::error::{NuageError, Result};
+use crate::provider::{Provider, Tiles};
+use crate::template::{TileServer, TileServerConfig};
+
+/// The basemap and how the frames are drawn over it.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct BasemapConfig {
+    /// The URL of a tile, with `{z}`, `{x}` and `{y}` as in
+    /// [`TileServerConfig`]
+    pub url: String,
+    /// The opacity of the frames over the map, from 0 to 1
+    pub opacity: f32,
+    /// Show the map at startup
+    pub visible: bool,
+}
+
+impl Default for BasemapConfig {
+    fn default() -> Self {
+        BasemapConfig { url: "https://tile.openstreetmap.org/{z}/{x}/{y}.png".into(), opacity: 0.7, visible: false }
+    }
+}
+
+/// The map of the tile window `tiles` at `zoom`, from the cache if it was
+/// stitched before. Tiles missing are drawn blank. Blocking.
+pub fn fetch(url: &str, (tiles, zoom): (Tiles, u16)) -> Result<image::RgbImage> {
+    let ((x1, y1), (x2, y2)) = tiles;
+    let name = format!("{}_{}_{}_{}_{}_{}.png", &sha256_hex(url.as_bytes())[..12], zoom, x1, y1, x2, y2);
+    let folder = Cache::default_location()?.folder().join("basemaps");
+    let path = folder.join(&name);
+    if let Ok(image) = image::open(&path) {
+        return Ok(image.to_rgb8());
+    }
+    let config = TileServerConfig { name: "basemap".into(), url: url.to_string(), tiles, zoom, missing_tiles: 1., ..Default::default() };
+    let image = TileServer::new(config).frame(Utc::now())?;
+    std::fs::create_dir_all(&folder).map_err(NuageError::cache_io(&folder))?;
+    image.save(&path)?;
+    Ok(image)
+}
+
+/// The map of a region, downloaded in a separate thread.
+#[derive(Clone)]
+pub struct BasemapLayer {
+    pub config: BasemapConfig,
+    pub region: (Tiles, u16),
+    pub image: Arc<Mutex<Option<image::RgbImage>>>,
+    pub last_error: Arc<Mutex<Option<String>>>,
+}
+
+impl BasemapLayer {
+    /// Start downloading the map of the tile window `region`. `ctx`, if any,
+    /// is asked to repaint when it arrives.
+    pub fn start(config: BasemapConfig, region: (Tiles, u16), ctx: Option<egui::Context>) -> Self {
+        let layer = BasemapLayer {
+            config,
+            region,
+            image: Arc::new(Mutex::new(None)),
+            last_error: Arc::new(Mutex::new(None)),
+        };
+        let downloader = layer.clone();
+        std::thread::spawn(move || {
+            match fetch(&downloader.config.url, downloader.region) {
+                Ok(image) => *downloader.image.lock().unwrap() = Some(image),
+                Err(e) => {
+                    log::warn!("basemap: {}", e);
+                    *downloader.last_error.lock().unwrap() = Some(e.to_string());
+                }
+            }
+            if let Some(ctx) = &ctx {
+                ctx.request_repaint();
+            }
+        });
+        layer
+    }
+}
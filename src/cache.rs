@@ -0,0 +1,560 @@
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::error::{NuageError, Result};
+use crate::provider::{Channel, Tiles};
+
+/// The index of a content-addressed cache, in its folder: a line per frame,
+/// its name and the name of its blob. Later lines win.
+const INDEX: &str = "index.txt";
+/// The folder of the blobs of a content-addressed cache.
+const BLOBS: &str = "blobs";
+
+/// The checksums of the frames as downloaded, in the cache folder: a line per
+/// frame, its name and the SHA-256 of its original bytes. Later lines win.
+const CHECKSUMS: &str = "checksums.txt";
+
+/// Where and when the frames were downloaded, in the cache folder: a line
+/// per frame, its name, the Unix time of the download, its size as
+/// downloaded and its URL. Later lines win.
+const METADATA: &str = "metadata.txt";
+
+/// The frames the provider answered were missing, in the cache folder: a
+/// line per frame, its name and the Unix time of the answer.
+const MISSING: &str = "missing.txt";
+
+/// How long a frame answered missing is not asked for again: long enough
+/// for restarts and refreshes not to ask again, short enough for a frame
+/// published late to show up.
+pub const MISSING_FOR: chrono::Duration = chrono::Duration::minutes(30);
+
+/// FNV-1a, stable across builds unlike the standard hasher.
+fn content_hash(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| (hash ^ *byte as u64).wrapping_mul(0x100000001b3))
+}
+
+/// The SHA-256 of `bytes`, in lowercase hexadecimal.
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes).iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// How the frames of a channel are kept in the cache, for ever by default.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct CachePolicy {
+    /// Hours a frame is kept after it was taken, for ever if `None`
+    pub ttl_hours: Option<u32>,
+    /// The most frames kept, the oldest removed first, no limit if `None`
+    pub max_entries: Option<usize>,
+    /// Whether the frames are part of the archive: listed by `nuage archive
+    /// query` and played from the archive window
+    pub archive: bool,
+}
+
+impl Default for CachePolicy {
+    fn default() -> Self {
+        CachePolicy {
+            ttl_hours: None,
+            max_entries: None,
+            archive: true,
+        }
+    }
+}
+
+/// What pruning a cache removed.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PruneStats {
+    pub frames: usize,
+    /// Freed on disk, blobs still used by other frames excluded
+    pub bytes: u64,
+}
+
+/// What deduplicating a cache saved.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DedupStats {
+    pub frames: usize,
+    /// Distinct frames stored
+    pub blobs: usize,
+    pub bytes_before: u64,
+    pub bytes_after: u64,
+}
+
+/// Where and when a frame was downloaded.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FrameMetadata {
+    pub url: String,
+    pub fetched: DateTime<Utc>,
+    /// The width and height of the frame as downloaded, before it is fit to
+    /// the screen
+    pub size: (u32, u32),
+}
+
+/// The on-disk store of downloaded frames.
+///
+/// Frames are files named after their time and tile window, or, once the
+/// cache is converted with [`Cache::deduplicate`], blobs named after their
+/// content so that identical frames are stored once, found through an index.
+#[derive(Clone, Debug)]
+pub struct Cache {
+    folder: PathBuf,
+}
+
+impl Cache {
+    /// A cache storing its frames in `folder`, created on first write.
+    pub fn new(folder: impl Into<PathBuf>) -> Self {
+        Cache { folder: folder.into() }
+    }
+
+    /// The standard cache, in `$XDG_CACHE_HOME/nuage` (defaulting to
+    /// `~/.cache/nuage`).
+    pub fn default_location() -> Result<Self> {
+        let standard_cache_folder = match std::env::var("XDG_CACHE_HOME") {
+            Ok(folder) => folder,
+            Err(_) => {
+                let username = std::env::var("USER")
+                    .map_err(|_| NuageError::Config("neither XDG_CACHE_HOME nor USER are set".into()))?;
+                format!("/home/{}/.cache", username)
+            }
+        };
+        Ok(Cache::new(PathBuf::from(standard_cache_folder).join("nuage")))
+    }
+
+    pub fn folder(&self) -> &Path {
+        &self.folder
+    }
+
+    /// Path of the cached frame for a timestamp, a tile window and a
+    /// channel. The channel is not named for the composite, cached before
+    /// there were channels.
+    pub fn frame_path(&self, timestamp: DateTime<Utc>, zoom: u16, tiles: Tiles, channel: Channel) -> PathBuf {
+        let suffix = match channel {
+            Channel::Composite => String::new(),
+            channel => format!("_{}", channel.name()),
+        };
+        self.folder.join(format!(
+            "{}_{}_{}_{}_{}_{}{}.jpg",
+            timestamp.format("%Y%m%d%H%M"),
+            zoom,
+            tiles.0.0,
+            tiles.0.1,
+            tiles.1.0,
+            tiles.1.1,
+            suffix,
+        ))
+    }
+
+    /// The frames of a content-addressed cache, by name, `None` for a
+    /// cache of plain files.
+    fn index(&self) -> Result<Option<HashMap<String, String>>> {
+        let path = self.folder.join(INDEX);
+        if !std::fs::exists(&path).map_err(NuageError::cache_io(&path))? {
+            return Ok(None);
+        }
+        let text = std::fs::read_to_string(&path).map_err(NuageError::cache_io(&path))?;
+        let index = text
+            .lines()
+            .filter_map(|line| line.split_once(' '))
+            .map(|(name, blob)| (name.to_string(), blob.to_string()))
+            .collect();
+        Ok(Some(index))
+    }
+
+    /// Where the file of the frame `name` is stored, if cached.
+    pub fn locate(&self, name: &str) -> Result<Option<PathBuf>> {
+        match self.index()? {
+            Some(index) => Ok(index.get(name).map(|blob| self.folder.join(BLOBS).join(blob))),
+            None => {
+                let path = self.folder.join(name);
+                let exists = std::fs::exists(&path).map_err(NuageError::cache_io(&path))?;
+                Ok(exists.then_some(path))
+            }
+        }
+    }
+
+    /// The file names of the cached frames, empty if the cache was never
+    /// written.
+    pub fn frame_names(&self) -> Result<Vec<String>> {
+        if !std::fs::exists(&self.folder).map_err(NuageError::cache_io(&self.folder))? {
+            return Ok(vec![]);
+        }
+        let mut names = vec![];
+        match self.index()? {
+            Some(index) => names.extend(index.into_keys()),
+            None => {
+                for entry in std::fs::read_dir(&self.folder).map_err(NuageError::cache_io(&self.folder))? {
+                    let name = entry.map_err(NuageError::cache_io(&self.folder))?.file_name().to_string_lossy().into_owned();
+                    if is_frame_name(&name) {
+                        names.push(name);
+                    }
+                }
+            }
+        }
+        names.sort();
+        Ok(names)
+    }
+
+    pub fn contains(&self, path: &Path) -> Result<bool> {
+        Ok(self.locate(&file_name(path))?.is_some())
+    }
+
+    /// Read a cached frame.
+    pub fn read(&self, path: &Path) -> Result<image::RgbImage> {
+        log::info!("reading {}", path.display());
+        let stored = self.locate(&file_name(path))?.unwrap_or(path.to_path_buf());
+        let img = image::ImageReader::open(&stored)
+            .map_err(NuageError::cache_io(&stored))?
+            .with_guessed_format()
+            .map_err(NuageError::cache_io(&stored))?
+            .decode()?;
+        Ok(img.to_rgb8())
+    }
+
+    /// The file of the cached frame `name`, as stored.
+    pub fn read_file(&self, name: &str) -> Result<Vec<u8>> {
+        let path = self.locate(name)?.unwrap_or(self.folder.join(name));
+        std::fs::read(&path).map_err(NuageError::cache_io(&path))
+    }
+
+    /// Store the file of a frame as received from another cache, under its
+    /// name. Written aside first so an interrupted copy is not taken for a
+    /// frame.
+    pub fn write_file(&self, name: &str, bytes: &[u8]) -> Result<()> {
+        std::fs::create_dir_all(&self.folder).map_err(NuageError::cache_io(&self.folder))?;
+        if self.index()?.is_some() {
+            return self.store_blob(name, bytes);
+        }
+        write_atomically(&self.folder.join(name), bytes)
+    }
+
+    /// Store a frame, the format is deduced from the extension of `path`.
+    pub fn write(&self, path: &Path, img: &image::DynamicImage) -> Result<()> {
+        std::fs::create_dir_all(&self.folder).map_err(NuageError::cache_io(&self.folder))?;
+        if self.index()?.is_some() {
+            let mut bytes = std::io::Cursor::new(vec![]);
+            img.write_to(&mut bytes, image::ImageFormat::from_path(path)?)?;
+            return self.store_blob(&file_name(path), &bytes.into_inner());
+        }
+        img.save(path)?;
+        Ok(())
+    }
+
+    /// Store `bytes` in the blob named after their content, unless already
+    /// there, and index it as the frame `name`. Blobs of the same hash but
+    /// different content get a suffix.
+    fn store_blob(&self, name: &str, bytes: &[u8]) -> Result<()> {
+        let blobs = self.folder.join(BLOBS);
+        std::fs::create_dir_all(&blobs).map_err(NuageError::cache_io(&blobs))?;
+        let extension = Path::new(name).extension().and_then(|extension| extension.to_str()).unwrap_or("jpg");
+        let hash = content_hash(bytes);
+        let mut suffix = 0;
+        let blob = loop {
+            let blob = match suffix {
+                0 => format!("{:016x}.{}", hash, extension),
+                _ => format!("{:016x}-{}.{}", hash, suffix, extension),
+            };
+            let path = blobs.join(&blob);
+            match std::fs::read(&path) {
+                Ok(stored) if stored == bytes => break blob,
+                Ok(_) => suffix += 1,
+                Err(_) => {
+                    write_atomically(&path, bytes)?;
+                    break blob;
+                }
+            }
+        };
+        let index = self.folder.join(INDEX);
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&index)
+            .map_err(NuageError::cache_io(&index))?;
+        writeln!(file, "{} {}", name, blob).map_err(NuageError::cache_io(&index))
+    }
+
+    /// Record the SHA-256 of the bytes of the frame `name` as downloaded,
+    /// before it is resized and stored.
+    pub fn record_checksum(&self, name: &str, original: &[u8]) -> Result<()> {
+        self.record_checksums(&[(name.to_string(), sha256_hex(original))])
+    }
+
+    /// Record checksums computed elsewhere, by frame name.
+    pub fn record_checksums(&self, checksums: &[(String, String)]) -> Result<()> {
+        std::fs::create_dir_all(&self.folder).map_err(NuageError::cache_io(&self.folder))?;
+        let path = self.folder.join(CHECKSUMS);
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .map_err(NuageError::cache_io(&path))?;
+        for (name, checksum) in checksums {
+            writeln!(file, "{} {}", name, checksum).map_err(NuageError::cache_io(&path))?;
+        }
+        Ok(())
+    }
+
+    /// The SHA-256 of the frames as downloaded, by name, for the frames
+    /// downloaded since checksums are recorded.
+    pub fn checksums(&self) -> Result<HashMap<String, String>> {
+        let path = self.folder.join(CHECKSUMS);
+        if !std::fs::exists(&path).map_err(NuageError::cache_io(&path))? {
+            return Ok(HashMap::new());
+        }
+        let text = std::fs::read_to_string(&path).map_err(NuageError::cache_io(&path))?;
+        Ok(text
+            .lines()
+            .filter_map(|line| line.split_once(' '))
+            .map(|(name, checksum)| (name.to_string(), checksum.to_string()))
+            .collect())
+    }
+
+    /// Record where and when the frame `name` was downloaded.
+    pub fn record_metadata(&self, name: &str, metadata: &FrameMetadata) -> Result<()> {
+        std::fs::create_dir_all(&self.folder).map_err(NuageError::cache_io(&self.folder))?;
+        let path = self.folder.join(METADATA);
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .map_err(NuageError::cache_io(&path))?;
+        let (width, height) = metadata.size;
+        writeln!(file, "{} {} {}x{} {}", name, metadata.fetched.timestamp(), width, height, metadata.url)
+            .map_err(NuageError::cache_io(&path))
+    }
+
+    /// Where and when the frames were downloaded, by name, for the frames
+    /// downloaded since it is recorded.
+    pub fn metadata(&self) -> Result<HashMap<String, FrameMetadata>> {
+        let path = self.folder.join(METADATA);
+        if !std::fs::exists(&path).map_err(NuageError::cache_io(&path))? {
+            return Ok(HashMap::new());
+        }
+        let text = std::fs::read_to_string(&path).map_err(NuageError::cache_io(&path))?;
+        Ok(text
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.splitn(4, ' ');
+                let (name, fetched, size, url) = (fields.next()?, fields.next()?, fields.next()?, fields.next()?);
+                let (width, height) = size.split_once('x')?;
+                let metadata = FrameMetadata {
+                    url: url.to_string(),
+                    fetched: DateTime::from_timestamp(fetched.parse().ok()?, 0)?,
+                    size: (width.parse().ok()?, height.parse().ok()?),
+                };
+                Some((name.to_string(), metadata))
+            })
+            .collect())
+    }
+
+    /// The frames answered missing less than `MISSING_FOR` before `now`,
+    /// and when.
+    fn missing(&self, now: DateTime<Utc>) -> Result<Vec<(String, DateTime<Utc>)>> {
+        let path = self.folder.join(MISSING);
+        if !std::fs::exists(&path).map_err(NuageError::cache_io(&path))? {
+            return Ok(vec![]);
+        }
+        let text = std::fs::read_to_string(&path).map_err(NuageError::cache_io(&path))?;
+        Ok(text
+            .lines()
+            .filter_map(|line| line.split_once(' '))
+            .filter_map(|(name, time)| Some((name.to_string(), DateTime::from_timestamp(time.parse().ok()?, 0)?)))
+            .filter(|(_, time)| now - *time < MISSING_FOR)
+            .collect())
+    }
+
+    /// Record that the provider answered the frame `name` was missing, at
+    /// `now`, forgetting the answers that are too old.
+    pub fn record_missing(&self, name: &str, now: DateTime<Utc>) -> Result<()> {
+        let mut missing = self.missing(now)?;
+        missing.retain(|(missing_name, _)| missing_name != name);
+        missing.push((name.to_string(), now));
+        let text: String = missing.iter().map(|(name, time)| format!("{} {}\n", name, time.timestamp())).collect();
+        std::fs::create_dir_all(&self.folder).map_err(NuageError::cache_io(&self.folder))?;
+        write_atomically(&self.folder.join(MISSING), text.as_bytes())
+    }
+
+    /// Whether the provider answered the frame `name` was missing less than
+    /// `MISSING_FOR` before `now`.
+    pub fn is_missing(&self, name: &str, now: DateTime<Utc>) -> Result<bool> {
+        Ok(self.missing(now)?.iter().any(|(missing_name, _)| missing_name == name))
+    }
+
+    /// Remove the frames that `policies` no longer keep at `now`: those
+    /// taken more than `ttl_hours` before, and the oldest beyond
+    /// `max_entries` of each channel. Channels without a policy are kept.
+    pub fn prune(&self, policies: &HashMap<Channel, CachePolicy>, now: DateTime<Utc>) -> Result<PruneStats> {
+        let mut by_channel: HashMap<Channel, Vec<(DateTime<Utc>, String)>> = HashMap::new();
+        for name in self.frame_names()? {
+            if let Some((timestamp, _, _, channel)) = parse_frame_name(&name) {
+                by_channel.entry(channel).or_default().push((timestamp, name));
+            }
+        }
+        let mut removed: Vec<String> = vec![];
+        for (channel, mut frames) in by_channel {
+            let Some(policy) = policies.get(&channel) else {
+                continue;
+            };
+            // The most recent first
+            frames.sort_by(|a, b| b.cmp(a));
+            for (index, (timestamp, name)) in frames.into_iter().enumerate() {
+                let expired = policy.ttl_hours.is_some_and(|hours| now - timestamp > chrono::Duration::hours(hours as i64));
+                if expired || policy.max_entries.is_some_and(|max_entries| index >= max_entries) {
+                    removed.push(name);
+                }
+            }
+        }
+        let mut stats = PruneStats { frames: removed.len(), bytes: 0 };
+        if removed.is_empty() {
+            return Ok(stats);
+        }
+        let Some(mut index) = self.index()? else {
+            for name in removed {
+                let path = self.folder.join(name);
+                stats.bytes += std::fs::metadata(&path).map_or(0, |metadata| metadata.len());
+                std::fs::remove_file(&path).map_err(NuageError::cache_io(&path))?;
+            }
+            return Ok(stats);
+        };
+        // The index is written again without the frames, then the blobs no
+        // frame uses any more are removed
+        let blobs: Vec<String> = removed.iter().filter_map(|name| index.remove(name)).collect();
+        let mut names: Vec<&String> = index.keys().collect();
+        names.sort();
+        let text: String = names.iter().map(|name| format!("{} {}\n", name, index[*name])).collect();
+        write_atomically(&self.folder.join(INDEX), text.as_bytes())?;
+        let used: std::collections::HashSet<&String> = index.values().collect();
+        for blob in blobs {
+            let path = self.folder.join(BLOBS).join(&blob);
+            if used.contains(&blob) || !std::fs::exists(&path).map_err(NuageError::cache_io(&path))? {
+                continue;
+            }
+            stats.bytes += std::fs::metadata(&path).map_or(0, |metadata| metadata.len());
+            std::fs::remove_file(&path).map_err(NuageError::cache_io(&path))?;
+        }
+        Ok(stats)
+    }
+
+    /// Convert a cache of plain files to a content-addressed one, storing
+    /// identical frames once. Frames written afterwards are deduplicated
+    /// too.
+    pub fn deduplicate(&self) -> Result<DedupStats> {
+        let mut stats = DedupStats::default();
+        let plain: Vec<String> = match self.index()? {
+            Some(_) => vec![],
+            None => self.frame_names()?,
+        };
+        std::fs::create_dir_all(&self.folder).map_err(NuageError::cache_io(&self.folder))?;
+        let index = self.folder.join(INDEX);
+        std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&index)
+            .map_err(NuageError::cache_io(&index))?;
+        for name in plain {
+            let path = self.folder.join(&name);
+            let bytes = std::fs::read(&path).map_err(NuageError::cache_io(&path))?;
+            stats.bytes_before += bytes.len() as u64;
+            self.store_blob(&name, &bytes)?;
+            std::fs::remove_file(&path).map_err(NuageError::cache_io(&path))?;
+        }
+        let blobs = self.folder.join(BLOBS);
+        stats.frames = self.index()?.map_or(0, |index| index.len());
+        if std::fs::exists(&blobs).map_err(NuageError::cache_io(&blobs))? {
+            for entry in std::fs::read_dir(&blobs).map_err(NuageError::cache_io(&blobs))? {
+                let metadata = entry.and_then(|entry| entry.metadata()).map_err(NuageError::cache_io(&blobs))?;
+                stats.blobs += 1;
+                stats.bytes_after += metadata.len();
+            }
+        }
+        Ok(stats)
+    }
+}
+
+/// The file name of `path`, as indexed.
+pub(crate) fn file_name(path: &Path) -> String {
+    path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default()
+}
+
+/// Write `bytes` to `path` through a temporary file, so an interrupted write
+/// is not taken for a frame.
+fn write_atomically(path: &Path, bytes: &[u8]) -> Result<()> {
+    let partial = path.with_extension("part");
+    std::fs::write(&partial, bytes).map_err(NuageError::cache_io(&partial))?;
+    std::fs::rename(&partial, path).map_err(NuageError::cache_io(path))
+}
+
+/// The time, zoom, tile window and channel in a name given by
+/// [`Cache::frame_path`], `None` for other names.
+pub fn parse_frame_name(name: &str) -> Option<(DateTime<Utc>, u16, Tiles, Channel)> {
+    if !is_frame_name(name) {
+        return None;
+    }
+    let parts: Vec<&str> = name.trim_end_matches(".jpg").split('_').collect();
+    let timestamp = NaiveDateTime::parse_from_str(parts[0], "%Y%m%d%H%M").ok()?.and_utc();
+    let numbers: Vec<u16> = parts[1..6].iter().map(|part| part.parse()).collect::<std::result::Result<_, _>>().ok()?;
+    let channel = parts.get(6).map_or(Some(Channel::Composite), |name| Channel::parse(name))?;
+    Some((timestamp, numbers[0], ((numbers[1], numbers[2]), (numbers[3], numbers[4])), channel))
+}
+
+/// Whether `name` is the file name of a cached frame, as given by
+/// [`Cache::frame_path`]: `YYYYMMDDHHMM_zoom_x1_y1_x2_y2[_channel].jpg`.
+pub fn is_frame_name(name: &str) -> bool {
+    let Some(stem) = name.strip_suffix(".jpg") else {
+        return false;
+    };
+    let mut parts: Vec<&str> = stem.split('_').collect();
+    if parts.len() == 7 && parts.last().and_then(|channel| Channel::parse(channel)).is_some() {
+        parts.pop();
+    }
+    parts.len() == 6
+        && parts[0].len() == 12
+        && parts.iter().all(|part| !part.is_empty() && part.bytes().all(|byte| byte.is_ascii_digit()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn sha256_known_answers() {
+        // FIPS 180-2, appendix B
+        assert_eq!(sha256_hex(b""), "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855");
+        assert_eq!(sha256_hex(b"abc"), "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+        assert_eq!(
+            sha256_hex(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"),
+            "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1"
+        );
+    }
+
+    #[test]
+    fn frame_names_parsed_back() {
+        let cache = Cache::new("/tmp/nuage");
+        let timestamp = Utc.with_ymd_and_hms(2025, 1, 1, 15, 30, 0).unwrap();
+        let tiles = ((10, 20), (13, 22));
+        for channel in Channel::ALL {
+            let name = file_name(&cache.frame_path(timestamp, 6, tiles, channel));
+            assert!(is_frame_name(&name), "{}", name);
+            assert_eq!(parse_frame_name(&name), Some((timestamp, 6, tiles, channel)));
+        }
+        assert_eq!(file_name(&cache.frame_path(timestamp, 6, tiles, Channel::Composite)), "202501011530_6_10_20_13_22.jpg");
+    }
+
+    #[test]
+    fn other_names_are_not_frames() {
+        for name in [
+            "202501011530_6_10_20_13_22.png",
+            "202501011530_6_10_20_13.jpg",
+            "202501011530_6_10_20_13_22_ultraviolet.jpg",
+            "20250101153_6_10_20_13_22.jpg",
+            "202501011530_6_10__13_22.jpg",
+            "checksums.txt",
+        ] {
+            assert!(!is_frame_name(name), "{}", name);
+            assert_eq!(parse_frame_name(name), None, "{}", name);
+        }
+    }
+}
@@ -0,0 +1,1082 @@
+//! The subcommands of `nuage` that do not open a window.
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use nuage::analysis::{self, Analysis};
+use nuage::config::RegionPreset;
+use nuage::export::{Checkpoint, Metadata};
+use nuage::archive::{self, Query};
+use nuage::projection::Bounds;
+use nuage::quality::{self, FrameMetrics};
+use nuage::projection::Georeference;
+use nuage::provider::{DELAY, INTERVAL, Tiles};
+use nuage::print::{self, Paper};
+use nuage::render::{Palette, TimeZone};
+use nuage::timeline::HISTORY_HOURS;
+use nuage::template::{TileServer, TileServerConfig};
+use nuage::wallpaper::{Backend, Wallpaper};
+use nuage::wms::Wms;
+use nuage::wmts::Wmts;
+use nuage::failover::Failover;
+use nuage::server::Served;
+use nuage::{
+    Cache, Channel, Config, Infoplaza, LocalFolder, Provider, RemoteServer, SatImage, Timeline, export, fixtures, geo, mosaic, server,
+    timeline,
+};
+
+pub fn usage() {
+    eprintln!("usage: nuage [--local <folder>] [--watch <folder>] [--connect <http://host:port>] [--xyz <url>]");
+    eprintln!("             [--fallback <folder|http://host:port>]... [--gpx <track.gpx>] [--channel <channel>]");
+    eprintln!("             [--region <name>] [--serve <address:port>]");
+    eprintln!("             [--wallpaper [--wallpaper-backend feh|swaybg|gnome|windows]]");
+    eprintln!("             [--eink <output.png> [--eink-every <minutes>] [--eink-size <WxH>] [--eink-command <command>]]");
+    eprintln!("             [--utc] [--hours <n>] [--delay <minutes>] [--interval <minutes>] [--fullscreen]");
+    eprintln!("             [--kiosk] [--low-power]");
+    eprintln!("       nuage fetch [--tiles <x1,y1,x2,y2>] [--zoom <z>] [--region <name>] [--channel <channel>]");
+    eprintln!("                   [--from <YYYYMMDDHHMM>] [--to <YYYYMMDDHHMM>] [--hours <n>] [--delay <minutes>]");
+    eprintln!("                   [--interval <minutes>]");
+    eprintln!("       nuage latest <output.png|output.jpg> [--tiles <x1,y1,x2,y2>] [--zoom <z>] [--region <name>]");
+    eprintln!("                    [--channel <channel>] [--dpi <n>] [--paper a5|a4|a3|letter|legal]");
+    eprintln!("                    [--palette original|grayscale|infrared|viridis]");
+    eprintln!("       nuage export <output.gif|output.mp4|folder> [--format gif|mp4|png] [--fps <n>] [--resume]");
+    eprintln!("                    [--local <folder>] [--tiles <x1,y1,x2,y2>] [--zoom <z>] [--region <name>]");
+    eprintln!("                    [--channel <channel>] [--from <YYYYMMDDHHMM>] [--to <YYYYMMDDHHMM>]");
+    eprintln!("       nuage analyze <output.geojson|output.kml> [--local <folder>] [--tiles <x1,y1,x2,y2>]");
+    eprintln!("                     [--zoom <z>] [--region <name>] [--to <YYYYMMDDHHMM>]");
+    eprintln!("       nuage motion <output.nc|output.npy> [--local <folder>] [--tiles <x1,y1,x2,y2>] [--zoom <z>]");
+    eprintln!("                    [--region <name>] [--from <YYYYMMDDHHMM>] [--to <YYYYMMDDHHMM>]");
+    eprintln!("       nuage compare <infoplaza|folder|http://host:port> <infoplaza|folder|http://host:port>");
+    eprintln!("                     [--output <report.csv>] [--tiles <x1,y1,x2,y2>] [--zoom <z>] [--region <name>]");
+    eprintln!("                     [--channel <channel>] [--from <YYYYMMDDHHMM>] [--to <YYYYMMDDHHMM>]");
+    eprintln!("       nuage archive query [--bbox <west,south,east,north>] [--from <YYYYMMDDHHMM>]");
+    eprintln!("                           [--to <YYYYMMDDHHMM>] [--channel <channel>]");
+    eprintln!("       nuage cache sync <http://host:port> [--from <YYYYMMDDHHMM>]");
+    eprintln!("       nuage cache dedup");
+    eprintln!("       nuage cache prune");
+    eprintln!("       nuage gen-fixtures <folder> [--count <n>] [--step <minutes>]");
+    eprintln!("channels: composite, visible, infrared, watervapor, sandwich");
+}
+
+fn parse_hours<'a>(args: &mut impl Iterator<Item = &'a String>) -> Result<u32, String> {
+    args.next()
+        .and_then(|hours| hours.parse().ok())
+        .filter(|hours| *hours > 0)
+        .ok_or("--hours expects a number of hours".into())
+}
+
+fn parse_interval<'a>(args: &mut impl Iterator<Item = &'a String>) -> Result<u32, String> {
+    args.next()
+        .and_then(|interval| interval.parse().ok())
+        .filter(|interval| nuage::timeline::is_valid_interval(*interval))
+        .ok_or("--interval expects a number of minutes dividing a day, like 5, 10 or 15".into())
+}
+
+/// The tile window and zoom of a region preset, by name.
+fn parse_region<'a>(args: &mut impl Iterator<Item = &'a String>) -> Result<(Tiles, u16), String> {
+    let name = args.next().ok_or("--region expects the name of a region")?;
+    match RegionPreset::find(name) {
+        Some(preset) => Ok((preset.tiles, preset.zoom)),
+        None => {
+            let names: Vec<String> = RegionPreset::built_in().into_iter().map(|preset| preset.name).collect();
+            Err(format!("unknown region {}, built-in regions: {}", name, names.join(", ")))
+        }
+    }
+}
+
+fn parse_channel<'a>(args: &mut impl Iterator<Item = &'a String>) -> Result<Channel, String> {
+    args.next()
+        .and_then(|name| Channel::parse(name))
+        .ok_or("--channel expects composite, visible, infrared, watervapor or sandwich".into())
+}
+
+/// The options of the viewer.
+#[derive(Default)]
+pub struct Options {
+    /// Play the frames of a local folder instead of downloading them.
+    pub local_folder: Option<PathBuf>,
+    /// Only play the last hours of frames of the local folder, as it is
+    /// filled by another tool.
+    pub watch: bool,
+    /// Play the frames of another nuage started with `--serve` instead of
+    /// downloading them, e.g. `http://raspberrypi:8080`.
+    pub connect: Option<String>,
+    /// Stitch the frames from the slippy tiles of this server instead,
+    /// overriding that of the configuration file.
+    pub tile_server: Option<TileServerConfig>,
+    /// Where to get the frames from, in order, when the provider fails: local
+    /// folders or other nuage servers.
+    pub fallbacks: Vec<String>,
+    /// A track to show, the animation following its time.
+    pub gpx: Option<PathBuf>,
+    /// The satellite product downloaded.
+    pub channel: Channel,
+    /// The tile window shown and its zoom level, from a region preset,
+    /// rather than western europe or that of the configuration file.
+    pub region: Option<(Tiles, u16)>,
+    /// Serve the frames over HTTP on this address instead of opening a window.
+    pub serve: Option<String>,
+    /// Set the latest frame as the desktop background instead of opening a
+    /// window.
+    pub wallpaper: bool,
+    /// The program setting the background, detected if not given.
+    pub wallpaper_backend: Option<Backend>,
+    /// Write the latest frame for an e-ink panel to this file instead of
+    /// opening a window.
+    pub eink: Option<PathBuf>,
+    /// Minutes between e-ink updates, `EINK_EVERY` if not given.
+    pub eink_every: Option<u64>,
+    /// Resolution of the e-ink panel, `EINK_SIZE` if not given.
+    pub eink_size: Option<(u32, u32)>,
+    /// Run with the path of the frame after each update, to refresh the
+    /// panel.
+    pub eink_command: Option<String>,
+    /// Show the times in UTC rather than local time.
+    pub utc: bool,
+    /// Open the window fullscreen.
+    pub fullscreen: bool,
+    /// Run unattended: fullscreen, without the header, looking for new
+    /// frames forever, quitting only when the quit key is held.
+    pub kiosk: bool,
+    /// Spare a Raspberry Pi Zero class board: fewer repaints, frames and
+    /// downloads, smaller frames.
+    pub low_power: bool,
+    /// Hours of frames shown, those of the configuration file if not given.
+    pub hours: Option<u32>,
+    /// Minutes after which the frames are published, those of the
+    /// configuration file or the provider if not given.
+    pub delay: Option<u32>,
+    /// Minutes between two frames, those of the configuration file or the
+    /// provider if not given.
+    pub interval: Option<u32>,
+}
+
+impl Options {
+    pub fn parse(args: &[String]) -> Result<Self, String> {
+        let mut options = Options::default();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--local" => options.local_folder = Some(PathBuf::from(args.next().ok_or("--local expects a folder")?)),
+                "--watch" => {
+                    options.local_folder = Some(PathBuf::from(args.next().ok_or("--watch expects a folder")?));
+                    options.watch = true;
+                }
+                "--connect" => options.connect = Some(args.next().ok_or("--connect expects a URL")?.clone()),
+                "--xyz" => {
+                    let url = args.next().ok_or("--xyz expects a URL with {z}, {x} and {y}")?;
+                    options.tile_server = Some(TileServerConfig { name: "xyz".into(), url: url.clone(), ..Default::default() });
+                }
+                "--fallback" => options.fallbacks.push(args.next().ok_or("--fallback expects a folder or a URL")?.clone()),
+                "--gpx" => options.gpx = Some(PathBuf::from(args.next().ok_or("--gpx expects a GPX file")?)),
+                "--channel" => options.channel = parse_channel(&mut args)?,
+                "--region" => options.region = Some(parse_region(&mut args)?),
+                "--serve" => options.serve = Some(args.next().ok_or("--serve expects an address:port")?.clone()),
+                "--wallpaper" => options.wallpaper = true,
+                "--utc" => options.utc = true,
+                "--fullscreen" => options.fullscreen = true,
+                "--kiosk" => options.kiosk = true,
+                "--low-power" => options.low_power = true,
+                "--hours" => options.hours = Some(parse_hours(&mut args)?),
+                "--delay" => options.delay = Some(parse_value(&mut args, "--delay")?),
+                "--interval" => options.interval = Some(parse_interval(&mut args)?),
+                "--eink" => options.eink = Some(PathBuf::from(args.next().ok_or("--eink expects a PNG file")?)),
+                "--eink-every" => options.eink_every = Some(parse_value(&mut args, "--eink-every")?),
+                "--eink-size" => {
+                    options.eink_size = Some(
+                        args.next()
+                            .and_then(|size| size.split_once('x'))
+                            .and_then(|(width, height)| Some((width.parse().ok()?, height.parse().ok()?)))
+                            .filter(|(width, height)| *width > 0 && *height > 0)
+                            .ok_or("--eink-size expects a resolution as WxH")?,
+                    )
+                }
+                "--eink-command" => options.eink_command = Some(args.next().ok_or("--eink-command expects a command")?.clone()),
+                "--wallpaper-backend" => {
+                    options.wallpaper_backend = Some(
+                        args.next()
+                            .and_then(|name| Backend::parse(name))
+                            .ok_or("--wallpaper-backend expects feh, swaybg, gnome or windows")?,
+                    )
+                }
+                _ => return Err(format!("unknown argument {}", arg)),
+            }
+        }
+        Ok(options)
+    }
+
+    /// The provider, switching to the fallbacks when it fails.
+    pub fn provider(&self) -> Result<Arc<dyn Provider>, String> {
+        Ok(match self.failover()? {
+            Some(failover) => failover,
+            None => self.primary()?,
+        })
+    }
+
+    fn primary(&self) -> Result<Arc<dyn Provider>, String> {
+        match (&self.local_folder, &self.connect) {
+            (Some(local_folder), _) => {
+                let mut local_folder = LocalFolder::new(local_folder);
+                if self.watch {
+                    local_folder.hours = Some(self.hours.unwrap_or(HISTORY_HOURS));
+                }
+                Ok(Arc::new(local_folder))
+            }
+            (None, Some(url)) => Ok(Arc::new(RemoteServer::new(url))),
+            (None, None) => {
+                let config = Config::load().unwrap_or_default();
+                if let Some(mut config) = config.wms {
+                    if let Some(region) = self.region {
+                        (config.tiles, config.zoom) = region;
+                    }
+                    let mut wms = Wms::new(config);
+                    wms.hours = self.hours.unwrap_or(HISTORY_HOURS);
+                    return Ok(Arc::new(wms));
+                }
+                if let Some(mut config) = config.wmts {
+                    if let Some(region) = self.region {
+                        (config.tiles, config.zoom) = region;
+                    }
+                    let mut wmts = Wmts::new(config);
+                    wmts.hours = self.hours.unwrap_or(HISTORY_HOURS);
+                    return Ok(Arc::new(wmts));
+                }
+                if let Some(mut config) = self.tile_server.clone().or(config.tile_server) {
+                    if let Some(region) = self.region {
+                        (config.tiles, config.zoom) = region;
+                    }
+                    let mut tile_server = TileServer::new(config);
+                    tile_server.hours = self.hours.unwrap_or(HISTORY_HOURS);
+                    tile_server.config.delay = self.delay.unwrap_or(tile_server.config.delay);
+                    tile_server.config.interval = self.interval.unwrap_or(tile_server.config.interval);
+                    return Ok(Arc::new(tile_server));
+                }
+                let mut infoplaza = Infoplaza::new(Cache::default_location().map_err(|e| e.to_string())?);
+                infoplaza.channel = self.channel;
+                infoplaza.products = config.channel_products;
+                if let Some(region) = self.region {
+                    (infoplaza.tiles, infoplaza.zoom) = region;
+                }
+                infoplaza.hours = self.hours.unwrap_or(HISTORY_HOURS);
+                infoplaza.delay = self.delay.unwrap_or(infoplaza.delay);
+                infoplaza.interval = self.interval.unwrap_or(infoplaza.interval);
+                infoplaza.low_data = nuage::metered::is_low_data();
+                Ok(Arc::new(infoplaza))
+            }
+        }
+    }
+
+    /// The provider and its fallbacks, `None` without fallbacks.
+    fn failover(&self) -> Result<Option<Arc<Failover>>, String> {
+        if self.fallbacks.is_empty() {
+            return Ok(None);
+        }
+        let mut providers = vec![self.primary()?];
+        providers.extend(self.fallbacks.iter().map(|fallback| provider_at(fallback)));
+        Ok(Some(Arc::new(Failover::new(providers))))
+    }
+}
+
+/// The frames of another nuage server if `location` is a URL, of a local
+/// folder otherwise.
+fn provider_at(location: &str) -> Arc<dyn Provider> {
+    if location.starts_with("http://") || location.starts_with("https://") {
+        Arc::new(RemoteServer::new(location))
+    } else {
+        Arc::new(LocalFolder::new(location))
+    }
+}
+
+/// `nuage --serve <address:port>`: serve the latest frames over HTTP without
+/// opening a window, checking for new frames every 5 minutes. The health of
+/// the providers is served too when there are fallbacks.
+pub fn serve(options: &Options, addr: &str) -> Result<(), String> {
+    nuage::metered::watch();
+    let failover = options.failover()?;
+    let provider: Arc<dyn Provider> = match &failover {
+        Some(failover) => failover.clone(),
+        None => options.primary()?,
+    };
+    let region = provider.region();
+    let timeline = Timeline::load_and_refresh(provider, std::time::Duration::from_secs(5 * 60));
+    let cache = Cache::default_location().ok();
+    server::serve(addr, Served { timeline, region, failover, cache }).map_err(|e| e.to_string())
+}
+
+/// `nuage --wallpaper`: keep the desktop background set to the latest frame,
+/// with the point of interest and its time, checking for new frames every 5
+/// minutes.
+pub fn wallpaper(options: &Options) -> Result<(), String> {
+    nuage::metered::watch();
+    let provider = options.provider()?;
+    let (tiles, _) = provider.region();
+    let folder = Cache::default_location().map_err(|e| e.to_string())?.folder().to_path_buf();
+    std::fs::create_dir_all(&folder).map_err(|e| format!("{}: {}", folder.display(), e))?;
+    let mut wallpaper = Wallpaper::new(options.wallpaper_backend.unwrap_or_else(Backend::detect));
+    println!("setting the wallpaper with {:?}", wallpaper.backend());
+    let timeline = Timeline::load_and_refresh(provider, std::time::Duration::from_secs(5 * 60));
+    let mut current: Option<(chrono::DateTime<Utc>, PathBuf)> = None;
+    loop {
+        let latest = timeline.sat_images.lock().unwrap().latest().cloned();
+        if let Some(latest) = latest.filter(|latest| current.as_ref().is_none_or(|(timestamp, _)| *timestamp != latest.timestamp)) {
+            // A new name each time, as desktops may not reload a known path
+            let path = folder.join(format!("wallpaper_{}.png", latest.timestamp.format("%Y%m%d%H%M")));
+            nuage::render::composite_with_label(&latest, tiles, &timestamp_label(options, latest.timestamp))
+                .save(&path)
+                .map_err(|e| format!("{}: {}", path.display(), e))?;
+            match wallpaper.set(&path) {
+                Ok(()) => println!("wallpaper set to {}", latest.timestamp),
+                Err(e) => eprintln!("could not set the wallpaper: {}", e),
+            }
+            if let Some((_, previous)) = current.replace((latest.timestamp, path)) {
+                let _ = std::fs::remove_file(previous);
+            }
+        }
+        std::thread::sleep(std::time::Duration::from_secs(5));
+    }
+}
+
+/// The time of a frame as labelled on the wallpaper and the e-ink panel: in
+/// the format of the configuration file, in UTC with `--utc`.
+fn timestamp_label(options: &Options, timestamp: DateTime<Utc>) -> String {
+    let format = Config::load().map(|config| config.timestamp_format).unwrap_or_default();
+    let zone = if options.utc { TimeZone::Utc } else { TimeZone::Local };
+    nuage::render::timestamp_label(timestamp, zone, &format.label)
+}
+
+/// Minutes between e-ink updates by default.
+const EINK_EVERY: u64 = 10;
+
+/// Resolution of the e-ink panel by default, that of the common 7.5" panels.
+const EINK_SIZE: (u32, u32) = (800, 480);
+
+/// The most recent frame available, trying the last few time points as the
+/// latest may not be published yet.
+fn latest_frame(provider: &dyn Provider) -> Result<SatImage, String> {
+    let mut error = "no frame".to_string();
+    for timestamp in provider.timepoints(Utc::now()).map_err(|e| e.to_string())?.into_iter().take(3) {
+        match provider.frame(timestamp) {
+            Ok(image) => return Ok(SatImage::new(image, timestamp)),
+            Err(e) => error = format!("{}: {}", timestamp, e),
+        }
+    }
+    Err(error)
+}
+
+/// `nuage --eink <output.png>`: without opening a window, write the latest
+/// frame dithered to black and white for an e-ink panel, then check again
+/// every `--eink-every` minutes. Only the latest frame is downloaded, and
+/// `--eink-command` is run with the path of the frame when it changes.
+pub fn eink(options: &Options, output: &Path) -> Result<(), String> {
+    nuage::metered::watch();
+    let provider = options.provider()?;
+    let (tiles, _) = provider.region();
+    let every = std::time::Duration::from_secs(60 * options.eink_every.unwrap_or(EINK_EVERY).max(1));
+    let size = options.eink_size.unwrap_or(EINK_SIZE);
+    let mut shown = None;
+    loop {
+        match latest_frame(provider.as_ref()) {
+            Ok(latest) if shown != Some(latest.timestamp) => {
+                // Renamed once written, so that the panel never reads half a
+                // frame
+                let partial = output.with_extension("partial.png");
+                nuage::render::eink_image(&latest, tiles, size, &timestamp_label(options, latest.timestamp))
+                    .save(&partial)
+                    .map_err(|e| format!("{}: {}", partial.display(), e))?;
+                std::fs::rename(&partial, output).map_err(|e| format!("{}: {}", output.display(), e))?;
+                println!("e-ink frame of {}", latest.timestamp);
+                if let Some(command) = &options.eink_command {
+                    let mut words = command.split_whitespace();
+                    let program = words.next().ok_or("--eink-command is empty")?;
+                    match std::process::Command::new(program).args(words).arg(output).status() {
+                        Ok(status) if status.success() => (),
+                        Ok(status) => eprintln!("{} failed: {}", command, status),
+                        Err(e) => eprintln!("could not run {}: {}", command, e),
+                    }
+                }
+                shown = Some(latest.timestamp);
+            }
+            Ok(_) => (),
+            Err(e) => eprintln!("{}", e),
+        }
+        std::thread::sleep(every);
+    }
+}
+
+/// Parse the value following a `--flag`.
+pub fn parse_value<'a, T: std::str::FromStr>(args: &mut impl Iterator<Item = &'a String>, flag: &str) -> Result<T, String> {
+    args.next()
+        .and_then(|value| value.parse().ok())
+        .ok_or(format!("{} expects a number", flag))
+}
+
+/// Parse the UTC timestamp following a `--flag`, formatted as YYYYMMDDHHMM.
+pub fn parse_timestamp<'a>(args: &mut impl Iterator<Item = &'a String>, flag: &str) -> Result<DateTime<Utc>, String> {
+    args.next()
+        .and_then(|value| NaiveDateTime::parse_from_str(value, "%Y%m%d%H%M").ok())
+        .map(|timestamp| timestamp.and_utc())
+        .ok_or(format!("{} expects a UTC time formatted as YYYYMMDDHHMM", flag))
+}
+
+/// `nuage archive query`: print the cached frames covering part of `--bbox`
+/// (in degrees) between `--from` and `--to`, as JSON.
+pub fn archive(args: &[String]) -> Result<(), String> {
+    if args.first().map(String::as_str) != Some("query") {
+        return Err("archive expects query".into());
+    }
+    let mut query = Query::default();
+    let mut args = args[1..].iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--bbox" => {
+                let bbox: Vec<f64> = args
+                    .next()
+                    .ok_or("--bbox expects west,south,east,north")?
+                    .split(',')
+                    .map(|value| value.parse())
+                    .collect::<Result<_, _>>()
+                    .map_err(|_| "--bbox expects west,south,east,north")?;
+                let [west, south, east, north] = bbox[..] else {
+                    return Err("--bbox expects west,south,east,north".into());
+                };
+                query.bbox = Some(Bounds { west, south, east, north });
+            }
+            "--from" => query.from = Some(parse_timestamp(&mut args, "--from")?),
+            "--to" => query.to = Some(parse_timestamp(&mut args, "--to")?),
+            "--channel" => query.channel = Some(parse_channel(&mut args)?),
+            _ => return Err(format!("unknown argument {}", arg)),
+        }
+    }
+    let config = Config::load().map_err(|e| e.to_string())?;
+    query.unarchived = config.cache_policies.iter().filter(|(_, policy)| !policy.archive).map(|(channel, _)| *channel).collect();
+    let cache = Cache::default_location().map_err(|e| e.to_string())?;
+    let frames = archive::query(&cache, &query).map_err(|e| e.to_string())?;
+    println!("{}", archive::to_json(&frames));
+    Ok(())
+}
+
+/// `nuage cache sync|dedup|prune`.
+pub fn cache(args: &[String]) -> Result<(), String> {
+    match args.first().map(String::as_str) {
+        Some("sync") => cache_sync(&args[1..]),
+        Some("dedup") if args.len() == 1 => cache_dedup(),
+        Some("prune") if args.len() == 1 => cache_prune(),
+        _ => Err("cache expects sync, dedup or prune".into()),
+    }
+}
+
+/// `nuage cache prune`: remove the frames the cache policies of the
+/// configuration file no longer keep.
+fn cache_prune() -> Result<(), String> {
+    let config = Config::load().map_err(|e| e.to_string())?;
+    let cache = Cache::default_location().map_err(|e| e.to_string())?;
+    let stats = cache.prune(&config.cache_policies, Utc::now()).map_err(|e| e.to_string())?;
+    println!("{} frames removed, {} bytes freed", stats.frames, stats.bytes);
+    Ok(())
+}
+
+/// `nuage cache dedup`: store the cached frames by content, identical frames
+/// once.
+fn cache_dedup() -> Result<(), String> {
+    let cache = Cache::default_location().map_err(|e| e.to_string())?;
+    let stats = cache.deduplicate().map_err(|e| e.to_string())?;
+    println!(
+        "{} frames in {} blobs, {} bytes converted, {} bytes stored",
+        stats.frames, stats.blobs, stats.bytes_before, stats.bytes_after,
+    );
+    Ok(())
+}
+
+/// `nuage cache sync <url>`: copy the frames cached by the nuage serving on
+/// `url` that are missing from the local cache, optionally only those taken
+/// since `--from`.
+fn cache_sync(args: &[String]) -> Result<(), String> {
+    let mut args = args.iter();
+    let mut remote = None;
+    let mut from = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--from" => from = Some(parse_timestamp(&mut args, "--from")?),
+            _ if arg.starts_with("--") => return Err(format!("unknown argument {}", arg)),
+            _ => remote = Some(arg.trim_end_matches('/').to_string()),
+        }
+    }
+    let remote = remote.ok_or("cache sync expects the URL of a nuage server")?;
+    let cache = Cache::default_location().map_err(|e| e.to_string())?;
+
+    let url = format!("{}/cache/index.json", remote);
+    let text = ureq::get(&url)
+        .call()
+        .and_then(|mut response| response.body_mut().read_to_string())
+        .map_err(|e| format!("{}: {}", url, e))?;
+    let index = nuage::json::parse(&text).map_err(|e| format!("{}: {}", url, e))?;
+    let remote_names: Vec<&str> = index
+        .get("frames")
+        .and_then(nuage::json::Json::as_array)
+        .ok_or(format!("{}: no frames list", url))?
+        .iter()
+        .filter_map(nuage::json::Json::as_str)
+        .collect();
+    let remote_checksums = index.get("checksums");
+    let local_names: HashSet<String> = cache.frame_names().map_err(|e| e.to_string())?.into_iter().collect();
+    // Names start with the time of the frame
+    let since = from.map(|from| from.format("%Y%m%d%H%M").to_string());
+    let (cached, missing): (Vec<&str>, Vec<&str>) = remote_names
+        .into_iter()
+        .filter(|name| nuage::cache::is_frame_name(name))
+        .filter(|name| since.as_ref().is_none_or(|since| name[..12] >= since[..]))
+        .partition(|name| local_names.contains(*name));
+
+    let mut failures = 0;
+    for (index, name) in missing.iter().enumerate() {
+        let url = format!("{}/cache/{}", remote, name);
+        let result = ureq::get(&url)
+            .call()
+            .and_then(|mut response| response.body_mut().with_config().limit(20 * 1024 * 1024).read_to_vec())
+            .map_err(|e| e.to_string())
+            .and_then(|bytes| cache.write_file(name, &bytes).map_err(|e| e.to_string()))
+            .and_then(|()| match remote_checksums.and_then(|checksums| checksums.get(name)?.as_str()) {
+                Some(checksum) => cache.record_checksums(&[(name.to_string(), checksum.to_string())]).map_err(|e| e.to_string()),
+                None => Ok(()),
+            });
+        let status = match result {
+            Ok(()) => "copied".to_string(),
+            Err(e) => {
+                failures += 1;
+                format!("failed: {}", e)
+            }
+        };
+        println!("[{}/{}] {} {}", index + 1, missing.len(), name, status);
+    }
+    println!("{} frames copied, {} failed, {} already cached", missing.len() - failures, failures, cached.len());
+    Ok(())
+}
+
+/// `nuage gen-fixtures <folder>`: write a synthetic frame sequence that can
+/// then be played with `nuage --local <folder>`.
+pub fn gen_fixtures(args: &[String]) -> Result<(), String> {
+    let mut folder = None;
+    let mut count = 24;
+    let mut step = 5;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--count" => count = parse_value(&mut args, "--count")?,
+            "--step" => step = parse_value(&mut args, "--step")?,
+            _ if arg.starts_with('-') => return Err(format!("unknown argument {}", arg)),
+            _ => folder = Some(PathBuf::from(arg)),
+        }
+    }
+    let folder = folder.ok_or("gen-fixtures expects a folder")?;
+    let end = timeline::previous_time(Utc::now(), HISTORY_HOURS, DELAY, INTERVAL)[0];
+    let written = fixtures::generate(&folder, count, step, end).map_err(|e| e.to_string())?;
+    println!("wrote {} frames in {}", written.len(), folder.display());
+    Ok(())
+}
+
+/// The frames selected on the command line: a tile window (or a local folder)
+/// and a time range.
+#[derive(Default)]
+struct FrameArgs {
+    tiles: Option<Tiles>,
+    zoom: Option<u16>,
+    channel: Channel,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+    hours: Option<u32>,
+    delay: Option<u32>,
+    interval: Option<u32>,
+    local: Option<PathBuf>,
+}
+
+impl FrameArgs {
+    /// Parse `arg` if it is one of the frame selection flags, returns whether
+    /// it was.
+    fn parse<'a>(&mut self, arg: &str, args: &mut impl Iterator<Item = &'a String>) -> Result<bool, String> {
+        match arg {
+            "--tiles" => {
+                let tiles = args.next().ok_or("--tiles expects x1,y1,x2,y2")?;
+                let tiles: Vec<u16> = tiles
+                    .split(',')
+                    .map(|tile| tile.parse())
+                    .collect::<Result<_, _>>()
+                    .map_err(|_| "--tiles expects x1,y1,x2,y2")?;
+                let [x1, y1, x2, y2] = tiles[..] else {
+                    return Err("--tiles expects x1,y1,x2,y2".into());
+                };
+                self.tiles = Some(((x1, y1), (x2, y2)));
+            }
+            "--zoom" => self.zoom = Some(parse_value(args, "--zoom")?),
+            "--region" => {
+                let (tiles, zoom) = parse_region(args)?;
+                (self.tiles, self.zoom) = (Some(tiles), Some(zoom));
+            }
+            "--channel" => self.channel = parse_channel(args)?,
+            "--from" => self.from = Some(parse_timestamp(args, "--from")?),
+            "--to" => self.to = Some(parse_timestamp(args, "--to")?),
+            "--hours" => self.hours = Some(parse_hours(args)?),
+            "--delay" => self.delay = Some(parse_value(args, "--delay")?),
+            "--interval" => self.interval = Some(parse_interval(args)?),
+            "--local" => self.local = Some(PathBuf::from(args.next().ok_or("--local expects a folder")?)),
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    fn infoplaza(&self) -> Result<Infoplaza, String> {
+        let mut provider = Infoplaza::new(Cache::default_location().map_err(|e| e.to_string())?);
+        if let Some(tiles) = self.tiles {
+            provider.tiles = tiles;
+        }
+        if let Some(zoom) = self.zoom {
+            provider.zoom = zoom;
+        }
+        provider.channel = self.channel;
+        provider.products = Config::load().unwrap_or_default().channel_products;
+        provider.hours = self.hours.unwrap_or(HISTORY_HOURS);
+        provider.delay = self.delay.unwrap_or(provider.delay);
+        provider.interval = self.interval.unwrap_or(provider.interval);
+        Ok(provider)
+    }
+
+    fn provider(&self) -> Result<Arc<dyn Provider>, String> {
+        match &self.local {
+            Some(local) => Ok(Arc::new(LocalFolder::new(local))),
+            None => Ok(Arc::new(self.infoplaza()?)),
+        }
+    }
+
+    /// The time points in the range, from the most recent to the least.
+    /// Without `--from`/`--to`, the frames the viewer would show now, over
+    /// the last `--hours`.
+    fn timepoints(&self, provider: &dyn Provider) -> Result<Vec<DateTime<Utc>>, String> {
+        let timepoints = match (self.from, self.to) {
+            (None, None) => provider.timepoints(Utc::now()).map_err(|e| e.to_string())?,
+            // Local folders have their own time points
+            (from, to) if self.local.is_some() => provider
+                .timepoints(Utc::now())
+                .map_err(|e| e.to_string())?
+                .into_iter()
+                .filter(|t| from.is_none_or(|from| *t >= from) && to.is_none_or(|to| *t <= to))
+                .collect(),
+            (from, to) => {
+                let hours = self.hours.unwrap_or(HISTORY_HOURS);
+                let delay = self.delay.unwrap_or(DELAY);
+                let interval = provider.interval();
+                let to = to.unwrap_or(timeline::previous_time(Utc::now(), hours, delay, interval)[0]);
+                let from = from.unwrap_or(to - chrono::Duration::hours(hours as i64));
+                timeline::time_range(from, to, interval)
+            }
+        };
+        Ok(timepoints)
+    }
+}
+
+/// `nuage fetch`: download the frames of a tile window and time range in the
+/// cache, without opening a window.
+pub fn fetch(args: &[String]) -> Result<(), String> {
+    let mut frame_args = FrameArgs::default();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if !frame_args.parse(arg, &mut args)? || arg == "--local" {
+            return Err(format!("unknown argument {}", arg));
+        }
+    }
+    let provider = frame_args.infoplaza()?;
+    let timepoints = frame_args.timepoints(&provider)?;
+
+    let mut failures = 0;
+    for (index, timepoint) in timepoints.iter().enumerate() {
+        let cached = provider
+            .cache()
+            .contains(&provider.cache().frame_path(*timepoint, provider.zoom, provider.tiles, provider.channel))
+            .unwrap_or(false);
+        let status = match provider.frame(*timepoint) {
+            Ok(_) if cached => "cached".to_string(),
+            Ok(_) => "downloaded".to_string(),
+            Err(e) => {
+                failures += 1;
+                format!("failed: {}", e)
+            }
+        };
+        println!("[{}/{}] {} {}", index + 1, timepoints.len(), timepoint.format("%Y-%m-%d %H:%M UTC"), status);
+    }
+    println!("{} frames fetched, {} failed", timepoints.len() - failures, failures);
+    Ok(())
+}
+
+/// `nuage latest <output.png|output.jpg>`: write the freshest picture of a
+/// tile window, its tiles taken at different times when the latest ones are
+/// not all published yet. Tiles older than the newest are framed and labeled
+/// with their time, and the time of each tile is printed as JSON on the last
+/// line. With `--dpi` or `--paper`, the picture is laid out for print and
+/// the share of its pixels out of the CMYK gamut is told for each palette.
+pub fn latest(args: &[String]) -> Result<(), String> {
+    let mut frame_args = FrameArgs::default();
+    let mut output = None;
+    let (mut dpi, mut paper, mut palette) = (None, None, Palette::Original);
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if frame_args.parse(arg, &mut args)? {
+            if arg == "--local" || arg == "--from" || arg == "--to" {
+                return Err(format!("{} is not supported", arg));
+            }
+        } else if arg == "--dpi" {
+            dpi = Some(parse_value::<u32>(&mut args, "--dpi")?.max(1));
+        } else if arg == "--paper" {
+            paper = Some(args.next().and_then(|name| Paper::parse(name)).ok_or("--paper expects a5, a4, a3, letter or legal")?);
+        } else if arg == "--palette" {
+            palette = args
+                .next()
+                .and_then(|name| Palette::ALL.into_iter().find(|palette| palette.name().to_lowercase() == name.to_lowercase()))
+                .ok_or("--palette expects original, grayscale, infrared or viridis")?;
+        } else if output.is_none() && !arg.starts_with("--") {
+            output = Some(PathBuf::from(arg));
+        } else {
+            return Err(format!("unknown argument {}", arg));
+        }
+    }
+    let output = output.ok_or("an output file is required")?;
+    let provider = frame_args.infoplaza()?;
+    let mut mosaic = mosaic::best_available(&provider, Utc::now()).map_err(|e| e.to_string())?;
+    let printing = dpi.is_some() || paper.is_some();
+    if printing {
+        // On the side, the JSON going to the standard output
+        for (proofed, share) in print::palette_proofs(&mosaic.image) {
+            let chosen = if proofed == palette { " (chosen)" } else { "" };
+            eprintln!("{}: {:.1}% of the pixels brighter or more saturated than CMYK prints{}", proofed.name(), share * 100., chosen);
+        }
+    }
+    mosaic.image = palette.apply(&mosaic.image);
+    let annotated = mosaic.annotated();
+    if printing {
+        let dpi = dpi.unwrap_or(print::PRINT_DPI);
+        let image = match paper {
+            Some(paper) => print::layout(&annotated, paper, dpi),
+            None => annotated,
+        };
+        print::write(&output, &image, dpi).map_err(|e| e.to_string())?;
+    } else {
+        annotated.save(&output).map_err(|e| format!("{}: {}", output.display(), e))?;
+    }
+    println!("{}", mosaic.to_json());
+    Ok(())
+}
+
+/// `nuage compare <provider> <provider>`: the PSNR and SSIM of the frames of
+/// two providers taken at the same time, on the area they both cover, and
+/// their mean. Providers are `infoplaza` (the tile window given by `--tiles`
+/// and `--zoom`), folders or nuage servers. With `--output`, the metrics of
+/// each frame are written as CSV.
+pub fn compare(args: &[String]) -> Result<(), String> {
+    let mut frame_args = FrameArgs::default();
+    let mut locations = vec![];
+    let mut output = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg != "--local" && frame_args.parse(arg, &mut args)? {
+            continue;
+        }
+        match arg.as_str() {
+            "--output" => output = Some(PathBuf::from(args.next().ok_or("--output expects a file")?)),
+            _ if arg.starts_with("--") => return Err(format!("unknown argument {}", arg)),
+            _ => locations.push(arg.clone()),
+        }
+    }
+    let [a, b] = &locations[..] else {
+        return Err("compare expects two providers".into());
+    };
+    let provider = |location: &str| -> Result<Arc<dyn Provider>, String> {
+        if location != "infoplaza" {
+            return Ok(provider_at(location));
+        }
+        let mut infoplaza = frame_args.infoplaza()?;
+        if let (Some(from), Some(to)) = (frame_args.from, frame_args.to) {
+            infoplaza.span = Some((from, to));
+        }
+        Ok(Arc::new(infoplaza))
+    };
+    let (a, b) = (provider(a)?, provider(b)?);
+    let in_range = |timestamp: &DateTime<Utc>| {
+        frame_args.from.is_none_or(|from| *timestamp >= from) && frame_args.to.is_none_or(|to| *timestamp <= to)
+    };
+    let timepoints_b = b.timepoints(Utc::now()).map_err(|e| format!("{}: {}", b.name(), e))?;
+    let mut timepoints: Vec<DateTime<Utc>> = a
+        .timepoints(Utc::now())
+        .map_err(|e| format!("{}: {}", a.name(), e))?
+        .into_iter()
+        .filter(|timestamp| in_range(timestamp) && timepoints_b.contains(timestamp))
+        .collect();
+    timepoints.sort();
+
+    let mut report = vec![];
+    for timepoint in timepoints {
+        let label = timepoint.format("%Y-%m-%d %H:%M UTC");
+        let (image_a, image_b) = match (a.frame(timepoint), b.frame(timepoint)) {
+            (Ok(image_a), Ok(image_b)) => (image_a, image_b),
+            (Err(e), _) | (_, Err(e)) => {
+                eprintln!("skipping {}: {}", label, e);
+                continue;
+            }
+        };
+        let (gray_a, gray_b) = quality::common_area(&image_a, a.region(), &image_b, b.region())
+            .ok_or("the providers do not cover a common area at the same zoom level")?;
+        let metrics = FrameMetrics::between(&gray_a, &gray_b);
+        println!("{} PSNR {:.2} dB SSIM {:.4}", label, metrics.psnr, metrics.ssim);
+        report.push((timepoint, metrics));
+    }
+    if report.is_empty() {
+        return Err("no frames taken at the same time could be read".into());
+    }
+    // Identical frames have an infinite PSNR, left out of the mean
+    let finite: Vec<f64> = report.iter().map(|(_, metrics)| metrics.psnr).filter(|psnr| psnr.is_finite()).collect();
+    let mean_psnr = match finite.len() {
+        0 => f64::INFINITY,
+        count => finite.iter().sum::<f64>() / count as f64,
+    };
+    let mean_ssim = report.iter().map(|(_, metrics)| metrics.ssim).sum::<f64>() / report.len() as f64;
+    println!(
+        "{} frames compared between {} and {}: mean PSNR {:.2} dB ({} identical), mean SSIM {:.4}",
+        report.len(),
+        a.name(),
+        b.name(),
+        mean_psnr,
+        report.len() - finite.len(),
+        mean_ssim,
+    );
+    if let Some(output) = output {
+        let mut csv = String::from("time,psnr,ssim\n");
+        for (timepoint, metrics) in &report {
+            csv.push_str(&format!("{},{:.4},{:.6}\n", timepoint.to_rfc3339(), metrics.psnr, metrics.ssim));
+        }
+        std::fs::write(&output, csv).map_err(|e| format!("{}: {}", output.display(), e))?;
+        println!("report written to {}", output.display());
+    }
+    Ok(())
+}
+
+/// List the frames at `timestamps` next to the export `output`, with their
+/// checksum as downloaded by `provider`.
+fn write_sources(output: &Path, provider: &dyn Provider, timestamps: impl IntoIterator<Item = DateTime<Utc>>) -> Result<(), String> {
+    let sources: Vec<(DateTime<Utc>, Option<String>)> =
+        timestamps.into_iter().map(|timestamp| (timestamp, provider.checksum(timestamp))).collect();
+    export::write_sources(output, provider.name(), &sources).map_err(|e| e.to_string())
+}
+
+/// `nuage export <output>`: write the frames of a time range as an animation
+/// without opening a window. The format is given by `--format` or deduced from
+/// the extension of the output: .gif, .mp4 or a folder for a PNG sequence.
+pub fn export(args: &[String]) -> Result<(), String> {
+    let mut frame_args = FrameArgs::default();
+    let mut output = None;
+    let mut format = None;
+    let mut fps = None;
+    let mut resume = false;
+    // The flags choosing the frames, those of an interrupted export being
+    // kept when resuming it
+    let mut selection = vec![];
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if frame_args.parse(arg, &mut args)? {
+            if ["--from", "--to", "--hours", "--delay", "--interval"].contains(&arg.as_str()) {
+                selection.push(arg.as_str());
+            }
+            continue;
+        }
+        match arg.as_str() {
+            "--format" => format = Some(args.next().ok_or("--format expects gif, mp4 or png")?.clone()),
+            "--fps" => match parse_value::<f32>(&mut args, "--fps")? {
+                value if value.is_finite() && value > 0. => fps = Some(value),
+                value => return Err(format!("--fps expects a positive number of frames per second, not {}", value)),
+            },
+            "--resume" => resume = true,
+            _ if arg.starts_with("--") => return Err(format!("unknown argument {}", arg)),
+            _ => output = Some(PathBuf::from(arg)),
+        }
+    }
+    let output = output.ok_or("export expects an output file")?;
+    let format = format.unwrap_or_else(|| match output.extension().and_then(|extension| extension.to_str()) {
+        Some(extension) => extension.to_lowercase(),
+        None => "png".into(),
+    });
+    if !["gif", "mp4", "png"].contains(&format.as_str()) {
+        return Err(format!("unknown format {}, expected gif, mp4 or png", format));
+    }
+    let provider = frame_args.provider()?;
+    let interrupted = Checkpoint::load(&output).map_err(|e| e.to_string())?;
+    let (frames, checkpoint) = match interrupted {
+        Some(checkpoint) if resume => {
+            if checkpoint.format != format || fps.is_some_and(|fps| fps != checkpoint.fps) {
+                return Err(format!(
+                    "the interrupted export to {} is a {} at {} frames per second, resume it as such or start over without --resume",
+                    output.display(),
+                    checkpoint.format,
+                    checkpoint.fps
+                ));
+            }
+            if !selection.is_empty() {
+                eprintln!("resuming with the frames chosen then, {} ignored", selection.join(", "));
+            }
+            println!("resuming the export of {} frames to {}", checkpoint.timestamps.len(), output.display());
+            // The frames chosen then, whatever the time now
+            let mut frames = vec![];
+            for timestamp in &checkpoint.timestamps {
+                let image = provider
+                    .frame(*timestamp)
+                    .map_err(|e| format!("frame {} of the interrupted export: {}", timestamp, e))?;
+                frames.push(SatImage::new(image, *timestamp));
+            }
+            (frames, checkpoint)
+        }
+        None if resume => return Err(format!("no interrupted export to {} to resume", output.display())),
+        interrupted => {
+            if interrupted.is_some() {
+                println!("starting over the interrupted export to {}, --resume continues it", output.display());
+            }
+            let timepoints = frame_args.timepoints(provider.as_ref())?;
+            // Exports play forward in time
+            let mut frames = vec![];
+            for (index, timepoint) in timepoints.iter().rev().enumerate() {
+                match provider.frame(*timepoint) {
+                    Ok(image) => frames.push(SatImage::new(image, *timepoint)),
+                    Err(e) => eprintln!("skipping {}: {}", timepoint, e),
+                }
+                println!("[{}/{}] {}", index + 1, timepoints.len(), timepoint.format("%Y-%m-%d %H:%M UTC"));
+            }
+            if frames.is_empty() {
+                return Err("no frame to export".into());
+            }
+            let timestamps = frames.iter().map(|frame| frame.timestamp).collect();
+            let checkpoint = Checkpoint::start(&output, &format, fps.unwrap_or(5.), timestamps).map_err(|e| e.to_string())?;
+            (frames, checkpoint)
+        }
+    };
+    let (Some(first), Some(last)) = (frames.first(), frames.last()) else {
+        return Err("no frame to export".into());
+    };
+    let metadata = Metadata::new(provider.name(), provider.region(), first.timestamp, last.timestamp);
+    let result = match checkpoint.format.as_str() {
+        "gif" => export::write_gif(&output, &frames, checkpoint.fps, &metadata, Some(&checkpoint)),
+        "mp4" => export::write_mp4(&output, &frames, checkpoint.fps, &metadata, Some(&checkpoint)),
+        _ => export::write_png_sequence(&output, &frames, &metadata, Some(&checkpoint)),
+    };
+    result.map_err(|e| e.to_string())?;
+    write_sources(&output, provider.as_ref(), frames.iter().map(|frame| frame.timestamp))?;
+    checkpoint.remove().map_err(|e| e.to_string())?;
+    println!("wrote {} frames to {}", frames.len(), output.display());
+    Ok(())
+}
+
+/// `nuage analyze <output>`: detect the cells and their motion on the two most
+/// recent frames of the range and write them, with their predicted positions,
+/// as GeoJSON or KML.
+pub fn analyze(args: &[String]) -> Result<(), String> {
+    let mut frame_args = FrameArgs::default();
+    let mut output = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if frame_args.parse(arg, &mut args)? {
+            continue;
+        }
+        match arg.as_str() {
+            _ if arg.starts_with("--") => return Err(format!("unknown argument {}", arg)),
+            _ => output = Some(PathBuf::from(arg)),
+        }
+    }
+    let output = output.ok_or("analyze expects an output file")?;
+    let provider = frame_args.provider()?;
+    let timepoints = frame_args.timepoints(provider.as_ref())?;
+
+    // The two most recent frames that can be read
+    let mut frames = vec![];
+    for timepoint in timepoints {
+        match provider.frame(timepoint) {
+            Ok(image) => frames.push(SatImage::new(image, timepoint)),
+            Err(e) => eprintln!("skipping {}: {}", timepoint, e),
+        }
+        if frames.len() == 2 {
+            break;
+        }
+    }
+    let [current, previous] = &frames[..] else {
+        return Err("analyze needs two frames".into());
+    };
+    let minutes = (current.timestamp - previous.timestamp).num_minutes() as f32;
+    let analysis = Analysis::new(&previous.image, &current.image, minutes);
+    let (tiles, zoom) = provider.region();
+    let georeference = Georeference::new(tiles, zoom, current.image.dimensions());
+    let features = geo::analysis_features(&analysis, &georeference);
+    geo::write_features(&output, &features).map_err(|e| e.to_string())?;
+    write_sources(&output, provider.as_ref(), [previous.timestamp, current.timestamp])?;
+    println!(
+        "{} cells, {} motion vectors at {}, written to {}",
+        analysis.cells.len(),
+        analysis.vectors.len(),
+        current.timestamp.format("%Y-%m-%d %H:%M UTC"),
+        output.display(),
+    );
+    Ok(())
+}
+
+/// `nuage motion <output>`: write the motion fields between the consecutive
+/// frames of the range as NetCDF (.nc) or NumPy (.npy), for nowcasting
+/// experiments.
+pub fn motion(args: &[String]) -> Result<(), String> {
+    let mut frame_args = FrameArgs::default();
+    let mut output = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if frame_args.parse(arg, &mut args)? {
+            continue;
+        }
+        match arg.as_str() {
+            _ if arg.starts_with("--") => return Err(format!("unknown argument {}", arg)),
+            _ => output = Some(PathBuf::from(arg)),
+        }
+    }
+    let output = output.ok_or("motion expects an output file")?;
+    let extension = output.extension().and_then(|extension| extension.to_str()).map(str::to_lowercase);
+    if !matches!(extension.as_deref(), Some("nc" | "npy")) {
+        return Err(format!("{}: expected a .nc or .npy file", output.display()));
+    }
+    let provider = frame_args.provider()?;
+    let mut timepoints = frame_args.timepoints(provider.as_ref())?;
+    timepoints.sort();
+
+    let mut frames: Vec<SatImage> = vec![];
+    for timepoint in timepoints {
+        match provider.frame(timepoint) {
+            // All the fields must have the same dimensions
+            Ok(image) if frames.first().is_some_and(|first| first.image.dimensions() != image.dimensions()) => {
+                eprintln!("skipping {}: not the size of the other frames", timepoint)
+            }
+            Ok(image) => frames.push(SatImage::new(image, timepoint)),
+            Err(e) => eprintln!("skipping {}: {}", timepoint, e),
+        }
+    }
+    let fields: Vec<(DateTime<Utc>, analysis::MotionField)> = frames
+        .windows(2)
+        .map(|pair| {
+            let minutes = (pair[1].timestamp - pair[0].timestamp).num_minutes().max(1) as f32;
+            (pair[1].timestamp, analysis::MotionField::between(&pair[0].image, &pair[1].image, minutes))
+        })
+        .collect();
+    if fields.is_empty() {
+        return Err("motion needs two frames".into());
+    }
+    let result = match extension.as_deref() {
+        Some("npy") => export::write_motion_npy(&output, &fields),
+        _ => {
+            let (tiles, zoom) = provider.region();
+            let georeference = Georeference::new(tiles, zoom, frames[0].image.dimensions());
+            let metadata = Metadata::new(provider.name(), (tiles, zoom), frames[0].timestamp, frames[frames.len() - 1].timestamp);
+            export::write_motion_netcdf(&output, &fields, &georeference, &metadata)
+        }
+    };
+    result.map_err(|e| e.to_string())?;
+    write_sources(&output, provider.as_ref(), frames.iter().map(|frame| frame.timestamp))?;
+    println!("{} motion fields written to {}", fields.len(), output.display());
+    Ok(())
+}
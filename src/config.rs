@@ -0,0 +1,371 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::accessibility::Announcements;
+use crate::annotation::Annotation;
+use crate::basemap::BasemapConfig;
+use crate::cache::CachePolicy;
+use crate::error::{NuageError, Result};
+use crate::hud::HudConfig;
+use crate::keymap::Keymap;
+use crate::lightning::LightningConfig;
+use crate::live::LiveConfig;
+use crate::metered::LowData;
+use crate::processing::ProcessingStep;
+use crate::projection::{Bounds, tiles_covering};
+use crate::provider::{Channel, TILES, Tiles, ZOOM};
+use crate::render::{Adjustments, Palette, TimeZone, TimestampFormat};
+use crate::scene::{ScheduleEntry, SceneConfig};
+use crate::template::TileServerConfig;
+use crate::timeline::HISTORY_HOURS;
+use crate::wind::WindConfig;
+use crate::wms::WmsConfig;
+use crate::wmts::WmtsConfig;
+
+/// A GeoJSON file drawn over the frames.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct OverlayConfig {
+    pub path: PathBuf,
+    /// Stroke color, (red, green, blue)
+    pub color: (u8, u8, u8),
+    /// Stroke width, in points
+    pub width: f32,
+}
+
+impl Default for OverlayConfig {
+    fn default() -> Self {
+        OverlayConfig {
+            path: PathBuf::new(),
+            color: (255, 200, 0),
+            width: 2.0,
+        }
+    }
+}
+
+/// When to warn about rain approaching home.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct RainAlertConfig {
+    /// Send a desktop notification when rain is on its way
+    pub notify: bool,
+    /// Radius around home in which clouds are looked for, in kilometers
+    pub radius_km: f64,
+    /// Fraction (0-1) of bright cloud in that radius meaning rain
+    pub cover: f32,
+    /// How far ahead to look, in minutes
+    pub horizon_minutes: u32,
+}
+
+impl Default for RainAlertConfig {
+    fn default() -> Self {
+        RainAlertConfig {
+            notify: true,
+            radius_km: 25.0,
+            cover: 0.5,
+            horizon_minutes: 60,
+        }
+    }
+}
+
+/// How the window is drawn.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub enum Renderer {
+    /// wgpu, falling back to OpenGL with fewer effects when it cannot start
+    #[default]
+    Auto,
+    Wgpu,
+    /// OpenGL with fewer effects, for old or software-rendered displays
+    Glow,
+}
+
+/// The configuration file, `$XDG_CONFIG_HOME/nuage/config.ron` (defaulting to
+/// `~/.config/nuage/config.ron`). Every field is optional:
+///
+/// ```ron
+/// (
+///     home: (2.3514, 48.8575),
+///     rain_alert: (notify: true, radius_km: 10.0, horizon_minutes: 45),
+///     overlays: [
+///         (path: "/home/me/hike.geojson", color: (255, 0, 0), width: 3.0),
+///     ],
+///     live: [
+///         (kind: Adsb, url: "http://raspberrypi:8080/data/aircraft.json", callsigns: ["AFR1234"]),
+///     ],
+///     hud: (top_right: [Clock, DataAge], bottom_left: [Timestamp]),
+/// )
+/// ```
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Config {
+    /// (longitude, latitude) of home, Paris by default
+    pub home: (f64, f64),
+    pub rain_alert: RainAlertConfig,
+    pub overlays: Vec<OverlayConfig>,
+    /// Live aircraft and vessel positions
+    pub live: Vec<LiveConfig>,
+    /// The labels shown in the corners of the view
+    pub hud: HudConfig,
+    /// Play in-between frames following the motion of the clouds, rather
+    /// than the raw frames
+    pub interpolate: bool,
+    /// Fade from one frame to the next when not playing in-between frames,
+    /// against flicker on large displays
+    pub crossfade: bool,
+    /// Pause the animation while the window is not focused, not to spend
+    /// power drawing frames nobody looks at
+    pub pause_unfocused: bool,
+    /// Seconds without a key pressed or the mouse used after which the
+    /// animation plays again when paused, not at all by default
+    pub resume_after: Option<u32>,
+    /// The processing steps of the frames of each channel, see
+    /// [`crate::processing`]
+    pub processing: HashMap<Channel, Vec<ProcessingStep>>,
+    /// Brightness, contrast and gamma of the frames, e.g. brighter for a dim
+    /// display at night
+    pub adjustments: Adjustments,
+    /// Colors given to the luminance of the frames
+    pub palette: Palette,
+    /// Display presets, and the times of the day they are shown at
+    pub scenes: Vec<SceneConfig>,
+    pub schedule: Vec<ScheduleEntry>,
+    /// Show the Open-Meteo forecast at home next to the frames at startup
+    pub forecast: bool,
+    /// Show the thumbnails of the frames under them at startup
+    pub filmstrip: bool,
+    /// Show whether rain is likely near home in the system tray, the window
+    /// only hiding when closed
+    pub tray: bool,
+    /// Live lightning strikes
+    pub lightning: LightningConfig,
+    /// Wind barbs from Open-Meteo
+    pub wind: WindConfig,
+    /// A map under the frames, OpenStreetMap by default
+    pub basemap: BasemapConfig,
+    /// When the frame shown is announced to screen readers
+    pub announcements: Announcements,
+    /// How the window is drawn
+    pub renderer: Renderer,
+    /// The time zone of the times shown, `Local` or `Utc`
+    pub time_zone: TimeZone,
+    /// How the times of the frames are written in the labels and in the
+    /// names of the files saved
+    pub timestamp_format: TimestampFormat,
+    /// Hours of frames shown, thinned out beyond 8 hours not to take too
+    /// much memory
+    pub history_hours: u32,
+    /// Minutes after which the frames are published, overriding that of the
+    /// provider
+    pub availability_delay: Option<u32>,
+    /// Minutes between two frames, overriding that of the provider for
+    /// products taken every 10 or 15 minutes
+    pub frame_interval: Option<u32>,
+    /// When to download smaller frames and poll the feeds less often, on
+    /// metered connections by default
+    pub low_data: LowData,
+    /// The keyboard shortcuts rebound
+    pub keys: Keymap,
+    /// How long the frames of each channel are kept in the cache, for ever
+    /// by default
+    pub cache_policies: HashMap<Channel, CachePolicy>,
+    /// The Infoplaza products of the channels other than the composite, e.g.
+    /// `{Infrared: "..."}`, see the README
+    pub channel_products: HashMap<Channel, String>,
+    /// A tile server of one's own to show the frames of instead of
+    /// Infoplaza's, see [`crate::template`]
+    pub tile_server: Option<TileServerConfig>,
+    /// A layer of a Web Map Service with a time dimension to show the
+    /// frames of instead of Infoplaza's, see [`crate::wms`]
+    pub wms: Option<WmsConfig>,
+    /// A layer of a Web Map Tile Service to show the frames of instead of
+    /// Infoplaza's, see [`crate::wmts`]
+    pub wmts: Option<WmtsConfig>,
+    /// The region presets shown in tabs, by name, each with frames of its
+    /// own
+    pub region_tabs: Vec<String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            home: (crate::PARIS.1 as f64, crate::PARIS.0 as f64),
+            rain_alert: RainAlertConfig::default(),
+            overlays: vec![],
+            live: vec![],
+            hud: HudConfig::default(),
+            interpolate: true,
+            crossfade: false,
+            pause_unfocused: true,
+            resume_after: None,
+            processing: HashMap::new(),
+            adjustments: Adjustments::default(),
+            palette: Palette::Original,
+            scenes: vec![],
+            schedule: vec![],
+            forecast: false,
+            filmstrip: false,
+            tray: false,
+            lightning: LightningConfig::default(),
+            wind: WindConfig::default(),
+            basemap: BasemapConfig::default(),
+            announcements: Announcements::default(),
+            renderer: Renderer::default(),
+            time_zone: TimeZone::default(),
+            timestamp_format: TimestampFormat::default(),
+            history_hours: HISTORY_HOURS,
+            availability_delay: None,
+            frame_interval: None,
+            low_data: LowData::Auto,
+            keys: Keymap::default(),
+            cache_policies: HashMap::new(),
+            channel_products: HashMap::new(),
+            tile_server: None,
+            wms: None,
+            wmts: None,
+            region_tabs: vec![],
+        }
+    }
+}
+
+impl Config {
+    /// The folder of the configuration file.
+    pub fn folder() -> Result<PathBuf> {
+        let standard_config_folder = match std::env::var("XDG_CONFIG_HOME") {
+            Ok(folder) => folder,
+            Err(_) => {
+                let username = std::env::var("USER")
+                    .map_err(|_| NuageError::Config("neither XDG_CONFIG_HOME nor USER are set".into()))?;
+                format!("/home/{}/.config", username)
+            }
+        };
+        Ok(PathBuf::from(standard_config_folder).join("nuage"))
+    }
+
+    /// Load the configuration file, the default configuration if there is
+    /// none.
+    pub fn load() -> Result<Config> {
+        let path = Config::folder()?.join("config.ron");
+        if !std::fs::exists(&path).map_err(NuageError::cache_io(&path))? {
+            return Ok(Config::default());
+        }
+        Config::load_from(&path)
+    }
+
+    pub fn load_from(path: &Path) -> Result<Config> {
+        let text = std::fs::read_to_string(path).map_err(NuageError::cache_io(path))?;
+        let config: Config = ron::from_str(&text).map_err(|e| NuageError::Config(format!("{}: {}", path.display(), e)))?;
+        config
+            .timestamp_format
+            .check()
+            .map_err(|e| NuageError::Config(format!("{}: {}", path.display(), e)))?;
+        config.keys.check().map_err(|e| NuageError::Config(format!("{}: {}", path.display(), e)))?;
+        if config.history_hours == 0 {
+            return Err(NuageError::Config(format!("{}: history_hours must be at least 1", path.display())));
+        }
+        if config.frame_interval.is_some_and(|interval| !crate::timeline::is_valid_interval(interval)) {
+            return Err(NuageError::Config(format!("{}: frame_interval must divide a day, like 5, 10 or 15", path.display())));
+        }
+        Ok(config)
+    }
+}
+
+/// A saved session, `*.nuage`: where the frames come from, the overlays and
+/// the annotations. Written with Ctrl+S and restored by dropping it on the
+/// window.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Session {
+    /// The folder played instead of downloading the frames, if any
+    pub local_folder: Option<PathBuf>,
+    pub overlays: Vec<OverlayConfig>,
+    pub annotations: Vec<Annotation>,
+}
+
+impl Session {
+    pub fn load(path: &Path) -> Result<Session> {
+        let text = std::fs::read_to_string(path).map_err(NuageError::cache_io(path))?;
+        ron::from_str(&text).map_err(|e| NuageError::Config(format!("{}: {}", path.display(), e)))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let text = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|e| NuageError::Config(format!("{}: {}", path.display(), e)))?;
+        std::fs::write(path, text).map_err(NuageError::cache_io(path))
+    }
+}
+
+/// The regions shipped with nuage besides western europe: name, bounds and
+/// zoom level.
+const BUILT_IN_REGIONS: [(&str, Bounds, u16); 8] = [
+    ("British Isles", Bounds { west: -11., south: 49.5, east: 2.5, north: 61. }, 7),
+    ("Scandinavia", Bounds { west: 4., south: 54.5, east: 32., north: 71.5 }, 6),
+    ("Iberia", Bounds { west: -10., south: 35.5, east: 4.5, north: 44.5 }, 7),
+    ("France", Bounds { west: -5.5, south: 41., east: 10., north: 51.5 }, 7),
+    ("Benelux", Bounds { west: 2., south: 49.3, east: 7.3, north: 53.7 }, 8),
+    ("Alps", Bounds { west: 5., south: 43.5, east: 16.5, north: 48.5 }, 8),
+    ("Italy", Bounds { west: 6.5, south: 36.5, east: 19., north: 47.2 }, 7),
+    ("Central Europe", Bounds { west: 5., south: 45., east: 25., north: 55. }, 7),
+];
+
+/// A named tile window: shipped with nuage, or saved from the region window
+/// and kept in `regions.ron` next to the configuration file so that the
+/// latter is never rewritten.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RegionPreset {
+    pub name: String,
+    pub tiles: Tiles,
+    pub zoom: u16,
+}
+
+impl RegionPreset {
+    fn path() -> Result<PathBuf> {
+        Ok(Config::folder()?.join("regions.ron"))
+    }
+
+    /// The presets shipped with nuage, western europe, the default window,
+    /// first.
+    pub fn built_in() -> Vec<RegionPreset> {
+        let mut presets = vec![RegionPreset { name: "Western Europe".into(), tiles: TILES, zoom: ZOOM }];
+        presets.extend(BUILT_IN_REGIONS.iter().map(|(name, bounds, zoom)| RegionPreset {
+            name: name.to_string(),
+            tiles: tiles_covering((bounds.west, bounds.north), (bounds.east, bounds.south), *zoom),
+            zoom: *zoom,
+        }));
+        presets
+    }
+
+    /// The preset named `name`, saved or built in, ignoring case and taking
+    /// dashes and underscores for spaces: `british-isles` is the British
+    /// Isles.
+    pub fn find(name: &str) -> Option<RegionPreset> {
+        let normalize = |name: &str| name.to_lowercase().replace(['-', '_'], " ");
+        let mut presets = RegionPreset::load_all().unwrap_or_default();
+        presets.extend(RegionPreset::built_in());
+        presets.into_iter().find(|preset| normalize(&preset.name) == normalize(name))
+    }
+
+    /// The saved presets, none if there is no `regions.ron`.
+    pub fn load_all() -> Result<Vec<RegionPreset>> {
+        let path = RegionPreset::path()?;
+        if !std::fs::exists(&path).map_err(NuageError::cache_io(&path))? {
+            return Ok(vec![]);
+        }
+        let text = std::fs::read_to_string(&path).map_err(NuageError::cache_io(&path))?;
+        ron::from_str(&text).map_err(|e| NuageError::Config(format!("{}: {}", path.display(), e)))
+    }
+
+    /// Save the preset, replacing the one of the same name.
+    pub fn save(&self) -> Result<Vec<RegionPreset>> {
+        let mut presets = RegionPreset::load_all()?;
+        presets.retain(|preset| preset.name != self.name);
+        presets.push(self.clone());
+        let path = RegionPreset::path()?;
+        let folder = Config::folder()?;
+        std::fs::create_dir_all(&folder).map_err(NuageError::cache_io(&folder))?;
+        let text = ron::ser::to_string_pretty(&presets, ron::ser::PrettyConfig::default())
+            .map_err(|e| NuageError::Config(format!("{}: {}", path.display(), e)))?;
+        std::fs::write(&path, text).map_err(NuageError::cache_io(&path))?;
+        Ok(presets)
+    }
+}
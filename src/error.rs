@@ -0,0 +1,33 @@
+use std::path::PathBuf;
+
+/// Everything that can go wrong while getting frames.
+#[derive(Debug, thiserror::Error)]
+pub enum NuageError {
+    #[error("network error: {0}")]
+    Network(#[from] ureq::Error),
+    #[error("could not decode image: {0}")]
+    Decode(#[from] image::ImageError),
+    #[error("cache error on {path}: {source}")]
+    CacheIo {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("export error: {0}")]
+    Export(String),
+    #[error("configuration error: {0}")]
+    Config(String),
+    #[error("{0} is missing at the provider, not asked again for a while")]
+    Missing(String),
+    #[error("websocket error: {0}")]
+    WebSocket(String),
+}
+
+impl NuageError {
+    /// Wrap an IO error with the path it happened on.
+    pub fn cache_io(path: impl Into<PathBuf>) -> impl FnOnce(std::io::Error) -> NuageError {
+        let path = path.into();
+        move |source| NuageError::CacheIo { path, source }
+    }
+}
+
+pub type Result<T> = std::result::Result<T, NuageError>;
@@ -0,0 +1,718 @@
+use chrono::{DateTime, Utc};
+use image::codecs::gif::GifEncoder;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use tiff::encoder::{TiffEncoder, colortype};
+use tiff::tags::Tag;
+
+use crate::analysis::MotionField;
+use crate::error::{NuageError, Result};
+use crate::projection::{Bounds, Georeference, lonlat_to_mercator, tiles_bounds};
+use crate::provider::Tiles;
+use crate::timeline::SatImage;
+
+// GeoTIFF tags and keys, see http://docs.opengeospatial.org/is/19-008r4/19-008r4.html
+const MODEL_PIXEL_SCALE_TAG: u16 = 33550;
+const MODEL_TIEPOINT_TAG: u16 = 33922;
+const GEO_KEY_DIRECTORY_TAG: u16 = 34735;
+const GT_MODEL_TYPE_GEO_KEY: u16 = 1024;
+const GT_RASTER_TYPE_GEO_KEY: u16 = 1025;
+const PROJECTED_CS_TYPE_GEO_KEY: u16 = 3072;
+const MODEL_TYPE_PROJECTED: u16 = 1;
+const RASTER_PIXEL_IS_AREA: u16 = 1;
+const EPSG_WEB_MERCATOR: u16 = 3857;
+const XMP_TAG: u16 = 700;
+
+fn export_error(path: &Path, e: impl std::fmt::Display) -> NuageError {
+    NuageError::Export(format!("{}: {}", path.display(), e))
+}
+
+/// What an export shows, embedded in it so that a shared file describes
+/// itself: the time range of its frames, their bounds, where they come from
+/// and the version of nuage that wrote it.
+#[derive(Clone, Debug)]
+pub struct Metadata {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub bounds: Bounds,
+    pub provider: String,
+}
+
+/// The name and version of the application, as written in exports.
+const SOFTWARE: &str = concat!("nuage ", env!("CARGO_PKG_VERSION"));
+
+impl Metadata {
+    /// The frames of `provider` from `from` to `to`, covering the tile window
+    /// `tiles` at `zoom`.
+    pub fn new(provider: &str, (tiles, zoom): (Tiles, u16), from: DateTime<Utc>, to: DateTime<Utc>) -> Self {
+        Metadata {
+            from,
+            to,
+            bounds: tiles_bounds(tiles, zoom),
+            provider: provider.to_string(),
+        }
+    }
+
+    /// A sentence for comments and descriptions.
+    pub fn description(&self) -> String {
+        let Bounds { west, south, east, north } = self.bounds;
+        format!(
+            "Satellite frames from {} to {} by {}, longitudes {:.3} to {:.3}, latitudes {:.3} to {:.3}",
+            self.from.format("%Y-%m-%d %H:%M UTC"),
+            self.to.format("%Y-%m-%d %H:%M UTC"),
+            self.provider,
+            west,
+            east,
+            south,
+            north,
+        )
+    }
+
+    /// The metadata as keys and values, for the formats with text tags.
+    pub fn fields(&self) -> Vec<(&'static str, String)> {
+        let Bounds { west, south, east, north } = self.bounds;
+        vec![
+            ("description", self.description()),
+            ("start", self.from.format("%Y-%m-%dT%H:%M:%SZ").to_string()),
+            ("end", self.to.format("%Y-%m-%dT%H:%M:%SZ").to_string()),
+            ("bounds", format!("{},{},{},{}", west, south, east, north)),
+            ("source", self.provider.clone()),
+            ("software", SOFTWARE.to_string()),
+        ]
+    }
+
+    /// The metadata as an XMP packet, the fields being in the nuage namespace
+    /// besides the Dublin Core description and source.
+    pub fn xmp(&self) -> String {
+        let escape = |text: &str| text.replace('&', "&amp;").replace('<', "&lt;").replace('"', "&quot;");
+        let fields: Vec<String> = self
+            .fields()
+            .iter()
+            .map(|(name, value)| match *name {
+                "description" | "source" => format!("dc:{}=\"{}\"", name, escape(value)),
+                "software" => format!("xmp:CreatorTool=\"{}\"", escape(value)),
+                _ => format!("nuage:{}=\"{}\"", name, escape(value)),
+            })
+            .collect();
+        format!(
+            concat!(
+                "<?xpacket begin=\"\u{feff}\" id=\"W5M0MpCehiHzreSzNTczkc9d\"?>",
+                "<x:xmpmeta xmlns:x=\"adobe:ns:meta/\"><rdf:RDF xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\">",
+                "<rdf:Description rdf:about=\"\" xmlns:dc=\"http://purl.org/dc/elements/1.1/\" ",
+                "xmlns:xmp=\"http://ns.adobe.com/xap/1.0/\" xmlns:nuage=\"https://github.com/jdmichaud/nuage/ns/1.0/\" {}/>",
+                "</rdf:RDF></x:xmpmeta><?xpacket end=\"r\"?>",
+            ),
+            fields.join(" "),
+        )
+    }
+}
+
+/// Write `image`, covering the tile window `tiles` at `zoom`, as a GeoTIFF in
+/// Web Mercator (EPSG:3857) so it can be opened in a GIS like QGIS, with
+/// `metadata` in its description and XMP tags.
+pub fn write_geotiff(path: &Path, image: &image::RgbImage, tiles: Tiles, zoom: u16, metadata: &Metadata) -> Result<()> {
+    let bounds = tiles_bounds(tiles, zoom);
+    let (left, top) = lonlat_to_mercator(bounds.west, bounds.north);
+    let (right, bottom) = lonlat_to_mercator(bounds.east, bounds.south);
+    let (width, height) = image.dimensions();
+    let pixel_scale = [(right - left) / width as f64, (top - bottom) / height as f64, 0.0];
+    // The top-left pixel corner is at (left, top)
+    let tiepoint = [0.0, 0.0, 0.0, left, top, 0.0];
+    let geo_keys: [u16; 16] = [
+        // version, revision, minor revision, number of keys
+        1, 1, 0, 3,
+        GT_MODEL_TYPE_GEO_KEY, 0, 1, MODEL_TYPE_PROJECTED,
+        GT_RASTER_TYPE_GEO_KEY, 0, 1, RASTER_PIXEL_IS_AREA,
+        PROJECTED_CS_TYPE_GEO_KEY, 0, 1, EPSG_WEB_MERCATOR,
+    ];
+
+    let file = std::fs::File::create(path).map_err(NuageError::cache_io(path))?;
+    let mut encoder = TiffEncoder::new(std::io::BufWriter::new(file)).map_err(|e| export_error(path, e))?;
+    let mut tiff_image = encoder
+        .new_image::<colortype::RGB8>(width, height)
+        .map_err(|e| export_error(path, e))?;
+    let directory = tiff_image.encoder();
+    directory
+        .write_tag(Tag::Unknown(MODEL_PIXEL_SCALE_TAG), &pixel_scale[..])
+        .and_then(|_| directory.write_tag(Tag::Unknown(MODEL_TIEPOINT_TAG), &tiepoint[..]))
+        .and_then(|_| directory.write_tag(Tag::Unknown(GEO_KEY_DIRECTORY_TAG), &geo_keys[..]))
+        .and_then(|_| directory.write_tag(Tag::ImageDescription, metadata.description().as_str()))
+        .and_then(|_| directory.write_tag(Tag::Software, SOFTWARE))
+        .and_then(|_| directory.write_tag(Tag::DateTime, metadata.to.format("%Y:%m:%d %H:%M:%S").to_string().as_str()))
+        .and_then(|_| directory.write_tag(Tag::Unknown(XMP_TAG), metadata.xmp().as_bytes()))
+        .map_err(|e| export_error(path, e))?;
+    tiff_image.write_data(image.as_raw()).map_err(|e| export_error(path, e))?;
+    Ok(())
+}
+
+/// The frames an export was derived from: their time and the SHA-256 of their
+/// original bytes, when known.
+pub type Sources = [(DateTime<Utc>, Option<String>)];
+
+/// Frames of an MP4 encoded at once, as a segment kept until the export is
+/// complete.
+const SEGMENT_FRAMES: usize = 50;
+
+/// Write `bytes` to `path` whole or not at all, through a temporary file, so
+/// that an interrupted export leaves no truncated part.
+fn write_whole(path: &Path, bytes: &[u8]) -> Result<()> {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".tmp");
+    let temporary = PathBuf::from(name);
+    std::fs::write(&temporary, bytes).map_err(NuageError::cache_io(&temporary))?;
+    std::fs::rename(&temporary, path).map_err(NuageError::cache_io(path))
+}
+
+/// The progress of an export, kept in `<output>.parts` next to the output so
+/// that it can be resumed after a crash or Ctrl+C: `checkpoint.txt` with the
+/// format, the frame rate and the times of the frames, and the GIF frames or
+/// MP4 segments encoded so far, each written whole. PNG frames are written
+/// whole in the output folder. Removed once the export is complete.
+#[derive(Clone, Debug)]
+pub struct Checkpoint {
+    pub folder: PathBuf,
+    pub format: String,
+    pub fps: f32,
+    /// The times of the frames, oldest first
+    pub timestamps: Vec<DateTime<Utc>>,
+    /// Whether the export was interrupted before, what is already written
+    /// being kept
+    pub resumed: bool,
+}
+
+impl Checkpoint {
+    /// The folder of the progress of the export `output`.
+    pub fn folder_of(output: &Path) -> PathBuf {
+        let mut name = output.file_name().unwrap_or(output.as_os_str()).to_owned();
+        name.push(".parts");
+        output.with_file_name(name)
+    }
+
+    /// Start an export to `output`, forgetting any interrupted one.
+    pub fn start(output: &Path, format: &str, fps: f32, timestamps: Vec<DateTime<Utc>>) -> Result<Self> {
+        let folder = Checkpoint::folder_of(output);
+        if std::fs::exists(&folder).map_err(NuageError::cache_io(&folder))? {
+            std::fs::remove_dir_all(&folder).map_err(NuageError::cache_io(&folder))?;
+        }
+        std::fs::create_dir_all(&folder).map_err(NuageError::cache_io(&folder))?;
+        let mut text = format!("format {}\nfps {}\n", format, fps);
+        for timestamp in &timestamps {
+            text.push_str(&format!("frame {}\n", timestamp.format("%Y-%m-%dT%H:%M:%SZ")));
+        }
+        write_whole(&folder.join("checkpoint.txt"), text.as_bytes())?;
+        Ok(Checkpoint { folder, format: format.to_string(), fps, timestamps, resumed: false })
+    }
+
+    /// The interrupted export to `output`, if any.
+    pub fn load(output: &Path) -> Result<Option<Self>> {
+        let folder = Checkpoint::folder_of(output);
+        let path = folder.join("checkpoint.txt");
+        if !std::fs::exists(&path).map_err(NuageError::cache_io(&path))? {
+            return Ok(None);
+        }
+        let text = std::fs::read_to_string(&path).map_err(NuageError::cache_io(&path))?;
+        let mut checkpoint = Checkpoint { folder, format: String::new(), fps: 0., timestamps: vec![], resumed: true };
+        for line in text.lines() {
+            let invalid = || export_error(&path, format!("invalid line {}", line));
+            match line.split_once(' ').ok_or_else(invalid)? {
+                ("format", format) => checkpoint.format = format.to_string(),
+                ("fps", fps) => checkpoint.fps = fps.parse().map_err(|_| invalid())?,
+                ("frame", timestamp) => {
+                    let timestamp = DateTime::parse_from_rfc3339(timestamp).map_err(|_| invalid())?;
+                    checkpoint.timestamps.push(timestamp.with_timezone(&Utc));
+                }
+                _ => return Err(invalid()),
+            }
+        }
+        Ok(Some(checkpoint))
+    }
+
+    /// The path of the part `name` of the export.
+    fn part(&self, name: &str) -> PathBuf {
+        self.folder.join(name)
+    }
+
+    /// The part `name` written before the export was interrupted, if any.
+    fn written(&self, name: &str) -> Result<Option<Vec<u8>>> {
+        let path = self.part(name);
+        if !self.resumed || !std::fs::exists(&path).map_err(NuageError::cache_io(&path))? {
+            return Ok(None);
+        }
+        std::fs::read(&path).map(Some).map_err(NuageError::cache_io(&path))
+    }
+
+    /// Forget the progress, once the export is complete.
+    pub fn remove(&self) -> Result<()> {
+        std::fs::remove_dir_all(&self.folder).map_err(NuageError::cache_io(&self.folder))
+    }
+}
+
+/// Where the sources of the export `output` are listed: `sources.txt` in a
+/// folder of images, `<output>.sources.txt` next to a file.
+pub fn sources_path(output: &Path) -> std::path::PathBuf {
+    if output.is_dir() {
+        output.join("sources.txt")
+    } else {
+        let mut name = output.as_os_str().to_owned();
+        name.push(".sources.txt");
+        name.into()
+    }
+}
+
+/// Write the sources of the export `output` from `provider`, a line per frame
+/// with its UTC time and SHA-256, so an analysis can later be checked against
+/// the frames it was made from.
+pub fn write_sources(output: &Path, provider: &str, sources: &Sources) -> Result<()> {
+    let path = sources_path(output);
+    let mut text = format!("# frames of {} from {}, time and SHA-256 as downloaded\n", output.display(), provider);
+    for (timestamp, checksum) in sources {
+        text.push_str(&format!(
+            "{} {}\n",
+            timestamp.format("%Y-%m-%dT%H:%M:%SZ"),
+            checksum.as_deref().unwrap_or("unknown"),
+        ));
+    }
+    std::fs::write(&path, text).map_err(|e| export_error(&path, e))
+}
+
+/// Append a PNG chunk of `kind` with `data`.
+pub(crate) fn push_png_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend((data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend(kind);
+    png.extend(data);
+    let crc = crc32fast::hash(&png[start..]);
+    png.extend(crc.to_be_bytes());
+}
+
+/// Encode `image` as a PNG with `metadata` as text chunks, one per field
+/// with its frame time, and XMP.
+fn encode_png(image: &image::RgbImage, timestamp: DateTime<Utc>, metadata: &Metadata) -> Result<Vec<u8>> {
+    let mut png = vec![];
+    image.write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)?;
+    // The text goes before the IEND chunk, the last 12 bytes
+    let end = png.split_off(png.len() - 12);
+    let mut fields = vec![("Creation Time", timestamp.to_rfc2822())];
+    fields.extend(metadata.fields().into_iter().map(|(name, value)| match name {
+        "description" => ("Description", value),
+        "source" => ("Source", value),
+        "software" => ("Software", value),
+        _ => (name, value),
+    }));
+    for (name, value) in fields {
+        // tEXt is Latin-1, the values are ASCII but for the provider
+        let value: String = value.chars().map(|c| if c.is_ascii() { c } else { '?' }).collect();
+        push_png_chunk(&mut png, b"tEXt", &[name.as_bytes(), b"\0", value.as_bytes()].concat());
+    }
+    // Uncompressed international text, with no language
+    let xmp = [b"XML:com.adobe.xmp\0\0\0\0\0".as_slice(), metadata.xmp().as_bytes()].concat();
+    push_png_chunk(&mut png, b"iTXt", &xmp);
+    png.extend(end);
+    Ok(png)
+}
+
+/// Write the frames, oldest first, as `frame_0001.png`, `frame_0002.png`, ...
+/// in `folder`, with `metadata` and the time of each frame. The frames
+/// written before a resumed export was interrupted are kept.
+pub fn write_png_sequence(folder: &Path, frames: &[SatImage], metadata: &Metadata, checkpoint: Option<&Checkpoint>) -> Result<()> {
+    std::fs::create_dir_all(folder).map_err(NuageError::cache_io(folder))?;
+    let resumed = checkpoint.is_some_and(|checkpoint| checkpoint.resumed);
+    for (index, frame) in frames.iter().enumerate() {
+        let path = folder.join(format!("frame_{:04}.png", index + 1));
+        if resumed && std::fs::exists(&path).map_err(NuageError::cache_io(&path))? {
+            continue;
+        }
+        let png = encode_png(&frame.image, frame.timestamp, metadata)?;
+        write_whole(&path, &png)?;
+    }
+    Ok(())
+}
+
+/// Append `text` as GIF data sub-blocks, of 255 bytes at most, and their
+/// terminator.
+fn push_gif_blocks(gif: &mut Vec<u8>, text: &[u8]) {
+    for block in text.chunks(255) {
+        gif.push(block.len() as u8);
+        gif.extend(block);
+    }
+    gif.push(0);
+}
+
+/// The length of the header and logical screen descriptor of a GIF, before
+/// its global color table if any.
+const GIF_HEADER: usize = 13;
+
+/// A frame of a GIF of `width` by `height` shown for `delay`, black where
+/// the frame is smaller: its graphic control extension, its image and its
+/// local color table.
+fn gif_frame(frame: &SatImage, width: u32, height: u32, delay: image::Delay) -> Result<Vec<u8>> {
+    let mut canvas = image::RgbaImage::from_pixel(width, height, image::Rgba([0, 0, 0, 255]));
+    image::imageops::replace(&mut canvas, &image::DynamicImage::ImageRgb8(frame.image.clone()).to_rgba8(), 0, 0);
+    // Encoded alone, the frame is between the header, with the global color
+    // table left unused, and the trailer
+    let mut gif = vec![];
+    GifEncoder::new_with_speed(&mut gif, 10).encode_frame(image::Frame::from_parts(canvas, 0, 0, delay))?;
+    let flags = gif[GIF_HEADER - 3];
+    let color_table = if flags & 0x80 != 0 { 3 << ((flags & 0x07) + 1) } else { 0 };
+    Ok(gif[GIF_HEADER + color_table..gif.len() - 1].to_vec())
+}
+
+/// Write the frames, oldest first, as a looping GIF animation showing `fps`
+/// frames per second, with `metadata` as a comment and XMP. Frames are
+/// cropped to the size of the first one. With a checkpoint, each frame is
+/// kept as it is encoded, those of an interrupted export not being encoded
+/// again.
+pub fn write_gif(path: &Path, frames: &[SatImage], fps: f32, metadata: &Metadata, checkpoint: Option<&Checkpoint>) -> Result<()> {
+    let Some(first) = frames.first() else {
+        return Err(NuageError::Export("no frame to export".into()));
+    };
+    let (width, height) = first.image.dimensions();
+    let (width, height) = (width.min(u16::MAX as u32), height.min(u16::MAX as u32));
+    let delay = image::Delay::from_saturating_duration(std::time::Duration::from_secs_f32(1. / fps));
+
+    let mut gif = b"GIF89a".to_vec();
+    gif.extend((width as u16).to_le_bytes());
+    gif.extend((height as u16).to_le_bytes());
+    // No global color table
+    gif.extend([0, 0, 0]);
+    // Loop forever
+    gif.extend(b"\x21\xff\x0bNETSCAPE2.0\x03\x01\x00\x00\x00");
+    // Comment extension
+    gif.extend([0x21, 0xfe]);
+    let comment: Vec<String> = metadata.fields().iter().map(|(name, value)| format!("{}: {}", name, value)).collect();
+    push_gif_blocks(&mut gif, comment.join("\n").as_bytes());
+    // XMP application extension: the packet as is, followed by a "magic
+    // trailer" that makes it readable as sub-blocks
+    gif.extend(b"\x21\xff\x0bXMP DataXMP");
+    gif.extend(metadata.xmp().as_bytes());
+    gif.push(1);
+    gif.extend((0..=255u8).rev());
+    gif.push(0);
+    for (index, frame) in frames.iter().enumerate() {
+        let name = format!("frame_{:04}.gif", index + 1);
+        if let Some(written) = checkpoint.map(|checkpoint| checkpoint.written(&name)).transpose()?.flatten() {
+            gif.extend(written);
+            continue;
+        }
+        let encoded = gif_frame(frame, width, height, delay)?;
+        if let Some(checkpoint) = checkpoint {
+            write_whole(&checkpoint.part(&name), &encoded)?;
+        }
+        gif.extend(encoded);
+    }
+    gif.push(0x3b);
+    write_whole(path, &gif)
+}
+
+/// Write the frames, oldest first, as an H.264 MP4 video by piping them to
+/// `ffmpeg`, which must be installed, with `metadata` as container tags.
+/// Frames are resized to the size of the first one. With a checkpoint, the
+/// frames are encoded by segments of `SEGMENT_FRAMES` then joined, those of
+/// an interrupted export not being encoded again.
+pub fn write_mp4(path: &Path, frames: &[SatImage], fps: f32, metadata: &Metadata, checkpoint: Option<&Checkpoint>) -> Result<()> {
+    let Some(first) = frames.first() else {
+        return Err(NuageError::Export("no frame to export".into()));
+    };
+    let (width, height) = first.image.dimensions();
+    // yuv420p needs even dimensions
+    let (width, height) = (width & !1, height & !1);
+    // Tags other than the standard ones need use_metadata_tags
+    let mut tags = vec![
+        format!("title=nuage {} to {}", metadata.from.format("%Y-%m-%d %H:%M"), metadata.to.format("%Y-%m-%d %H:%M UTC")),
+        format!("creation_time={}", metadata.to.to_rfc3339()),
+        format!("comment={}", metadata.description()),
+    ];
+    tags.extend(metadata.fields().into_iter().map(|(name, value)| format!("{}={}", name, value)));
+    let Some(checkpoint) = checkpoint else {
+        return encode_mp4(path, frames, fps, (width, height), &tags);
+    };
+    let mut list = String::new();
+    for (index, segment) in frames.chunks(SEGMENT_FRAMES).enumerate() {
+        let name = format!("segment_{:04}.mp4", index + 1);
+        let part = checkpoint.part(&name);
+        if !checkpoint.resumed || !std::fs::exists(&part).map_err(NuageError::cache_io(&part))? {
+            // Written whole, ffmpeg telling the container by the extension
+            let temporary = checkpoint.part(&format!("segment_{:04}.tmp.mp4", index + 1));
+            encode_mp4(&temporary, segment, fps, (width, height), &[])?;
+            std::fs::rename(&temporary, &part).map_err(NuageError::cache_io(&part))?;
+        }
+        list.push_str(&format!("file '{}'\n", name));
+    }
+    let list_path = checkpoint.part("segments.txt");
+    std::fs::write(&list_path, list).map_err(NuageError::cache_io(&list_path))?;
+    let status = std::process::Command::new("ffmpeg")
+        .args(["-y", "-loglevel", "error", "-f", "concat", "-safe", "0", "-i"])
+        .arg(&list_path)
+        .args(["-c", "copy", "-movflags", "use_metadata_tags"])
+        .args(tags.iter().flat_map(|tag| ["-metadata", tag]))
+        .arg(path)
+        .status()
+        .map_err(|e| NuageError::Export(format!("could not run ffmpeg: {}", e)))?;
+    if !status.success() {
+        return Err(NuageError::Export(format!("ffmpeg failed: {}", status)));
+    }
+    Ok(())
+}
+
+/// Encode the frames to `path` with `ffmpeg`, resized to `(width, height)`,
+/// with the container `tags`.
+fn encode_mp4(path: &Path, frames: &[SatImage], fps: f32, (width, height): (u32, u32), tags: &[String]) -> Result<()> {
+    let mut ffmpeg = std::process::Command::new("ffmpeg")
+        .args(["-y", "-loglevel", "error", "-f", "rawvideo", "-pix_fmt", "rgb24"])
+        .args(["-s", &format!("{}x{}", width, height), "-r", &fps.to_string(), "-i", "-"])
+        .args(["-c:v", "libx264", "-pix_fmt", "yuv420p", "-movflags", "use_metadata_tags"])
+        .args(tags.iter().flat_map(|tag| ["-metadata", tag]))
+        .arg(path)
+        .stdin(std::process::Stdio::piped())
+        .spawn()
+        .map_err(|e| NuageError::Export(format!("could not run ffmpeg: {}", e)))?;
+    let mut stdin = ffmpeg.stdin.take().unwrap();
+    for frame in frames {
+        let image = if frame.image.dimensions() == (width, height) {
+            std::borrow::Cow::Borrowed(&frame.image)
+        } else {
+            std::borrow::Cow::Owned(image::imageops::resize(&frame.image, width, height, image::imageops::FilterType::Triangle))
+        };
+        stdin
+            .write_all(image.as_raw())
+            .map_err(|e| NuageError::Export(format!("could not write to ffmpeg: {}", e)))?;
+    }
+    drop(stdin);
+    let status = ffmpeg
+        .wait()
+        .map_err(|e| NuageError::Export(format!("ffmpeg failed: {}", e)))?;
+    if !status.success() {
+        return Err(NuageError::Export(format!("ffmpeg failed: {}", status)));
+    }
+    Ok(())
+}
+
+/// The motion between consecutive frames, by the time of the newer one, all
+/// of the same dimensions.
+pub type MotionSeries = [(DateTime<Utc>, MotionField)];
+
+/// Write motion fields as a NumPy array of shape (time, rows, columns, 2),
+/// in little-endian float32: the motion of each block, right and down, in
+/// pixels per minute.
+pub fn write_motion_npy(path: &Path, fields: &MotionSeries) -> Result<()> {
+    let Some((_, first)) = fields.first() else {
+        return Err(NuageError::Export("no motion to export".into()));
+    };
+    let (columns, rows) = first.dimensions();
+    let header = format!(
+        "{{'descr': '<f4', 'fortran_order': False, 'shape': ({}, {}, {}, 2), }}",
+        fields.len(),
+        rows,
+        columns,
+    );
+    // The data starts on a multiple of 64 bytes, the header ends with a new line
+    let padding = 63 - (10 + header.len()) % 64;
+    let header = format!("{}{}\n", header, " ".repeat(padding));
+    let mut npy = b"\x93NUMPY\x01\x00".to_vec();
+    npy.extend((header.len() as u16).to_le_bytes());
+    npy.extend(header.as_bytes());
+    for (_, field) in fields {
+        for (dx, dy) in field.vectors() {
+            npy.extend(dx.to_le_bytes());
+            npy.extend(dy.to_le_bytes());
+        }
+    }
+    std::fs::write(path, npy).map_err(NuageError::cache_io(path))
+}
+
+/// The values of a NetCDF variable.
+enum NetcdfValues {
+    Double(Vec<f64>),
+    Float(Vec<f32>),
+}
+
+/// A NetCDF variable: its name, the indices of its dimensions, its text
+/// attributes and values.
+struct NetcdfVariable {
+    name: &'static str,
+    dimensions: Vec<u32>,
+    attributes: Vec<(&'static str, String)>,
+    values: NetcdfValues,
+}
+
+// NetCDF classic format tags, see https://docs.unidata.ucar.edu/netcdf-c/current/file_format_specifications.html
+const NC_DIMENSION: u32 = 0x0a;
+const NC_VARIABLE: u32 = 0x0b;
+const NC_ATTRIBUTE: u32 = 0x0c;
+const NC_CHAR: u32 = 2;
+const NC_FLOAT: u32 = 5;
+const NC_DOUBLE: u32 = 6;
+
+/// Append `bytes` padded with zeros to a multiple of 4 bytes.
+fn push_padded(buffer: &mut Vec<u8>, bytes: &[u8]) {
+    buffer.extend(bytes);
+    buffer.resize(buffer.len().next_multiple_of(4), 0);
+}
+
+fn push_name(buffer: &mut Vec<u8>, name: &str) {
+    buffer.extend((name.len() as u32).to_be_bytes());
+    push_padded(buffer, name.as_bytes());
+}
+
+fn push_attributes(buffer: &mut Vec<u8>, attributes: &[(&str, String)]) {
+    if attributes.is_empty() {
+        buffer.extend([0; 8]);
+        return;
+    }
+    buffer.extend(NC_ATTRIBUTE.to_be_bytes());
+    buffer.extend((attributes.len() as u32).to_be_bytes());
+    for (name, value) in attributes {
+        push_name(buffer, name);
+        buffer.extend(NC_CHAR.to_be_bytes());
+        buffer.extend((value.len() as u32).to_be_bytes());
+        push_padded(buffer, value.as_bytes());
+    }
+}
+
+/// Write a NetCDF classic file of fixed size dimensions.
+fn write_netcdf(path: &Path, dimensions: &[(&str, usize)], attributes: &[(&str, String)], variables: &[NetcdfVariable]) -> Result<()> {
+    let mut header = b"CDF\x01".to_vec();
+    // No record dimension
+    header.extend(0u32.to_be_bytes());
+    header.extend(NC_DIMENSION.to_be_bytes());
+    header.extend((dimensions.len() as u32).to_be_bytes());
+    for (name, size) in dimensions {
+        push_name(&mut header, name);
+        header.extend((*size as u32).to_be_bytes());
+    }
+    push_attributes(&mut header, attributes);
+    header.extend(NC_VARIABLE.to_be_bytes());
+    header.extend((variables.len() as u32).to_be_bytes());
+    // Where the offset of the data of each variable goes, known once the
+    // header is complete
+    let mut offsets = vec![];
+    let mut data = vec![];
+    for variable in variables {
+        push_name(&mut header, variable.name);
+        header.extend((variable.dimensions.len() as u32).to_be_bytes());
+        for dimension in &variable.dimensions {
+            header.extend(dimension.to_be_bytes());
+        }
+        push_attributes(&mut header, &variable.attributes);
+        let start = data.len();
+        let nc_type = match &variable.values {
+            NetcdfValues::Double(values) => {
+                values.iter().for_each(|value| data.extend(value.to_be_bytes()));
+                NC_DOUBLE
+            }
+            NetcdfValues::Float(values) => {
+                values.iter().for_each(|value| data.extend(value.to_be_bytes()));
+                NC_FLOAT
+            }
+        };
+        data.resize(data.len().next_multiple_of(4), 0);
+        header.extend(nc_type.to_be_bytes());
+        header.extend(((data.len() - start) as u32).to_be_bytes());
+        offsets.push((header.len(), start));
+        header.extend(0u32.to_be_bytes());
+    }
+    let header_size = header.len();
+    for (position, start) in offsets {
+        header[position..position + 4].copy_from_slice(&((header_size + start) as u32).to_be_bytes());
+    }
+    header.extend(data);
+    std::fs::write(path, header).map_err(NuageError::cache_io(path))
+}
+
+/// Write motion fields as NetCDF (classic format): `u` and `v`, the motion of
+/// each block right and down in pixels per minute, by `time` (of the newer
+/// frame, in minutes since 1970), `y` and `x`, with the `lat` and `lon` of
+/// the block centers.
+pub fn write_motion_netcdf(path: &Path, fields: &MotionSeries, georeference: &Georeference, metadata: &Metadata) -> Result<()> {
+    let Some((_, first)) = fields.first() else {
+        return Err(NuageError::Export("no motion to export".into()));
+    };
+    let (columns, rows) = first.dimensions();
+    let block = first.block();
+    let centers: Vec<(f64, f64)> = (0..rows * columns)
+        .map(|index| {
+            let (column, row) = (index % columns, index / columns);
+            georeference.pixel_to_lonlat((column as f64 + 0.5) * block as f64, (row as f64 + 0.5) * block as f64)
+        })
+        .collect();
+    let text = |name, value: &str| (name, value.to_string());
+    let variables = [
+        NetcdfVariable {
+            name: "time",
+            dimensions: vec![0],
+            attributes: vec![text("units", "minutes since 1970-01-01 00:00:00")],
+            values: NetcdfValues::Double(fields.iter().map(|(time, _)| time.timestamp() as f64 / 60.).collect()),
+        },
+        NetcdfVariable {
+            name: "lat",
+            dimensions: vec![1, 2],
+            attributes: vec![text("units", "degrees_north")],
+            values: NetcdfValues::Float(centers.iter().map(|(_, lat)| *lat as f32).collect()),
+        },
+        NetcdfVariable {
+            name: "lon",
+            dimensions: vec![1, 2],
+            attributes: vec![text("units", "degrees_east")],
+            values: NetcdfValues::Float(centers.iter().map(|(lon, _)| *lon as f32).collect()),
+        },
+        NetcdfVariable {
+            name: "u",
+            dimensions: vec![0, 1, 2],
+            attributes: vec![text("units", "pixels/minute"), text("long_name", "motion to the right of the frame")],
+            values: NetcdfValues::Float(fields.iter().flat_map(|(_, field)| field.vectors().iter().map(|(dx, _)| *dx)).collect()),
+        },
+        NetcdfVariable {
+            name: "v",
+            dimensions: vec![0, 1, 2],
+            attributes: vec![text("units", "pixels/minute"), text("long_name", "motion to the bottom of the frame")],
+            values: NetcdfValues::Float(fields.iter().flat_map(|(_, field)| field.vectors().iter().map(|(_, dy)| *dy)).collect()),
+        },
+    ];
+    let mut attributes = vec![
+        text("title", "nuage motion fields"),
+        ("block_size", format!("{} pixels", block)),
+        ("tiles", format!("{:?} at zoom {}", georeference.tiles, georeference.zoom)),
+    ];
+    attributes.extend(metadata.fields());
+    write_netcdf(path, &[("time", fields.len()), ("y", rows), ("x", columns)], &attributes, &variables)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::AnimationDecoder;
+
+    #[test]
+    fn gif_animation() {
+        let folder = std::env::temp_dir().join(format!("nuage-gif-{}", std::process::id()));
+        std::fs::create_dir_all(&folder).unwrap();
+        let output = folder.join("loop.gif");
+        let start = DateTime::from_timestamp(1_735_732_800, 0).unwrap();
+        let time = |index: usize| start + chrono::Duration::minutes(5 * index as i64);
+        let mut frames: Vec<SatImage> = (0..3).map(|index| SatImage::new(crate::fixtures::render_frame(index), time(index))).collect();
+        // Smaller frames are padded
+        frames[2].image = image::imageops::crop_imm(&frames[2].image, 0, 0, 100, 100).to_image();
+        let metadata = Metadata::new("fixtures", (crate::provider::TILES, crate::provider::ZOOM), start, frames[2].timestamp);
+        let timestamps = frames.iter().map(|frame| frame.timestamp).collect();
+        let checkpoint = Checkpoint::start(&output, "gif", 5., timestamps).unwrap();
+        write_gif(&output, &frames, 5., &metadata, Some(&checkpoint)).unwrap();
+        let gif = std::fs::read(&output).unwrap();
+        let decoder = image::codecs::gif::GifDecoder::new(std::io::Cursor::new(&gif)).unwrap();
+        let decoded = decoder.into_frames().collect_frames().unwrap();
+        assert_eq!(decoded.len(), 3);
+        for frame in &decoded {
+            assert_eq!(frame.buffer().dimensions(), (crate::fixtures::FIXTURE_WIDTH, crate::fixtures::FIXTURE_HEIGHT));
+            assert_eq!(frame.delay().numer_denom_ms(), (200, 1));
+        }
+        assert_eq!(decoded[2].buffer().get_pixel(200, 200).0, [0, 0, 0, 255]);
+        let comment = metadata.fields().into_iter().find(|(name, _)| *name == "software").unwrap().1;
+        assert!(gif.windows(comment.len()).any(|window| window == comment.as_bytes()));
+        // Resumed, the frames kept are not encoded again
+        let resumed = Checkpoint::load(&output).unwrap().unwrap();
+        std::fs::remove_file(&output).unwrap();
+        write_gif(&output, &frames, 5., &metadata, Some(&resumed)).unwrap();
+        assert_eq!(std::fs::read(&output).unwrap(), gif);
+        std::fs::remove_dir_all(&folder).unwrap();
+    }
+}
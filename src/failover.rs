@@ -0,0 +1,240 @@
+//! Switching between providers as they fail and recover.
+
+use chrono::{DateTime, Utc};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use crate::error::Result;
+use crate::provider::{Provenance, Provider, Tiles};
+
+/// Number of requests the error rate of a provider is computed on.
+const WINDOW: usize = 10;
+/// A provider failing at least this fraction of its last requests, with at
+/// least `MIN_SAMPLES` of them, is degraded.
+const DEGRADED_RATE: f32 = 0.5;
+const MIN_SAMPLES: usize = 4;
+/// Number of successful probes in a row for a degraded provider to be used
+/// again.
+const RECOVERY_PROBES: usize = 3;
+
+/// A change of the provider used.
+#[derive(Clone, Debug)]
+pub struct Transition {
+    pub at: DateTime<Utc>,
+    pub from: String,
+    pub to: String,
+    pub reason: String,
+}
+
+/// The outcomes of the last requests of a provider, the most recent last.
+#[derive(Default)]
+struct Health {
+    outcomes: VecDeque<bool>,
+}
+
+impl Health {
+    fn record(&mut self, success: bool) {
+        if self.outcomes.len() == WINDOW {
+            self.outcomes.pop_front();
+        }
+        self.outcomes.push_back(success);
+    }
+
+    fn error_rate(&self) -> f32 {
+        if self.outcomes.is_empty() {
+            return 0.;
+        }
+        self.outcomes.iter().filter(|success| !**success).count() as f32 / self.outcomes.len() as f32
+    }
+
+    fn is_degraded(&self) -> bool {
+        self.outcomes.len() >= MIN_SAMPLES && self.error_rate() >= DEGRADED_RATE
+    }
+
+    fn has_recovered(&self) -> bool {
+        self.outcomes.len() >= RECOVERY_PROBES && self.outcomes.iter().rev().take(RECOVERY_PROBES).all(|success| *success)
+    }
+}
+
+struct State {
+    health: Vec<Health>,
+    active: usize,
+    transitions: Vec<Transition>,
+}
+
+/// The health of a provider, for status output.
+#[derive(Clone, Debug)]
+pub struct ProviderStatus {
+    pub name: String,
+    /// Fraction (0-1) of the last requests that failed
+    pub error_rate: f32,
+    pub active: bool,
+}
+
+/// Providers in order of preference. The first one is used until it
+/// degrades, the next healthy one is then used while the preferred ones are
+/// probed at each listing, and used again once they recover.
+pub struct Failover {
+    providers: Vec<Arc<dyn Provider>>,
+    state: Mutex<State>,
+}
+
+impl Failover {
+    /// `providers` must not be empty.
+    pub fn new(providers: Vec<Arc<dyn Provider>>) -> Self {
+        assert!(!providers.is_empty(), "a failover needs at least one provider");
+        Failover {
+            state: Mutex::new(State {
+                health: providers.iter().map(|_| Health::default()).collect(),
+                active: 0,
+                transitions: vec![],
+            }),
+            providers,
+        }
+    }
+
+    fn active(&self) -> usize {
+        self.state.lock().unwrap().active
+    }
+
+    /// Use the provider at `index`, logging why.
+    fn switch(&self, state: &mut State, index: usize, reason: String) {
+        let transition = Transition {
+            at: Utc::now(),
+            from: self.providers[state.active].name().to_string(),
+            to: self.providers[index].name().to_string(),
+            reason,
+        };
+        log::info!("switching from {} to {}: {}", transition.from, transition.to, transition.reason);
+        state.active = index;
+        state.transitions.push(transition);
+    }
+
+    /// Record the outcome of a request to the provider at `index`, and switch
+    /// to the next healthy provider if it degraded.
+    fn record(&self, index: usize, success: bool) {
+        let mut state = self.state.lock().unwrap();
+        state.health[index].record(success);
+        if index == state.active && state.health[index].is_degraded() {
+            let reason = format!("{:.0}% of the last requests failed", state.health[index].error_rate() * 100.);
+            self.leave(&mut state, reason);
+        }
+    }
+
+    /// Switch from the active provider to the next healthy one, if any.
+    fn leave(&self, state: &mut State, reason: String) {
+        let (active, count) = (state.active, self.providers.len());
+        let next = (1..count).map(|offset| (active + offset) % count).find(|other| !state.health[*other].is_degraded());
+        if let Some(next) = next {
+            self.switch(state, next, reason);
+        }
+    }
+
+    /// Try the providers preferred to the active one, and use the first that
+    /// recovered. A probe lists the frames and gets the most recent one.
+    fn probe_preferred(&self, now: DateTime<Utc>) {
+        for index in 0..self.active() {
+            let provider = &self.providers[index];
+            let success = provider
+                .timepoints(now)
+                .is_ok_and(|timepoints| timepoints.first().is_none_or(|latest| provider.frame(*latest).is_ok()));
+            let mut state = self.state.lock().unwrap();
+            state.health[index].record(success);
+            if index < state.active && state.health[index].has_recovered() {
+                let reason = format!("{} successful probes", RECOVERY_PROBES);
+                self.switch(&mut state, index, reason);
+                return;
+            }
+        }
+    }
+
+    /// The health of each provider, in order of preference.
+    pub fn status(&self) -> Vec<ProviderStatus> {
+        let state = self.state.lock().unwrap();
+        self.providers
+            .iter()
+            .zip(&state.health)
+            .enumerate()
+            .map(|(index, (provider, health))| ProviderStatus {
+                name: provider.name().to_string(),
+                error_rate: health.error_rate(),
+                active: index == state.active,
+            })
+            .collect()
+    }
+
+    /// The changes of provider, the most recent last.
+    pub fn transitions(&self) -> Vec<Transition> {
+        self.state.lock().unwrap().transitions.clone()
+    }
+}
+
+impl Provider for Failover {
+    fn name(&self) -> &str {
+        self.providers[self.active()].name()
+    }
+
+    /// The frames of the active provider. Nothing can be shown without
+    /// them, so the next provider is used right away if it fails.
+    fn timepoints(&self, now: DateTime<Utc>) -> Result<Vec<DateTime<Utc>>> {
+        self.probe_preferred(now);
+        let index = self.active();
+        let result = self.providers[index].timepoints(now);
+        self.record(index, result.is_ok());
+        let Err(e) = &result else {
+            return result;
+        };
+        let mut state = self.state.lock().unwrap();
+        if state.active == index {
+            self.leave(&mut state, format!("could not list the frames: {}", e));
+        }
+        let next = state.active;
+        drop(state);
+        if next == index {
+            return result;
+        }
+        let result = self.providers[next].timepoints(now);
+        self.record(next, result.is_ok());
+        result
+    }
+
+    /// The frame from the active provider, or from the next one if the
+    /// active one degrades on this request.
+    fn frame(&self, timestamp: DateTime<Utc>) -> Result<image::RgbImage> {
+        let index = self.active();
+        let result = self.providers[index].frame(timestamp);
+        self.record(index, result.is_ok());
+        match result {
+            Err(_) if self.active() != index => {
+                let next = self.active();
+                let result = self.providers[next].frame(timestamp);
+                self.record(next, result.is_ok());
+                result
+            }
+            result => result,
+        }
+    }
+
+    fn region(&self) -> (Tiles, u16) {
+        self.providers[self.active()].region()
+    }
+
+    fn interval(&self) -> u32 {
+        self.providers[self.active()].interval()
+    }
+
+    /// The checksum recorded by whichever provider downloaded the frame.
+    fn checksum(&self, timestamp: DateTime<Utc>) -> Option<String> {
+        self.providers.iter().find_map(|provider| provider.checksum(timestamp))
+    }
+
+    /// The provenance recorded by whichever provider got the frame, that of
+    /// the active provider otherwise.
+    fn provenance(&self, timestamp: DateTime<Utc>) -> Provenance {
+        self.providers
+            .iter()
+            .map(|provider| provider.provenance(timestamp))
+            .find(|provenance| provenance.cache_hit.is_some())
+            .unwrap_or_else(|| self.providers[self.active()].provenance(timestamp))
+    }
+}
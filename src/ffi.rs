@@ -0,0 +1,221 @@
+//! C API, see `include/nuage.h`.
+//!
+//! Functions return `NUAGE_OK` (0) on success or a negative error code. The
+//! message of the last error of the calling thread is available with
+//! `nuage_last_error`.
+
+use chrono::{DateTime, Utc};
+use std::cell::RefCell;
+use std::ffi::c_char;
+
+use crate::cache::Cache;
+use crate::error::{NuageError, Result};
+use crate::mosaic;
+use crate::provider::{Infoplaza, Provider};
+
+pub const NUAGE_OK: i32 = 0;
+pub const NUAGE_ERROR: i32 = -1;
+pub const NUAGE_INVALID_ARGUMENT: i32 = -2;
+pub const NUAGE_BUFFER_TOO_SMALL: i32 = -3;
+pub const NUAGE_NOT_FOUND: i32 = -4;
+
+thread_local! {
+    static LAST_ERROR: RefCell<String> = const { RefCell::new(String::new()) };
+}
+
+fn set_last_error(message: impl ToString) {
+    LAST_ERROR.with(|last_error| *last_error.borrow_mut() = message.to_string());
+}
+
+pub(crate) fn provider() -> Result<Infoplaza> {
+    Ok(Infoplaza::new(Cache::default_location()?))
+}
+
+/// Fetch (or read from the cache) the most recent available frame, and give
+/// its time, `None` if the provider lists none.
+pub(crate) fn fetch_latest() -> Result<Option<DateTime<Utc>>> {
+    let provider = provider()?;
+    let mut last_error = None;
+    for timepoint in provider.timepoints(Utc::now())? {
+        match provider.frame(timepoint) {
+            Ok(_) => return Ok(Some(timepoint)),
+            Err(e) => last_error = Some(e),
+        }
+    }
+    last_error.map_or(Ok(None), Err)
+}
+
+fn fail(error: NuageError) -> i32 {
+    set_last_error(&error);
+    NUAGE_ERROR
+}
+
+/// Write in `timestamps` the unix timestamps of the frames the viewer would
+/// show now, from the most recent to the least, and return how many there
+/// are. At most `capacity` timestamps are written: call with a null buffer to
+/// get the size to allocate.
+///
+/// # Safety
+///
+/// `timestamps` must be either null or valid for `capacity` `int64_t`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn nuage_timeline(timestamps: *mut i64, capacity: usize) -> i64 {
+    let timepoints = match provider().and_then(|provider| provider.timepoints(Utc::now())) {
+        Ok(timepoints) => timepoints,
+        Err(e) => return fail(e) as i64,
+    };
+    if !timestamps.is_null() {
+        for (index, timepoint) in timepoints.iter().take(capacity).enumerate() {
+            // SAFETY: the caller guarantees `timestamps` is valid for
+            // `capacity` elements, and we write no more than that.
+            unsafe { *timestamps.add(index) = timepoint.timestamp() };
+        }
+    }
+    timepoints.len() as i64
+}
+
+/// Fetch (or read from the cache) the most recent available frame and write
+/// its unix timestamp in `timestamp`.
+///
+/// # Safety
+///
+/// `timestamp` must be a valid pointer to an `int64_t`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn nuage_fetch_latest(timestamp: *mut i64) -> i32 {
+    if timestamp.is_null() {
+        set_last_error("timestamp is null");
+        return NUAGE_INVALID_ARGUMENT;
+    }
+    match fetch_latest() {
+        Ok(Some(latest)) => {
+            // SAFETY: checked for null above, validity is up to the caller.
+            unsafe { *timestamp = latest.timestamp() };
+            NUAGE_OK
+        }
+        Ok(None) => {
+            set_last_error("no frame available");
+            NUAGE_NOT_FOUND
+        }
+        Err(e) => fail(e),
+    }
+}
+
+/// Write the frame at the unix `timestamp` in `buffer` as RGBA (4 bytes per
+/// pixel, row after row) and its dimensions in `width` and `height`.
+///
+/// If `buffer` is null or `buffer_len` is too small, only the dimensions are
+/// written and `NUAGE_BUFFER_TOO_SMALL` is returned, so the caller can
+/// allocate `width * height * 4` bytes and call again.
+///
+/// # Safety
+///
+/// `width` and `height` must be valid pointers to `uint32_t`, and `buffer`
+/// either null or valid for `buffer_len` bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn nuage_render_frame_rgba(
+    timestamp: i64,
+    buffer: *mut u8,
+    buffer_len: usize,
+    width: *mut u32,
+    height: *mut u32,
+) -> i32 {
+    if width.is_null() || height.is_null() {
+        set_last_error("width and height must not be null");
+        return NUAGE_INVALID_ARGUMENT;
+    }
+    let Some(timestamp) = DateTime::<Utc>::from_timestamp(timestamp, 0) else {
+        set_last_error("invalid timestamp");
+        return NUAGE_INVALID_ARGUMENT;
+    };
+    let image = match provider().and_then(|provider| provider.frame(timestamp)) {
+        Ok(image) => image,
+        Err(e) => return fail(e),
+    };
+    // SAFETY: forwarded from the caller.
+    unsafe { write_rgba(image, buffer, buffer_len, width, height) }
+}
+
+/// Write the freshest picture of the frames in `buffer` as RGBA, like
+/// `nuage_render_frame_rgba`: the latest whole frame with the tiles published
+/// since laid over it, so its tiles may have been taken at different times.
+/// Tiles older than the newest are framed and labeled with their time. The
+/// unix timestamps of the newest and oldest tiles are written in `newest` and
+/// `oldest`, equal when the picture is not mixed.
+///
+/// # Safety
+///
+/// `width`, `height`, `newest` and `oldest` must be valid pointers, and
+/// `buffer` either null or valid for `buffer_len` bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn nuage_render_best_rgba(
+    buffer: *mut u8,
+    buffer_len: usize,
+    width: *mut u32,
+    height: *mut u32,
+    newest: *mut i64,
+    oldest: *mut i64,
+) -> i32 {
+    if width.is_null() || height.is_null() || newest.is_null() || oldest.is_null() {
+        set_last_error("width, height, newest and oldest must not be null");
+        return NUAGE_INVALID_ARGUMENT;
+    }
+    let mosaic = match provider().and_then(|provider| mosaic::best_available(&provider, Utc::now())) {
+        Ok(mosaic) => mosaic,
+        Err(e) => return fail(e),
+    };
+    // SAFETY: checked for null above, validity is up to the caller.
+    unsafe {
+        *newest = mosaic.newest().timestamp();
+        *oldest = mosaic.oldest().timestamp();
+        write_rgba(mosaic.annotated(), buffer, buffer_len, width, height)
+    }
+}
+
+/// Write `image` in `buffer` as RGBA and its dimensions in `width` and
+/// `height`, only the dimensions if `buffer` is too small.
+///
+/// # Safety
+///
+/// `width` and `height` must be valid pointers to `uint32_t`, and `buffer`
+/// either null or valid for `buffer_len` bytes.
+unsafe fn write_rgba(image: image::RgbImage, buffer: *mut u8, buffer_len: usize, width: *mut u32, height: *mut u32) -> i32 {
+    let rgba = image::DynamicImage::ImageRgb8(image).to_rgba8();
+    // SAFETY: validity is up to the caller.
+    unsafe {
+        *width = rgba.width();
+        *height = rgba.height();
+    }
+    let pixels = rgba.as_raw();
+    if buffer.is_null() || buffer_len < pixels.len() {
+        set_last_error(format!("buffer must be at least {} bytes", pixels.len()));
+        return NUAGE_BUFFER_TOO_SMALL;
+    }
+    // SAFETY: the caller guarantees `buffer` points to `buffer_len` writable
+    // bytes, and we checked we write no more than that.
+    unsafe { std::ptr::copy_nonoverlapping(pixels.as_ptr(), buffer, pixels.len()) };
+    NUAGE_OK
+}
+
+/// Copy the message of the last error of the calling thread in `buffer` as a
+/// nul terminated string, truncated to `buffer_len` bytes. Returns the length
+/// of the full message.
+///
+/// # Safety
+///
+/// `buffer` must be either null or valid for `buffer_len` bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn nuage_last_error(buffer: *mut c_char, buffer_len: usize) -> usize {
+    LAST_ERROR.with(|last_error| {
+        let message = last_error.borrow();
+        if !buffer.is_null() && buffer_len > 0 {
+            let len = message.len().min(buffer_len - 1);
+            // SAFETY: the caller guarantees `buffer` points to `buffer_len`
+            // writable bytes, we write at most `buffer_len` of them.
+            unsafe {
+                std::ptr::copy_nonoverlapping(message.as_ptr() as *const c_char, buffer, len);
+                *buffer.add(len) = 0;
+            }
+        }
+        message.len()
+    })
+}
@@ -0,0 +1,68 @@
+use chrono::{DateTime, Duration, Utc};
+use std::path::{Path, PathBuf};
+
+use crate::error::{NuageError, Result};
+
+/// Size of the generated frames. Small enough to be quick to write, large
+/// enough for the blobs to visibly move from one frame to the next.
+pub const FIXTURE_WIDTH: u32 = 640;
+pub const FIXTURE_HEIGHT: u32 = 480;
+
+/// A synthetic "cloud": a gaussian blob travelling in a straight line.
+struct Blob {
+    x: f32,
+    y: f32,
+    dx: f32,
+    dy: f32,
+    radius: f32,
+}
+
+const BLOBS: [Blob; 3] = [
+    Blob { x: 80., y: 120., dx: 9., dy: 2., radius: 70. },
+    Blob { x: 500., y: 380., dx: -6., dy: -4., radius: 50. },
+    Blob { x: 300., y: 60., dx: 2., dy: 8., radius: 90. },
+];
+
+/// Name of a frame file for a timestamp. The local directory source parses
+/// this back to order the frames.
+pub fn frame_filename(timestamp: DateTime<Utc>) -> String {
+    format!("{}.jpg", timestamp.format("%Y%m%d%H%M"))
+}
+
+/// Render the frame number `index` of a synthetic sequence: a slowly drifting
+/// dark gradient (the "ground") with bright blobs moving over it.
+pub fn render_frame(index: usize) -> image::RgbImage {
+    let t = index as f32;
+    image::RgbImage::from_fn(FIXTURE_WIDTH, FIXTURE_HEIGHT, |x, y| {
+        let gradient = ((x as f32 + t * 4.) % FIXTURE_WIDTH as f32) / FIXTURE_WIDTH as f32;
+        let mut value = 20. + 40. * gradient + 20. * (y as f32 / FIXTURE_HEIGHT as f32);
+        for blob in &BLOBS {
+            let cx = blob.x + blob.dx * t;
+            let cy = blob.y + blob.dy * t;
+            let distance2 = (x as f32 - cx).powi(2) + (y as f32 - cy).powi(2);
+            value += 200. * (-distance2 / (2. * blob.radius * blob.radius)).exp();
+        }
+        let value = value.min(255.) as u8;
+        image::Rgb([value, value, value])
+    })
+}
+
+/// Generate `count` frames spaced by `step_minutes`, the most recent one
+/// being at `end`, and write them in `folder`.
+pub fn generate(
+    folder: &Path,
+    count: usize,
+    step_minutes: i64,
+    end: DateTime<Utc>,
+) -> Result<Vec<PathBuf>> {
+    std::fs::create_dir_all(folder).map_err(NuageError::cache_io(folder))?;
+    let mut written = vec![];
+    // Frame 0 is the oldest so that blobs move forward in time.
+    for index in 0..count {
+        let timestamp = end - Duration::minutes(step_minutes * (count - 1 - index) as i64);
+        let filepath = folder.join(frame_filename(timestamp));
+        render_frame(index).save(&filepath)?;
+        written.push(filepath);
+    }
+    Ok(written)
+}
@@ -0,0 +1,70 @@
+//! The hourly forecast at a place, from the Open-Meteo API
+//! (https://open-meteo.com), free for non-commercial use without a key.
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+
+use crate::error::{NuageError, Result};
+use crate::geo::LonLat;
+use crate::json::{self, Json};
+
+/// How many days of forecast are fetched.
+const FORECAST_DAYS: u32 = 2;
+
+/// An hourly forecast, its values `None` when not given.
+#[derive(Clone, Debug, Default)]
+pub struct Forecast {
+    pub times: Vec<DateTime<Utc>>,
+    /// Precipitation of the preceding hour, in mm
+    pub precipitation: Vec<Option<f64>>,
+    /// Total cloud cover, in %
+    pub cloud_cover: Vec<Option<f64>>,
+    /// Temperature at 2 m, in °C
+    pub temperature: Vec<Option<f64>>,
+}
+
+/// Parse an Open-Meteo forecast response, asked in UTC.
+pub fn parse(text: &str) -> Result<Forecast> {
+    let response = json::parse(text).map_err(|e| NuageError::Config(format!("forecast: {}", e)))?;
+    if let Some(reason) = response.get("reason").and_then(Json::as_str) {
+        return Err(NuageError::Config(format!("forecast: {}", reason)));
+    }
+    let hourly = response
+        .get("hourly")
+        .ok_or_else(|| NuageError::Config("forecast: no hourly values".into()))?;
+    let values = |key: &str| -> Vec<Option<f64>> {
+        hourly
+            .get(key)
+            .and_then(Json::as_array)
+            .map(|values| values.iter().map(Json::as_f64).collect())
+            .unwrap_or_default()
+    };
+    let times = hourly
+        .get("time")
+        .and_then(Json::as_array)
+        .ok_or_else(|| NuageError::Config("forecast: no times".into()))?
+        .iter()
+        .filter_map(Json::as_str)
+        .filter_map(|time| NaiveDateTime::parse_from_str(time, "%Y-%m-%dT%H:%M").ok())
+        .map(|time| time.and_utc())
+        .collect();
+    Ok(Forecast {
+        times,
+        precipitation: values("precipitation"),
+        cloud_cover: values("cloud_cover"),
+        temperature: values("temperature_2m"),
+    })
+}
+
+/// The forecast of the next days at `position`. Blocking.
+pub fn fetch(position: LonLat) -> Result<Forecast> {
+    let url = format!(
+        concat!(
+            "https://api.open-meteo.com/v1/forecast?latitude={:.4}&longitude={:.4}",
+            "&hourly=precipitation,cloud_cover,temperature_2m&timezone=UTC&forecast_days={}",
+        ),
+        position.1, position.0, FORECAST_DAYS,
+    );
+    log::info!("fetching {}", url);
+    let text = ureq::get(url).call()?.body_mut().read_to_string()?;
+    parse(&text)
+}
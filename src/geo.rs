@@ -0,0 +1,378 @@
+//! Vector features (points, lines, polygons in longitude/latitude) and their
+//! GeoJSON and KML serialization.
+
+use std::fmt::Write;
+use std::path::Path;
+
+use crate::analysis::Analysis;
+use crate::error::{NuageError, Result};
+use crate::json::{self, Json};
+use crate::projection::{Georeference, bearing_degrees, distance_km};
+
+/// A (longitude, latitude) in degrees.
+pub type LonLat = (f64, f64);
+
+#[derive(Clone, Debug)]
+pub enum Geometry {
+    Point(LonLat),
+    LineString(Vec<LonLat>),
+    /// An outer ring, closed (first and last points are the same)
+    Polygon(Vec<LonLat>),
+    MultiPolygon(Vec<Vec<LonLat>>),
+}
+
+#[derive(Clone, Debug)]
+pub enum Value {
+    Number(f64),
+    Text(String),
+}
+
+#[derive(Clone, Debug)]
+pub struct Feature {
+    pub geometry: Geometry,
+    pub properties: Vec<(String, Value)>,
+}
+
+impl Feature {
+    pub fn new(geometry: Geometry) -> Self {
+        Feature { geometry, properties: vec![] }
+    }
+
+    pub fn with(mut self, key: &str, value: Value) -> Self {
+        self.properties.push((key.to_string(), value));
+        self
+    }
+
+    /// A property as text, numbers being formatted.
+    pub fn property(&self, key: &str) -> Option<String> {
+        self.properties.iter().find(|(k, _)| k == key).map(|(_, value)| match value {
+            Value::Number(number) => number.to_string(),
+            Value::Text(text) => text.clone(),
+        })
+    }
+}
+
+/// Escape a string for a JSON string literal, quotes included.
+pub fn json_string(text: &str) -> String {
+    let mut escaped = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 => write!(escaped, "\\u{:04x}", c as u32).unwrap(),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+fn json_number(number: f64) -> String {
+    if number.is_finite() { format!("{}", number) } else { "null".into() }
+}
+
+fn json_coordinates(points: &[LonLat]) -> String {
+    let points: Vec<String> = points
+        .iter()
+        .map(|(lon, lat)| format!("[{},{}]", json_number(*lon), json_number(*lat)))
+        .collect();
+    format!("[{}]", points.join(","))
+}
+
+fn json_geometry(geometry: &Geometry) -> String {
+    match geometry {
+        Geometry::Point((lon, lat)) => {
+            format!("{{\"type\":\"Point\",\"coordinates\":[{},{}]}}", json_number(*lon), json_number(*lat))
+        }
+        Geometry::LineString(points) => {
+            format!("{{\"type\":\"LineString\",\"coordinates\":{}}}", json_coordinates(points))
+        }
+        Geometry::Polygon(ring) => {
+            format!("{{\"type\":\"Polygon\",\"coordinates\":[{}]}}", json_coordinates(ring))
+        }
+        Geometry::MultiPolygon(rings) => {
+            let polygons: Vec<String> = rings.iter().map(|ring| format!("[{}]", json_coordinates(ring))).collect();
+            format!("{{\"type\":\"MultiPolygon\",\"coordinates\":[{}]}}", polygons.join(","))
+        }
+    }
+}
+
+/// Serialize features as a GeoJSON FeatureCollection.
+pub fn to_geojson(features: &[Feature]) -> String {
+    let features: Vec<String> = features
+        .iter()
+        .map(|feature| {
+            let properties: Vec<String> = feature
+                .properties
+                .iter()
+                .map(|(key, value)| {
+                    let value = match value {
+                        Value::Number(number) => json_number(*number),
+                        Value::Text(text) => json_string(text),
+                    };
+                    format!("{}:{}", json_string(key), value)
+                })
+                .collect();
+            format!(
+                "{{\"type\":\"Feature\",\"geometry\":{},\"properties\":{{{}}}}}",
+                json_geometry(&feature.geometry),
+                properties.join(","),
+            )
+        })
+        .collect();
+    format!("{{\"type\":\"FeatureCollection\",\"features\":[\n{}\n]}}\n", features.join(",\n"))
+}
+
+fn kml_coordinates(points: &[LonLat]) -> String {
+    let points: Vec<String> = points.iter().map(|(lon, lat)| format!("{},{}", lon, lat)).collect();
+    points.join(" ")
+}
+
+fn kml_polygon(ring: &[LonLat]) -> String {
+    format!(
+        "<Polygon><outerBoundaryIs><LinearRing><coordinates>{}</coordinates></LinearRing></outerBoundaryIs></Polygon>",
+        kml_coordinates(ring)
+    )
+}
+
+/// Serialize features as a KML document. The `name` property, if any, names
+/// the placemarks, the other properties are kept as extended data.
+pub fn to_kml(name: &str, features: &[Feature]) -> String {
+    let mut kml = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<kml xmlns=\"http://www.opengis.net/kml/2.2\">\n<Document>\n<name>{}</name>\n",
+        xml_escape(name)
+    );
+    for feature in features {
+        kml.push_str("<Placemark>");
+        if let Some(name) = feature.property("name") {
+            write!(kml, "<name>{}</name>", xml_escape(&name)).unwrap();
+        }
+        kml.push_str("<ExtendedData>");
+        for (key, _) in &feature.properties {
+            let value = feature.property(key).unwrap_or_default();
+            write!(kml, "<Data name=\"{}\"><value>{}</value></Data>", xml_escape(key), xml_escape(&value)).unwrap();
+        }
+        kml.push_str("</ExtendedData>");
+        match &feature.geometry {
+            Geometry::Point(point) => {
+                write!(kml, "<Point><coordinates>{}</coordinates></Point>", kml_coordinates(&[*point])).unwrap()
+            }
+            Geometry::LineString(points) => {
+                write!(kml, "<LineString><coordinates>{}</coordinates></LineString>", kml_coordinates(points)).unwrap()
+            }
+            Geometry::Polygon(ring) => kml.push_str(&kml_polygon(ring)),
+            Geometry::MultiPolygon(rings) => {
+                kml.push_str("<MultiGeometry>");
+                for ring in rings {
+                    kml.push_str(&kml_polygon(ring));
+                }
+                kml.push_str("</MultiGeometry>");
+            }
+        }
+        kml.push_str("</Placemark>\n");
+    }
+    kml.push_str("</Document>\n</kml>\n");
+    kml
+}
+
+/// Write features as GeoJSON or KML depending on the extension of `path`.
+pub fn write_features(path: &Path, features: &[Feature]) -> Result<()> {
+    let content = match path.extension().and_then(|extension| extension.to_str()) {
+        Some("kml") => to_kml("nuage", features),
+        Some("geojson" | "json") => to_geojson(features),
+        _ => return Err(NuageError::Export(format!("{}: expected a .geojson or .kml file", path.display()))),
+    };
+    std::fs::write(path, content).map_err(NuageError::cache_io(path))
+}
+
+/// Closed ring of a pixel rectangle, shifted by `offset` pixels.
+fn rectangle(georeference: &Georeference, min: (u32, u32), max: (u32, u32), offset: (f32, f32)) -> Vec<LonLat> {
+    let (x1, y1) = (min.0 as f64 + offset.0 as f64, min.1 as f64 + offset.1 as f64);
+    let (x2, y2) = (max.0 as f64 + 1. + offset.0 as f64, max.1 as f64 + 1. + offset.1 as f64);
+    [(x1, y1), (x2, y1), (x2, y2), (x1, y2), (x1, y1)]
+        .iter()
+        .map(|(x, y)| georeference.pixel_to_lonlat(*x, *y))
+        .collect()
+}
+
+/// Time offsets of the predicted cell positions, in minutes.
+pub const ISOCHRONES: [u32; 4] = [15, 30, 45, 60];
+
+/// The products of an analysis as features:
+/// - `cell`: bounding box of the detected cells,
+/// - `motion`: one hour of displacement of each motion vector, with its speed
+///   and heading,
+/// - `isochrone`: where the cells are predicted to be after 15, 30, 45 and 60
+///   minutes, moving with the median motion.
+pub fn analysis_features(analysis: &Analysis, georeference: &Georeference) -> Vec<Feature> {
+    let mut features = vec![];
+    for (index, cell) in analysis.cells.iter().enumerate() {
+        let centroid = georeference.pixel_to_lonlat(cell.centroid.0 as f64, cell.centroid.1 as f64);
+        features.push(
+            Feature::new(Geometry::Polygon(rectangle(georeference, cell.min, cell.max, (0., 0.))))
+                .with("kind", Value::Text("cell".into()))
+                .with("name", Value::Text(format!("cell {}", index + 1)))
+                .with("area_px", Value::Number(cell.area as f64))
+                .with("mean_luminance", Value::Number(cell.mean as f64))
+                .with("centroid_lon", Value::Number(centroid.0))
+                .with("centroid_lat", Value::Number(centroid.1)),
+        );
+    }
+    for vector in &analysis.vectors {
+        let from = georeference.pixel_to_lonlat(vector.x as f64, vector.y as f64);
+        let to = georeference.pixel_to_lonlat((vector.x + vector.dx * 60.) as f64, (vector.y + vector.dy * 60.) as f64);
+        features.push(
+            Feature::new(Geometry::LineString(vec![from, to]))
+                .with("kind", Value::Text("motion".into()))
+                .with("speed_kmh", Value::Number(distance_km(from, to)))
+                .with("heading_degrees", Value::Number(bearing_degrees(from, to))),
+        );
+    }
+    if !analysis.cells.is_empty() {
+        for minutes in ISOCHRONES {
+            let offset = (analysis.motion.0 * minutes as f32, analysis.motion.1 * minutes as f32);
+            let rings = analysis
+                .cells
+                .iter()
+                .map(|cell| rectangle(georeference, cell.min, cell.max, offset))
+                .collect();
+            features.push(
+                Feature::new(Geometry::MultiPolygon(rings))
+                    .with("kind", Value::Text("isochrone".into()))
+                    .with("name", Value::Text(format!("+{} min", minutes)))
+                    .with("minutes", Value::Number(minutes as f64)),
+            );
+        }
+    }
+    features
+}
+
+fn parse_position(json: &Json) -> Option<LonLat> {
+    let position = json.as_array()?;
+    Some((position.first()?.as_f64()?, position.get(1)?.as_f64()?))
+}
+
+fn parse_positions(json: &Json) -> Option<Vec<LonLat>> {
+    json.as_array()?.iter().map(parse_position).collect()
+}
+
+/// The geometries of a GeoJSON geometry object. Multi-geometries and
+/// collections are split, polygon holes are ignored.
+fn parse_geometry(json: &Json) -> std::result::Result<Vec<Geometry>, String> {
+    let kind = json.get("type").and_then(Json::as_str).ok_or("geometry without type")?;
+    let coordinates = json.get("coordinates");
+    let invalid = || format!("invalid {} coordinates", kind);
+    let geometries = match kind {
+        "Point" => vec![Geometry::Point(coordinates.and_then(parse_position).ok_or_else(invalid)?)],
+        "MultiPoint" => coordinates
+            .and_then(parse_positions)
+            .ok_or_else(invalid)?
+            .into_iter()
+            .map(Geometry::Point)
+            .collect(),
+        "LineString" => vec![Geometry::LineString(coordinates.and_then(parse_positions).ok_or_else(invalid)?)],
+        "MultiLineString" => coordinates
+            .and_then(Json::as_array)
+            .and_then(|lines| lines.iter().map(parse_positions).collect::<Option<Vec<_>>>())
+            .ok_or_else(invalid)?
+            .into_iter()
+            .map(Geometry::LineString)
+            .collect(),
+        "Polygon" => {
+            let rings = coordinates.and_then(Json::as_array).ok_or_else(invalid)?;
+            let outer = rings.first().and_then(parse_positions).ok_or_else(invalid)?;
+            vec![Geometry::Polygon(outer)]
+        }
+        "MultiPolygon" => {
+            let polygons = coordinates.and_then(Json::as_array).ok_or_else(invalid)?;
+            let rings = polygons
+                .iter()
+                .map(|polygon| polygon.as_array().and_then(|rings| rings.first()).and_then(parse_positions))
+                .collect::<Option<Vec<_>>>()
+                .ok_or_else(invalid)?;
+            vec![Geometry::MultiPolygon(rings)]
+        }
+        "GeometryCollection" => {
+            let mut geometries = vec![];
+            for geometry in json.get("geometries").and_then(Json::as_array).ok_or_else(invalid)? {
+                geometries.extend(parse_geometry(geometry)?);
+            }
+            geometries
+        }
+        _ => return Err(format!("unsupported geometry {}", kind)),
+    };
+    Ok(geometries)
+}
+
+fn parse_feature(json: &Json, features: &mut Vec<Feature>) -> std::result::Result<(), String> {
+    let Some(geometry) = json.get("geometry").filter(|geometry| **geometry != Json::Null) else {
+        return Ok(());
+    };
+    let properties: Vec<(String, Value)> = json
+        .get("properties")
+        .and_then(Json::as_object)
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|(key, value)| match value {
+            Json::Number(number) => Some((key.clone(), Value::Number(*number))),
+            Json::String(text) => Some((key.clone(), Value::Text(text.clone()))),
+            Json::Bool(b) => Some((key.clone(), Value::Text(b.to_string()))),
+            _ => None,
+        })
+        .collect();
+    for geometry in parse_geometry(geometry)? {
+        features.push(Feature { geometry, properties: properties.clone() });
+    }
+    Ok(())
+}
+
+/// Parse a GeoJSON document: a FeatureCollection, a Feature or a bare
+/// geometry.
+pub fn parse_geojson(text: &str) -> Result<Vec<Feature>> {
+    let parse = || -> std::result::Result<Vec<Feature>, String> {
+        let json = json::parse(text)?;
+        let mut features = vec![];
+        match json.get("type").and_then(Json::as_str) {
+            Some("FeatureCollection") => {
+                for feature in json.get("features").and_then(Json::as_array).ok_or("FeatureCollection without features")? {
+                    parse_feature(feature, &mut features)?;
+                }
+            }
+            Some("Feature") => parse_feature(&json, &mut features)?,
+            Some(_) => {
+                for geometry in parse_geometry(&json)? {
+                    features.push(Feature::new(geometry));
+                }
+            }
+            None => return Err("not a GeoJSON document".into()),
+        }
+        Ok(features)
+    };
+    parse().map_err(|e| NuageError::Config(format!("invalid GeoJSON: {}", e)))
+}
+
+/// Read a GeoJSON file.
+pub fn read_geojson(path: &Path) -> Result<Vec<Feature>> {
+    let text = std::fs::read_to_string(path).map_err(NuageError::cache_io(path))?;
+    parse_geojson(&text).map_err(|e| NuageError::Config(format!("{}: {}", path.display(), e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_string_escapes() {
+        let text = "timeout: \"tile\\0\"\n\tretrying\r\u{1}";
+        let escaped = json_string(text);
+        assert_eq!(escaped, "\"timeout: \\\"tile\\\\0\\\"\\n\\tretrying\\r\\u0001\"");
+        assert_eq!(crate::json::parse(&escaped), Ok(crate::json::Json::String(text.to_string())));
+    }
+}
@@ -0,0 +1,136 @@
+//! GPX tracks (hikes, flights...) and the position along them at a time.
+
+use chrono::{DateTime, Utc};
+use quick_xml::events::{BytesStart, Event};
+use std::path::Path;
+
+use crate::error::{NuageError, Result};
+use crate::geo::{Feature, Geometry, LonLat, Value};
+
+/// A point of a track, with the time it was recorded if known.
+#[derive(Clone, Debug)]
+pub struct TrackPoint {
+    pub position: LonLat,
+    pub time: Option<DateTime<Utc>>,
+}
+
+/// The points of all the track segments and routes of a GPX file, in order.
+#[derive(Clone, Debug, Default)]
+pub struct Track {
+    pub name: String,
+    pub points: Vec<TrackPoint>,
+}
+
+impl Track {
+    /// The times of the first and last timed points.
+    pub fn time_range(&self) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+        let mut times = self.points.iter().filter_map(|point| point.time);
+        let first = times.next()?;
+        Some(times.fold((first, first), |(from, to), time| (from.min(time), to.max(time))))
+    }
+
+    /// The position at `time`, interpolated between the recorded points.
+    /// `None` outside of the track time range.
+    pub fn position_at(&self, time: DateTime<Utc>) -> Option<LonLat> {
+        let timed: Vec<(DateTime<Utc>, LonLat)> = self
+            .points
+            .iter()
+            .filter_map(|point| point.time.map(|t| (t, point.position)))
+            .collect();
+        for pair in timed.windows(2) {
+            let ((t0, p0), (t1, p1)) = (pair[0], pair[1]);
+            if time < t0 || time > t1 {
+                continue;
+            }
+            let span = (t1 - t0).num_milliseconds();
+            let ratio = if span == 0 { 0. } else { (time - t0).num_milliseconds() as f64 / span as f64 };
+            return Some((p0.0 + (p1.0 - p0.0) * ratio, p0.1 + (p1.1 - p0.1) * ratio));
+        }
+        timed.first().filter(|(t, _)| *t == time).map(|(_, position)| *position)
+    }
+
+    /// The track as a line.
+    pub fn feature(&self) -> Feature {
+        let line = self.points.iter().map(|point| point.position).collect();
+        Feature::new(Geometry::LineString(line)).with("name", Value::Text(self.name.clone()))
+    }
+}
+
+fn invalid(message: impl std::fmt::Display) -> NuageError {
+    NuageError::Config(format!("invalid GPX: {}", message))
+}
+
+/// The (longitude, latitude) attributes of a `trkpt` or `rtept`.
+fn parse_point(element: &BytesStart) -> Result<LonLat> {
+    let coordinate = |name: &str| -> Result<f64> {
+        let attribute = element
+            .try_get_attribute(name)
+            .map_err(invalid)?
+            .ok_or_else(|| invalid(format!("point without {}", name)))?;
+        let value = attribute.unescape_value().map_err(invalid)?;
+        value.trim().parse().map_err(|_| invalid(format!("bad {} {}", name, value)))
+    };
+    Ok((coordinate("lon")?, coordinate("lat")?))
+}
+
+/// Parse the track and route points of a GPX document.
+pub fn parse_gpx(text: &str) -> Result<Track> {
+    let mut reader = quick_xml::Reader::from_str(text);
+    let mut track = Track::default();
+    // The point being read and the element whose text is expected
+    let mut point: Option<TrackPoint> = None;
+    let mut element = Vec::new();
+    loop {
+        match reader.read_event().map_err(invalid)? {
+            Event::Start(start) => {
+                let name = start.local_name().as_ref().to_vec();
+                if matches!(name.as_slice(), b"trkpt" | b"rtept") {
+                    point = Some(TrackPoint { position: parse_point(&start)?, time: None });
+                }
+                element = name;
+            }
+            Event::Empty(start) => {
+                if matches!(start.local_name().as_ref(), b"trkpt" | b"rtept") {
+                    track.points.push(TrackPoint { position: parse_point(&start)?, time: None });
+                }
+            }
+            Event::Text(text) => {
+                let text = text.unescape().map_err(invalid)?;
+                match (element.as_slice(), &mut point) {
+                    (b"time", Some(point)) => {
+                        let time = DateTime::parse_from_rfc3339(text.trim())
+                            .map_err(|_| invalid(format!("bad time {}", text)))?;
+                        point.time = Some(time.with_timezone(&Utc));
+                    }
+                    (b"name", None) if track.name.is_empty() => track.name = text.trim().to_string(),
+                    _ => (),
+                }
+            }
+            Event::End(end) => {
+                if matches!(end.local_name().as_ref(), b"trkpt" | b"rtept") {
+                    track.points.extend(point.take());
+                }
+                element.clear();
+            }
+            Event::Eof => break,
+            _ => (),
+        }
+    }
+    if track.points.is_empty() {
+        return Err(invalid("no track or route points"));
+    }
+    Ok(track)
+}
+
+/// Read a GPX file, named after the file if it has no name.
+pub fn read_gpx(path: &Path) -> Result<Track> {
+    let text = std::fs::read_to_string(path).map_err(NuageError::cache_io(path))?;
+    let mut track = parse_gpx(&text)?;
+    if track.name.is_empty() {
+        track.name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+    }
+    Ok(track)
+}
@@ -0,0 +1,116 @@
+//! The labels drawn over the frames, in the corners chosen in the
+//! configuration.
+
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+/// What can be shown in a corner.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub enum HudElement {
+    /// The index and time of the frame
+    Timestamp,
+    /// The current local time
+    Clock,
+    /// How old the latest frame is
+    DataAge,
+    /// The number of frames and of loading errors
+    Stats,
+    /// The colors of the layers shown
+    Legend,
+    /// A bar of a round number of kilometers at the latitude of the middle
+    /// of the frames
+    ScaleBar,
+}
+
+/// Which elements go in which corner, in order from the corner inwards:
+///
+/// ```ron
+/// hud: (
+///     top_right: [Clock, DataAge],
+///     bottom_left: [Timestamp],
+///     bottom_right: [Stats, Legend],
+///     margin: 20.0,
+/// ),
+/// ```
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct HudConfig {
+    pub top_left: Vec<HudElement>,
+    pub top_right: Vec<HudElement>,
+    pub bottom_left: Vec<HudElement>,
+    pub bottom_right: Vec<HudElement>,
+    /// Distance to the edges of the view, in points
+    pub margin: f32,
+}
+
+impl Default for HudConfig {
+    fn default() -> Self {
+        HudConfig {
+            top_left: vec![],
+            top_right: vec![],
+            bottom_left: vec![HudElement::Timestamp],
+            bottom_right: vec![HudElement::ScaleBar],
+            margin: 10.0,
+        }
+    }
+}
+
+impl HudConfig {
+    /// Whether `element` is shown in any corner.
+    pub fn shows(&self, element: HudElement) -> bool {
+        [&self.top_left, &self.top_right, &self.bottom_left, &self.bottom_right]
+            .iter()
+            .any(|corner| corner.contains(&element))
+    }
+}
+
+/// The longest scale bar, in points.
+pub const SCALE_BAR_MAX: f32 = 150.;
+
+/// The longest round length, 1, 2 or 5 times a power of ten, of at most
+/// `max` kilometers.
+pub fn scale_bar_km(max: f64) -> f64 {
+    let power = 10f64.powf(max.log10().floor());
+    [5., 2., 1.].into_iter().map(|factor| factor * power).find(|length| *length <= max).unwrap_or(power)
+}
+
+/// Draw a scale bar `length` points long next to `label`, the painted label
+/// of the bar, on the side of the middle of the view: on its right in the
+/// left corners, on its left in the right ones.
+pub fn paint_scale_bar(painter: &egui::Painter, label: egui::Rect, align: egui::Align2, length: f32) {
+    let (start, end) = match align.x() {
+        egui::Align::Max => (label.left() - 8. - length, label.left() - 8.),
+        _ => (label.right() + 8., label.right() + 8. + length),
+    };
+    let y = label.center().y;
+    for (width, color) in [(4., egui::Color32::BLACK), (2., egui::Color32::WHITE)] {
+        let stroke = egui::Stroke::new(width, color);
+        painter.line_segment([egui::pos2(start, y), egui::pos2(end, y)], stroke);
+        for x in [start, end] {
+            painter.line_segment([egui::pos2(x, y - 5.), egui::pos2(x, y + 5.)], stroke);
+        }
+    }
+}
+
+/// A line of text of the HUD.
+pub struct HudLine {
+    pub text: String,
+    pub font: egui::FontId,
+    pub color: egui::Color32,
+}
+
+/// Stack `lines` from the corner `align` of `rect` inwards, `margin` away
+/// from its edges. Returns where each line was painted.
+pub fn paint_corner(painter: &egui::Painter, rect: egui::Rect, align: egui::Align2, margin: f32, lines: &[HudLine]) -> Vec<egui::Rect> {
+    let rect = rect.shrink(margin);
+    let (x, mut y) = (rect.min.x + align.x().to_factor() * rect.width(), rect.min.y + align.y().to_factor() * rect.height());
+    // Going down from the top corners, up from the bottom ones
+    let direction = if align.y() == egui::Align::Max { -1. } else { 1. };
+    let mut painted_rects = vec![];
+    for line in lines {
+        let painted = painter.text(egui::pos2(x, y), align, &line.text, line.font.clone(), line.color);
+        y += direction * (painted.height() + 6.);
+        painted_rects.push(painted);
+    }
+    painted_rects
+}
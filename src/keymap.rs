@@ -0,0 +1,436 @@
+//! Keyboard shortcuts, rebindable in the configuration file, e.g.
+//! `keys: {Quit: "Q", Previous: "H", Next: ["L", "ArrowRight"]}`. A shortcut
+//! is the name of a key as egui writes it (`A`, `0`, `Space`, `ArrowLeft`,
+//! `Escape`, `F1`, `?`...), after `Ctrl+`, `Shift+` or `Alt+` if needed.
+//! `Ctrl` is Cmd on macOS. The mouse gestures are listed here too, for the
+//! help, but cannot be rebound.
+
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+
+/// What a key does.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq, Hash)]
+pub enum Action {
+    Quit,
+    PlayPause,
+    Reverse,
+    Faster,
+    Slower,
+    /// The frame before
+    Previous,
+    /// The frame after
+    Next,
+    /// 15 minutes before
+    PreviousQuarter,
+    /// 15 minutes after
+    NextQuarter,
+    /// An hour before
+    PreviousHour,
+    /// An hour after
+    NextHour,
+    Oldest,
+    Latest,
+    SaveSession,
+    SaveFrame,
+    SaveAnnotatedFrame,
+    CopyFrame,
+    FollowTrack,
+    Stations,
+    Nowcast,
+    Interpolate,
+    Crossfade,
+    Adjustments,
+    Histogram,
+    Announcements,
+    TimeZone,
+    Wind,
+    Basemap,
+    Measure,
+    Annotate,
+    SaveAnnotations,
+    Lightning,
+    Forecast,
+    Filmstrip,
+    Palette,
+    Difference,
+    Compare,
+    GeoTiff,
+    BrightnessUp,
+    BrightnessDown,
+    ContrastUp,
+    ContrastDown,
+    GammaUp,
+    GammaDown,
+    ResetAdjustments,
+    Fullscreen,
+    TileDebug,
+    Perf,
+    Compact,
+    Help,
+}
+
+impl Action {
+    pub const ALL: [Action; 50] = [
+        Action::Quit,
+        Action::PlayPause,
+        Action::Reverse,
+        Action::Faster,
+        Action::Slower,
+        Action::Previous,
+        Action::Next,
+        Action::PreviousQuarter,
+        Action::NextQuarter,
+        Action::PreviousHour,
+        Action::NextHour,
+        Action::Oldest,
+        Action::Latest,
+        Action::SaveSession,
+        Action::SaveFrame,
+        Action::SaveAnnotatedFrame,
+        Action::CopyFrame,
+        Action::FollowTrack,
+        Action::Stations,
+        Action::Nowcast,
+        Action::Interpolate,
+        Action::Crossfade,
+        Action::Adjustments,
+        Action::Histogram,
+        Action::Announcements,
+        Action::TimeZone,
+        Action::Wind,
+        Action::Basemap,
+        Action::Measure,
+        Action::Annotate,
+        Action::SaveAnnotations,
+        Action::Lightning,
+        Action::Forecast,
+        Action::Filmstrip,
+        Action::Palette,
+        Action::Difference,
+        Action::Compare,
+        Action::GeoTiff,
+        Action::BrightnessUp,
+        Action::BrightnessDown,
+        Action::ContrastUp,
+        Action::ContrastDown,
+        Action::GammaUp,
+        Action::GammaDown,
+        Action::ResetAdjustments,
+        Action::Fullscreen,
+        Action::TileDebug,
+        Action::Perf,
+        Action::Compact,
+        Action::Help,
+    ];
+
+    /// What the action does, for the help.
+    pub fn description(self) -> &'static str {
+        match self {
+            Action::Quit => "Quit",
+            Action::PlayPause => "Pause or resume the animation",
+            Action::Reverse => "Play the animation backwards or forwards",
+            Action::Faster => "Play the animation twice as fast",
+            Action::Slower => "Play the animation twice as slow",
+            Action::Previous => "The frame before",
+            Action::Next => "The frame after",
+            Action::PreviousQuarter => "15 minutes before",
+            Action::NextQuarter => "15 minutes after",
+            Action::PreviousHour => "An hour before",
+            Action::NextHour => "An hour after",
+            Action::Oldest => "Pause on the oldest frame",
+            Action::Latest => "Pause on the latest frame",
+            Action::SaveSession => "Save the session in the current directory",
+            Action::SaveFrame => "Save the frame shown as a PNG in the current directory",
+            Action::SaveAnnotatedFrame => "Save the frame shown as a PNG with the overlays and its time",
+            Action::CopyFrame => "Copy the frame shown, with the overlays and its time",
+            Action::FollowTrack => "Follow the time of the GPX track, or play all the frames",
+            Action::Stations => "Show the radiosonde launch sites",
+            Action::Nowcast => "Append forecasts to the loop",
+            Action::Interpolate => "Play in-between frames or the raw frames only",
+            Action::Crossfade => "Fade from one frame to the next",
+            Action::Adjustments => "Show the brightness, contrast and gamma sliders",
+            Action::Histogram => "Show the luminance histogram",
+            Action::Announcements => "Change when frames are announced to screen readers",
+            Action::TimeZone => "Show the times in UTC or in local time",
+            Action::Wind => "Show the wind barbs",
+            Action::Basemap => "Draw the frames over a map",
+            Action::Measure => "Measure the distance and bearing between two points clicked",
+            Action::Annotate => "Draw arrows, circles or text over the frames, then stop",
+            Action::SaveAnnotations => "Save the annotations as GeoJSON, with the time of the frame shown",
+            Action::Lightning => "Show the lightning strikes",
+            Action::Forecast => "Show the forecast at home",
+            Action::Filmstrip => "Show the thumbnails of the frames",
+            Action::Palette => "Next palette",
+            Action::Difference => "Show the changes since the previous frame",
+            Action::Compare => "Compare two frames side by side",
+            Action::GeoTiff => "Save the frame as a GeoTIFF",
+            Action::BrightnessUp => "Raise the brightness",
+            Action::BrightnessDown => "Lower the brightness",
+            Action::ContrastUp => "Raise the contrast",
+            Action::ContrastDown => "Lower the contrast",
+            Action::GammaUp => "Raise the gamma",
+            Action::GammaDown => "Lower the gamma",
+            Action::ResetAdjustments => "Reset the brightness, contrast and gamma",
+            Action::Fullscreen => "Switch between fullscreen and a window",
+            Action::TileDebug => "Show the tiles and whether they came from the cache",
+            Action::Perf => "Show the drawing times, the loading queue, the memory and the cache hits",
+            Action::Compact => "Switch to a small window on top of the others, showing home",
+            Action::Help => "Show or hide this help",
+        }
+    }
+
+    /// The shortcuts of the action unless rebound.
+    pub fn default_shortcuts(self) -> Vec<Shortcut> {
+        use egui::{Key, Modifiers};
+        let (modifiers, key) = match self {
+            Action::Quit => (Modifiers::NONE, Key::Escape),
+            Action::PlayPause => (Modifiers::NONE, Key::Space),
+            Action::Reverse => (Modifiers::SHIFT, Key::Space),
+            Action::Faster => (Modifiers::NONE, Key::Plus),
+            Action::Slower => (Modifiers::NONE, Key::Minus),
+            Action::Previous => (Modifiers::NONE, Key::ArrowLeft),
+            Action::Next => (Modifiers::NONE, Key::ArrowRight),
+            Action::PreviousQuarter => (Modifiers::SHIFT, Key::ArrowLeft),
+            Action::NextQuarter => (Modifiers::SHIFT, Key::ArrowRight),
+            Action::PreviousHour => (Modifiers::COMMAND, Key::ArrowLeft),
+            Action::NextHour => (Modifiers::COMMAND, Key::ArrowRight),
+            Action::Oldest => (Modifiers::NONE, Key::Home),
+            Action::Latest => (Modifiers::NONE, Key::End),
+            Action::SaveSession => (Modifiers::COMMAND, Key::S),
+            Action::SaveFrame => (Modifiers::NONE, Key::S),
+            Action::SaveAnnotatedFrame => (Modifiers::SHIFT, Key::S),
+            Action::CopyFrame => (Modifiers::COMMAND, Key::C),
+            Action::FollowTrack => (Modifiers::NONE, Key::T),
+            Action::Stations => (Modifiers::NONE, Key::U),
+            Action::Nowcast => (Modifiers::NONE, Key::N),
+            Action::Interpolate => (Modifiers::NONE, Key::I),
+            Action::Crossfade => (Modifiers::NONE, Key::X),
+            Action::Adjustments => (Modifiers::NONE, Key::A),
+            Action::Histogram => (Modifiers::NONE, Key::H),
+            Action::Announcements => (Modifiers::NONE, Key::R),
+            Action::TimeZone => (Modifiers::NONE, Key::Z),
+            Action::Wind => (Modifiers::NONE, Key::W),
+            Action::Basemap => (Modifiers::NONE, Key::O),
+            Action::Measure => (Modifiers::NONE, Key::K),
+            Action::Annotate => (Modifiers::NONE, Key::E),
+            Action::SaveAnnotations => (Modifiers::SHIFT, Key::E),
+            Action::Lightning => (Modifiers::NONE, Key::L),
+            Action::Forecast => (Modifiers::NONE, Key::F),
+            Action::Filmstrip => (Modifiers::NONE, Key::J),
+            Action::Palette => (Modifiers::NONE, Key::P),
+            Action::Difference => (Modifiers::NONE, Key::D),
+            Action::Compare => (Modifiers::NONE, Key::V),
+            Action::GeoTiff => (Modifiers::NONE, Key::G),
+            Action::BrightnessUp => (Modifiers::NONE, Key::B),
+            Action::BrightnessDown => (Modifiers::SHIFT, Key::B),
+            Action::ContrastUp => (Modifiers::NONE, Key::C),
+            Action::ContrastDown => (Modifiers::SHIFT, Key::C),
+            Action::GammaUp => (Modifiers::NONE, Key::Y),
+            Action::GammaDown => (Modifiers::SHIFT, Key::Y),
+            Action::ResetAdjustments => (Modifiers::NONE, Key::Num0),
+            Action::Fullscreen => (Modifiers::NONE, Key::F11),
+            Action::TileDebug => (Modifiers::NONE, Key::F12),
+            Action::Perf => (Modifiers::NONE, Key::F10),
+            Action::Compact => (Modifiers::NONE, Key::M),
+            Action::Help => (Modifiers::NONE, Key::F1),
+        };
+        let mut shortcuts = vec![Shortcut(egui::KeyboardShortcut::new(modifiers, key))];
+        let other = match self {
+            Action::Help => Some(Key::Questionmark),
+            Action::Faster => Some(Key::CloseBracket),
+            Action::Slower => Some(Key::OpenBracket),
+            _ => None,
+        };
+        if let Some(other) = other {
+            shortcuts.push(Shortcut(egui::KeyboardShortcut::new(Modifiers::NONE, other)));
+        }
+        shortcuts
+    }
+}
+
+/// The keys showing the region of a tab, 1 to 9 without modifiers, which
+/// cannot be bound.
+pub const TAB_KEYS: [egui::Key; 9] = [
+    egui::Key::Num1,
+    egui::Key::Num2,
+    egui::Key::Num3,
+    egui::Key::Num4,
+    egui::Key::Num5,
+    egui::Key::Num6,
+    egui::Key::Num7,
+    egui::Key::Num8,
+    egui::Key::Num9,
+];
+
+/// The mouse gestures and what they do, for the help.
+pub const GESTURES: [(&str, &str); 11] = [
+    ("1 to 9", "Show the region of that tab, with region tabs"),
+    ("Alt (held)", "The value, position and time of the pixel under the mouse"),
+    ("Click", "A corner of the region being defined, or an end of the distance measured"),
+    ("Click a station", "Its latest sounding, with the stations shown"),
+    ("Right-click", "Copy the latitude and longitude of the point, or add a marker there"),
+    ("Wheel", "Zoom both frames when comparing, or step through the frames over the filmstrip"),
+    ("Shift+Wheel", "Step through the frames"),
+    ("Drag", "Pan both frames when comparing, or draw an arrow or a circle when annotating"),
+    ("Double-click", "Reset the zoom when comparing"),
+    ("Click the minimap", "Center both frames there when comparing zoomed in"),
+    ("Drop a file", "Load a GPX track, a GeoJSON overlay or annotations, a session or a folder of frames"),
+];
+
+/// A key and the modifiers held with it, written `Ctrl+Shift+S`.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq, Hash)]
+#[serde(try_from = "String", into = "String")]
+pub struct Shortcut(pub egui::KeyboardShortcut);
+
+impl Shortcut {
+    /// Whether the shortcut was pressed during this frame, with exactly its
+    /// modifiers, but for Shift with punctuation as many layouts need it.
+    pub fn pressed(&self, input: &egui::InputState) -> bool {
+        let modifiers = self.0.modifiers;
+        // Ctrl+C and Ctrl+X come as clipboard events rather than keys
+        let clipboard_event = match self.0.logical_key {
+            egui::Key::C => Some(egui::Event::Copy),
+            egui::Key::X => Some(egui::Event::Cut),
+            _ => None,
+        };
+        if modifiers == egui::Modifiers::COMMAND && clipboard_event.is_some_and(|event| input.events.contains(&event)) {
+            return true;
+        }
+        let shifted = is_punctuation(self.0.logical_key) && input.modifiers.matches_exact(modifiers | egui::Modifiers::SHIFT);
+        input.key_pressed(self.0.logical_key) && (input.modifiers.matches_exact(modifiers) || shifted)
+    }
+
+    /// Whether the shortcut is held down, with its modifiers as for
+    /// `pressed`.
+    pub fn down(&self, input: &egui::InputState) -> bool {
+        let modifiers = self.0.modifiers;
+        let shifted = is_punctuation(self.0.logical_key) && input.modifiers.matches_exact(modifiers | egui::Modifiers::SHIFT);
+        input.key_down(self.0.logical_key) && (input.modifiers.matches_exact(modifiers) || shifted)
+    }
+}
+
+/// Whether `key` is written with a punctuation character, `?` or `+`.
+fn is_punctuation(key: egui::Key) -> bool {
+    key.symbol_or_name().chars().all(|c| c.is_ascii_punctuation())
+}
+
+impl TryFrom<String> for Shortcut {
+    type Error = String;
+
+    fn try_from(text: String) -> Result<Self, String> {
+        let mut modifiers = egui::Modifiers::NONE;
+        // `+` alone, or after a modifier, is the key
+        let (prefix, key) = match (text.strip_suffix("++"), text.rsplit_once('+')) {
+            _ if text == "+" => (None, "+"),
+            (Some(prefix), _) => (Some(prefix), "+"),
+            (None, Some((prefix, key))) => (Some(prefix), key),
+            (None, None) => (None, text.as_str()),
+        };
+        for modifier in prefix.into_iter().flat_map(|prefix| prefix.split('+')) {
+            modifiers |= match modifier.to_lowercase().as_str() {
+                "ctrl" | "cmd" | "command" => egui::Modifiers::COMMAND,
+                "shift" => egui::Modifiers::SHIFT,
+                "alt" => egui::Modifiers::ALT,
+                _ => return Err(format!("unknown modifier {} in {}, expected Ctrl, Shift or Alt", modifier, text)),
+            };
+        }
+        let key = egui::Key::from_name(key).ok_or_else(|| format!("unknown key {} in {}", key, text))?;
+        Ok(Shortcut(egui::KeyboardShortcut::new(modifiers, key)))
+    }
+}
+
+impl From<Shortcut> for String {
+    fn from(shortcut: Shortcut) -> String {
+        shortcut.to_string()
+    }
+}
+
+impl fmt::Display for Shortcut {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let modifiers = self.0.modifiers;
+        if modifiers.command || modifiers.ctrl {
+            write!(f, "Ctrl+")?;
+        }
+        if modifiers.alt {
+            write!(f, "Alt+")?;
+        }
+        if modifiers.shift {
+            write!(f, "Shift+")?;
+        }
+        let key = self.0.logical_key;
+        write!(f, "{}", if is_punctuation(key) { key.symbol_or_name() } else { key.name() })
+    }
+}
+
+/// The shortcuts of an action rebound: one, or a list.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(untagged)]
+enum Bindings {
+    One(Shortcut),
+    Several(Vec<Shortcut>),
+}
+
+/// The shortcuts rebound, the others keeping their default.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+#[serde(transparent)]
+pub struct Keymap(HashMap<Action, Bindings>);
+
+impl Keymap {
+    /// The shortcuts of `action`.
+    pub fn shortcuts(&self, action: Action) -> Vec<Shortcut> {
+        match self.0.get(&action) {
+            Some(Bindings::One(shortcut)) => vec![*shortcut],
+            Some(Bindings::Several(shortcuts)) => shortcuts.clone(),
+            None => action.default_shortcuts(),
+        }
+    }
+
+    /// Whether a shortcut of `action` was pressed during this frame.
+    pub fn pressed(&self, ctx: &egui::Context, action: Action) -> bool {
+        // Keys typed in a text field are not shortcuts
+        if ctx.wants_keyboard_input() {
+            return false;
+        }
+        let shortcuts = self.shortcuts(action);
+        ctx.input(|i| shortcuts.iter().any(|shortcut| shortcut.pressed(i)))
+    }
+
+    /// Whether a shortcut of `action` is held down.
+    pub fn held(&self, ctx: &egui::Context, action: Action) -> bool {
+        let shortcuts = self.shortcuts(action);
+        ctx.input(|i| shortcuts.iter().any(|shortcut| shortcut.down(i)))
+    }
+
+    /// Check that no two actions share a shortcut, and that none takes a
+    /// key of the tabs.
+    pub fn check(&self) -> Result<(), String> {
+        let mut bound: HashMap<Shortcut, Action> = HashMap::new();
+        for action in Action::ALL {
+            for shortcut in self.shortcuts(action) {
+                if shortcut.0.modifiers.is_none() && TAB_KEYS.contains(&shortcut.0.logical_key) {
+                    return Err(format!("{} shows the region of a tab, it cannot be bound to {:?}", shortcut, action));
+                }
+                if let Some(other) = bound.insert(shortcut, action).filter(|other| *other != action) {
+                    return Err(format!("{} is bound to both {:?} and {:?}", shortcut, other, action));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_clashes() {
+        let keymap = |text: &str| ron::from_str::<Keymap>(text).unwrap();
+        assert_eq!(Keymap::default().check(), Ok(()));
+        assert_eq!(keymap("{Quit: \"Shift+1\"}").check(), Ok(()));
+        assert!(keymap("{Quit: \"Space\"}").check().unwrap_err().contains("bound to both"));
+        assert!(keymap("{Histogram: [\"H\", \"3\"]}").check().unwrap_err().contains("region of a tab"));
+    }
+}
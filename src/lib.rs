@@ -0,0 +1,68 @@
+//! Nuage shows an animation of the last satellite images of western europe.
+//!
+//! Besides the `nuage` application, the crate can be used to embed the
+//! animated view in another egui application with [`Viewer`], or to fetch and
+//! cache the frames without any UI:
+//! - a [`Provider`] lists the timestamps of the frames and gets them,
+//!   [`Infoplaza`] downloads them and keeps them in a [`Cache`], [`LocalFolder`]
+//!   reads them from a folder,
+//! - a [`Timeline`] loads the frames of a provider in the background into a
+//!   [`FrameSequence`],
+//! - [`projection`] places geographic coordinates on the frames,
+//! - [`archive`] queries the frames kept in the cache.
+
+pub mod accessibility;
+pub mod analysis;
+pub mod annotation;
+pub mod archive;
+pub mod basemap;
+pub mod cache;
+pub mod config;
+pub mod error;
+pub mod export;
+pub mod failover;
+pub mod ffi;
+pub mod fixtures;
+pub mod forecast;
+pub mod geo;
+pub mod gpx;
+pub mod hud;
+pub mod json;
+pub mod keymap;
+pub mod lightning;
+pub mod live;
+pub mod metered;
+pub mod mosaic;
+pub mod notify;
+pub mod overlay;
+pub mod palettes;
+pub mod power;
+pub mod print;
+pub mod processing;
+pub mod projection;
+pub mod provider;
+#[cfg(feature = "python")]
+pub mod python;
+pub mod quality;
+pub mod render;
+pub mod scene;
+pub mod server;
+pub mod sounding;
+pub mod template;
+pub mod timeline;
+pub mod timezone;
+pub mod tray;
+pub mod wallpaper;
+pub mod wind;
+pub mod wms;
+pub mod wmts;
+mod viewer;
+mod websocket;
+
+pub use cache::Cache;
+pub use config::{Config, Session};
+pub use error::NuageError;
+pub use overlay::Overlay;
+pub use provider::{Channel, Infoplaza, LocalFiles, LocalFolder, Provider, RemoteServer};
+pub use timeline::{FrameSequence, SatImage, Timeline};
+pub use viewer::{PARIS, Viewer, install_fonts, load_image_from_memory};
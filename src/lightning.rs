@@ -0,0 +1,175 @@
+//! Lightning strikes, live from the Blitzortung.org community network, kept
+//! for the time span of the loop to be shown on the frames they fell on.
+
+use chrono::{DateTime, Duration, Utc};
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use crate::error::Result;
+use crate::geo::LonLat;
+use crate::json;
+use crate::projection::Bounds;
+use crate::websocket::WebSocket;
+
+/// How long strikes are kept, longer than the loop.
+const KEEP_HOURS: i64 = 3;
+
+/// How often the strikes older than `KEEP_HOURS` are dropped.
+const PRUNE: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// How long to wait before connecting again after the feed was lost.
+const RECONNECT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// The lightning feed and how its strikes are shown.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct LightningConfig {
+    /// The WebSocket of a Blitzortung server
+    pub url: String,
+    /// How long a strike stays on the frames after it, fading out, in minutes
+    pub minutes: u32,
+    /// Marker color, (red, green, blue)
+    pub color: (u8, u8, u8),
+    /// Show the strikes at startup
+    pub visible: bool,
+}
+
+impl Default for LightningConfig {
+    fn default() -> Self {
+        LightningConfig {
+            url: "wss://ws1.blitzortung.org/".into(),
+            minutes: 10,
+            color: (255, 255, 0),
+            visible: false,
+        }
+    }
+}
+
+/// A lightning strike.
+#[derive(Clone, Copy, Debug)]
+pub struct Strike {
+    pub time: DateTime<Utc>,
+    pub position: LonLat,
+}
+
+/// Undo the LZW-like compression of the Blitzortung messages: codes from 256
+/// on stand for sequences seen before.
+pub fn decode(text: &str) -> String {
+    let mut chars = text.chars();
+    let Some(first) = chars.next() else {
+        return String::new();
+    };
+    let mut dictionary: HashMap<u32, String> = HashMap::new();
+    let mut previous = first.to_string();
+    let mut output = previous.clone();
+    for (next_code, c) in (256..).zip(chars) {
+        let code = c as u32;
+        let entry = if code < 256 {
+            c.to_string()
+        } else if let Some(entry) = dictionary.get(&code) {
+            entry.clone()
+        } else {
+            // The sequence being defined
+            format!("{}{}", previous, previous.chars().next().unwrap_or(c))
+        };
+        output.push_str(&entry);
+        dictionary.insert(next_code, format!("{}{}", previous, entry.chars().next().unwrap_or(c)));
+        previous = entry;
+    }
+    output
+}
+
+/// The strike of a decoded Blitzortung message.
+pub fn parse_strike(text: &str) -> Option<Strike> {
+    let message = json::parse(text).ok()?;
+    // In nanoseconds
+    let time = message.get("time")?.as_f64()?;
+    let position = (message.get("lon")?.as_f64()?, message.get("lat")?.as_f64()?);
+    let time = DateTime::from_timestamp_millis((time / 1e6) as i64)?;
+    Some(Strike { time, position })
+}
+
+/// The strikes of a feed over a region, received in a separate thread.
+#[derive(Clone)]
+pub struct LightningFeed {
+    pub config: LightningConfig,
+    /// Strikes elsewhere are dropped as they arrive
+    pub bounds: Arc<Mutex<Bounds>>,
+    pub strikes: Arc<Mutex<Vec<Strike>>>,
+    pub last_error: Arc<Mutex<Option<String>>>,
+}
+
+impl LightningFeed {
+    /// Start listening to `config.url` for the strikes within `bounds`,
+    /// connecting again when the feed is lost. `ctx`, if any, is asked to
+    /// repaint as strikes arrive.
+    pub fn start(config: LightningConfig, bounds: Bounds, ctx: Option<egui::Context>) -> Self {
+        let feed = LightningFeed {
+            config,
+            bounds: Arc::new(Mutex::new(bounds)),
+            strikes: Arc::new(Mutex::new(vec![])),
+            last_error: Arc::new(Mutex::new(None)),
+        };
+        let listener = feed.clone();
+        std::thread::spawn(move || {
+            loop {
+                if let Err(e) = listener.listen(ctx.as_ref()) {
+                    log::warn!("{}: {}", listener.config.url, e);
+                    *listener.last_error.lock().unwrap() = Some(e.to_string());
+                }
+                std::thread::sleep(RECONNECT);
+            }
+        });
+        feed
+    }
+
+    /// Receive strikes until the connection fails.
+    fn listen(&self, ctx: Option<&egui::Context>) -> Result<()> {
+        log::info!("connecting to {}", self.config.url);
+        let mut socket = WebSocket::connect(&self.config.url)?;
+        // Subscribe to the strikes
+        socket.send_text("{\"a\":111}")?;
+        *self.last_error.lock().unwrap() = None;
+        let mut pruned = std::time::Instant::now();
+        loop {
+            let Some(strike) = parse_strike(&decode(&socket.read_text()?)) else {
+                continue;
+            };
+            if !self.bounds.lock().unwrap().contains(strike.position) {
+                continue;
+            }
+            let mut strikes = self.strikes.lock().unwrap();
+            if pruned.elapsed() >= PRUNE {
+                strikes.retain(|kept| strike.time - kept.time < Duration::hours(KEEP_HOURS));
+                pruned = std::time::Instant::now();
+            }
+            strikes.push(strike);
+            // Strikes come in bursts
+            if let Some(ctx) = ctx {
+                ctx.request_repaint_after(std::time::Duration::from_secs(1));
+            }
+        }
+    }
+
+    /// Keep the strikes within `bounds` from now on, those elsewhere
+    /// dropped.
+    pub fn set_bounds(&self, bounds: Bounds) {
+        *self.bounds.lock().unwrap() = bounds;
+        self.strikes.lock().unwrap().retain(|strike| bounds.contains(strike.position));
+    }
+
+    /// The strikes in the `config.minutes` up to `time` and how far they are
+    /// in that window, from 0 at `time` to 1.
+    pub fn strikes_before(&self, time: DateTime<Utc>) -> Vec<(Strike, f32)> {
+        let window = Duration::minutes(self.config.minutes.max(1) as i64);
+        self.strikes
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|strike| strike.time <= time && time - strike.time < window)
+            .map(|strike| (*strike, (time - strike.time).num_seconds() as f32 / window.num_seconds() as f32))
+            .collect()
+    }
+}
@@ -0,0 +1,150 @@
+//! Live positions of aircraft (ADS-B, from dump1090's `aircraft.json`) or
+//! vessels (AIS, from AIS-catcher's `ships.json`), polled in the background.
+
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+
+use crate::error::{NuageError, Result};
+use crate::geo::LonLat;
+use crate::json::{self, Json};
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub enum LiveKind {
+    /// dump1090 / readsb `aircraft.json`
+    Adsb,
+    /// AIS-catcher `ships.json`
+    Ais,
+}
+
+/// A live position source and the aircraft or vessels to show.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct LiveConfig {
+    pub kind: LiveKind,
+    pub url: String,
+    /// Callsigns, flight numbers, ship names, hex codes or MMSIs to show, all
+    /// of them if empty
+    pub callsigns: Vec<String>,
+    /// Marker color, (red, green, blue)
+    pub color: (u8, u8, u8),
+    /// Seconds between two polls
+    pub period: u64,
+}
+
+impl Default for LiveConfig {
+    fn default() -> Self {
+        LiveConfig {
+            kind: LiveKind::Adsb,
+            url: "http://localhost:8080/data/aircraft.json".into(),
+            callsigns: vec![],
+            color: (0, 255, 255),
+            period: 10,
+        }
+    }
+}
+
+/// An aircraft or a vessel.
+#[derive(Clone, Debug)]
+pub struct Target {
+    /// Callsign if known, identifier otherwise
+    pub name: String,
+    /// Every name the target answers to
+    identifiers: Vec<String>,
+    pub position: LonLat,
+    /// Course over ground, in degrees from north
+    pub heading: Option<f64>,
+}
+
+impl Target {
+    fn matches(&self, callsigns: &[String]) -> bool {
+        callsigns.is_empty()
+            || callsigns
+                .iter()
+                .any(|callsign| self.identifiers.iter().any(|id| id.eq_ignore_ascii_case(callsign.trim())))
+    }
+}
+
+/// A field as text, numbers (MMSIs) included.
+fn text(entry: &Json, key: &str) -> Option<String> {
+    match entry.get(key)? {
+        Json::String(text) => Some(text.trim().to_string()).filter(|text| !text.is_empty()),
+        Json::Number(number) => Some(number.to_string()),
+        _ => None,
+    }
+}
+
+/// The targets with a position in a dump1090 or AIS-catcher document.
+pub fn parse_targets(kind: LiveKind, text_json: &str) -> Result<Vec<Target>> {
+    let document = json::parse(text_json).map_err(|e| NuageError::Config(format!("invalid {:?} data: {}", kind, e)))?;
+    let (list, name_keys, id_keys, heading_key) = match kind {
+        LiveKind::Adsb => ("aircraft", &["flight"][..], &["flight", "hex", "r"][..], "track"),
+        LiveKind::Ais => ("ships", &["shipname", "callsign"][..], &["shipname", "callsign", "mmsi"][..], "cog"),
+    };
+    let entries = document
+        .get(list)
+        .and_then(Json::as_array)
+        .ok_or_else(|| NuageError::Config(format!("invalid {:?} data: no {} list", kind, list)))?;
+    let targets = entries
+        .iter()
+        .filter_map(|entry| {
+            let position = (entry.get("lon")?.as_f64()?, entry.get("lat")?.as_f64()?);
+            let identifiers: Vec<String> = id_keys.iter().filter_map(|key| text(entry, key)).collect();
+            let name = name_keys
+                .iter()
+                .find_map(|key| text(entry, key))
+                .or_else(|| identifiers.last().cloned())
+                .unwrap_or_default();
+            let heading = entry.get(heading_key).and_then(Json::as_f64);
+            Some(Target { name, identifiers, position, heading })
+        })
+        .collect();
+    Ok(targets)
+}
+
+/// The targets of a source, refreshed in a separate thread.
+#[derive(Clone)]
+pub struct LiveSource {
+    pub config: LiveConfig,
+    pub targets: Arc<Mutex<Vec<Target>>>,
+    pub last_error: Arc<Mutex<Option<String>>>,
+}
+
+impl LiveSource {
+    /// Start polling `config.url`. `ctx`, if any, is asked to repaint on each
+    /// update.
+    pub fn start(config: LiveConfig, ctx: Option<egui::Context>) -> Self {
+        let source = LiveSource {
+            config,
+            targets: Arc::new(Mutex::new(vec![])),
+            last_error: Arc::new(Mutex::new(None)),
+        };
+        let poller = source.clone();
+        std::thread::spawn(move || {
+            loop {
+                match poller.poll() {
+                    Ok(targets) => {
+                        *poller.targets.lock().unwrap() = targets;
+                        *poller.last_error.lock().unwrap() = None;
+                    }
+                    Err(e) => {
+                        log::warn!("{}: {}", poller.config.url, e);
+                        *poller.last_error.lock().unwrap() = Some(e.to_string());
+                    }
+                }
+                if let Some(ctx) = &ctx {
+                    ctx.request_repaint();
+                }
+                std::thread::sleep(crate::metered::poll_period(std::time::Duration::from_secs(poller.config.period.max(1))));
+            }
+        });
+        source
+    }
+
+    fn poll(&self) -> Result<Vec<Target>> {
+        let text = ureq::get(&self.config.url).call()?.body_mut().read_to_string()?;
+        let mut targets = parse_targets(self.config.kind, &text)?;
+        targets.retain(|target| target.matches(&self.config.callsigns));
+        Ok(targets)
+    }
+}
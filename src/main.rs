@@ -1,386 +1,1952 @@
-use chrono::prelude::*;
-use chrono::{DateTime, Duration, Utc};
+use chrono::{Datelike, TimeZone as _, Timelike};
 use eframe::egui;
-use image;
-use image::GenericImageView;
-use std::sync::{Arc, Condvar, Mutex};
-use ureq;
-
-const PARIS: (f32, f32) = (48.8575, 2.3514);
-const TILES: ((u16, u16), (u16, u16)) = ((41, 61), (50, 68));
-
-/// A helper function to load the image from bytes and create an egui texture.
-fn load_image_from_memory(image_bytes: &[u8], name: &str, ctx: &egui::Context) -> Result<egui::TextureHandle, String> {
-    // 1. Decode the image using the `image` crate.
-    let image = image::load_from_memory_with_format(image_bytes, image::ImageFormat::Png)
-        .map_err(|e| format!("Failed to decode PNG: {}", e))?;
-
-    // 2. Convert the image to a format `egui` can use.
-    let size = [image.width() as usize, image.height() as usize];
-    let image_buffer = image.to_rgba8();
-    let pixels = image_buffer.as_flat_samples();
-    let color_image = egui::ColorImage::from_rgba_unmultiplied(size, pixels.as_slice());
-
-    // 3. Load the image into an `egui` texture.
-    Ok(ctx.load_texture(name, color_image, Default::default()))
-}
+use std::collections::HashMap;
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 
-fn get_image(
-    year: i32,
-    month: u32,
-    day: u32,
-    hour: u32,
-    minute: u32,
-    zoom: u16,
-    x1: u16,
-    y1: u16,
-    x2: u16,
-    y2: u16,
-) -> Result<image::RgbImage, Box<dyn std::error::Error>> {
-    let username = std::env::var("USER").unwrap();
-    let standard_cache_folder =
-        std::env::var("XDG_CACHE_HOME").unwrap_or(format!("/home/{}/.cache", username));
-    let nuage_cache_folder = format!("{}/nuage/", standard_cache_folder);
-    if !std::fs::exists(&nuage_cache_folder)? {
-        std::fs::create_dir_all(&nuage_cache_folder)?;
-    }
-
-    let filepath = format!(
-        "{}/{}{:0>2}{:0>2}{:0>2}{:0>2}_{}_{}_{}_{}_{}.jpg",
-        &nuage_cache_folder, year, month, day, hour, minute, zoom, x1, y1, x2, y2,
-    );
-
-    if !std::fs::exists(&filepath)? {
-        let url = format!(
-            "https://imn-rust-lb.infoplaza.io/v4/nowcast/tiles/satellite-europe/{}{:0>2}{:0>2}{:0>2}{:0>2}/{}/{}/{}/{}/{}?outputtype=jpeg",
-            year, month, day, hour, minute, zoom, x1, y1, x2, y2
-        );
-        println!("fetching {}", url);
-        let mut res = ureq::get(url).call()?;
-        let image_bytes = res
-            .body_mut()
-            .with_config()
-            .limit(20 * 1024 * 1024)
-            .read_to_vec()?;
-        let img = image::load_from_memory(&image_bytes)?;
-        let screen_width = 1920;
-        let screen_height = 1080;
-        let (width, height) = img.dimensions();
-        let (new_width, new_height) = if width > screen_width || height > screen_height {
-            let typical_screen_ratio = screen_width as f32 / screen_height as f32;
-            let image_ratio = width as f32 / height as f32;
-            if image_ratio < typical_screen_ratio {
-                (
-                    (width as f32 / (height as f32 / screen_height as f32)) as u32,
-                    screen_height,
-                )
-            } else {
-                (
-                    screen_width,
-                    (height as f32 / (width as f32 / screen_width as f32)) as u32,
-                )
+use nuage::lightning::LightningFeed;
+use nuage::basemap::BasemapLayer;
+use nuage::wind::WindLayer;
+use nuage::live::LiveSource;
+use nuage::scene::{self, SceneConfig};
+use nuage::forecast::{self, Forecast};
+use nuage::{analysis, notify};
+use nuage::cache::CachePolicy;
+use nuage::annotation::{AnnotationLayer, Tool};
+use nuage::tray::{self, StatusTray, TrayEvent};
+use nuage::keymap::{Action, TAB_KEYS};
+use nuage::metered::LowData;
+use nuage::config::RegionPreset;
+use nuage::projection::{self, Georeference};
+use nuage::provider::{TILE_BYTES, TILE_SIZE, Tiles};
+use nuage::render::{Difference, TimeZone};
+use nuage::{Cache, Channel, Config, Infoplaza, LocalFiles, LocalFolder, Overlay, Provider, Session, Viewer, export, gpx, timeline};
+
+mod cli;
+
+/// How long a status message stays on screen, in seconds.
+const STATUS_DURATION: f64 = 3.0;
+
+/// How often the forecast at home is downloaded again, in seconds.
+const FORECAST_REFRESH: f64 = 3600.0;
+
+/// How often new frames are looked for in kiosk and compact modes.
+const REFRESH: std::time::Duration = std::time::Duration::from_secs(5 * 60);
+
+/// The size of the window in compact mode, in points.
+const COMPACT_SIZE: egui::Vec2 = egui::vec2(240., 240.);
+
+/// How long the quit key must be held to quit in kiosk mode, in seconds.
+const KIOSK_QUIT_HOLD: f64 = 3.0;
+
+/// The size, position and fullscreen state of the window, saved on exit in
+/// the configuration folder.
+const WINDOW_STATE: &str = "window.ron";
+
+/// How often the frames the cache policies no longer keep are removed.
+const PRUNE_EVERY: std::time::Duration = std::time::Duration::from_secs(3600);
+
+/// Remove the frames `policies` no longer keep from the cache, now and every
+/// `PRUNE_EVERY`, in a separate thread.
+fn prune_cache(policies: HashMap<Channel, CachePolicy>) {
+    std::thread::spawn(move || {
+        loop {
+            match Cache::default_location().and_then(|cache| cache.prune(&policies, chrono::Utc::now())) {
+                Ok(stats) if stats.frames > 0 => println!("cache: {} frames removed, {} bytes freed", stats.frames, stats.bytes),
+                Ok(_) => (),
+                Err(e) => eprintln!("cache: {}", e),
             }
-        } else {
-            (width, height)
-        };
-        let resized_img = img.resize(new_width, new_height, image::imageops::FilterType::Triangle);
-        resized_img.save(&filepath)?;
-    };
-    println!("reading {}", filepath);
-    let img = match image::ImageReader::open(filepath)?.decode()? {
-        image::DynamicImage::ImageRgb8(rgb_image) => rgb_image,
-        _ => return Err("Unsupported type of Jpeg".into()),
+            std::thread::sleep(PRUNE_EVERY);
+        }
+    });
+}
+
+/// The forecast at home, filled by the download thread.
+type ForecastSlot = Arc<Mutex<Option<Result<Forecast, String>>>>;
+
+/// Draw `values`, at `times`, as a small chart: bars from zero or a line,
+/// with a mark at `now`. The chart spans `range`, or the values.
+fn forecast_chart(
+    ui: &mut egui::Ui,
+    times: &[chrono::DateTime<chrono::Utc>],
+    values: &[Option<f64>],
+    range: Option<(f64, f64)>,
+    bars: bool,
+    color: egui::Color32,
+) {
+    let (rect, _) = ui.allocate_exact_size(egui::vec2(ui.available_width(), 60.), egui::Sense::hover());
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 0., egui::Color32::from_gray(20));
+    let known: Vec<f64> = values.iter().flatten().copied().collect();
+    let (Some(first), Some(last)) = (times.first(), times.last()) else {
+        return;
     };
-    Ok(img)
+    let (low, high) = range.unwrap_or_else(|| {
+        let low = known.iter().copied().fold(f64::INFINITY, f64::min);
+        let high = known.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        if bars { (0., high.max(1.)) } else { (low - 1., high + 1.) }
+    });
+    let span = (*last - *first).num_seconds().max(1) as f32;
+    let x = |time: &chrono::DateTime<chrono::Utc>| rect.left() + (*time - *first).num_seconds() as f32 / span * rect.width();
+    let y = |value: f64| rect.bottom() - ((value - low) / (high - low)) as f32 * rect.height();
+    let width = rect.width() / times.len() as f32;
+    let points: Vec<egui::Pos2> = times
+        .iter()
+        .zip(values)
+        .filter_map(|(time, value)| value.map(|value| egui::pos2(x(time), y(value))))
+        .collect();
+    if bars {
+        for point in &points {
+            let top = egui::pos2(point.x, point.y.min(rect.bottom() - 1.));
+            let bar = egui::Rect::from_min_max(top, egui::pos2(point.x + width, rect.bottom()));
+            painter.rect_filled(bar, 0., color);
+        }
+    } else {
+        painter.add(egui::Shape::line(points, egui::Stroke::new(1.5, color)));
+    }
+    let now = x(&chrono::Utc::now());
+    painter.vline(now, rect.y_range(), egui::Stroke::new(1., egui::Color32::WHITE));
 }
 
-fn convert_gps_to_pixels(_tiles: ((u16, u16), (u16, u16)), image_rect: &egui::Rect, _gps: (f32, f32)) -> (f32, f32) {
-    // Stopgap while trying to figure out the coordinate system which does not
-    // seem to follow slippy tiles.
-    let center_x: f32 = image_rect.min.x + (image_rect.max.x - image_rect.min.x) / 2.;
-    let center_y: f32 = image_rect.min.y + (image_rect.max.y - image_rect.min.y) / 2.;
-    (center_x * 1.045 as f32, center_y * 0.68 as f32)
+/// The longest span of frames loaded from the archive, not to run out of
+/// memory.
+const ARCHIVE_MAX_HOURS: i64 = 6;
+
+/// The archive window: the month shown by the calendar, the day picked and
+/// the times of the first and last frames, (hour, minute).
+struct ArchivePicker {
+    month: chrono::NaiveDate,
+    day: chrono::NaiveDate,
+    from: (u32, u32),
+    to: (u32, u32),
 }
 
-fn previous_time(now: DateTime<Utc>) -> Vec<DateTime<Utc>> {
-    let minute = now.minute();
-    let to_five: u32 = minute - (minute as f32 / 5.) as u32 * 5;
-    let now_at_five = now
-        .checked_sub_signed(Duration::minutes(to_five as i64))
-        .unwrap();
-
-    let mut result = vec![];
-    // can only access image older than 15 minutes
-    let delay = 15;
-    for x in (0..120).step_by(5) {
-        let timepoint = now_at_five
-            .checked_sub_signed(Duration::minutes(x + delay))
-            .unwrap();
-        result.push(timepoint);
-    }
-    result
+impl ArchivePicker {
+    /// The four hours up to now.
+    fn new(zone: TimeZone) -> Self {
+        let now = match zone {
+            TimeZone::Local => chrono::Local::now().naive_local(),
+            TimeZone::Utc => chrono::Utc::now().naive_utc(),
+        };
+        let day = now.date();
+        ArchivePicker {
+            month: day.with_day(1).unwrap_or(day),
+            day,
+            from: (now.hour().saturating_sub(4), 0),
+            to: (now.hour(), 0),
+        }
+    }
+
+    /// The span picked, in UTC, `None` for a time skipped by a daylight
+    /// saving change. A span ending before it starts ends the next day.
+    fn span(&self, zone: TimeZone) -> Option<(chrono::DateTime<chrono::Utc>, chrono::DateTime<chrono::Utc>)> {
+        let time = |(hour, minute): (u32, u32)| {
+            let naive = self.day.and_hms_opt(hour, minute, 0)?;
+            match zone {
+                TimeZone::Local => chrono::Local.from_local_datetime(&naive).earliest().map(|time| time.to_utc()),
+                TimeZone::Utc => Some(naive.and_utc()),
+            }
+        };
+        let (from, mut to) = (time(self.from)?, time(self.to)?);
+        if to <= from {
+            to += chrono::Duration::days(1);
+        }
+        Some((from, to))
+    }
+}
+
+/// A region shown in a tab, and the viewer of its frames while another tab
+/// is shown.
+struct RegionTab {
+    preset: RegionPreset,
+    parked: Option<(Viewer, Arc<dyn Provider>)>,
 }
 
-struct SatImage {
-    image: image::RgbImage,
-    timestamp: DateTime<Utc>,
+/// A viewer of the frames of `provider`, looking for new frames every
+/// `REFRESH` if `refreshing`.
+fn new_viewer(ctx: &egui::Context, provider: Arc<dyn Provider>, refreshing: bool) -> Viewer {
+    if refreshing { Viewer::refreshing(ctx, provider, REFRESH) } else { Viewer::new(ctx, provider) }
 }
 
+/// The median motion of the clouds up to the latest frame, by its time,
+/// `None` while it is being found.
+type HomeMotion = Option<(chrono::DateTime<chrono::Utc>, Option<(f32, f32)>)>;
+
+/// A time span, from and to.
+type TimeSpan = (chrono::DateTime<chrono::Utc>, chrono::DateTime<chrono::Utc>);
+
 struct MyApp {
-    // image: Result<egui::TextureHandle, String>,
-    sat_images: Arc<(Mutex<Vec<SatImage>>, Condvar)>,
-    image_index: usize,
-    auto_play: bool,
-    pinpoint_icon: egui::TextureHandle,
-    downloading: Arc<Mutex<bool>>,
+    viewer: Viewer,
+    provider: Arc<dyn Provider>,
+    // The folder played instead of downloading the frames, if any
+    local_folder: Option<PathBuf>,
+    // The nuage server the frames are played from otherwise, if any
+    server: Option<String>,
+    // The image files played instead, dropped on the window
+    local_files: Vec<PathBuf>,
+    // The satellite product downloaded
+    channel: Channel,
+    // Message shown under the header and the time it was set
+    status: Option<(String, f64)>,
+    // The time of the frame reflected in the window title and icon
+    home_status: Option<chrono::DateTime<chrono::Utc>>,
+    // Whether the rain on its way home was notified, not to repeat it
+    rain_notified: bool,
+    // The motion of the clouds for the status of home, found in the
+    // background
+    home_motion: Arc<Mutex<HomeMotion>>,
+    // The tray icon, if shown, and whether quitting was asked from it: the
+    // window only hides when closed while the icon is there
+    tray: Option<StatusTray>,
+    quitting: bool,
+    // The name of the scheduled scene shown
+    scene: Option<String>,
+    // Whether the brightness, contrast and gamma sliders are shown
+    adjustments_visible: bool,
+    // Whether the luminance histogram of the frame shown is shown
+    histogram_visible: bool,
+    // Whether the keys and mouse gestures are listed
+    help_visible: bool,
+    // Whether the forecast at home is shown next to the frames
+    forecast_visible: bool,
+    // Whether the thumbnails of the frames are shown under them
+    filmstrip_visible: bool,
+    // The forecast at home and the time it was asked for
+    forecast: Option<(ForecastSlot, f64)>,
+    // The lightning feed, started the first time strikes are shown
+    lightning: Option<LightningFeed>,
+    // The frames picked in the archive instead of the last hours, and
+    // the archive window when open
+    archive_span: Option<(chrono::DateTime<chrono::Utc>, chrono::DateTime<chrono::Utc>)>,
+    archive: Option<ArchivePicker>,
+    // The zoom and the name of the region being defined, and the presets
+    // saved
+    region_zoom: u16,
+    region_name: String,
+    region_presets: Vec<RegionPreset>,
+    // The regions shown in tabs and the index of the one shown
+    tabs: Vec<RegionTab>,
+    tab: usize,
+    // Whether the frames are downloaded in low-data mode
+    low_data: bool,
+    // Whether running unattended: no header, new frames looked for forever
+    // and the quit key to be held
+    kiosk: bool,
+    // Since when the quit key is held, in kiosk mode
+    quit_held_since: Option<f64>,
+    // Whether new frames are looked for periodically: in kiosk mode, from
+    // a nuage server, and once the compact mode was used
+    refreshing: bool,
+    // Whether only home is shown, in a small window on top of the others,
+    // and the size of the window before
+    compact: bool,
+    size_before_compact: Option<egui::Vec2>,
+    config: Config,
 }
 
 impl MyApp {
-    fn new(
-        cc: &eframe::CreationContext<'_>,
-    ) -> Self {
-        // Add a custom font
-        let font_bytes = include_bytes!("../VCR_OSD_MONO_1.001.ttf");
-        // Load fonts
-        let mut fonts = egui::FontDefinitions::default();
-        // Install my own font
-        fonts.font_data.insert(
-            "vcr".to_owned(),
-            egui::FontData::from_static(font_bytes).into(),
-        );
-        // Put my font first (highest priority):
-        fonts
-            .families
-            .entry(egui::FontFamily::Name("vcr".into()))
-            .or_default()
-            .insert(0, "vcr".to_owned());
-        // Tell egui to use the new `FontDefinitions`.
-        cc.egui_ctx.set_fonts(fonts);
-
-        // Build the time points use to create the image url
-        let now = Utc::now();
-        let timepoints = previous_time(now);
-        let sat_images = Arc::new((Mutex::new(Vec::<SatImage>::new()), Condvar::new()));
-        let sat_images_clone = sat_images.clone();
-        let downloading = Arc::new(Mutex::new(true));
-        let downloading_clone = downloading.clone();
-        let ctx = Arc::new(cc.egui_ctx.clone());
-        // Load the image in a separate thread
-        std::thread::spawn(move || {
-            for timepoint in timepoints {
-                match get_image(
-                    timepoint.year(),
-                    timepoint.month(),
-                    timepoint.day(),
-                    timepoint.hour(),
-                    timepoint.minute(),
-                    7,
-                    TILES.0.0,
-                    TILES.0.1,
-                    TILES.1.0,
-                    TILES.1.1,
-                ) {
-                    Ok(image) => {
-                        let (images, cvar) = &*sat_images;
-                        let mut images = images.lock().unwrap();
-                        images.push(SatImage {
-                            image,
-                            timestamp: timepoint,
+    fn new(cc: &eframe::CreationContext<'_>, provider: Arc<dyn Provider>, options: &cli::Options, config: Config) -> Self {
+        nuage::install_fonts(&cc.egui_ctx);
+        let (channel, kiosk) = (options.channel, options.kiosk);
+        // The server refreshes its frames, the client has to as well
+        let refreshing = kiosk || options.connect.is_some();
+        let mut app = Self {
+            viewer: new_viewer(&cc.egui_ctx, provider.clone(), refreshing),
+            provider,
+            local_folder: options.local_folder.clone(),
+            server: options.connect.clone(),
+            local_files: vec![],
+            channel,
+            status: None,
+            home_status: None,
+            rain_notified: false,
+            home_motion: Arc::new(Mutex::new(None)),
+            tray: None,
+            quitting: false,
+            scene: None,
+            adjustments_visible: false,
+            histogram_visible: false,
+            help_visible: false,
+            forecast_visible: config.forecast,
+            filmstrip_visible: config.filmstrip,
+            forecast: None,
+            lightning: None,
+            archive_span: None,
+            archive: None,
+            region_zoom: nuage::provider::ZOOM,
+            region_name: String::new(),
+            region_presets: RegionPreset::load_all().unwrap_or_default(),
+            tabs: vec![],
+            tab: 0,
+            low_data: nuage::metered::is_low_data(),
+            kiosk,
+            quit_held_since: None,
+            refreshing,
+            compact: false,
+            size_before_compact: None,
+            config: config.clone(),
+        };
+        // An unattended display has no one to click it
+        if config.tray && !kiosk {
+            match StatusTray::start(&cc.egui_ctx) {
+                Ok(tray) => app.tray = Some(tray),
+                Err(e) => eprintln!("{}", e),
+            }
+        }
+        app.viewer.set_hud(config.hud.clone());
+        app.viewer.set_interpolate(config.interpolate);
+        app.viewer.set_crossfade(config.crossfade);
+        // The motion between the frames takes too long to compute
+        if nuage::power::is_low_power() {
+            app.viewer.set_interpolate(false);
+        }
+        // An unattended display plays whatever has the focus
+        app.viewer.set_pause_unfocused(config.pause_unfocused && !kiosk);
+        app.viewer.set_resume_after(config.resume_after);
+        app.viewer.set_adjustments(config.adjustments);
+        // Before the palette, which may be one of the files
+        nuage::palettes::watch(cc.egui_ctx.clone());
+        app.viewer.set_palette(config.palette.clone());
+        app.viewer.set_announcements(config.announcements);
+        app.viewer.set_time_zone(config.time_zone);
+        app.viewer.set_timestamp_format(config.timestamp_format.clone());
+        app.viewer.set_keymap(config.keys.clone());
+        app.viewer.set_processing(config.processing.get(&channel).cloned().unwrap_or_default());
+        for overlay_config in &config.overlays {
+            match Overlay::from_config(overlay_config) {
+                Ok(overlay) => app.viewer.add_overlay(overlay),
+                Err(e) => app.set_status(&cc.egui_ctx, format!("Could not load overlay: {}", e)),
+            }
+        }
+        for live_config in &config.live {
+            let source = LiveSource::start(live_config.clone(), Some(cc.egui_ctx.clone()));
+            app.viewer.add_live_source(source);
+        }
+        if config.lightning.visible {
+            app.toggle_lightning(&cc.egui_ctx);
+        }
+        if config.wind.visible {
+            app.toggle_wind(&cc.egui_ctx);
+        }
+        if config.basemap.visible {
+            app.toggle_basemap(&cc.egui_ctx);
+        }
+        if let Some(gpx) = &options.gpx {
+            let message = app.load_track(&cc.egui_ctx, gpx);
+            app.set_status(&cc.egui_ctx, message);
+        }
+        // A client of a nuage server has no cache of its own
+        if !config.cache_policies.is_empty() && app.server.is_none() {
+            prune_cache(config.cache_policies.clone());
+        }
+        if app.downloading() {
+            app.open_tabs(&cc.egui_ctx);
+        }
+        app
+    }
+
+    /// Open the tabs of the configuration, showing the one of the region
+    /// asked for if any, the first otherwise, and start downloading the
+    /// frames of the others in the background.
+    fn open_tabs(&mut self, ctx: &egui::Context) {
+        for name in &self.config.region_tabs {
+            match RegionPreset::find(name) {
+                Some(preset) => self.tabs.push(RegionTab { preset, parked: None }),
+                None => eprintln!("no region named {}", name),
+            }
+        }
+        if self.tabs.is_empty() {
+            return;
+        }
+        let region = self.provider.region();
+        match self.tabs.iter().position(|tab| (tab.preset.tiles, tab.preset.zoom) == region) {
+            Some(index) => self.tab = index,
+            None => {
+                self.set_region(ctx, self.tabs[0].preset.tiles, self.tabs[0].preset.zoom);
+            }
+        }
+        for index in (0..self.tabs.len()).filter(|index| *index != self.tab) {
+            match self.tab_viewer(ctx, &self.tabs[index].preset) {
+                Ok(parked) => self.tabs[index].parked = Some(parked),
+                Err(e) => eprintln!("{}: {}", self.tabs[index].preset.name, e),
+            }
+        }
+    }
+
+    /// A viewer of the frames of `preset`, for the same channel and time
+    /// span, downloaded in the background.
+    fn tab_viewer(&self, ctx: &egui::Context, preset: &RegionPreset) -> Result<(Viewer, Arc<dyn Provider>), nuage::NuageError> {
+        let mut infoplaza = self.infoplaza(Cache::default_location()?);
+        (infoplaza.tiles, infoplaza.zoom) = (preset.tiles, preset.zoom);
+        infoplaza.span = self.span();
+        let provider: Arc<dyn Provider> = Arc::new(infoplaza);
+        let viewer = new_viewer(ctx, provider.clone(), self.refreshing);
+        viewer.timeline().set_background(true);
+        Ok((viewer, provider))
+    }
+
+    /// Show the region of tab `index`, the frames of the tab shown being
+    /// kept and downloaded in the background.
+    fn switch_tab(&mut self, ctx: &egui::Context, index: usize) {
+        if index == self.tab || index >= self.tabs.len() || !self.downloading() {
+            return;
+        }
+        let parked = match self.tabs[index].parked.take() {
+            Some(parked) => Ok(parked),
+            None => self.tab_viewer(ctx, &self.tabs[index].preset),
+        };
+        let (viewer, provider) = match parked {
+            Ok(parked) => parked,
+            Err(e) => return self.set_status(ctx, format!("Could not switch region: {}", e)),
+        };
+        viewer.timeline().set_background(false);
+        let previous = self.replace_viewer(ctx, viewer, provider);
+        previous.0.timeline().set_background(true);
+        self.tabs[self.tab].parked = Some(previous);
+        self.tab = index;
+        self.set_status(ctx, self.tabs[index].preset.name.clone());
+    }
+
+    /// Forget the frames of the tabs not shown, downloaded again when
+    /// shown, once the channel or the frames asked for changed.
+    fn forget_tabs(&mut self) {
+        for tab in &mut self.tabs {
+            tab.parked = None;
+        }
+    }
+
+    /// Play the frames of `local_folder`, or those of the nuage server or
+    /// download them if `None`, keeping the overlays.
+    fn set_source(&mut self, ctx: &egui::Context, local_folder: Option<PathBuf>) -> Result<(), String> {
+        let options = cli::Options {
+            local_folder: local_folder.clone(),
+            connect: self.server.clone(),
+            tile_server: self.config.tile_server.clone(),
+            channel: self.channel,
+            ..Default::default()
+        };
+        self.set_provider(ctx, options.provider()?);
+        self.local_folder = local_folder;
+        self.local_files.clear();
+        Ok(())
+    }
+
+    /// Whether the frames are downloaded from Infoplaza, rather than played
+    /// from a local folder, image files, a nuage server, a tile server, a
+    /// WMS or a WMTS.
+    fn downloading(&self) -> bool {
+        self.local_folder.is_none()
+            && self.local_files.is_empty()
+            && self.server.is_none()
+            && self.config.tile_server.is_none()
+            && self.config.wms.is_none()
+            && self.config.wmts.is_none()
+    }
+
+    /// Download the frames of `channel` instead, for the same region and
+    /// time span.
+    fn set_channel(&mut self, ctx: &egui::Context, channel: Channel) -> String {
+        if !self.downloading() {
+            return "The frames played have no other channel".to_string();
+        }
+        let mut infoplaza = match Cache::default_location() {
+            Ok(cache) => self.infoplaza(cache),
+            Err(e) => return format!("Could not switch channel: {}", e),
+        };
+        (infoplaza.tiles, infoplaza.zoom) = self.provider.region();
+        infoplaza.span = self.span();
+        infoplaza.channel = channel;
+        self.channel = channel;
+        self.set_provider(ctx, Arc::new(infoplaza));
+        self.forget_tabs();
+        format!("Channel {}", channel.name())
+    }
+
+    /// Turn off what costs the most to draw, for old or software OpenGL
+    /// drivers: in-between and cross-faded frames, animations and shadows.
+    fn reduce_effects(&mut self, ctx: &egui::Context) {
+        self.viewer.set_interpolate(false);
+        self.viewer.set_crossfade(false);
+        ctx.style_mut(|style| {
+            style.animation_time = 0.;
+            style.visuals.window_shadow = egui::epaint::Shadow::NONE;
+            style.visuals.popup_shadow = egui::epaint::Shadow::NONE;
+        });
+        self.set_status(ctx, "OpenGL renderer, fewer effects".to_string());
+    }
+
+    /// Download the frames of the tile window `tiles` at `zoom` instead, for
+    /// the same channel.
+    fn set_region(&mut self, ctx: &egui::Context, tiles: Tiles, zoom: u16) -> String {
+        if !self.downloading() {
+            return "The frames played cover a fixed region".to_string();
+        }
+        let mut infoplaza = match Cache::default_location() {
+            Ok(cache) => self.infoplaza(cache),
+            Err(e) => return format!("Could not switch region: {}", e),
+        };
+        (infoplaza.tiles, infoplaza.zoom) = (tiles, zoom);
+        infoplaza.span = self.span();
+        self.set_provider(ctx, Arc::new(infoplaza));
+        format!("Tiles {:?} at zoom {}", tiles, zoom)
+    }
+
+    /// Start or stop defining a region by clicking two corners on the
+    /// frames.
+    fn toggle_region(&mut self, ctx: &egui::Context) {
+        let defining = !self.viewer.is_defining_region();
+        self.viewer.set_defining_region(defining);
+        if defining {
+            self.region_zoom = self.provider.region().1;
+            match RegionPreset::load_all() {
+                Ok(presets) => self.region_presets = presets,
+                Err(e) => self.set_status(ctx, format!("Could not load the regions: {}", e)),
+            }
+        }
+    }
+
+    /// The window of the region being defined: the tile window covering the
+    /// corners clicked at the zoom chosen, what its frames weigh, and the
+    /// saved presets.
+    fn show_region(&mut self, ctx: &egui::Context) {
+        if !self.viewer.is_defining_region() {
+            return;
+        }
+        let mut open = true;
+        let mut chosen = None;
+        let mut save = false;
+        egui::Window::new("Region")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.add(egui::Slider::new(&mut self.region_zoom, 3..=10).text("Zoom"));
+                match self.viewer.region_corners() {
+                    None => {
+                        ui.label("Click two opposite corners on the frames");
+                    }
+                    Some((a, b)) => {
+                        let tiles = projection::tiles_covering(a, b, self.region_zoom);
+                        let (columns, rows) = ((tiles.1.0 - tiles.0.0 + 1) as u32, (tiles.1.1 - tiles.0.1 + 1) as u32);
+                        let ((x1, y1), (x2, y2)) = tiles;
+                        ui.label(format!("Tiles ({}, {}) to ({}, {}), {} × {}", x1, y1, x2, y2, columns, rows));
+                        ui.label(format!("Frames of {} × {} pixels", columns * TILE_SIZE, rows * TILE_SIZE));
+                        let hours = self.config.history_hours;
+                        let frames = timeline::previous_time(chrono::Utc::now(), hours, self.delay(), self.interval()).len();
+                        let megabytes = ((columns * rows) as u64 * TILE_BYTES) as f64 / 1e6;
+                        let total = megabytes * frames as f64;
+                        ui.label(format!("About {:.1} MB a frame, {:.0} MB for the {} frames of the loop", megabytes, total, frames));
+                        ui.horizontal(|ui| {
+                            ui.text_edit_singleline(&mut self.region_name);
+                            save = ui.add_enabled(!self.region_name.trim().is_empty(), egui::Button::new("Save")).clicked();
                         });
-                        cvar.notify_one();
-                        ctx.request_repaint();
+                        if ui.button("Show").clicked() {
+                            chosen = Some((tiles, self.region_zoom));
+                        }
+                    }
+                }
+                if !self.region_presets.is_empty() {
+                    ui.separator();
+                }
+                for preset in &self.region_presets {
+                    if ui.button(&preset.name).clicked() {
+                        chosen = Some((preset.tiles, preset.zoom));
                     }
-                    _ => {}
                 }
+            });
+        if save && let Some((a, b)) = self.viewer.region_corners() {
+            let preset = RegionPreset {
+                name: self.region_name.trim().to_string(),
+                tiles: projection::tiles_covering(a, b, self.region_zoom),
+                zoom: self.region_zoom,
+            };
+            let message = match preset.save() {
+                Ok(presets) => {
+                    self.region_presets = presets;
+                    format!("Region {} saved", preset.name)
+                }
+                Err(e) => format!("Could not save the region: {}", e),
+            };
+            self.set_status(ctx, message);
+        }
+        if let Some((tiles, zoom)) = chosen {
+            let message = self.set_region(ctx, tiles, zoom);
+            self.set_status(ctx, message);
+        } else if !open {
+            self.viewer.set_defining_region(false);
+        }
+    }
+
+    /// Play the frames of `provider`, keeping the overlays, the track, the
+    /// markers, the annotations, the live sources and the display settings.
+    fn set_provider(&mut self, ctx: &egui::Context, provider: Arc<dyn Provider>) {
+        let markers = self.viewer.markers().to_vec();
+        let annotations = self.viewer.annotations().to_vec();
+        self.replace_viewer(ctx, new_viewer(ctx, provider.clone(), self.refreshing), provider);
+        self.viewer.set_markers(markers);
+        self.viewer.set_annotations(annotations);
+    }
+
+    /// Show `viewer`, playing the frames of `provider`, instead of the
+    /// current viewer, returned with its provider, moving the overlays, the
+    /// track, the live sources and the display settings to it.
+    fn replace_viewer(&mut self, ctx: &egui::Context, mut viewer: Viewer, provider: Arc<dyn Provider>) -> (Viewer, Arc<dyn Provider>) {
+        let overlays = std::mem::take(self.viewer.overlays_mut());
+        let track = self.viewer.track().cloned();
+        let track_sync = self.viewer.is_track_synced();
+        let nowcast = self.viewer.is_nowcasting();
+        let interpolate = self.viewer.is_interpolating();
+        let crossfade = self.viewer.is_crossfading();
+        let pause_unfocused = self.viewer.pauses_unfocused();
+        let resume_after = self.viewer.resume_after();
+        let tile_debug = self.viewer.tile_debug();
+        let perf_visible = self.viewer.perf_visible();
+        let adjustments = self.viewer.adjustments();
+        let palette = self.viewer.palette().clone();
+        let live_sources = self.viewer.live_sources().to_vec();
+        let live_visible = self.viewer.live_visible();
+        let lightning = self.viewer.lightning().cloned();
+        // The wind is downloaded again for another region, the strikes kept
+        // over it
+        let (tiles, zoom) = provider.region();
+        if let Some(feed) = &self.lightning
+            && (tiles, zoom) != self.provider.region()
+        {
+            feed.set_bounds(projection::tiles_bounds(tiles, zoom));
+        }
+        let wind = self.viewer.wind().cloned().map(|layer| {
+            if (tiles, zoom) == self.provider.region() {
+                layer
+            } else {
+                WindLayer::start(layer.config, projection::tiles_bounds(tiles, zoom), Some(ctx.clone()))
             }
-            *downloading.lock().unwrap() = false;
         });
+        // And the map
+        let basemap = self.viewer.basemap().cloned().map(|layer| match layer.region == (tiles, zoom) {
+            true => layer,
+            false => BasemapLayer::start(layer.config, (tiles, zoom), Some(ctx.clone())),
+        });
+        let hud = self.viewer.hud().clone();
+        let comparing = self.viewer.is_comparing();
+        let difference = self.viewer.difference();
+        let announcements = self.viewer.announcements();
+        let time_zone = self.viewer.time_zone();
+        let timestamp_format = self.viewer.timestamp_format().clone();
+        *viewer.overlays_mut() = overlays;
+        for source in live_sources {
+            viewer.add_live_source(source);
+        }
+        viewer.set_track(track, track_sync);
+        viewer.set_nowcast(nowcast);
+        viewer.set_live_visible(live_visible);
+        viewer.set_lightning(lightning);
+        viewer.set_wind(wind);
+        viewer.set_basemap(basemap);
+        viewer.set_hud(hud);
+        viewer.set_interpolate(interpolate);
+        viewer.set_crossfade(crossfade);
+        viewer.set_pause_unfocused(pause_unfocused);
+        viewer.set_resume_after(resume_after);
+        viewer.set_tile_debug(tile_debug);
+        viewer.set_perf_visible(perf_visible);
+        viewer.set_adjustments(adjustments);
+        viewer.set_palette(palette);
+        viewer.set_comparing(comparing);
+        viewer.set_difference(difference);
+        viewer.set_announcements(announcements);
+        viewer.set_time_zone(time_zone);
+        viewer.set_timestamp_format(timestamp_format);
+        viewer.set_keymap(self.config.keys.clone());
+        // The channel may have changed
+        viewer.set_processing(self.config.processing.get(&self.channel).cloned().unwrap_or_default());
+        let viewer = std::mem::replace(&mut self.viewer, viewer);
+        (viewer, std::mem::replace(&mut self.provider, provider))
+    }
 
-        Self {
-            image_index: 0,
-            sat_images: sat_images_clone,
-            auto_play: true,
-            pinpoint_icon: load_image_from_memory(
-                include_bytes!("../pinpoint-icon.png"),
-                "pinpoint_icon", &cc.egui_ctx).expect("Could not load pinpoint"),
-            downloading: downloading_clone,
+    /// Show or hide the lightning strikes, connecting to the feed the first
+    /// time.
+    fn toggle_lightning(&mut self, ctx: &egui::Context) {
+        if self.viewer.lightning().is_some() {
+            self.viewer.set_lightning(None);
+            self.set_status(ctx, "Lightning hidden".to_string());
+            return;
         }
+        let (tiles, zoom) = self.provider.region();
+        let bounds = projection::tiles_bounds(tiles, zoom);
+        let feed = self
+            .lightning
+            .get_or_insert_with(|| LightningFeed::start(self.config.lightning.clone(), bounds, Some(ctx.clone())));
+        self.viewer.set_lightning(Some(feed.clone()));
+        self.set_status(ctx, format!("Lightning from {}", self.config.lightning.url));
     }
 
-    fn increase_image_index(image_index: &mut usize, nb_images: usize) {
-        if *image_index == nb_images - 1 {
-            *image_index = 0;
-        } else {
-            *image_index += 1;
+    /// Show or hide the wind barbs, downloading the wind over the region
+    /// when shown.
+    fn toggle_wind(&mut self, ctx: &egui::Context) {
+        if self.viewer.wind().is_some() {
+            self.viewer.set_wind(None);
+            self.set_status(ctx, "Wind hidden".to_string());
+            return;
         }
+        let (tiles, zoom) = self.provider.region();
+        let layer = WindLayer::start(self.config.wind.clone(), projection::tiles_bounds(tiles, zoom), Some(ctx.clone()));
+        self.viewer.set_wind(Some(layer));
+        self.set_status(ctx, "Wind at 10 m".to_string());
     }
 
-    fn decrease_image_index(image_index: &mut usize, nb_images: usize) {
-        if *image_index == 0 {
-            *image_index = nb_images - 1;
-        } else {
-            *image_index -= 1;
+    /// Draw the frames over the map or not, downloading the map of the
+    /// region when shown.
+    fn toggle_basemap(&mut self, ctx: &egui::Context) {
+        if self.viewer.basemap().is_some() {
+            self.viewer.set_basemap(None);
+            self.set_status(ctx, "Map hidden".to_string());
+            return;
         }
+        let layer = BasemapLayer::start(self.config.basemap.clone(), self.provider.region(), Some(ctx.clone()));
+        self.viewer.set_basemap(Some(layer));
+        self.set_status(ctx, "Frames over the map, opacity in the image sliders".to_string());
     }
-}
 
-impl eframe::App for MyApp {
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        // Check if the user has pressed the Escape key.
-        if ctx.input(|i| i.key_pressed(egui::Key::Escape)) {
-            // If so, tell the frame to close.
-            ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+    /// Show a GPX track and follow its time. Frames older than the last two
+    /// hours are downloaded for the time of the track.
+    fn load_track(&mut self, ctx: &egui::Context, path: &Path) -> String {
+        let (track, overlay) = match (gpx::read_gpx(path), Overlay::load(path)) {
+            (Ok(track), Ok(overlay)) => (track, overlay),
+            (Err(e), _) | (_, Err(e)) => return format!("Could not load track: {}", e),
+        };
+        let time_range = track.time_range();
+        let message = match time_range {
+            Some((from, to)) => format!(
+                "Loaded track {} ({} points, {} to {})",
+                track.name,
+                track.points.len(),
+                self.viewer.timestamp_label(from),
+                self.viewer.timestamp_label(to),
+            ),
+            None => format!("Loaded track {} ({} points, no time)", track.name, track.points.len()),
+        };
+        self.viewer.add_overlay(overlay);
+        self.viewer.set_track(Some(track), time_range.is_some());
+        let Some(span) = self.track_span() else {
+            return message;
+        };
+        if self.downloading() {
+            let cache = match Cache::default_location() {
+                Ok(cache) => cache,
+                Err(e) => return format!("{}, but could not get its frames: {}", message, e),
+            };
+            let mut infoplaza = self.infoplaza(cache);
+            infoplaza.span = Some(span);
+            self.set_provider(ctx, Arc::new(infoplaza));
+        }
+        message
+    }
+
+    /// The frames downloaded: those picked in the archive, else those of
+    /// the track, else `None` for the last hours.
+    fn span(&self) -> Option<(chrono::DateTime<chrono::Utc>, chrono::DateTime<chrono::Utc>)> {
+        self.archive_span.or_else(|| self.track_span())
+    }
+
+    /// Download the frames between two times, or the last hours with
+    /// `None`, reading those already cached from the cache.
+    fn set_archive_span(&mut self, ctx: &egui::Context, span: Option<TimeSpan>) -> String {
+        if !self.downloading() {
+            return "The frames played have no archive".to_string();
+        }
+        if span.is_some() && self.config.cache_policies.get(&self.channel).is_some_and(|policy| !policy.archive) {
+            return format!("The {} channel is not archived", self.channel.name());
+        }
+        let mut infoplaza = match Cache::default_location() {
+            Ok(cache) => self.infoplaza(cache),
+            Err(e) => return format!("Could not load the archive: {}", e),
+        };
+        self.archive_span = span;
+        (infoplaza.tiles, infoplaza.zoom) = self.provider.region();
+        infoplaza.span = self.span();
+        let message = match span {
+            Some((from, to)) => {
+                let timestamps = timeline::time_range(from, to, self.interval());
+                let cached = timestamps
+                    .iter()
+                    .filter(|timestamp| {
+                        let path = infoplaza.cache().frame_path(**timestamp, infoplaza.zoom, infoplaza.tiles, infoplaza.channel);
+                        infoplaza.cache().contains(&path).unwrap_or(false)
+                    })
+                    .count();
+                format!(
+                    "{} to {}: {} frames, {} already cached",
+                    self.viewer.timestamp_label(from),
+                    self.viewer.timestamp_label(to),
+                    timestamps.len(),
+                    cached,
+                )
+            }
+            None => "Latest frames".to_string(),
+        };
+        self.set_provider(ctx, Arc::new(infoplaza));
+        self.forget_tabs();
+        message
+    }
+
+    /// The archive window: a calendar and the hours of the frames to load,
+    /// in the time zone shown.
+    fn show_archive(&mut self, ctx: &egui::Context) {
+        let interval = self.interval();
+        let Some(picker) = &mut self.archive else {
+            return;
+        };
+        let zone = self.viewer.time_zone();
+        let today = match zone {
+            TimeZone::Local => chrono::Local::now().date_naive(),
+            TimeZone::Utc => chrono::Utc::now().date_naive(),
+        };
+        let mut open = true;
+        let mut load = None;
+        egui::Window::new("Archive")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    if ui.button("<").clicked() {
+                        picker.month = picker.month - chrono::Months::new(1);
+                    }
+                    ui.label(picker.month.format("%B %Y").to_string());
+                    if ui.add_enabled(picker.month + chrono::Months::new(1) <= today, egui::Button::new(">")).clicked() {
+                        picker.month = picker.month + chrono::Months::new(1);
+                    }
+                });
+                egui::Grid::new("archive_calendar").show(ui, |ui| {
+                    for name in ["Mo", "Tu", "We", "Th", "Fr", "Sa", "Su"] {
+                        ui.label(name);
+                    }
+                    ui.end_row();
+                    let offset = picker.month.weekday().num_days_from_monday();
+                    for _ in 0..offset {
+                        ui.label("");
+                    }
+                    let days = picker.month.iter_days().take_while(|day| day.month() == picker.month.month());
+                    for (column, day) in (offset..).zip(days) {
+                        let label = egui::SelectableLabel::new(day == picker.day, day.day().to_string());
+                        if ui.add_enabled(day <= today, label).clicked() {
+                            picker.day = day;
+                        }
+                        if column % 7 == 6 {
+                            ui.end_row();
+                        }
+                    }
+                });
+                for (name, (hour, minute)) in [("From", &mut picker.from), ("To", &mut picker.to)] {
+                    ui.horizontal(|ui| {
+                        ui.label(name);
+                        ui.add(egui::DragValue::new(hour).range(0..=23));
+                        ui.label(":");
+                        ui.add(egui::DragValue::new(minute).range(0..=55).speed(0.2));
+                    });
+                }
+                let span = picker.span(zone);
+                match span {
+                    Some((from, to)) if to - from > chrono::Duration::hours(ARCHIVE_MAX_HOURS) => {
+                        ui.label(format!("At most {} hours at a time", ARCHIVE_MAX_HOURS));
+                    }
+                    Some((from, to)) => {
+                        ui.label(format!("{} frames", timeline::time_range(from, to, interval).len()));
+                        if ui.button("Load").clicked() {
+                            load = Some(Some((from, to)));
+                        }
+                    }
+                    None => {
+                        ui.label("No such time");
+                    }
+                }
+                if self.archive_span.is_some() && ui.button("Back to the latest frames").clicked() {
+                    load = Some(None);
+                }
+            });
+        if !open {
+            self.archive = None;
+        }
+        if let Some(span) = load {
+            let message = self.set_archive_span(ctx, span);
+            self.set_status(ctx, message);
+        }
+    }
+
+    /// Download smaller frames or full ones again when low-data mode
+    /// changes, for the same region, channel and time span.
+    fn update_low_data(&mut self, ctx: &egui::Context) {
+        // Checked again when the connection is
+        ctx.request_repaint_after(nuage::metered::CHECK_EVERY);
+        let low_data = nuage::metered::is_low_data();
+        // Fallbacks and local folders are left alone
+        if low_data == self.low_data || self.provider.name() != "infoplaza" {
+            return;
         }
-        // Get the lock on the images
-        let (sat_images, cvar) = &*self.sat_images;
-        let mut sat_images = sat_images.lock().unwrap();
-        // Check we have images
-        if sat_images.len() == 0 {
-            // Wait for images
-            while sat_images.len() == 0 {
-                sat_images = cvar.wait(sat_images).unwrap();
+        self.low_data = low_data;
+        let (tiles, zoom) = self.provider.region();
+        self.set_region(ctx, tiles, zoom);
+        self.forget_tabs();
+        let message = if low_data { "Low data: smaller frames, feeds polled less often" } else { "Full frames" };
+        self.set_status(ctx, message.to_string());
+    }
+
+    /// Minutes after which the frames are published.
+    fn delay(&self) -> u32 {
+        self.config.availability_delay.unwrap_or(nuage::provider::DELAY)
+    }
+
+    /// Minutes between two frames.
+    fn interval(&self) -> u32 {
+        self.config.frame_interval.unwrap_or(nuage::provider::INTERVAL)
+    }
+
+    /// The Infoplaza frames of the channel, cached in `cache`, with the
+    /// history, delay and interval of the configuration.
+    fn infoplaza(&self, cache: Cache) -> Infoplaza {
+        let mut infoplaza = Infoplaza::new(cache);
+        infoplaza.channel = self.channel;
+        infoplaza.products = self.config.channel_products.clone();
+        infoplaza.hours = self.config.history_hours;
+        infoplaza.delay = self.delay();
+        infoplaza.interval = self.interval();
+        infoplaza.low_data = self.low_data;
+        infoplaza
+    }
+
+    /// The time span of the track, with a margin, if older than the frames
+    /// shown.
+    fn track_span(&self) -> Option<TimeSpan> {
+        let (from, to) = self.viewer.track()?.time_range()?;
+        let recent = timeline::previous_time(chrono::Utc::now(), self.config.history_hours, self.delay(), self.interval());
+        let oldest_recent = *recent.last().unwrap();
+        let margin = chrono::Duration::minutes(self.interval() as i64);
+        (from < oldest_recent).then_some((from - margin, to + margin))
+    }
+
+    /// Toggle whether the animation follows the time of the track.
+    fn toggle_track_sync(&mut self, ctx: &egui::Context) {
+        let message = match self.viewer.track().map(|track| track.time_range().is_some()) {
+            None => "No track loaded".to_string(),
+            Some(false) => "The track has no time".to_string(),
+            Some(true) => {
+                let sync = !self.viewer.is_track_synced();
+                self.viewer.set_track_sync(sync);
+                if sync { "Following the track" } else { "Playing all frames" }.to_string()
+            }
+        };
+        self.set_status(ctx, message);
+    }
+
+    /// Load a GeoJSON file as an overlay.
+    fn load_overlay(&mut self, path: &Path) -> String {
+        match Overlay::load(path) {
+            Ok(overlay) => {
+                let message = format!("Loaded overlay {} ({} features)", overlay.name, overlay.features.len());
+                self.viewer.add_overlay(overlay);
+                message
             }
+            Err(e) => format!("Could not load overlay: {}", e),
         }
-        let time = ctx.input(|i| i.time);
-        if self.auto_play {
-            // Let's say an image every 1/5th of a second
-            let cycle_duration = sat_images.len() as f64 / 5.;
-            let time_in_cycle = time % cycle_duration;
-            self.image_index = sat_images.len() - 1 - (time_in_cycle * sat_images.len() as f64 / cycle_duration) as usize;
-            ctx.request_repaint();
-        }
-        // Images are order from the most recent to the least.
-        // Index 0 is the most recent.
-        // Navigate the image with left...
-        if ctx.input(|i| i.key_pressed(egui::Key::ArrowRight)) {
-            self.auto_play = false;
-            MyApp::decrease_image_index(&mut self.image_index, sat_images.len());
-        }
-        // ... and right.
-        if ctx.input(|i| i.key_pressed(egui::Key::ArrowLeft)) {
-            self.auto_play = false;
-            MyApp::increase_image_index(&mut self.image_index, sat_images.len());
-        }
-        // Pause / Unpaause on space
-        if ctx.input(|i| i.key_pressed(egui::Key::Space)) {
-            self.auto_play = !self.auto_play;
-        }
-
-        let sat_image = &sat_images[self.image_index];
-        let dimensions = sat_image.image.dimensions();
-        let color_image = egui::ColorImage::from_rgb(
-            [dimensions.0 as usize, dimensions.1 as usize],
-            &sat_image.image.as_raw(),
+    }
+
+    /// Play the frames of a folder.
+    fn load_folder(&mut self, ctx: &egui::Context, folder: &Path) -> String {
+        match LocalFolder::new(folder).timepoints(chrono::Utc::now()) {
+            Ok(timepoints) if timepoints.is_empty() => format!("No frames in {}", folder.display()),
+            Ok(timepoints) => match self.set_source(ctx, Some(folder.to_path_buf())) {
+                Ok(()) => format!("Playing {} frames from {}", timepoints.len(), folder.display()),
+                Err(e) => format!("Could not play {}: {}", folder.display(), e),
+            },
+            Err(e) => format!("Could not read {}: {}", folder.display(), e),
+        }
+    }
+
+    /// Play the image files of `paths` with a time in their names.
+    fn load_files(&mut self, ctx: &egui::Context, paths: Vec<PathBuf>) -> String {
+        let files = LocalFiles::new(&paths);
+        if files.is_empty() {
+            return "No time in the names of the images, like 20250101_1530".to_string();
+        }
+        let message = format!("Playing {} of the {} images", files.len(), paths.len());
+        self.set_provider(ctx, Arc::new(files));
+        self.local_folder = None;
+        self.local_files = paths;
+        message
+    }
+
+    /// Restore a session saved with Ctrl+S.
+    fn load_session(&mut self, ctx: &egui::Context, path: &Path) -> String {
+        let session = match Session::load(path) {
+            Ok(session) => session,
+            Err(e) => return format!("Could not load session: {}", e),
+        };
+        if let Err(e) = self.set_source(ctx, session.local_folder) {
+            return format!("Could not load session: {}", e);
+        }
+        self.viewer.overlays_mut().clear();
+        self.viewer.set_annotations(session.annotations);
+        let mut failed = 0;
+        for overlay_config in &session.overlays {
+            match Overlay::from_config(overlay_config) {
+                Ok(overlay) => self.viewer.add_overlay(overlay),
+                Err(e) => {
+                    eprintln!("could not load overlay: {}", e);
+                    failed += 1;
+                }
+            }
+        }
+        match failed {
+            0 => format!("Restored session {}", path.display()),
+            _ => format!("Restored session {} ({} overlays could not be loaded)", path.display(), failed),
+        }
+    }
+
+    /// Save the frames source, the overlays and the annotations in the
+    /// current directory.
+    fn save_session(&mut self, ctx: &egui::Context) {
+        let filename = format!("nuage_{}.nuage", self.viewer.timestamp_format().file_part(chrono::Local::now()));
+        let session = Session {
+            local_folder: self.local_folder.clone(),
+            overlays: self.viewer.overlays_mut().iter().map(Overlay::config).collect(),
+            annotations: self.viewer.annotations().to_vec(),
+        };
+        let message = match session.save(Path::new(&filename)) {
+            Ok(()) => format!("Saved {}", filename),
+            Err(e) => format!("Could not save {}: {}", filename, e),
+        };
+        self.set_status(ctx, message);
+    }
+
+    /// Save the annotations as GeoJSON in the current directory, with the
+    /// time of the frame shown and the region.
+    fn save_annotations(&mut self, ctx: &egui::Context) {
+        let Some(sat_image) = self.viewer.current_frame().filter(|_| !self.viewer.annotations().is_empty()) else {
+            return self.set_status(ctx, "No annotations to save".to_string());
+        };
+        let layer = AnnotationLayer {
+            timestamp: sat_image.timestamp,
+            region: self.provider.region(),
+            annotations: self.viewer.annotations().to_vec(),
+        };
+        let filename = format!("nuage_annotations_{}.geojson", self.viewer.timestamp_format().file_part(sat_image.timestamp));
+        let message = match layer.save(Path::new(&filename)) {
+            Ok(()) => format!("Saved {}", filename),
+            Err(e) => format!("Could not save {}: {}", filename, e),
+        };
+        self.set_status(ctx, message);
+    }
+
+    /// Show annotations saved, on the region and the frame they were drawn
+    /// on if its frames are downloaded.
+    fn load_annotations(&mut self, ctx: &egui::Context, layer: AnnotationLayer) -> String {
+        let (tiles, zoom) = layer.region;
+        if layer.region != self.provider.region() && self.downloading() {
+            self.set_region(ctx, tiles, zoom);
+        }
+        let count = layer.annotations.len();
+        self.viewer.set_annotations(layer.annotations);
+        self.viewer.show_frame(layer.timestamp);
+        format!("{} annotations of the frame of {}", count, self.viewer.timestamp_label(layer.timestamp))
+    }
+
+    /// Route the files dropped on the window: GeoJSON files become overlays,
+    /// or annotations if saved as such, GPX files tracks, folders and images
+    /// dropped together are played and sessions restored.
+    fn handle_dropped_files(&mut self, ctx: &egui::Context) {
+        let dropped_files = ctx.input(|i| i.raw.dropped_files.clone());
+        let mut images = vec![];
+        for path in dropped_files.into_iter().filter_map(|file| file.path) {
+            let extension = path.extension().and_then(|extension| extension.to_str()).map(str::to_lowercase);
+            let message = match extension.as_deref() {
+                _ if path.is_dir() => self.load_folder(ctx, &path),
+                Some("geojson" | "json") => match AnnotationLayer::load(&path) {
+                    Ok(Some(layer)) => self.load_annotations(ctx, layer),
+                    Ok(None) => self.load_overlay(&path),
+                    Err(e) => format!("Could not load {}: {}", path.display(), e),
+                },
+                Some("gpx") => self.load_track(ctx, &path),
+                Some("nuage") => self.load_session(ctx, &path),
+                Some("jpg" | "jpeg" | "png") => {
+                    images.push(path);
+                    continue;
+                }
+                _ => format!("Don't know what to do with {}", path.display()),
+            };
+            self.set_status(ctx, message);
+        }
+        if !images.is_empty() {
+            let message = self.load_files(ctx, images);
+            self.set_status(ctx, message);
+        }
+    }
+
+    /// Open the window or quit as asked from the tray icon, and hide the
+    /// window rather than quit when it is closed while the icon is there.
+    fn update_tray(&mut self, ctx: &egui::Context) {
+        let Some(tray) = &self.tray else {
+            return;
+        };
+        for event in tray.events() {
+            match event {
+                TrayEvent::Open => {
+                    ctx.send_viewport_cmd(egui::ViewportCommand::Visible(true));
+                    ctx.send_viewport_cmd(egui::ViewportCommand::Minimized(false));
+                    ctx.send_viewport_cmd(egui::ViewportCommand::Focus);
+                }
+                TrayEvent::Quit => {
+                    self.quitting = true;
+                    ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+                }
+            }
+        }
+        if ctx.input(|i| i.viewport().close_requested()) && !self.quitting {
+            ctx.send_viewport_cmd(egui::ViewportCommand::CancelClose);
+            ctx.send_viewport_cmd(egui::ViewportCommand::Visible(false));
+        }
+    }
+
+    /// Find the median motion of the clouds from `previous` to `latest` in
+    /// a separate thread, for the status of home.
+    fn find_home_motion(&self, ctx: &egui::Context, previous: nuage::SatImage, latest: nuage::SatImage) {
+        *self.home_motion.lock().unwrap() = Some((latest.timestamp, None));
+        let (home_motion, ctx) = (self.home_motion.clone(), ctx.clone());
+        std::thread::spawn(move || {
+            let minutes = (latest.timestamp - previous.timestamp).num_minutes() as f32;
+            let motion = analysis::median_motion(&analysis::motion_vectors(&previous.image, &latest.image, minutes));
+            let mut home_motion = home_motion.lock().unwrap();
+            // Unless a newer frame came meanwhile
+            if home_motion.is_some_and(|(timestamp, _)| timestamp == latest.timestamp) {
+                *home_motion = Some((latest.timestamp, Some(motion)));
+                ctx.request_repaint();
+            }
+        });
+    }
+
+    /// Show in the window title and icon whether rain is likely near home on
+    /// the latest frame, and its time, for a glance from the taskbar. Send a
+    /// notification when rain is on its way.
+    fn update_home_status(&mut self, ctx: &egui::Context) {
+        let (previous, latest) = {
+            let sat_images = self.viewer.timeline().sat_images.lock().unwrap();
+            match sat_images.latest() {
+                Some(latest) if self.home_status != Some(latest.timestamp) => (sat_images.observed().nth(1).cloned(), latest.clone()),
+                _ => return,
+            }
+        };
+        // Without a previous frame, the clouds are assumed still. Their
+        // motion takes a while to find, in the background.
+        let motion = match &previous {
+            Some(previous) => match *self.home_motion.lock().unwrap() {
+                Some((timestamp, Some(motion))) if timestamp == latest.timestamp => motion,
+                Some((timestamp, None)) if timestamp == latest.timestamp => return,
+                _ => {
+                    self.find_home_motion(ctx, previous.clone(), latest);
+                    return;
+                }
+            },
+            None => (0., 0.),
+        };
+        self.home_status = Some(latest.timestamp);
+        let (tiles, zoom) = self.provider.region();
+        let georeference = Georeference::new(tiles, zoom, latest.image.dimensions());
+        let alert = &self.config.rain_alert;
+        let eta = analysis::minutes_until_cover(
+            &latest.image,
+            motion,
+            &georeference,
+            self.config.home,
+            alert.radius_km,
+            alert.cover,
+            alert.horizon_minutes,
         );
-        let texture_handle = ctx.load_texture("my-jpeg-image", color_image, Default::default());
+        let (home_x, home_y) = georeference.lonlat_to_pixel(self.config.home.0, self.config.home.1);
+        let home_in_view = (0. ..georeference.width as f64).contains(&home_x) && (0. ..georeference.height as f64).contains(&home_y);
+        let summary = match eta {
+            _ if !home_in_view => "home out of view".to_string(),
+            Some(0) => "rain likely near home".to_string(),
+            Some(minutes) => format!("rain likely in ~{} min", minutes),
+            None => "no rain near home".to_string(),
+        };
+        let status = format!("{} - last image {}", summary, self.viewer.timestamp_label(latest.timestamp));
+        ctx.send_viewport_cmd(egui::ViewportCommand::Title(format!("Nuage - {}", status)));
+        ctx.send_viewport_cmd(egui::ViewportCommand::Icon(Some(Arc::new(tray::status_icon(eta == Some(0))))));
+        if let Some(tray) = &self.tray {
+            tray.set_status(&status, eta == Some(0));
+        }
 
-        // Blinking download label
-        const BLINK_HZ: f64 = 2.0;
-        let cycle_duration = 1.0 / BLINK_HZ;
-        let time_in_cycle = time % cycle_duration;
-        let downloading_is_visible = time_in_cycle < (cycle_duration / 2.0);
+        match eta {
+            Some(minutes) if minutes > 0 && alert.notify && !self.rain_notified => {
+                self.rain_notified = true;
+                if let Err(e) = notify::notify("Nuage", &format!("Rain likely in ~{} min", minutes)) {
+                    eprintln!("{}", e);
+                }
+            }
+            Some(_) => (),
+            None => self.rain_notified = false,
+        }
+    }
 
-        let mut displayed_image_rect: Option<egui::Rect> = None;
-        egui::CentralPanel::default().show(ctx, |ui| {
-            ui.centered_and_justified(|ui| {
-                // This code here center the image but we loose its actual position in pixel
-                // let available_size = ui.available_size();
-                // ui.add(egui::Image::new(&texture_handle).max_size(available_size));
-                // We center is ourselves here so we keep its exact position
-                let available_rect = ui.available_rect_before_wrap();
-                let image_size = texture_handle.size_vec2();
-                let aspect_ratio = image_size.x / image_size.y;
-                let mut target_size = available_rect.size();
-                if target_size.x / target_size.y > aspect_ratio {
-                    // The container is wider than the image.
-                    target_size.x = target_size.y * aspect_ratio;
-                } else {
-                    // The container is taller than the image.
-                    target_size.y = target_size.x / aspect_ratio;
+    /// Switch to the scene scheduled now, if not shown yet.
+    fn update_scene(&mut self, ctx: &egui::Context) {
+        let Some(name) = scene::scheduled(&self.config.schedule, chrono::Local::now().time()) else {
+            return;
+        };
+        if self.scene.as_deref() == Some(name) {
+            return;
+        }
+        let name = name.to_string();
+        let message = match self.config.scenes.iter().find(|scene| scene.name == name).cloned() {
+            Some(scene) => self.apply_scene(ctx, &scene),
+            None => format!("No scene named {}", name),
+        };
+        self.scene = Some(name);
+        self.set_status(ctx, message);
+    }
+
+    /// Show the region, the layers and the labels of `scene`. The frames of a
+    /// local folder or a nuage server are kept.
+    fn apply_scene(&mut self, ctx: &egui::Context, scene: &SceneConfig) -> String {
+        if self.downloading() && self.provider.region() != (scene.tiles, scene.zoom) {
+            let cache = match Cache::default_location() {
+                Ok(cache) => cache,
+                Err(e) => return format!("Could not show scene {}: {}", scene.name, e),
+            };
+            let mut infoplaza = self.infoplaza(cache);
+            infoplaza.tiles = scene.tiles;
+            infoplaza.zoom = scene.zoom;
+            self.set_provider(ctx, Arc::new(infoplaza));
+        }
+        for overlay in self.viewer.overlays_mut() {
+            overlay.visible = scene.overlays.as_ref().is_none_or(|names| names.contains(&overlay.name));
+        }
+        self.viewer.set_live_visible(scene.live);
+        self.viewer.set_stations_visible(scene.stations);
+        if self.viewer.is_nowcasting() != scene.nowcast {
+            self.viewer.set_nowcast(scene.nowcast);
+        }
+        self.viewer.set_hud(scene.hud.clone().unwrap_or_else(|| self.config.hud.clone()));
+        format!("Scene {}", scene.name)
+    }
+
+    /// Brighten or darken, add or remove contrast and gamma with B, C and Y,
+    /// Shift lowering, unless rebound. 0 resets.
+    fn handle_adjustment_keys(&mut self, ctx: &egui::Context) {
+        let mut adjustments = self.viewer.adjustments();
+        let keys = &self.config.keys;
+        let sign = |up, down| match (keys.pressed(ctx, up), keys.pressed(ctx, down)) {
+            (true, false) => Some(1.),
+            (false, true) => Some(-1.),
+            _ => None,
+        };
+        if let Some(sign) = sign(Action::BrightnessUp, Action::BrightnessDown) {
+            adjustments.brightness = (adjustments.brightness + sign * 0.05).clamp(-1., 1.);
+        }
+        if let Some(sign) = sign(Action::ContrastUp, Action::ContrastDown) {
+            adjustments.contrast = (adjustments.contrast + sign * 0.1).clamp(0., 3.);
+        }
+        if let Some(sign) = sign(Action::GammaUp, Action::GammaDown) {
+            adjustments.gamma = (adjustments.gamma + sign * 0.1).clamp(0.2, 5.);
+        }
+        if keys.pressed(ctx, Action::ResetAdjustments) {
+            adjustments = Default::default();
+        }
+        if adjustments != self.viewer.adjustments() {
+            self.viewer.set_adjustments(adjustments);
+            let message = format!(
+                "Brightness {:+.2}, contrast {:.1}, gamma {:.1}",
+                adjustments.brightness, adjustments.contrast, adjustments.gamma,
+            );
+            self.set_status(ctx, message);
+        }
+    }
+
+    /// The brightness, contrast and gamma sliders.
+    fn show_adjustments(&mut self, ctx: &egui::Context) {
+        if !self.adjustments_visible {
+            return;
+        }
+        let mut adjustments = self.viewer.adjustments();
+        let mut opacity = self.viewer.basemap().map(|layer| layer.config.opacity);
+        egui::Window::new("Image")
+            .open(&mut self.adjustments_visible)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.add(egui::Slider::new(&mut adjustments.brightness, -1.0..=1.0).text("Brightness"));
+                ui.add(egui::Slider::new(&mut adjustments.contrast, 0.0..=3.0).text("Contrast"));
+                ui.add(egui::Slider::new(&mut adjustments.gamma, 0.2..=5.0).text("Gamma"));
+                if let Some(opacity) = &mut opacity {
+                    ui.add(egui::Slider::new(opacity, 0.0..=1.0).text("Over the map"));
                 }
-                let image_rect = egui::Rect::from_center_size(available_rect.center(), target_size);
-                let response = ui.allocate_rect(image_rect, egui::Sense::hover());
-                if ui.is_rect_visible(response.rect) {
-                    let mut mesh = egui::Mesh::with_texture(texture_handle.id());
-                    mesh.add_rect_with_uv(response.rect, egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0)), egui::Color32::WHITE);
-                    ui.painter().add(egui::Shape::mesh(mesh));
+                if ui.button("Reset").clicked() {
+                    adjustments = Default::default();
                 }
-                displayed_image_rect = Some(response.rect);
+            });
+        self.viewer.set_adjustments(adjustments);
+        if let Some(opacity) = opacity {
+            self.viewer.set_basemap_opacity(opacity);
+        }
+    }
+
+    /// The keys, as bound, and the mouse gestures, over the frames.
+    fn show_help(&mut self, ctx: &egui::Context) {
+        if !self.help_visible {
+            return;
+        }
+        let keys = &self.config.keys;
+        egui::Window::new("Help")
+            .open(&mut self.help_visible)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                egui::Grid::new("keys").striped(true).show(ui, |ui| {
+                    for action in Action::ALL {
+                        let shortcuts: Vec<String> = keys.shortcuts(action).iter().map(ToString::to_string).collect();
+                        ui.strong(shortcuts.join(" or "));
+                        ui.label(action.description());
+                        ui.end_row();
+                    }
+                    for (gesture, description) in nuage::keymap::GESTURES {
+                        ui.strong(gesture);
+                        ui.label(description);
+                        ui.end_row();
+                    }
+                });
+            });
+    }
+
+    /// The luminance histogram of the frame as displayed, with the share of
+    /// black and white pixels, clipped by the adjustments or dead on the
+    /// sensor.
+    fn show_histogram(&mut self, ctx: &egui::Context) {
+        if !self.histogram_visible {
+            return;
+        }
+        let histogram = self.viewer.current_frame().map(|frame| {
+            let (processing, palette) = (self.viewer.processing(), self.viewer.palette());
+            let image = nuage::render::display_image(&frame.image, processing, &self.viewer.adjustments(), palette);
+            analysis::luminance_histogram(&image)
+        });
+        egui::Window::new("Histogram")
+            .open(&mut self.histogram_visible)
+            .resizable(false)
+            .show(ctx, |ui| {
+                let Some(histogram) = histogram else {
+                    ui.label("No frame");
+                    return;
+                };
+                let (rect, _) = ui.allocate_exact_size(egui::vec2(256., 100.), egui::Sense::hover());
+                let painter = ui.painter_at(rect);
+                painter.rect_filled(rect, 0., egui::Color32::from_gray(20));
+                // The tallest bar is often black or white, the others are
+                // scaled on the square root to stay visible
+                let highest = histogram.iter().max().copied().unwrap_or(1).max(1) as f32;
+                for (luminance, count) in histogram.iter().enumerate() {
+                    let height = (*count as f32 / highest).sqrt() * rect.height();
+                    let x = rect.left() + luminance as f32 + 0.5;
+                    painter.line_segment(
+                        [egui::pos2(x, rect.bottom()), egui::pos2(x, rect.bottom() - height)],
+                        egui::Stroke::new(1., egui::Color32::from_gray(200)),
+                    );
+                }
+                let total = histogram.iter().sum::<u32>().max(1) as f32;
+                let mean = histogram.iter().enumerate().map(|(luminance, count)| luminance as f32 * *count as f32).sum::<f32>() / total;
+                ui.label(format!(
+                    "Mean {:.0}, black {:.1}%, white {:.1}%",
+                    mean,
+                    histogram[0] as f32 / total * 100.,
+                    histogram[255] as f32 / total * 100.,
+                ));
+            });
+    }
+
+    /// The hourly precipitation, cloud cover and temperature forecast at
+    /// home, in a panel on the right, downloaded again every hour.
+    fn show_forecast(&mut self, ctx: &egui::Context) {
+        if !self.forecast_visible {
+            return;
+        }
+        let time = ctx.input(|i| i.time);
+        let refresh = nuage::metered::poll_period(std::time::Duration::from_secs_f64(FORECAST_REFRESH)).as_secs_f64();
+        if self.forecast.as_ref().is_none_or(|(_, since)| time - since > refresh) {
+            let slot: ForecastSlot = Arc::new(Mutex::new(None));
+            let result = slot.clone();
+            let (home, ctx) = (self.config.home, ctx.clone());
+            std::thread::spawn(move || {
+                *result.lock().unwrap() = Some(forecast::fetch(home).map_err(|e| e.to_string()));
+                ctx.request_repaint();
+            });
+            self.forecast = Some((slot, time));
+        }
+        let Some((slot, _)) = &self.forecast else {
+            return;
+        };
+        egui::SidePanel::right("forecast").resizable(false).default_width(240.).show(ctx, |ui| {
+            ui.heading("Forecast at home");
+            match &*slot.lock().unwrap() {
+                None => {
+                    ui.label("Downloading...");
+                }
+                Some(Err(e)) => {
+                    ui.label(e);
+                }
+                Some(Ok(forecast)) => {
+                    let times = &forecast.times;
+                    // The value of the hour in progress
+                    let now = times.iter().rposition(|time| *time <= chrono::Utc::now()).unwrap_or(0);
+                    let current = |values: &[Option<f64>], unit: &str| {
+                        values.get(now).copied().flatten().map_or("-".to_string(), |value| format!("{:.1} {}", value, unit))
+                    };
+                    ui.label(format!("Precipitation: {}", current(&forecast.precipitation, "mm")));
+                    forecast_chart(ui, times, &forecast.precipitation, None, true, egui::Color32::from_rgb(64, 128, 255));
+                    ui.label(format!("Cloud cover: {}", current(&forecast.cloud_cover, "%")));
+                    forecast_chart(ui, times, &forecast.cloud_cover, Some((0., 100.)), true, egui::Color32::from_gray(180));
+                    ui.label(format!("Temperature: {}", current(&forecast.temperature, "°C")));
+                    forecast_chart(ui, times, &forecast.temperature, None, false, egui::Color32::from_rgb(255, 128, 64));
+                    if let (Some(first), Some(last)) = (times.first(), times.last()) {
+                        ui.small(format!("{} to {}", first.format("%a %H:%M"), last.format("%a %H:%M UTC")));
+                    }
+                    ui.small("Weather data by Open-Meteo.com");
+                }
+            }
+        });
+    }
+
+    fn set_status(&mut self, ctx: &egui::Context, message: String) {
+        println!("{}", message);
+        self.status = Some((message, ctx.input(|i| i.time)));
+    }
+
+    /// Save the current frame as a GeoTIFF in the current directory.
+    fn export_geotiff(&mut self, ctx: &egui::Context) {
+        let Some(sat_image) = self.viewer.current_frame() else {
+            return;
+        };
+        let filename = format!("nuage_{}.tif", self.viewer.timestamp_format().file_part(sat_image.timestamp));
+        let (tiles, zoom) = self.provider.region();
+        let metadata = export::Metadata::new(self.provider.name(), (tiles, zoom), sat_image.timestamp, sat_image.timestamp);
+        let message = match export::write_geotiff(Path::new(&filename), &sat_image.image, tiles, zoom, &metadata) {
+            Ok(()) => format!("Saved {}", filename),
+            Err(e) => format!("Could not save {}: {}", filename, e),
+        };
+        self.set_status(ctx, message);
+    }
+
+    /// The frame shown, as displayed: processed, adjusted and colored, with
+    /// the visible overlays, the point of interest and its time burned in if
+    /// `annotated`.
+    fn displayed_frame(&mut self, annotated: bool) -> Option<nuage::SatImage> {
+        let sat_image = self.viewer.current_frame()?;
+        let (processing, palette) = (self.viewer.processing(), self.viewer.palette());
+        let mut displayed = nuage::SatImage {
+            image: nuage::render::display_image(&sat_image.image, processing, &self.viewer.adjustments(), palette).into_owned(),
+            ..sat_image
+        };
+        if annotated {
+            let (tiles, zoom) = self.provider.region();
+            let label = self.viewer.timestamp_label(displayed.timestamp);
+            let mut image = nuage::render::composite_with_label(&displayed, tiles, &label);
+            let georeference = Georeference::new(tiles, zoom, image.dimensions());
+            for overlay in self.viewer.overlays_mut().iter() {
+                overlay.burn(&mut image, &georeference);
+            }
+            for annotation in self.viewer.annotations() {
+                annotation.burn(&mut image, &georeference);
+            }
+            displayed.image = image;
+        }
+        Some(displayed)
+    }
+
+    /// Save the frame shown as a PNG in the current directory, annotated or
+    /// not.
+    fn save_frame(&mut self, ctx: &egui::Context, annotated: bool) {
+        let Some(frame) = self.displayed_frame(annotated) else {
+            return;
+        };
+        let filename = format!("nuage_{}.png", self.viewer.timestamp_format().file_part(frame.timestamp));
+        let message = match frame.image.save(&filename) {
+            Ok(()) => format!("Saved {}", filename),
+            Err(e) => format!("Could not save {}: {}", filename, e),
+        };
+        self.set_status(ctx, message);
+    }
+
+    /// Put the frame shown on the clipboard, annotated, to be pasted in a
+    /// chat.
+    fn copy_frame(&mut self, ctx: &egui::Context) {
+        let Some(frame) = self.displayed_frame(true) else {
+            return;
+        };
+        let (width, height) = frame.image.dimensions();
+        ctx.copy_image(egui::ColorImage::from_rgb([width as usize, height as usize], frame.image.as_raw()));
+        self.set_status(ctx, format!("Copied the frame of {}", self.viewer.timestamp_label(frame.timestamp)));
+    }
+
+    /// Switch to a small window on top of the others showing the latest
+    /// frame around home, or back. The frames are looked for periodically
+    /// from then on, as the window may stay up for long.
+    fn toggle_compact(&mut self, ctx: &egui::Context) {
+        self.compact = !self.compact;
+        if self.compact {
+            self.size_before_compact = ctx.input(|i| i.viewport().inner_rect.map(|rect| rect.size()));
+            ctx.send_viewport_cmd(egui::ViewportCommand::Fullscreen(false));
+            ctx.send_viewport_cmd(egui::ViewportCommand::WindowLevel(egui::WindowLevel::AlwaysOnTop));
+            ctx.send_viewport_cmd(egui::ViewportCommand::InnerSize(COMPACT_SIZE));
+            if !self.refreshing {
+                self.refreshing = true;
+                self.set_provider(ctx, self.provider.clone());
+            }
+        } else {
+            ctx.send_viewport_cmd(egui::ViewportCommand::WindowLevel(egui::WindowLevel::Normal));
+            if let Some(size) = self.size_before_compact.take() {
+                ctx.send_viewport_cmd(egui::ViewportCommand::InnerSize(size));
+            }
+        }
+    }
+}
 
+impl eframe::App for MyApp {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        // Check if the user has pressed the Escape key.
+        if self.kiosk {
+            // Passers-by must not close an unattended display by accident
+            let time = ctx.input(|i| i.time);
+            if self.config.keys.held(ctx, Action::Quit) {
+                let since = *self.quit_held_since.get_or_insert(time);
+                if time - since >= KIOSK_QUIT_HOLD {
+                    ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+                }
+                ctx.request_repaint();
+            } else {
+                self.quit_held_since = None;
+            }
+        } else if self.config.keys.pressed(ctx, Action::Quit) {
+            // If so, tell the frame to close, quitting even with the tray
+            // icon.
+            self.quitting = true;
+            ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+        }
+        self.update_tray(ctx);
+        if self.config.keys.pressed(ctx, Action::Fullscreen) {
+            let fullscreen = ctx.input(|i| i.viewport().fullscreen.unwrap_or(false));
+            ctx.send_viewport_cmd(egui::ViewportCommand::Fullscreen(!fullscreen));
+        }
+        self.handle_dropped_files(ctx);
+        self.update_scene(ctx);
+        self.update_home_status(ctx);
+        self.update_low_data(ctx);
+        // Save the session on Ctrl+S
+        if self.config.keys.pressed(ctx, Action::SaveSession) {
+            self.save_session(ctx);
+        }
+        // Save the frame shown on S, with the overlays and its time on
+        // Shift+S
+        if self.config.keys.pressed(ctx, Action::SaveFrame) {
+            self.save_frame(ctx, false);
+        }
+        if self.config.keys.pressed(ctx, Action::SaveAnnotatedFrame) {
+            self.save_frame(ctx, true);
+        }
+        // Copy the frame shown on Ctrl+C
+        if self.config.keys.pressed(ctx, Action::CopyFrame) {
+            self.copy_frame(ctx);
+        }
+        // Follow the time of the track on T
+        if self.config.keys.pressed(ctx, Action::FollowTrack) {
+            self.toggle_track_sync(ctx);
+        }
+        // Show the radiosonde stations on U
+        if self.config.keys.pressed(ctx, Action::Stations) {
+            let visible = !self.viewer.stations_visible();
+            self.viewer.set_stations_visible(visible);
+        }
+        // Draw the tiles on F12
+        if self.config.keys.pressed(ctx, Action::TileDebug) {
+            let tile_debug = !self.viewer.tile_debug();
+            self.viewer.set_tile_debug(tile_debug);
+        }
+        // Show the performance HUD on F10
+        if self.config.keys.pressed(ctx, Action::Perf) {
+            let visible = !self.viewer.perf_visible();
+            self.viewer.set_perf_visible(visible);
+        }
+        // Switch to the compact mode on M
+        if self.config.keys.pressed(ctx, Action::Compact) {
+            self.toggle_compact(ctx);
+        }
+        // Append forecasts to the loop on N
+        if self.config.keys.pressed(ctx, Action::Nowcast) {
+            let nowcast = !self.viewer.is_nowcasting();
+            self.viewer.set_nowcast(nowcast);
+            self.set_status(ctx, if nowcast { "Nowcast on" } else { "Nowcast off" }.to_string());
+        }
+        // Switch between in-between and raw frames on I
+        if self.config.keys.pressed(ctx, Action::Interpolate) {
+            let interpolate = !self.viewer.is_interpolating();
+            self.viewer.set_interpolate(interpolate);
+            self.set_status(ctx, if interpolate { "Smooth playback" } else { "Raw frames" }.to_string());
+        }
+        // Fade between frames on X
+        if self.config.keys.pressed(ctx, Action::Crossfade) {
+            let crossfade = !self.viewer.is_crossfading();
+            self.viewer.set_crossfade(crossfade);
+            self.set_status(ctx, if crossfade { "Crossfade on" } else { "Crossfade off" }.to_string());
+        }
+        // Image adjustments, sliders on A
+        self.handle_adjustment_keys(ctx);
+        if self.config.keys.pressed(ctx, Action::Adjustments) {
+            self.adjustments_visible = !self.adjustments_visible;
+        }
+        // List the keys on F1 or ?
+        if self.config.keys.pressed(ctx, Action::Help) {
+            self.help_visible = !self.help_visible;
+        }
+        // Show the luminance histogram on H
+        if self.config.keys.pressed(ctx, Action::Histogram) {
+            self.histogram_visible = !self.histogram_visible;
+        }
+        // Cycle through the screen reader announcements on R
+        if self.config.keys.pressed(ctx, Action::Announcements) {
+            let announcements = self.viewer.announcements().next();
+            self.viewer.set_announcements(announcements);
+            self.set_status(ctx, format!("Announcements {:?}", announcements));
+        }
+        // Switch between local time and UTC on Z
+        if self.config.keys.pressed(ctx, Action::TimeZone) {
+            let time_zone = self.viewer.time_zone().next();
+            self.viewer.set_time_zone(time_zone);
+            let message = if time_zone == TimeZone::Utc { "Times in UTC" } else { "Times in local time" };
+            self.set_status(ctx, message.to_string());
+        }
+        // Show the wind on W
+        if self.config.keys.pressed(ctx, Action::Wind) {
+            self.toggle_wind(ctx);
+        }
+        // Draw the frames over a map on O
+        if self.config.keys.pressed(ctx, Action::Basemap) {
+            self.toggle_basemap(ctx);
+        }
+        // Measure a distance on K
+        if self.config.keys.pressed(ctx, Action::Measure) {
+            let measuring = !self.viewer.is_measuring();
+            self.viewer.set_measuring(measuring);
+            let message = if measuring { "Click two points to measure the distance between them" } else { "Measure off" };
+            self.set_status(ctx, message.to_string());
+        }
+        // Cycle through the annotation tools on E
+        if self.config.keys.pressed(ctx, Action::Annotate) {
+            let tool = Tool::next(self.viewer.annotation_tool());
+            self.viewer.set_annotation_tool(tool);
+            self.set_status(ctx, tool.map_or("Annotations off", Tool::name).to_string());
+        }
+        // Save them on Shift+E
+        if self.config.keys.pressed(ctx, Action::SaveAnnotations) {
+            self.save_annotations(ctx);
+        }
+        // Show the lightning strikes on L
+        if self.config.keys.pressed(ctx, Action::Lightning) {
+            self.toggle_lightning(ctx);
+        }
+        // Show the forecast at home on F
+        if self.config.keys.pressed(ctx, Action::Forecast) {
+            self.forecast_visible = !self.forecast_visible;
+        }
+        // Show the thumbnails of the frames on J
+        if self.config.keys.pressed(ctx, Action::Filmstrip) {
+            self.filmstrip_visible = !self.filmstrip_visible;
+        }
+        // Cycle through the palettes on P
+        if self.config.keys.pressed(ctx, Action::Palette) {
+            let palette = self.viewer.palette().next();
+            let message = format!("Palette {}", palette.name());
+            self.viewer.set_palette(palette);
+            self.set_status(ctx, message);
+        }
+        // Cycle through the changes since the previous frame on D
+        if self.config.keys.pressed(ctx, Action::Difference) {
+            let difference = self.viewer.difference().next();
+            self.viewer.set_difference(difference);
+            let message = match difference {
+                Difference::Off => "Frames",
+                Difference::Absolute => "Changes since the previous frame",
+                Difference::Highlight => "Changes highlighted",
+            };
+            self.set_status(ctx, message.to_string());
+        }
+        // Compare two frames side by side on V
+        if self.config.keys.pressed(ctx, Action::Compare) {
+            let comparing = !self.viewer.is_comparing();
+            self.viewer.set_comparing(comparing);
+            self.set_status(ctx, if comparing { "Comparing frames" } else { "Animation" }.to_string());
+        }
+        // Export the current frame as a GeoTIFF on G
+        if self.config.keys.pressed(ctx, Action::GeoTiff) {
+            self.export_geotiff(ctx);
+        }
+        // Show the region of a tab on 1 to 9
+        let tab_key = ctx.input_mut(|i| TAB_KEYS.iter().position(|key| i.consume_key(egui::Modifiers::NONE, *key)));
+        if let Some(index) = tab_key.filter(|_| !ctx.wants_keyboard_input()) {
+            self.switch_tab(ctx, index);
+        }
+        let time = ctx.input(|i| i.time);
+        if self.status.as_ref().is_some_and(|(_, since)| time - since > STATUS_DURATION) {
+            self.status = None;
+        }
+        if self.compact {
+            egui::CentralPanel::default().frame(egui::Frame::NONE).show(ctx, |ui| {
+                self.viewer.show_compact(ui, self.config.home);
             });
+            return;
+        }
+        // Side panels go before the central one
+        self.show_forecast(ctx);
+        if self.filmstrip_visible {
+            egui::TopBottomPanel::bottom("filmstrip").show(ctx, |ui| self.viewer.show_filmstrip(ui));
+        }
+        let mut channel = self.channel;
+        let mut low_data = nuage::metered::mode();
+        let mut define_region = false;
+        let mut preset = None;
+        let mut tab = self.tab;
+        let mut open_archive = false;
+        let mut save_frame = None;
+        // The keys of Quit, however rebound
+        let quit: Vec<String> = self.config.keys.shortcuts(Action::Quit).iter().map(ToString::to_string).collect();
+        let heading = match quit.is_empty() {
+            true => "Nuage".to_string(),
+            false => format!("Nuage (Press {} to exit)", quit.join(" or ")),
+        };
+        egui::CentralPanel::default().show(ctx, |ui| {
+            self.viewer.show(ui);
+            if self.kiosk {
+                return;
+            }
             // Top-left corner for the header.
             egui::Area::new("header_area".into())
                 .anchor(egui::Align2::LEFT_TOP, egui::Vec2::new(10.0, 10.0)) // Anchor with a 10px margin.
                 .show(ctx, |ui| {
-                    ui.heading("Nuage (Press ESC to exit)");
-                });
-            // Bottom-left corner for the image detail label
-            egui::Area::new("custom_label_area".into())
-                .anchor(egui::Align2::LEFT_BOTTOM, egui::Vec2::new(10.0, -10.0)) // Anchor with a 10px margin.
-                .show(ctx, |ui| {
-                    let local_timestamp: DateTime<Local> = DateTime::from(sat_image.timestamp);
-                    let custom_label = egui::RichText::new(format!(
-                        "{:0>2}/{:0>2} {:0>2}-{:0>2}-{} {:0>2}:{:0>2}",
-                        // as image are order from most recent to least recent,
-                        // we display here a more natural index
-                        sat_images.len() - self.image_index,
-                        sat_images.len(),
-                        local_timestamp.day(),
-                        local_timestamp.month(),
-                        local_timestamp.year(),
-                        local_timestamp.hour(),
-                        local_timestamp.minute()
-                    ))
-                    .font(egui::FontId::new(
-                        24.0,
-                        egui::FontFamily::Name("vcr".into()),
-                    ))
-                    .color(egui::Color32::WHITE) // Make it visible on a dark image
-                    .background_color(egui::Color32::TRANSPARENT); // Semi-transparent background
-
-                    ui.add(egui::Label::new(custom_label).extend());
-                });
-
-            if *self.downloading.lock().unwrap() && downloading_is_visible {
-                // Bottom-left corner for the image detail label
-                egui::Area::new("downloading_area".into())
-                    .anchor(egui::Align2::LEFT_BOTTOM, egui::Vec2::new(10.0, -40.0)) // Anchor with a 10px margin.
-                    .show(ctx, |ui| {
-                        let custom_label = egui::RichText::new("DOWNLOADING...")
-                            .font(egui::FontId::new(
-                                24.0,
-                                egui::FontFamily::Name("vcr".into()),
-                            ))
-                            .color(egui::Color32::WHITE) // Make it visible on a dark image
-                            .background_color(egui::Color32::TRANSPARENT); // Semi-transparent background
-
-                        ui.add(egui::Label::new(custom_label).extend());
+                    ui.heading(&heading);
+                    if self.downloading() {
+                        egui::ComboBox::from_label("Channel").selected_text(channel.name()).show_ui(ui, |ui| {
+                            for option in Channel::ALL {
+                                ui.selectable_value(&mut channel, option, option.name());
+                            }
+                        });
+                        ui.horizontal(|ui| {
+                            ui.menu_button("Region", |ui| {
+                                let saved = self.region_presets.iter().cloned();
+                                for region in RegionPreset::built_in().into_iter().chain(saved) {
+                                    if ui.button(&region.name).clicked() {
+                                        preset = Some(region);
+                                        ui.close_menu();
+                                    }
+                                }
+                            });
+                            define_region = ui.button("Define region").clicked();
+                            open_archive = ui.button("Archive").clicked();
+                        });
+                        if self.tabs.len() > 1 {
+                            ui.horizontal(|ui| {
+                                for (index, region_tab) in self.tabs.iter().enumerate() {
+                                    ui.selectable_value(&mut tab, index, format!("{} {}", index + 1, region_tab.preset.name));
+                                }
+                            });
+                        }
+                        let metered = if nuage::metered::is_metered() { " (metered)" } else { "" };
+                        egui::ComboBox::from_label("Low data")
+                            .selected_text(format!("{}{}", low_data.name(), metered))
+                            .show_ui(ui, |ui| {
+                                for option in LowData::ALL {
+                                    ui.selectable_value(&mut low_data, option, option.name());
+                                }
+                            });
+                    }
+                    ui.horizontal(|ui| {
+                        if ui.button("Save frame").clicked() {
+                            save_frame = Some(false);
+                        }
+                        if ui.button("Save with overlays").clicked() {
+                            save_frame = Some(true);
+                        }
                     });
-            }
-
-            // Pinpoint icon
-            let point_of_interest = convert_gps_to_pixels(TILES, &displayed_image_rect.unwrap(), PARIS);
-            egui::Area::new("pinpoint_area".into())
-                .fixed_pos(egui::pos2(
-                    point_of_interest.0 as f32 - self.pinpoint_icon.size()[0] as f32 / 2.,
-                    point_of_interest.1 as f32 - self.pinpoint_icon.size()[1] as f32,
-                )) // The top-left corner of the Area
-                // .fixed_pos(egui::pos2(
-                //     point_of_interest.0,
-                //     point_of_interest.1,
-                // )) // The top-left corner of the Area
-                .show(ctx, |ui| {
-                    ui.image(&self.pinpoint_icon);
+                    if let Some((status, _)) = &self.status {
+                        ui.label(status);
+                    }
                 });
         });
+        if channel != self.channel {
+            let message = self.set_channel(ctx, channel);
+            self.set_status(ctx, message);
+        }
+        if low_data != nuage::metered::mode() {
+            nuage::metered::set_mode(low_data);
+            self.config.low_data = low_data;
+        }
+        if define_region {
+            self.toggle_region(ctx);
+        }
+        if let Some(preset) = preset {
+            let message = self.set_region(ctx, preset.tiles, preset.zoom);
+            self.set_status(ctx, format!("{}: {}", preset.name, message));
+        }
+        if tab != self.tab {
+            self.switch_tab(ctx, tab);
+        }
+        if let Some(annotated) = save_frame {
+            self.save_frame(ctx, annotated);
+        }
+        if open_archive {
+            self.archive = match self.archive {
+                Some(_) => None,
+                None => Some(ArchivePicker::new(self.viewer.time_zone())),
+            };
+        }
+        self.show_adjustments(ctx);
+        self.show_histogram(ctx);
+        self.show_region(ctx);
+        self.show_archive(ctx);
+        self.show_help(ctx);
     }
 }
 
 fn main() -> Result<(), eframe::Error> {
-    let options = eframe::NativeOptions::default();
-    eframe::run_native(
-        "Nuage",
-        options,
-        Box::new(|cc| Ok(Box::new(MyApp::new(cc)))),
-    )
+    // What the library fetches and reads, on stderr as RUST_LOG says
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("nuage=info"))
+        .format(|buf, record| writeln!(buf, "{}", record.args()))
+        .init();
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    const COMMANDS: [&str; 9] = ["analyze", "archive", "cache", "compare", "motion", "export", "fetch", "latest", "gen-fixtures"];
+    if let Some(command) = args.first().map(String::as_str).filter(|command| COMMANDS.contains(command)) {
+        let result = match command {
+            "analyze" => cli::analyze(&args[1..]),
+            "archive" => cli::archive(&args[1..]),
+            "cache" => cli::cache(&args[1..]),
+            "compare" => cli::compare(&args[1..]),
+            "export" => cli::export(&args[1..]),
+            "fetch" => cli::fetch(&args[1..]),
+            "latest" => cli::latest(&args[1..]),
+            "motion" => cli::motion(&args[1..]),
+            _ => cli::gen_fixtures(&args[1..]),
+        };
+        if let Err(e) = result {
+            eprintln!("{}: {}", command, e);
+            cli::usage();
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    let mut cli_options = match cli::Options::parse(&args) {
+        Ok(cli_options) => cli_options,
+        Err(e) => {
+            eprintln!("{}", e);
+            cli::usage();
+            std::process::exit(1);
+        }
+    };
+    // Before any frame is loaded
+    nuage::power::set_low_power(cli_options.low_power);
+    if let Some(addr) = &cli_options.serve {
+        if let Err(e) = cli::serve(&cli_options, addr) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    if let Some(output) = &cli_options.eink {
+        if let Err(e) = cli::eink(&cli_options, output) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    if cli_options.wallpaper {
+        if let Err(e) = cli::wallpaper(&cli_options) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    let mut config = match Config::load() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}, using the default configuration", e);
+            Config::default()
+        }
+    };
+    if cli_options.utc {
+        config.time_zone = TimeZone::Utc;
+    }
+    if let Some(tile_server) = &cli_options.tile_server {
+        config.tile_server = Some(tile_server.clone());
+    }
+    match cli_options.hours {
+        Some(hours) => config.history_hours = hours,
+        None => cli_options.hours = Some(config.history_hours),
+    }
+    match cli_options.delay {
+        Some(delay) => config.availability_delay = Some(delay),
+        None => cli_options.delay = config.availability_delay,
+    }
+    match cli_options.interval {
+        Some(interval) => config.frame_interval = Some(interval),
+        None => cli_options.interval = config.frame_interval,
+    }
+    // Before the provider, which downloads smaller frames on a metered
+    // connection
+    nuage::metered::set_mode(config.low_data);
+    // Only the server downloads
+    if cli_options.connect.is_none() {
+        nuage::metered::watch();
+    }
+    let provider = match cli_options.provider() {
+        Ok(provider) => provider,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let start = |renderer: eframe::Renderer| {
+        let fullscreen = cli_options.fullscreen || cli_options.kiosk;
+        let options = eframe::NativeOptions {
+            renderer,
+            viewport: egui::ViewportBuilder::default().with_fullscreen(fullscreen),
+            // Kiosk mode is always fullscreen, which must not be restored
+            // the next time
+            persist_window: !cli_options.kiosk,
+            persistence_path: Config::folder().ok().map(|folder| folder.join(WINDOW_STATE)),
+            ..Default::default()
+        };
+        let (provider, cli_options, config) = (provider.clone(), &cli_options, config.clone());
+        eframe::run_native(
+            "Nuage",
+            options,
+            Box::new(move |cc| {
+                // The window state restored would win over the builder
+                if fullscreen {
+                    cc.egui_ctx.send_viewport_cmd(egui::ViewportCommand::Fullscreen(true));
+                }
+                let mut app = MyApp::new(cc, provider, cli_options, config);
+                if renderer == eframe::Renderer::Glow {
+                    app.reduce_effects(&cc.egui_ctx);
+                }
+                Ok(Box::new(app))
+            }),
+        )
+    };
+    match config.renderer {
+        nuage::config::Renderer::Wgpu => start(eframe::Renderer::Wgpu),
+        nuage::config::Renderer::Glow => start(eframe::Renderer::Glow),
+        // Old kiosk hardware often has no driver wgpu can use
+        nuage::config::Renderer::Auto => match start(eframe::Renderer::Wgpu) {
+            // No display at all, OpenGL would not do better
+            Err(e @ eframe::Error::WinitEventLoop(_)) => Err(e),
+            Err(e) => {
+                eprintln!("could not start with wgpu ({}), falling back to OpenGL with fewer effects", e);
+                start(eframe::Renderer::Glow)
+            }
+            Ok(()) => Ok(()),
+        },
+    }
 }
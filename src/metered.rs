@@ -0,0 +1,118 @@
+//! Metered connections, told by NetworkManager on Linux and by the cost of
+//! the connection on Windows, and the low-data mode they switch to: frames
+//! downloaded one zoom level lower and feeds polled less often.
+
+use serde::{Deserialize, Serialize};
+use std::process::Command;
+use std::sync::RwLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// How often the connection is checked.
+pub const CHECK_EVERY: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// How many times longer the feeds wait between two polls in low-data mode.
+pub const SLOWDOWN: u32 = 3;
+
+/// When to save data.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub enum LowData {
+    /// On metered connections
+    #[default]
+    Auto,
+    /// Always
+    On,
+    /// Never
+    Off,
+}
+
+impl LowData {
+    pub const ALL: [LowData; 3] = [LowData::Auto, LowData::On, LowData::Off];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            LowData::Auto => "when metered",
+            LowData::On => "on",
+            LowData::Off => "off",
+        }
+    }
+}
+
+static MODE: RwLock<LowData> = RwLock::new(LowData::Auto);
+
+/// Whether the connection was metered when last checked.
+static METERED: AtomicBool = AtomicBool::new(false);
+
+/// Whether the connection is metered, `None` when it cannot be told.
+/// Blocking, a command is run.
+pub fn detect() -> Option<bool> {
+    if cfg!(windows) {
+        let script = "[void][Windows.Networking.Connectivity.NetworkInformation, Windows.Networking.Connectivity, \
+                      ContentType = WindowsRuntime]; \
+                      $profile = [Windows.Networking.Connectivity.NetworkInformation]::GetInternetConnectionProfile(); \
+                      if ($profile) { $profile.GetConnectionCost().NetworkCostType }";
+        let output = Command::new("powershell").args(["-NoProfile", "-Command", script]).output().ok()?;
+        match String::from_utf8_lossy(&output.stdout).trim() {
+            "Unrestricted" => Some(false),
+            "Fixed" | "Variable" => Some(true),
+            _ => None,
+        }
+    } else if cfg!(target_os = "linux") {
+        // NMMetered: 1 and 3 are yes and guessed yes, 2 and 4 no
+        let output = Command::new("busctl")
+            .args([
+                "--system",
+                "get-property",
+                "org.freedesktop.NetworkManager",
+                "/org/freedesktop/NetworkManager",
+                "org.freedesktop.NetworkManager",
+                "Metered",
+            ])
+            .output()
+            .ok()?;
+        match String::from_utf8_lossy(&output.stdout).trim() {
+            "u 1" | "u 3" => Some(true),
+            "u 2" | "u 4" => Some(false),
+            _ => None,
+        }
+    } else {
+        None
+    }
+}
+
+/// Check the connection now, then every `CHECK_EVERY` in the background.
+pub fn watch() {
+    METERED.store(detect().unwrap_or(false), Ordering::Relaxed);
+    std::thread::spawn(|| {
+        loop {
+            std::thread::sleep(CHECK_EVERY);
+            METERED.store(detect().unwrap_or(false), Ordering::Relaxed);
+        }
+    });
+}
+
+pub fn set_mode(mode: LowData) {
+    *MODE.write().unwrap() = mode;
+}
+
+pub fn mode() -> LowData {
+    *MODE.read().unwrap()
+}
+
+/// Whether the connection was metered when last checked.
+pub fn is_metered() -> bool {
+    METERED.load(Ordering::Relaxed)
+}
+
+/// Whether to save data now.
+pub fn is_low_data() -> bool {
+    match mode() {
+        LowData::Auto => is_metered(),
+        LowData::On => true,
+        LowData::Off => false,
+    }
+}
+
+/// `period` between two polls, longer in low-data mode.
+pub fn poll_period(period: std::time::Duration) -> std::time::Duration {
+    if is_low_data() { period * SLOWDOWN } else { period }
+}
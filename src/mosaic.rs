@@ -0,0 +1,179 @@
+//! The freshest picture of a tile window. Tiles are not all published at the
+//! same time: the newest frame the provider has whole is taken as the base,
+//! and every tile already published for a later time is laid over it. Each
+//! tile keeps the time it was taken at.
+
+use chrono::{DateTime, Duration, SecondsFormat, Utc};
+use std::fmt::Write;
+
+use crate::error::{NuageError, Result};
+use crate::geo::json_string;
+use crate::provider::{Infoplaza, Provider, Tiles};
+use crate::render::draw_label;
+use crate::timeline::{previous_time, time_range};
+
+/// How many of the frames the viewer would show are tried for the base.
+const BASE_TRIES: usize = 6;
+
+/// Color of the frame around the tiles older than the newest one.
+const STALE_COLOR: [u8; 3] = [255, 200, 0];
+
+/// A frame assembled from tiles taken at different times.
+#[derive(Clone, Debug)]
+pub struct Mosaic {
+    pub image: image::RgbImage,
+    pub tiles: Tiles,
+    pub zoom: u16,
+    /// The time of each tile, row by row from the north-west
+    pub times: Vec<((u16, u16), DateTime<Utc>)>,
+}
+
+impl Mosaic {
+    /// The time of the most recent tile.
+    pub fn newest(&self) -> DateTime<Utc> {
+        self.times.iter().map(|(_, time)| *time).max().unwrap_or_default()
+    }
+
+    /// The time of the oldest tile, that of the whole frame when it is not
+    /// mixed.
+    pub fn oldest(&self) -> DateTime<Utc> {
+        self.times.iter().map(|(_, time)| *time).min().unwrap_or_default()
+    }
+
+    /// Whether the tiles were taken at different times.
+    pub fn is_mixed(&self) -> bool {
+        self.newest() != self.oldest()
+    }
+
+    /// The pixels of tile `(x, y)` in the image: (left, top, width, height).
+    pub fn tile_rect(&self, (x, y): (u16, u16)) -> (u32, u32, u32, u32) {
+        let ((x1, y1), (x2, y2)) = self.tiles;
+        let (columns, rows) = ((x2 - x1 + 1) as u32, (y2 - y1 + 1) as u32);
+        let (column, row) = ((x - x1) as u32, (y - y1) as u32);
+        let left = column * self.image.width() / columns;
+        let top = row * self.image.height() / rows;
+        let right = (column + 1) * self.image.width() / columns;
+        let bottom = (row + 1) * self.image.height() / rows;
+        (left, top, right - left, bottom - top)
+    }
+
+    /// Serialize the times as a JSON document: `{"newest": ..., "oldest":
+    /// ..., "mixed": ..., "zoom": ..., "tiles": [...]}`, each tile with its
+    /// `x`, `y` and time (RFC 3339).
+    pub fn to_json(&self) -> String {
+        let time = |time: DateTime<Utc>| json_string(&time.to_rfc3339_opts(SecondsFormat::Secs, true));
+        let mut json = format!(
+            "{{\"newest\":{},\"oldest\":{},\"mixed\":{},\"zoom\":{},\"tiles\":[",
+            time(self.newest()),
+            time(self.oldest()),
+            self.is_mixed(),
+            self.zoom
+        );
+        for (index, ((x, y), tile_time)) in self.times.iter().enumerate() {
+            if index > 0 {
+                json.push(',');
+            }
+            write!(json, "{{\"x\":{},\"y\":{},\"time\":{}}}", x, y, time(*tile_time)).unwrap();
+        }
+        json.push_str("]}");
+        json
+    }
+
+    /// The image with the tiles older than the newest one framed and labeled
+    /// with their time, and the time span of the tiles in the bottom-left
+    /// corner.
+    pub fn annotated(&self) -> image::RgbImage {
+        let mut canvas = self.image.clone();
+        let newest = self.newest();
+        for (tile, time) in &self.times {
+            if *time == newest {
+                continue;
+            }
+            let (left, top, width, height) = self.tile_rect(*tile);
+            for x in left..left + width {
+                for y in [top, top + height - 1] {
+                    canvas.put_pixel(x, y, image::Rgb(STALE_COLOR));
+                }
+            }
+            for y in top..top + height {
+                for x in [left, left + width - 1] {
+                    canvas.put_pixel(x, y, image::Rgb(STALE_COLOR));
+                }
+            }
+            draw_label(&mut canvas, &time.format("%H:%M").to_string(), (left as f32 + 4., top as f32 + 18.), 14.);
+        }
+        let span = if self.is_mixed() {
+            format!("{} - {} UTC", self.oldest().format("%d-%m-%Y %H:%M"), newest.format("%H:%M"))
+        } else {
+            format!("{} UTC", newest.format("%d-%m-%Y %H:%M"))
+        };
+        let bottom = canvas.height() as f32 - 10.;
+        draw_label(&mut canvas, &span, (10., bottom), 20.);
+        canvas
+    }
+}
+
+/// The freshest picture of the window of `infoplaza` at `now`: the newest
+/// frame of the last ones it has whole, with each tile replaced by the most
+/// recent one published since. Tiles are asked one by one only for the times
+/// whose whole frame is not published yet, and those found missing are not
+/// asked again for a while. Blocking.
+pub fn best_available(infoplaza: &Infoplaza, now: DateTime<Utc>) -> Result<Mosaic> {
+    let mut last_error = None;
+    let mut base = None;
+    for timepoint in previous_time(now, infoplaza.hours, infoplaza.delay, infoplaza.interval).into_iter().take(BASE_TRIES) {
+        match infoplaza.frame(timepoint) {
+            Ok(image) => {
+                base = Some((timepoint, image));
+                break;
+            }
+            Err(e) => last_error = Some(e),
+        }
+    }
+    let Some((base_time, image)) = base else {
+        return Err(last_error.unwrap_or_else(|| NuageError::Missing("latest frame".into())));
+    };
+    let ((x1, y1), (x2, y2)) = infoplaza.tiles;
+    let mut mosaic = Mosaic {
+        image,
+        tiles: infoplaza.tiles,
+        zoom: infoplaza.zoom,
+        times: (y1..=y2).flat_map(|y| (x1..=x2).map(move |x| ((x, y), base_time))).collect(),
+    };
+
+    // From the most recent, so that each tile gets the newest one published
+    let mut pending: Vec<usize> = (0..mosaic.times.len()).collect();
+    // Not up to `now`: a frame asked before it is published would be
+    // recorded missing
+    let step = Duration::minutes(infoplaza.interval.max(1) as i64);
+    for timepoint in time_range(base_time + step, now - step, infoplaza.interval) {
+        if pending.is_empty() {
+            break;
+        }
+        if let Ok(image) = infoplaza.frame(timepoint) {
+            let (width, height) = mosaic.image.dimensions();
+            let image = image::imageops::resize(&image, width, height, image::imageops::FilterType::Triangle);
+            for index in pending.drain(..) {
+                let (left, top, width, height) = mosaic.tile_rect(mosaic.times[index].0);
+                let tile = image::imageops::crop_imm(&image, left, top, width, height).to_image();
+                image::imageops::replace(&mut mosaic.image, &tile, left as i64, top as i64);
+                mosaic.times[index].1 = timepoint;
+            }
+            break;
+        }
+        pending.retain(|index| {
+            let tile = mosaic.times[*index].0;
+            let mut single = infoplaza.clone();
+            single.tiles = (tile, tile);
+            let Ok(image) = single.frame(timepoint) else {
+                return true;
+            };
+            let (left, top, width, height) = mosaic.tile_rect(tile);
+            let image = image::imageops::resize(&image, width, height, image::imageops::FilterType::Triangle);
+            image::imageops::replace(&mut mosaic.image, &image, left as i64, top as i64);
+            mosaic.times[*index].1 = timepoint;
+            false
+        });
+    }
+    Ok(mosaic)
+}
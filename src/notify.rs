@@ -0,0 +1,38 @@
+//! Desktop notifications.
+
+use std::process::Command;
+
+use crate::error::{NuageError, Result};
+
+/// Show a desktop notification, with `notify-send` on Linux and the BSDs,
+/// `osascript` on macOS and a PowerShell balloon on Windows.
+pub fn notify(summary: &str, body: &str) -> Result<()> {
+    let mut command = if cfg!(target_os = "macos") {
+        let script = format!("display notification {:?} with title {:?}", body, summary);
+        let mut command = Command::new("osascript");
+        command.args(["-e", &script]);
+        command
+    } else if cfg!(windows) {
+        let script = format!(
+            "Add-Type -AssemblyName System.Windows.Forms; \
+             $n = New-Object System.Windows.Forms.NotifyIcon; \
+             $n.Icon = [System.Drawing.SystemIcons]::Information; $n.Visible = $true; \
+             $n.ShowBalloonTip(10000, '{}', '{}', 'Info'); Start-Sleep 10; $n.Dispose()",
+            summary.replace('\'', "''"),
+            body.replace('\'', "''"),
+        );
+        let mut command = Command::new("powershell");
+        command.args(["-NoProfile", "-Command", &script]);
+        command
+    } else {
+        let mut command = Command::new("notify-send");
+        command.args(["--app-name=nuage", summary, body]);
+        command
+    };
+    let mut child = command
+        .spawn()
+        .map_err(|e| NuageError::Config(format!("could not notify with {:?}: {}", command.get_program(), e)))?;
+    // Do not block the caller, the Windows balloon stays up for a while
+    std::thread::spawn(move || child.wait());
+    Ok(())
+}
@@ -0,0 +1,130 @@
+use eframe::egui;
+use std::path::{Path, PathBuf};
+
+use crate::config::OverlayConfig;
+use crate::error::Result;
+use crate::geo::{self, Feature, Geometry, LonLat};
+use crate::gpx;
+use crate::projection::Georeference;
+use crate::render;
+
+/// Position on screen of a (longitude, latitude), the frame being displayed in
+/// `image_rect`.
+pub fn screen_position(georeference: &Georeference, image_rect: egui::Rect, (lon, lat): LonLat) -> egui::Pos2 {
+    let (x, y) = georeference.lonlat_to_pixel(lon, lat);
+    egui::pos2(
+        image_rect.min.x + x as f32 / georeference.width as f32 * image_rect.width(),
+        image_rect.min.y + y as f32 / georeference.height as f32 * image_rect.height(),
+    )
+}
+
+/// The (longitude, latitude) shown at `position` on screen, the inverse of
+/// `screen_position`.
+pub fn lonlat_at(georeference: &Georeference, image_rect: egui::Rect, position: egui::Pos2) -> LonLat {
+    let x = (position.x - image_rect.min.x) / image_rect.width() * georeference.width as f32;
+    let y = (position.y - image_rect.min.y) / image_rect.height() * georeference.height as f32;
+    georeference.pixel_to_lonlat(x as f64, y as f64)
+}
+
+/// Vector features drawn over the frames.
+pub struct Overlay {
+    pub name: String,
+    /// The GeoJSON file the features were read from
+    pub path: PathBuf,
+    pub features: Vec<Feature>,
+    pub color: egui::Color32,
+    pub width: f32,
+    pub visible: bool,
+}
+
+impl Overlay {
+    /// Load a GeoJSON or GPX file with the default style.
+    pub fn load(path: &Path) -> Result<Overlay> {
+        Overlay::from_config(&OverlayConfig {
+            path: path.to_path_buf(),
+            ..Default::default()
+        })
+    }
+
+    pub fn from_config(config: &OverlayConfig) -> Result<Overlay> {
+        let (r, g, b) = config.color;
+        Ok(Overlay {
+            name: config
+                .path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            path: config.path.clone(),
+            features: match config.path.extension().and_then(|extension| extension.to_str()) {
+                Some(extension) if extension.eq_ignore_ascii_case("gpx") => vec![gpx::read_gpx(&config.path)?.feature()],
+                _ => geo::read_geojson(&config.path)?,
+            },
+            color: egui::Color32::from_rgb(r, g, b),
+            width: config.width,
+            visible: true,
+        })
+    }
+
+    /// The configuration reloading this overlay.
+    pub fn config(&self) -> OverlayConfig {
+        OverlayConfig {
+            path: self.path.clone(),
+            color: (self.color.r(), self.color.g(), self.color.b()),
+            width: self.width,
+        }
+    }
+
+    /// Draw the features in the pixels of `canvas`, a frame of the window of
+    /// `georeference`.
+    pub fn burn(&self, canvas: &mut image::RgbImage, georeference: &Georeference) {
+        if !self.visible {
+            return;
+        }
+        let to_pixel = |(lon, lat): LonLat| {
+            let (x, y) = georeference.lonlat_to_pixel(lon, lat);
+            (x as f32, y as f32)
+        };
+        let color = [self.color.r(), self.color.g(), self.color.b()];
+        let draw_path = |canvas: &mut image::RgbImage, points: &[LonLat]| {
+            for pair in points.windows(2) {
+                render::draw_line(canvas, to_pixel(pair[0]), to_pixel(pair[1]), self.width, color);
+            }
+        };
+        for feature in &self.features {
+            match &feature.geometry {
+                Geometry::Point(point) => render::draw_disc(canvas, to_pixel(*point), self.width * 2., color),
+                Geometry::LineString(points) | Geometry::Polygon(points) => draw_path(canvas, points),
+                Geometry::MultiPolygon(rings) => {
+                    for ring in rings {
+                        draw_path(canvas, ring);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Draw the features on the frame displayed in `image_rect`.
+    pub fn paint(&self, painter: &egui::Painter, georeference: &Georeference, image_rect: egui::Rect) {
+        if !self.visible {
+            return;
+        }
+        let to_screen = |lonlat: LonLat| screen_position(georeference, image_rect, lonlat);
+        let stroke = egui::Stroke::new(self.width, self.color);
+        let painter = painter.with_clip_rect(image_rect);
+        for feature in &self.features {
+            match &feature.geometry {
+                Geometry::Point(point) => {
+                    painter.circle(to_screen(*point), self.width * 2., self.color, egui::Stroke::NONE);
+                }
+                Geometry::LineString(points) | Geometry::Polygon(points) => {
+                    painter.line(points.iter().copied().map(to_screen).collect(), stroke);
+                }
+                Geometry::MultiPolygon(rings) => {
+                    for ring in rings {
+                        painter.line(ring.iter().copied().map(to_screen).collect(), stroke);
+                    }
+                }
+            }
+        }
+    }
+}
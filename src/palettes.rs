@@ -0,0 +1,205 @@
+//! Palettes shared as files, in the `palettes` folder next to the
+//! configuration file, named after the file (`enhanced-ir.csv` is
+//! `enhanced-ir`). They are read again as soon as the folder changes. Three
+//! formats are understood, line by line, `#` and `*` starting comments:
+//! - stops, `luminance,red,green,blue` (commas, semicolons or spaces), a
+//!   header line being skipped,
+//! - McIDAS-style ramps, `from to red green blue red green blue`, the color
+//!   going from the first to the second between the two luminances,
+//! - GrADS scripts, colors defined with `set rgb <number> <red> <green>
+//!   <blue>` and the shades with `set clevs` and `set ccols`, the levels
+//!   being luminances.
+//!
+//! Luminances and colors go from 0 to 255.
+
+use eframe::egui;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+use crate::config::Config;
+use crate::error::Result;
+
+/// (luminance, color) stops, in increasing luminance.
+pub type Stops = Vec<(u8, [u8; 3])>;
+
+/// How long to wait for the files of the folder to be written, as saving
+/// triggers several events.
+const WATCH_SETTLE: std::time::Duration = std::time::Duration::from_millis(500);
+
+/// The palettes read, by name, in the order of their names.
+static LOADED: RwLock<Vec<(String, Stops)>> = RwLock::new(vec![]);
+
+/// The folder of the palette files.
+pub fn folder() -> Result<PathBuf> {
+    Ok(Config::folder()?.join("palettes"))
+}
+
+/// Parse a palette file, in any of the formats.
+pub fn parse(text: &str) -> std::result::Result<Stops, String> {
+    let mut stops = vec![];
+    let mut colors: HashMap<u32, [u8; 3]> = HashMap::new();
+    let (mut levels, mut shades): (Vec<u8>, Vec<u32>) = (vec![], vec![]);
+    for (index, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() || line.starts_with('*') {
+            continue;
+        }
+        let error = |message: &str| format!("line {}: {}", index + 1, message);
+        let fields: Vec<&str> = line
+            .split(|c: char| c == ',' || c == ';' || c.is_whitespace())
+            .filter(|field| !field.is_empty())
+            .collect();
+        if fields[0] == "set" {
+            // Other GrADS settings do not change the colors
+            if !matches!(fields.get(1), Some(&("rgb" | "clevs" | "ccols"))) {
+                continue;
+            }
+            let values = fields[2..]
+                .iter()
+                .map(|value| value.parse::<u32>())
+                .collect::<std::result::Result<Vec<_>, _>>()
+                .map_err(|_| error("expected numbers"))?;
+            match (fields[1], &values[..]) {
+                ("rgb", &[number, red, green, blue]) => {
+                    let [Some(red), Some(green), Some(blue)] = [red, green, blue].map(byte) else {
+                        return Err(error("colors go up to 255"));
+                    };
+                    colors.insert(number, [red, green, blue]);
+                }
+                ("rgb", _) => return Err(error("set rgb expects a number and a color")),
+                ("clevs", _) => {
+                    levels = values.into_iter().map(byte).collect::<Option<_>>().ok_or_else(|| error("levels go up to 255"))?;
+                }
+                _ => shades = values,
+            }
+            continue;
+        }
+        let Ok(values) = fields.iter().map(|value| value.parse::<u32>()).collect::<std::result::Result<Vec<_>, _>>() else {
+            if stops.is_empty() {
+                // A header
+                continue;
+            }
+            return Err(error("expected numbers"));
+        };
+        let values: Vec<u8> = values.into_iter().map(byte).collect::<Option<_>>().ok_or_else(|| error("values go up to 255"))?;
+        match values[..] {
+            [luminance, red, green, blue] => stops.push((luminance, [red, green, blue])),
+            [from, to, r1, g1, b1, r2, g2, b2] => {
+                stops.push((from, [r1, g1, b1]));
+                stops.push((to, [r2, g2, b2]));
+            }
+            _ => return Err(error("expected luminance,red,green,blue or from to red green blue red green blue")),
+        }
+    }
+    if !shades.is_empty() {
+        if shades.len() != levels.len() + 1 {
+            return Err(format!("{} colors in ccols for {} levels in clevs, expected one more color", shades.len(), levels.len()));
+        }
+        // Each color fills the luminances between two levels
+        for (index, shade) in shades.iter().enumerate() {
+            let color = *colors.get(shade).ok_or_else(|| format!("color {} is not defined with set rgb", shade))?;
+            let from = if index == 0 { 0 } else { levels[index - 1] };
+            let to = levels.get(index).map_or(255, |level| level.saturating_sub(1));
+            if to >= from {
+                stops.push((from, color));
+                stops.push((to, color));
+            }
+        }
+    }
+    if stops.is_empty() {
+        return Err("no colors".into());
+    }
+    stops.sort_by_key(|(luminance, _)| *luminance);
+    Ok(stops)
+}
+
+fn byte(value: u32) -> Option<u8> {
+    u8::try_from(value).ok()
+}
+
+/// Read the palettes of `folder`, by name. Files that cannot be read are
+/// reported and skipped.
+pub fn load(folder: &Path) -> Vec<(String, Stops)> {
+    let Ok(entries) = std::fs::read_dir(folder) else {
+        return vec![];
+    };
+    let mut palettes: Vec<(String, Stops)> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_file())
+        .filter_map(|path| {
+            let name = path.file_stem()?.to_string_lossy().into_owned();
+            let text = match std::fs::read_to_string(&path) {
+                Ok(text) => text,
+                Err(e) => {
+                    log::warn!("{}: {}", path.display(), e);
+                    return None;
+                }
+            };
+            match parse(&text) {
+                Ok(stops) => Some((name, stops)),
+                Err(e) => {
+                    log::warn!("{}: {}", path.display(), e);
+                    None
+                }
+            }
+        })
+        .collect();
+    palettes.sort_by(|a, b| a.0.cmp(&b.0));
+    palettes
+}
+
+/// Read the palettes of the folder again.
+pub fn reload() {
+    let palettes = folder().map(|folder| load(&folder)).unwrap_or_default();
+    *LOADED.write().unwrap() = palettes;
+}
+
+/// The names of the palettes read.
+pub fn names() -> Vec<String> {
+    LOADED.read().unwrap().iter().map(|(name, _)| name.clone()).collect()
+}
+
+/// The stops of the palette `name`, if it was read.
+pub fn stops(name: &str) -> Option<Stops> {
+    LOADED.read().unwrap().iter().find(|(loaded, _)| loaded == name).map(|(_, stops)| stops.clone())
+}
+
+/// Read the palettes, then again each time the folder changes, asking `ctx`
+/// to repaint.
+pub fn watch(ctx: egui::Context) {
+    use ::notify::{RecursiveMode, Watcher};
+    reload();
+    let Ok(folder) = folder() else {
+        return;
+    };
+    // Created so that the palettes added later are seen
+    if let Err(e) = std::fs::create_dir_all(&folder) {
+        log::warn!("{}: {}", folder.display(), e);
+        return;
+    }
+    std::thread::spawn(move || {
+        let (sender, changes) = std::sync::mpsc::channel();
+        let mut watcher = match ::notify::recommended_watcher(move |event: ::notify::Result<::notify::Event>| {
+            if event.is_ok() {
+                let _ = sender.send(());
+            }
+        }) {
+            Ok(watcher) => watcher,
+            Err(e) => {
+                log::warn!("could not watch {}: {}", folder.display(), e);
+                return;
+            }
+        };
+        if let Err(e) = watcher.watch(&folder, RecursiveMode::NonRecursive) {
+            log::warn!("could not watch {}: {}", folder.display(), e);
+            return;
+        }
+        while changes.recv().is_ok() {
+            std::thread::sleep(WATCH_SETTLE);
+            changes.try_iter().for_each(drop);
+            reload();
+            ctx.request_repaint();
+        }
+    });
+}
@@ -0,0 +1,47 @@
+//! The low-power profile, for Raspberry Pi Zero class boards driving a small
+//! display: the animation drawn at a few frames per second, the frames shrunk
+//! to the display with nearest-neighbor resizes and fewer of them loaded, and
+//! a pause between two downloads.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// The most repaints per second of the animation.
+pub const MAX_FPS: f32 = 8.;
+
+/// The largest frame kept in memory, that of the official 7" Raspberry Pi
+/// display.
+pub const FRAME_SIZE: (u32, u32) = (800, 480);
+
+/// The most frames loaded at once, longer histories being thinned out.
+pub const MAX_FRAMES: u32 = 24;
+
+/// The pause between two frames downloaded, not to hog the CPU and the
+/// network.
+pub const DOWNLOAD_PAUSE: std::time::Duration = std::time::Duration::from_secs(2);
+
+static LOW_POWER: AtomicBool = AtomicBool::new(false);
+
+pub fn set_low_power(low_power: bool) {
+    LOW_POWER.store(low_power, Ordering::Relaxed);
+}
+
+pub fn is_low_power() -> bool {
+    LOW_POWER.load(Ordering::Relaxed)
+}
+
+/// The filter of the resizes of the frames: nearest-neighbor, the cheapest,
+/// in low-power mode.
+pub fn resize_filter() -> image::imageops::FilterType {
+    if is_low_power() { image::imageops::FilterType::Nearest } else { image::imageops::FilterType::Triangle }
+}
+
+/// `image` shrunk to fit in `FRAME_SIZE` in low-power mode, as is otherwise.
+pub fn shrink(image: image::RgbImage) -> image::RgbImage {
+    let (max_width, max_height) = FRAME_SIZE;
+    if !is_low_power() || (image.width() <= max_width && image.height() <= max_height) {
+        return image;
+    }
+    let scale = (max_width as f32 / image.width() as f32).min(max_height as f32 / image.height() as f32);
+    let (width, height) = ((image.width() as f32 * scale) as u32, (image.height() as f32 * scale) as u32);
+    image::imageops::resize(&image, width.max(1), height.max(1), image::imageops::FilterType::Nearest)
+}
@@ -0,0 +1,134 @@
+//! Frames prepared for print: laid out on a page of a paper size at a
+//! resolution, resampled with Lanczos, with the resolution written in the
+//! file so that it prints at the intended size, and soft-proofed against
+//! what a CMYK press renders.
+
+use std::path::Path;
+
+use crate::error::{NuageError, Result};
+use crate::export::push_png_chunk;
+use crate::render::Palette;
+
+/// The resolution of printed frames unless given.
+pub const PRINT_DPI: u32 = 300;
+
+/// The white margin around the frame on the page, in millimeters.
+const MARGIN_MM: f32 = 10.;
+
+const MM_PER_INCH: f32 = 25.4;
+
+/// Paper sizes, portrait.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Paper {
+    A5,
+    A4,
+    A3,
+    Letter,
+    Legal,
+}
+
+impl Paper {
+    pub const ALL: [Paper; 5] = [Paper::A5, Paper::A4, Paper::A3, Paper::Letter, Paper::Legal];
+
+    /// The name used on the command line.
+    pub fn name(self) -> &'static str {
+        match self {
+            Paper::A5 => "a5",
+            Paper::A4 => "a4",
+            Paper::A3 => "a3",
+            Paper::Letter => "letter",
+            Paper::Legal => "legal",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Paper> {
+        Paper::ALL.into_iter().find(|paper| paper.name() == name.to_lowercase())
+    }
+
+    /// Width and height, in millimeters.
+    pub fn size_mm(self) -> (f32, f32) {
+        match self {
+            Paper::A5 => (148., 210.),
+            Paper::A4 => (210., 297.),
+            Paper::A3 => (297., 420.),
+            Paper::Letter => (215.9, 279.4),
+            Paper::Legal => (215.9, 355.6),
+        }
+    }
+}
+
+/// `image` on a white page of `paper` at `dpi`, turned landscape for wide
+/// frames, fit within the margins and centered.
+pub fn layout(image: &image::RgbImage, paper: Paper, dpi: u32) -> image::RgbImage {
+    let pixels = |mm: f32| (mm / MM_PER_INCH * dpi as f32).round() as u32;
+    let (short, long) = paper.size_mm();
+    let (width, height) = if image.width() > image.height() { (long, short) } else { (short, long) };
+    let mut page = image::RgbImage::from_pixel(pixels(width), pixels(height), image::Rgb([255; 3]));
+    let (room_width, room_height) = (pixels(width - 2. * MARGIN_MM), pixels(height - 2. * MARGIN_MM));
+    let scale = (room_width as f32 / image.width() as f32).min(room_height as f32 / image.height() as f32);
+    let (fit_width, fit_height) = ((image.width() as f32 * scale) as u32, (image.height() as f32 * scale) as u32);
+    let fit = image::imageops::resize(image, fit_width.max(1), fit_height.max(1), image::imageops::FilterType::Lanczos3);
+    let (left, top) = ((page.width() - fit.width()) / 2, (page.height() - fit.height()) / 2);
+    image::imageops::replace(&mut page, &fit, left as i64, top as i64);
+    page
+}
+
+/// Write `image` to `path` with its resolution, `dpi`, in the file: a pHYs
+/// chunk in PNG, the JFIF density in JPEG. Other formats are written without
+/// it.
+pub fn write(path: &Path, image: &image::RgbImage, dpi: u32) -> Result<()> {
+    let print_error = |e: &dyn std::fmt::Display| NuageError::Export(format!("{}: {}", path.display(), e));
+    let extension = path.extension().and_then(|extension| extension.to_str()).map(str::to_lowercase);
+    let bytes = match extension.as_deref() {
+        Some("png") => {
+            let mut png = vec![];
+            image.write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)?;
+            // pHYs goes before the image data: after the signature and IHDR
+            let rest = png.split_off(8 + 25);
+            let pixels_per_meter = (dpi as f32 / MM_PER_INCH * 1000.).round() as u32;
+            let data = [pixels_per_meter.to_be_bytes().as_slice(), &pixels_per_meter.to_be_bytes(), &[1]].concat();
+            push_png_chunk(&mut png, b"pHYs", &data);
+            png.extend(rest);
+            png
+        }
+        Some("jpg" | "jpeg") => {
+            let mut jpeg = vec![];
+            let mut encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(&mut jpeg, 95);
+            encoder.set_pixel_density(image::codecs::jpeg::PixelDensity::dpi(dpi.min(u16::MAX as u32) as u16));
+            encoder.encode_image(image)?;
+            jpeg
+        }
+        _ => return image.save(path).map_err(|e| print_error(&e)),
+    };
+    std::fs::write(path, bytes).map_err(|e| print_error(&e))
+}
+
+/// Whether a CMYK press renders the color duller than a screen: bright,
+/// saturated blues, greens, reds and purples. Yellows print well.
+fn is_out_of_gamut([r, g, b]: [u8; 3]) -> bool {
+    let (max, min) = (r.max(g).max(b) as f32, r.min(g).min(b) as f32);
+    if max == 0. {
+        return false;
+    }
+    let (value, saturation) = (max / 255., (max - min) / max);
+    let hue = match max {
+        max if max == r as f32 => 60. * ((g as f32 - b as f32) / (max - min).max(1.)),
+        max if max == g as f32 => 60. * (2. + (b as f32 - r as f32) / (max - min).max(1.)),
+        _ => 60. * (4. + (r as f32 - g as f32) / (max - min).max(1.)),
+    }
+    .rem_euclid(360.);
+    saturation > 0.8 && value > 0.7 && !(40. ..=70.).contains(&hue)
+}
+
+/// The share of the pixels of `image` a CMYK press renders duller, from 0
+/// to 1.
+pub fn out_of_gamut(image: &image::RgbImage) -> f32 {
+    let count = image.pixels().filter(|pixel| is_out_of_gamut(pixel.0)).count();
+    count as f32 / image.pixels().len().max(1) as f32
+}
+
+/// The share of the pixels of `image` out of the CMYK gamut with each of
+/// the built-in palettes, the original colors first.
+pub fn palette_proofs(image: &image::RgbImage) -> Vec<(Palette, f32)> {
+    Palette::ALL.into_iter().map(|palette| (palette.clone(), out_of_gamut(&palette.apply(image)))).collect()
+}
@@ -0,0 +1,67 @@
+//! Processing steps applied to the frames before they are adjusted and
+//! colored, declared per channel in the configuration to build derived
+//! products:
+//!
+//! ```ron
+//! processing: {
+//!     // The coldest cloud tops only, smoothed
+//!     Infrared: [Blur(1.5), Threshold(220)],
+//!     Composite: [Crop(x: 100, y: 50, width: 600, height: 400), Gamma(1.4)],
+//! },
+//! ```
+
+use image::RgbImage;
+use serde::{Deserialize, Serialize};
+
+use crate::render;
+
+/// A processing step, the frame keeping its size so that it stays in place
+/// under the overlays.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub enum ProcessingStep {
+    /// Keep a rectangle of the frame, in pixels, black outside
+    Crop { x: u32, y: u32, width: u32, height: u32 },
+    /// Above 1 brightens the dark tones
+    Gamma(f32),
+    /// Color each luminance, interpolated between (luminance, (red, green,
+    /// blue)) stops in increasing luminance
+    Lut(Vec<(u8, [u8; 3])>),
+    /// White where the luminance is at least this value, black elsewhere
+    Threshold(u8),
+    /// Gaussian blur of this standard deviation, in pixels
+    Blur(f32),
+}
+
+impl ProcessingStep {
+    pub fn apply(&self, image: &RgbImage) -> RgbImage {
+        match self {
+            ProcessingStep::Crop { x, y, width, height } => RgbImage::from_fn(image.width(), image.height(), |column, row| {
+                let inside = (*x..x.saturating_add(*width)).contains(&column) && (*y..y.saturating_add(*height)).contains(&row);
+                if inside { *image.get_pixel(column, row) } else { image::Rgb([0; 3]) }
+            }),
+            ProcessingStep::Gamma(gamma) => {
+                let corrected = |value: u8| ((value as f32 / 255.).powf(1. / gamma.max(0.01)) * 255.).round() as u8;
+                let table: Vec<u8> = (0..=255).map(corrected).collect();
+                let mut corrected = image.clone();
+                for channel in corrected.iter_mut() {
+                    *channel = table[*channel as usize];
+                }
+                corrected
+            }
+            ProcessingStep::Lut(stops) if stops.is_empty() => image.clone(),
+            ProcessingStep::Lut(stops) => render::color_by_luminance(image, stops),
+            ProcessingStep::Threshold(threshold) => {
+                let gray = image::imageops::grayscale(image);
+                RgbImage::from_fn(image.width(), image.height(), |x, y| {
+                    image::Rgb([if gray.get_pixel(x, y).0[0] >= *threshold { 255 } else { 0 }; 3])
+                })
+            }
+            ProcessingStep::Blur(sigma) => image::imageops::blur(image, *sigma),
+        }
+    }
+}
+
+/// `image` through each step, in order.
+pub fn apply(steps: &[ProcessingStep], image: &RgbImage) -> RgbImage {
+    steps.iter().fold(image.clone(), |image, step| step.apply(&image))
+}
@@ -0,0 +1,143 @@
+use eframe::egui;
+
+use crate::provider::Tiles;
+
+/// Position on screen of a (latitude, longitude) point, the tile window being
+/// displayed in `image_rect`.
+pub fn convert_gps_to_pixels(_tiles: Tiles, image_rect: &egui::Rect, _gps: (f32, f32)) -> (f32, f32) {
+    // Stopgap while trying to figure out the coordinate system which does not
+    // seem to follow slippy tiles.
+    let center_x: f32 = image_rect.min.x + (image_rect.max.x - image_rect.min.x) / 2.;
+    let center_y: f32 = image_rect.min.y + (image_rect.max.y - image_rect.min.y) / 2.;
+    (center_x * 1.045, center_y * 0.68)
+}
+
+/// Geographic bounds, in degrees.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Bounds {
+    pub west: f64,
+    pub south: f64,
+    pub east: f64,
+    pub north: f64,
+}
+
+impl Bounds {
+    /// Whether the two bounds share some area, touching edges included.
+    pub fn intersects(&self, other: &Bounds) -> bool {
+        self.west <= other.east && other.west <= self.east && self.south <= other.north && other.south <= self.north
+    }
+
+    /// Whether (longitude, latitude) is inside, edges included.
+    pub fn contains(&self, (lon, lat): (f64, f64)) -> bool {
+        (self.west..=self.east).contains(&lon) && (self.south..=self.north).contains(&lat)
+    }
+}
+
+/// Radius of the sphere used by Web Mercator (EPSG:3857), in meters.
+pub const EARTH_RADIUS: f64 = 6378137.0;
+
+/// (longitude, latitude) of the top-left corner of the slippy tile (x, y).
+/// Fractional tile coordinates give points inside the tile.
+pub fn tile_to_lonlat(x: f64, y: f64, zoom: u16) -> (f64, f64) {
+    let n = (1u32 << zoom) as f64;
+    let lon = x / n * 360.0 - 180.0;
+    let lat = (std::f64::consts::PI * (1.0 - 2.0 * y / n)).sinh().atan().to_degrees();
+    (lon, lat)
+}
+
+/// Bounds of a tile window, both corner tiles included.
+pub fn tiles_bounds(tiles: Tiles, zoom: u16) -> Bounds {
+    let (west, north) = tile_to_lonlat(tiles.0.0 as f64, tiles.0.1 as f64, zoom);
+    let (east, south) = tile_to_lonlat(tiles.1.0 as f64 + 1.0, tiles.1.1 as f64 + 1.0, zoom);
+    Bounds { west, south, east, north }
+}
+
+/// The smallest tile window at `zoom` covering the rectangle of which `a` and
+/// `b` are opposite corners, (longitude, latitude).
+pub fn tiles_covering(a: (f64, f64), b: (f64, f64), zoom: u16) -> Tiles {
+    let last = ((1u32 << zoom) - 1) as f64;
+    let (x1, y1) = lonlat_to_tile(a.0.min(b.0), a.1.max(b.1), zoom);
+    let (x2, y2) = lonlat_to_tile(a.0.max(b.0), a.1.min(b.1), zoom);
+    let tile = |coordinate: f64| coordinate.floor().clamp(0., last) as u16;
+    ((tile(x1), tile(y1)), (tile(x2), tile(y2)))
+}
+
+/// Web Mercator (EPSG:3857) coordinates in meters of a (longitude, latitude).
+pub fn lonlat_to_mercator(lon: f64, lat: f64) -> (f64, f64) {
+    let x = EARTH_RADIUS * lon.to_radians();
+    let y = EARTH_RADIUS * (std::f64::consts::FRAC_PI_4 + lat.to_radians() / 2.0).tan().ln();
+    (x, y)
+}
+
+/// Fractional slippy tile coordinates of a (longitude, latitude).
+pub fn lonlat_to_tile(lon: f64, lat: f64, zoom: u16) -> (f64, f64) {
+    let n = (1u32 << zoom) as f64;
+    let x = (lon + 180.0) / 360.0 * n;
+    let y = (1.0 - lat.to_radians().tan().asinh() / std::f64::consts::PI) / 2.0 * n;
+    (x, y)
+}
+
+/// Maps the pixels of a frame covering a tile window to geographic
+/// coordinates, assuming slippy (Web Mercator) tiles.
+#[derive(Clone, Copy, Debug)]
+pub struct Georeference {
+    pub tiles: Tiles,
+    pub zoom: u16,
+    /// Size of the frame in pixels
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Georeference {
+    pub fn new(tiles: Tiles, zoom: u16, (width, height): (u32, u32)) -> Self {
+        Georeference { tiles, zoom, width, height }
+    }
+
+    /// Size of the tile window in tiles.
+    fn tile_span(&self) -> (f64, f64) {
+        (
+            (self.tiles.1.0 - self.tiles.0.0 + 1) as f64,
+            (self.tiles.1.1 - self.tiles.0.1 + 1) as f64,
+        )
+    }
+
+    /// (longitude, latitude) of a pixel of the frame.
+    pub fn pixel_to_lonlat(&self, x: f64, y: f64) -> (f64, f64) {
+        let (span_x, span_y) = self.tile_span();
+        let tile_x = self.tiles.0.0 as f64 + x / self.width as f64 * span_x;
+        let tile_y = self.tiles.0.1 as f64 + y / self.height as f64 * span_y;
+        tile_to_lonlat(tile_x, tile_y, self.zoom)
+    }
+
+    /// Pixel of the frame at a (longitude, latitude), possibly outside of the
+    /// frame.
+    pub fn lonlat_to_pixel(&self, lon: f64, lat: f64) -> (f64, f64) {
+        let (span_x, span_y) = self.tile_span();
+        let (tile_x, tile_y) = lonlat_to_tile(lon, lat, self.zoom);
+        (
+            (tile_x - self.tiles.0.0 as f64) / span_x * self.width as f64,
+            (tile_y - self.tiles.0.1 as f64) / span_y * self.height as f64,
+        )
+    }
+}
+
+/// Mean radius of the earth, in kilometers.
+const MEAN_EARTH_RADIUS_KM: f64 = 6371.0;
+
+/// Great-circle distance in kilometers between two (longitude, latitude).
+pub fn distance_km(from: (f64, f64), to: (f64, f64)) -> f64 {
+    let (lon1, lat1) = (from.0.to_radians(), from.1.to_radians());
+    let (lon2, lat2) = (to.0.to_radians(), to.1.to_radians());
+    let a = ((lat2 - lat1) / 2.).sin().powi(2) + lat1.cos() * lat2.cos() * ((lon2 - lon1) / 2.).sin().powi(2);
+    2. * MEAN_EARTH_RADIUS_KM * a.sqrt().asin()
+}
+
+/// Initial bearing in degrees (0 is north, 90 east) of the great circle from
+/// `from` to `to`, both (longitude, latitude).
+pub fn bearing_degrees(from: (f64, f64), to: (f64, f64)) -> f64 {
+    let (lon1, lat1) = (from.0.to_radians(), from.1.to_radians());
+    let (lon2, lat2) = (to.0.to_radians(), to.1.to_radians());
+    let y = (lon2 - lon1).sin() * lat2.cos();
+    let x = lat1.cos() * lat2.sin() - lat1.sin() * lat2.cos() * (lon2 - lon1).cos();
+    (y.atan2(x).to_degrees() + 360.) % 360.
+}
@@ -0,0 +1,669 @@
+use chrono::{DateTime, Datelike, Duration, NaiveDateTime, Utc};
+use image::GenericImageView;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use crate::cache::{Cache, FrameMetadata, file_name};
+use crate::error::{NuageError, Result};
+use crate::json::{self, Json};
+use crate::timeline::{HISTORY_HOURS, previous_time, time_range};
+
+/// A rectangle of tiles: (top-left (x, y), bottom-right (x, y)).
+pub type Tiles = ((u16, u16), (u16, u16));
+
+/// The tiles covering western europe at `ZOOM`.
+pub const TILES: Tiles = ((41, 61), (50, 68));
+
+/// Width and height of a tile, in pixels.
+pub const TILE_SIZE: u32 = 256;
+
+/// Rough size of a tile in the JPEG frames downloaded, for estimates.
+pub const TILE_BYTES: u64 = 20 * 1024;
+pub const ZOOM: u16 = 7;
+
+/// Minutes after which Infoplaza publishes a frame, usually.
+pub const DELAY: u32 = 15;
+
+/// Minutes between two Infoplaza frames.
+pub const INTERVAL: u32 = 5;
+
+/// Where a frame comes from and what was done to it before it is shown.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Provenance {
+    pub provider: String,
+    /// Where the frame was downloaded or read from
+    pub url: Option<String>,
+    /// When the frame was downloaded
+    pub fetched: Option<DateTime<Utc>>,
+    /// Whether the frame was found in the cache rather than downloaded, for
+    /// cached providers
+    pub cache_hit: Option<bool>,
+    /// What was done to the frame, e.g. `resized from 2560x2048`
+    pub processing: Vec<String>,
+}
+
+/// A source of frames.
+pub trait Provider: Send + Sync {
+    /// Short name used in logs.
+    fn name(&self) -> &str;
+    /// The timestamps of the frames to show at `now`, from the most recent to
+    /// the least.
+    fn timepoints(&self, now: DateTime<Utc>) -> Result<Vec<DateTime<Utc>>>;
+    /// Get the frame at `timestamp`.
+    fn frame(&self, timestamp: DateTime<Utc>) -> Result<image::RgbImage>;
+    /// The tile window covered by the frames and its zoom level.
+    fn region(&self) -> (Tiles, u16) {
+        (TILES, ZOOM)
+    }
+    /// Minutes between two frames.
+    fn interval(&self) -> u32 {
+        INTERVAL
+    }
+    /// The SHA-256 of the frame at `timestamp` as it was downloaded, if
+    /// known.
+    fn checksum(&self, _timestamp: DateTime<Utc>) -> Option<String> {
+        None
+    }
+    /// Where the frame at `timestamp` comes from, as far as known.
+    fn provenance(&self, _timestamp: DateTime<Utc>) -> Provenance {
+        Provenance { provider: self.name().to_string(), ..Provenance::default() }
+    }
+    /// The folder the frames are read from, watched for new frames while they
+    /// are shown.
+    fn watch_folder(&self) -> Option<&Path> {
+        None
+    }
+}
+
+/// Downscale images larger than a typical screen, keeping their aspect ratio.
+pub(crate) fn fit_to_screen(img: image::DynamicImage) -> image::DynamicImage {
+    let (width, height) = screen_size(img.dimensions());
+    img.resize(width, height, crate::power::resize_filter())
+}
+
+/// The size of an image of `(width, height)` once fit to the screen.
+pub(crate) fn screen_size((width, height): (u32, u32)) -> (u32, u32) {
+    let screen_width = 1920;
+    let screen_height = 1080;
+    if width > screen_width || height > screen_height {
+        let typical_screen_ratio = screen_width as f32 / screen_height as f32;
+        let image_ratio = width as f32 / height as f32;
+        if image_ratio < typical_screen_ratio {
+            (
+                (width as f32 / (height as f32 / screen_height as f32)) as u32,
+                screen_height,
+            )
+        } else {
+            (
+                screen_width,
+                (height as f32 / (width as f32 / screen_width as f32)) as u32,
+            )
+        }
+    } else {
+        (width, height)
+    }
+}
+
+/// The tile window covering `tiles` one zoom level lower.
+pub fn zoom_out(((x1, y1), (x2, y2)): Tiles) -> Tiles {
+    ((x1 / 2, y1 / 2), (x2 / 2, y2 / 2))
+}
+
+/// The satellite products of Infoplaza.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq, Hash)]
+pub enum Channel {
+    /// Visible by day, infrared by night
+    #[default]
+    Composite,
+    Visible,
+    Infrared,
+    WaterVapor,
+    /// Visible and infrared blended, to spot convective tops
+    Sandwich,
+}
+
+impl Channel {
+    pub const ALL: [Channel; 5] = [Channel::Composite, Channel::Visible, Channel::Infrared, Channel::WaterVapor, Channel::Sandwich];
+
+    /// The name used on the command line and in cache file names.
+    pub fn name(self) -> &'static str {
+        match self {
+            Channel::Composite => "composite",
+            Channel::Visible => "visible",
+            Channel::Infrared => "infrared",
+            Channel::WaterVapor => "watervapor",
+            Channel::Sandwich => "sandwich",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Channel> {
+        Channel::ALL.into_iter().find(|channel| channel.name() == name)
+    }
+
+    /// The product in the tiles URL, only known for the composite, the one
+    /// the Infoplaza nowcast map shows. Those of the other channels are not
+    /// published and have to be configured.
+    fn product(self) -> Option<&'static str> {
+        match self {
+            Channel::Composite => Some("satellite-europe"),
+            _ => None,
+        }
+    }
+}
+
+/// Infoplaza's satellite tiles, cached on disk.
+#[derive(Clone, Debug)]
+pub struct Infoplaza {
+    pub zoom: u16,
+    pub tiles: Tiles,
+    pub channel: Channel,
+    /// The products in the tiles URL of the channels other than the
+    /// composite, by channel
+    pub products: HashMap<Channel, String>,
+    /// The frames between two times instead of the last `hours`
+    pub span: Option<(DateTime<Utc>, DateTime<Utc>)>,
+    /// Hours of frames shown, `HISTORY_HOURS` by default
+    pub hours: u32,
+    /// Minutes after which the frames are published, `DELAY` by default.
+    /// Newer frames are still shown once published.
+    pub delay: u32,
+    /// Minutes between two frames of the product, `INTERVAL` by default
+    pub interval: u32,
+    /// Download the frames one zoom level lower, four times fewer tiles,
+    /// enlarged to the window
+    pub low_data: bool,
+    cache: Cache,
+    /// Whether each frame asked for, by name, was in the cache the first
+    /// time
+    cache_hits: Arc<Mutex<HashMap<String, bool>>>,
+}
+
+impl Infoplaza {
+    /// The western europe window, cached in `cache`.
+    pub fn new(cache: Cache) -> Self {
+        Infoplaza {
+            zoom: ZOOM,
+            tiles: TILES,
+            channel: Channel::Composite,
+            products: HashMap::new(),
+            span: None,
+            hours: HISTORY_HOURS,
+            delay: DELAY,
+            interval: INTERVAL,
+            low_data: false,
+            cache,
+            cache_hits: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    pub fn cache(&self) -> &Cache {
+        &self.cache
+    }
+
+    /// The tile window downloaded and its zoom level, lower with `low_data`.
+    fn downloaded(&self) -> (Tiles, u16) {
+        match self.zoom {
+            zoom if self.low_data && zoom > 0 => (zoom_out(self.tiles), zoom - 1),
+            zoom => (self.tiles, zoom),
+        }
+    }
+
+    /// The path of the frame at `timestamp` in the cache.
+    fn frame_path(&self, timestamp: DateTime<Utc>) -> PathBuf {
+        let (tiles, zoom) = self.downloaded();
+        self.cache.frame_path(timestamp, zoom, tiles, self.channel)
+    }
+
+    /// The part of a frame downloaded one zoom level lower covering the
+    /// window, enlarged to the size of a frame of the window.
+    fn enlarge(&self, image: image::RgbImage) -> image::RgbImage {
+        let (((x1, y1), (x2, y2)), ((zx1, zy1), (zx2, zy2))) = (self.tiles, zoom_out(self.tiles));
+        // Each tile downloaded covers two by two tiles of the window
+        let columns = 2 * (zx2 - zx1 + 1) as u32;
+        let rows = 2 * (zy2 - zy1 + 1) as u32;
+        let (tile_width, tile_height) = (image.width() / columns, image.height() / rows);
+        let cropped = image::imageops::crop_imm(
+            &image,
+            (x1 - 2 * zx1) as u32 * tile_width,
+            (y1 - 2 * zy1) as u32 * tile_height,
+            (x2 - x1 + 1) as u32 * tile_width,
+            (y2 - y1 + 1) as u32 * tile_height,
+        )
+        .to_image();
+        let (width, height) = screen_size(((x2 - x1 + 1) as u32 * TILE_SIZE, (y2 - y1 + 1) as u32 * TILE_SIZE));
+        image::imageops::resize(&cropped, width, height, crate::power::resize_filter())
+    }
+
+    /// The product of the channel in the tiles URL, configured or known.
+    fn product(&self) -> Result<&str> {
+        match self.products.get(&self.channel) {
+            Some(product) => Ok(product),
+            None => self.channel.product().ok_or_else(|| {
+                NuageError::Config(format!(
+                    "the Infoplaza product of the {} channel is not known, set it in channel_products",
+                    self.channel.name()
+                ))
+            }),
+        }
+    }
+
+    /// The URL of the frame at `timestamp`.
+    fn url(&self, timestamp: DateTime<Utc>) -> Result<String> {
+        let (((x1, y1), (x2, y2)), zoom) = self.downloaded();
+        Ok(format!(
+            "https://imn-rust-lb.infoplaza.io/v4/nowcast/tiles/{}/{}/{}/{}/{}/{}/{}?outputtype=jpeg",
+            self.product()?, timestamp.format("%Y%m%d%H%M"), zoom, x1, y1, x2, y2
+        ))
+    }
+
+    /// Download the frame at `timestamp` in the cache. When it is missing at
+    /// the provider, it is recorded as such with `record_missing`.
+    fn download(&self, timestamp: DateTime<Utc>, record_missing: bool) -> Result<()> {
+        let filepath = self.frame_path(timestamp);
+        let name = file_name(&filepath);
+        let url = self.url(timestamp)?;
+        log::info!("fetching {}", url);
+        let mut res = match ureq::get(&url).call() {
+            Err(ureq::Error::StatusCode(404 | 410)) => {
+                if record_missing {
+                    self.cache.record_missing(&name, Utc::now())?;
+                }
+                return Err(NuageError::Missing(name));
+            }
+            res => res?,
+        };
+        let image_bytes = res
+            .body_mut()
+            .with_config()
+            .limit(20 * 1024 * 1024)
+            .read_to_vec()?;
+        let img = image::load_from_memory(&image_bytes)?;
+        let metadata = FrameMetadata { url, fetched: Utc::now(), size: img.dimensions() };
+        self.cache.write(&filepath, &fit_to_screen(img))?;
+        self.cache_hits.lock().unwrap().entry(name.clone()).or_insert(false);
+        self.cache.record_metadata(&name, &metadata)?;
+        self.cache.record_checksum(&name, &image_bytes)
+    }
+
+    /// Whether the frame at `timestamp` is published already, downloading it
+    /// if so. Frames not published yet are not recorded missing, as they
+    /// soon will be.
+    fn is_published(&self, timestamp: DateTime<Utc>) -> bool {
+        let filepath = self.frame_path(timestamp);
+        match self.cache.contains(&filepath) {
+            Ok(true) => true,
+            Ok(false) if !self.cache.is_missing(&file_name(&filepath), Utc::now()).unwrap_or(true) => {
+                self.download(timestamp, false).is_ok()
+            }
+            _ => false,
+        }
+    }
+}
+
+impl Provider for Infoplaza {
+    fn name(&self) -> &str {
+        "infoplaza"
+    }
+
+    fn timepoints(&self, now: DateTime<Utc>) -> Result<Vec<DateTime<Utc>>> {
+        match self.span {
+            Some((from, to)) => Ok(time_range(from, to, self.interval)),
+            None => {
+                let timepoints = previous_time(now, self.hours, self.delay, self.interval);
+                // Frames are sometimes published before the delay
+                let step = Duration::minutes(self.interval.max(1) as i64);
+                let mut newest = timepoints[0];
+                while newest + step <= now && self.is_published(newest + step) {
+                    newest += step;
+                }
+                match newest - timepoints[0] {
+                    ahead if ahead > Duration::zero() => {
+                        Ok(previous_time(now + ahead, self.hours, self.delay, self.interval))
+                    }
+                    _ => Ok(timepoints),
+                }
+            }
+        }
+    }
+
+    /// Get the frame at `timestamp`, from the cache if present or downloaded
+    /// otherwise. Frames answered missing are not asked for again for
+    /// `MISSING_FOR`, across restarts.
+    fn frame(&self, timestamp: DateTime<Utc>) -> Result<image::RgbImage> {
+        let filepath = self.frame_path(timestamp);
+        let name = file_name(&filepath);
+        if self.cache.contains(&filepath)? {
+            self.cache_hits.lock().unwrap().entry(name).or_insert(true);
+        } else {
+            if self.cache.is_missing(&name, Utc::now())? {
+                return Err(NuageError::Missing(name));
+            }
+            self.download(timestamp, true)?;
+        };
+        let image = self.cache.read(&filepath)?;
+        match self.downloaded() {
+            (_, zoom) if zoom != self.zoom => Ok(self.enlarge(image)),
+            _ => Ok(image),
+        }
+    }
+
+    fn region(&self) -> (Tiles, u16) {
+        (self.tiles, self.zoom)
+    }
+
+    fn interval(&self) -> u32 {
+        self.interval
+    }
+
+    fn checksum(&self, timestamp: DateTime<Utc>) -> Option<String> {
+        let name = file_name(&self.frame_path(timestamp));
+        self.cache.checksums().ok()?.remove(&name)
+    }
+
+    /// The URL and the resizing of the frame, with when it was downloaded
+    /// if it was since it is recorded.
+    fn provenance(&self, timestamp: DateTime<Utc>) -> Provenance {
+        let name = file_name(&self.frame_path(timestamp));
+        let metadata = self.cache.metadata().ok().and_then(|mut metadata| metadata.remove(&name));
+        let mut processing = vec![];
+        if let Some(FrameMetadata { size: (width, height), .. }) = metadata
+            && screen_size((width, height)) != (width, height)
+        {
+            processing.push(format!("resized from {}x{}", width, height));
+        }
+        let (_, zoom) = self.downloaded();
+        if zoom != self.zoom {
+            processing.push(format!("enlarged from zoom {} (low data)", zoom));
+        }
+        Provenance {
+            provider: self.name().to_string(),
+            url: metadata.as_ref().map(|metadata| metadata.url.clone()).or_else(|| self.url(timestamp).ok()),
+            fetched: metadata.map(|metadata| metadata.fetched),
+            cache_hit: self.cache_hits.lock().unwrap().get(&name).copied(),
+            processing,
+        }
+    }
+}
+
+/// The time written in a file name: 12 digits, YYYYMMDDHHMM, possibly split
+/// by separators and among other text, as in `202501011530.jpg`,
+/// `nuage_20250101_1530.png` or `goes16-2025-01-01T15:30.png`.
+pub fn timestamp_in_name(name: &str) -> Option<DateTime<Utc>> {
+    let digits: String = name.chars().filter(char::is_ascii_digit).collect();
+    (0..digits.len().saturating_sub(11)).find_map(|start| {
+        let timestamp = NaiveDateTime::parse_from_str(&digits[start..start + 12], "%Y%m%d%H%M").ok()?;
+        (1970..=2100).contains(&timestamp.year()).then(|| timestamp.and_utc())
+    })
+}
+
+/// Decode the frame file at `path`.
+fn read_frame(path: &Path) -> Result<image::RgbImage> {
+    log::info!("reading {}", path.display());
+    let image = image::ImageReader::open(path)
+        .map_err(NuageError::cache_io(path))?
+        .decode()?
+        .to_rgb8();
+    Ok(image)
+}
+
+/// Frames named after their timestamp (see `timestamp_in_name`, as written
+/// by `nuage gen-fixtures`) in a local folder. Other files are ignored.
+/// Frames added to the folder while it is shown are appended.
+#[derive(Clone, Debug)]
+pub struct LocalFolder {
+    folder: PathBuf,
+    /// Only the frames of the last hours before the newest one, all of them
+    /// if `None`. For folders filled by another tool without end.
+    pub hours: Option<u32>,
+}
+
+impl LocalFolder {
+    pub fn new(folder: impl Into<PathBuf>) -> Self {
+        LocalFolder { folder: folder.into(), hours: None }
+    }
+
+    pub fn folder(&self) -> &Path {
+        &self.folder
+    }
+
+    /// The frame files of the folder and their timestamp, from the most
+    /// recent to the least.
+    fn frame_files(&self) -> Result<Vec<(DateTime<Utc>, PathBuf)>> {
+        let folder = &self.folder;
+        let mut result = vec![];
+        for entry in std::fs::read_dir(folder).map_err(NuageError::cache_io(folder))? {
+            let path = entry.map_err(NuageError::cache_io(folder))?.path();
+            let Some(timestamp) = path.file_stem().and_then(|stem| stem.to_str()).and_then(timestamp_in_name) else {
+                continue;
+            };
+            result.push((timestamp, path));
+        }
+        result.sort_by_key(|(timestamp, _)| std::cmp::Reverse(*timestamp));
+        if let (Some(hours), Some((newest, _))) = (self.hours, result.first()) {
+            let oldest = *newest - Duration::hours(hours as i64);
+            result.retain(|(timestamp, _)| *timestamp > oldest);
+        }
+        Ok(result)
+    }
+}
+
+impl Provider for LocalFolder {
+    fn name(&self) -> &str {
+        "local"
+    }
+
+    fn timepoints(&self, _now: DateTime<Utc>) -> Result<Vec<DateTime<Utc>>> {
+        Ok(self.frame_files()?.into_iter().map(|(timestamp, _)| timestamp).collect())
+    }
+
+    fn frame(&self, timestamp: DateTime<Utc>) -> Result<image::RgbImage> {
+        let Some((_, path)) = self.frame_files()?.into_iter().find(|(t, _)| *t == timestamp) else {
+            return Err(NuageError::CacheIo {
+                path: self.folder.clone(),
+                source: std::io::Error::new(std::io::ErrorKind::NotFound, format!("no frame at {}", timestamp)),
+            });
+        };
+        read_frame(&path)
+    }
+
+    fn watch_folder(&self) -> Option<&Path> {
+        Some(&self.folder)
+    }
+
+    fn provenance(&self, timestamp: DateTime<Utc>) -> Provenance {
+        let path = self.frame_files().ok().and_then(|files| files.into_iter().find(|(t, _)| *t == timestamp));
+        Provenance {
+            provider: self.name().to_string(),
+            url: path.map(|(_, path)| path.display().to_string()),
+            ..Provenance::default()
+        }
+    }
+}
+
+/// Frames read from a set of image files, such as those dropped on the
+/// window, their time parsed from their names.
+#[derive(Clone, Debug)]
+pub struct LocalFiles {
+    // From the most recent to the least
+    files: Vec<(DateTime<Utc>, PathBuf)>,
+}
+
+impl LocalFiles {
+    /// The files of `paths` with a time in their name, the others being
+    /// ignored.
+    pub fn new(paths: &[PathBuf]) -> Self {
+        let mut files: Vec<(DateTime<Utc>, PathBuf)> = paths
+            .iter()
+            .filter_map(|path| Some((timestamp_in_name(path.file_stem()?.to_str()?)?, path.clone())))
+            .collect();
+        files.sort_by_key(|(timestamp, _)| std::cmp::Reverse(*timestamp));
+        LocalFiles { files }
+    }
+
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    fn path(&self, timestamp: DateTime<Utc>) -> Option<&Path> {
+        self.files.iter().find(|(t, _)| *t == timestamp).map(|(_, path)| path.as_path())
+    }
+}
+
+impl Provider for LocalFiles {
+    fn name(&self) -> &str {
+        "local files"
+    }
+
+    fn timepoints(&self, _now: DateTime<Utc>) -> Result<Vec<DateTime<Utc>>> {
+        Ok(self.files.iter().map(|(timestamp, _)| *timestamp).collect())
+    }
+
+    fn frame(&self, timestamp: DateTime<Utc>) -> Result<image::RgbImage> {
+        let path = self.path(timestamp).ok_or_else(|| NuageError::Missing(format!("file of the frame at {}", timestamp)))?;
+        read_frame(path)
+    }
+
+    fn provenance(&self, timestamp: DateTime<Utc>) -> Provenance {
+        Provenance {
+            provider: self.name().to_string(),
+            url: self.path(timestamp).map(|path| path.display().to_string()),
+            ..Provenance::default()
+        }
+    }
+}
+
+/// The frames served by another nuage, started with `nuage --serve`.
+#[derive(Debug)]
+pub struct RemoteServer {
+    /// e.g. `http://raspberrypi:8080`
+    url: String,
+    // The region of the remote frames, as of the last listing
+    region: Mutex<(Tiles, u16)>,
+}
+
+impl RemoteServer {
+    pub fn new(url: &str) -> Self {
+        RemoteServer {
+            url: url.trim_end_matches('/').to_string(),
+            region: Mutex::new((TILES, ZOOM)),
+        }
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// The URL of the frame at `timestamp`.
+    fn frame_url(&self, timestamp: DateTime<Utc>) -> String {
+        format!("{}/frames/{}.jpg?raw", self.url, timestamp.format("%Y%m%d%H%M"))
+    }
+}
+
+impl Provider for RemoteServer {
+    fn name(&self) -> &str {
+        &self.url
+    }
+
+    fn timepoints(&self, _now: DateTime<Utc>) -> Result<Vec<DateTime<Utc>>> {
+        let url = format!("{}/index.json", self.url);
+        let text = ureq::get(&url).call()?.body_mut().read_to_string()?;
+        let index = json::parse(&text).map_err(|e| NuageError::Config(format!("{}: {}", url, e)))?;
+        let numbers = |key: &str| -> Option<Vec<u16>> {
+            index.get(key)?.as_array()?.iter().map(|n| n.as_f64().map(|n| n as u16)).collect()
+        };
+        if let (Some(tiles), Some(zoom)) = (numbers("tiles"), index.get("zoom").and_then(Json::as_f64))
+            && let [x1, y1, x2, y2] = tiles[..]
+        {
+            *self.region.lock().unwrap() = (((x1, y1), (x2, y2)), zoom as u16);
+        }
+        let frames = index
+            .get("frames")
+            .and_then(Json::as_array)
+            .ok_or_else(|| NuageError::Config(format!("{}: no frames list", url)))?;
+        Ok(frames
+            .iter()
+            .filter_map(|frame| frame.get("timestamp")?.as_str())
+            .filter_map(|timestamp| NaiveDateTime::parse_from_str(timestamp, "%Y-%m-%dT%H:%M:%SZ").ok())
+            .map(|timestamp| timestamp.and_utc())
+            .collect())
+    }
+
+    /// The frame without the point of interest burned in.
+    fn frame(&self, timestamp: DateTime<Utc>) -> Result<image::RgbImage> {
+        let url = self.frame_url(timestamp);
+        log::info!("fetching {}", url);
+        let image_bytes = ureq::get(&url)
+            .call()?
+            .body_mut()
+            .with_config()
+            .limit(20 * 1024 * 1024)
+            .read_to_vec()?;
+        Ok(image::load_from_memory(&image_bytes)?.to_rgb8())
+    }
+
+    fn region(&self) -> (Tiles, u16) {
+        *self.region.lock().unwrap()
+    }
+
+    fn provenance(&self, timestamp: DateTime<Utc>) -> Provenance {
+        Provenance {
+            provider: self.name().to_string(),
+            url: Some(self.frame_url(timestamp)),
+            ..Provenance::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 1, 1, hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn timestamps_in_names() {
+        for name in ["202501011530", "nuage_20250101_1530", "goes16-2025-01-01T15:30", "frame 2025-01-01 15h30"] {
+            assert_eq!(timestamp_in_name(name), Some(at(15, 30)), "{}", name);
+        }
+        for name in ["frame", "12345", "999912319999", "20250101153"] {
+            assert_eq!(timestamp_in_name(name), None, "{}", name);
+        }
+    }
+
+    #[test]
+    fn local_folder_of_fixtures() {
+        let folder = std::env::temp_dir().join(format!("nuage-fixtures-{}", std::process::id()));
+        let written = crate::fixtures::generate(&folder, 4, 10, at(15, 30)).unwrap();
+        // Other files are ignored
+        std::fs::write(folder.join("notes.txt"), "no time here").unwrap();
+        let local = LocalFolder::new(&folder);
+        let timepoints = local.timepoints(Utc::now()).unwrap();
+        assert_eq!(timepoints, [at(15, 30), at(15, 20), at(15, 10), at(15, 0)]);
+        let frame = local.frame(at(15, 10)).unwrap();
+        assert_eq!(frame.dimensions(), (crate::fixtures::FIXTURE_WIDTH, crate::fixtures::FIXTURE_HEIGHT));
+        assert!(local.frame(at(15, 5)).is_err());
+        assert_eq!(local.provenance(at(15, 0)).url, Some(written[0].display().to_string()));
+        let hours = LocalFolder { hours: Some(0), ..LocalFolder::new(&folder) };
+        assert!(hours.timepoints(Utc::now()).unwrap().is_empty());
+        std::fs::remove_dir_all(&folder).unwrap();
+    }
+
+    #[test]
+    fn channel_products() {
+        let mut infoplaza = Infoplaza::new(Cache::new(std::env::temp_dir()));
+        assert!(infoplaza.url(at(15, 30)).unwrap().contains("/satellite-europe/202501011530/"));
+        infoplaza.channel = Channel::Infrared;
+        assert!(matches!(infoplaza.url(at(15, 30)), Err(NuageError::Config(_))));
+        infoplaza.products.insert(Channel::Infrared, "infrared-product".to_string());
+        assert!(infoplaza.url(at(15, 30)).unwrap().contains("/infrared-product/202501011530/"));
+    }
+}
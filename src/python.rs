@@ -0,0 +1,101 @@
+//! Python module, built with maturin (see `pyproject.toml`) with the
+//! `python` feature:
+//!
+//! ```python
+//! import nuage
+//! timestamps = nuage.timeline()          # datetimes, most recent first
+//! frame = nuage.frame(timestamps[0])     # numpy array, height x width x RGBA
+//! ```
+//!
+//! The downloads run without the GIL, for other Python threads to go on.
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+use pyo3::exceptions::PyException;
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+
+use crate::ffi;
+use crate::mosaic;
+use crate::provider::Provider;
+
+pyo3::create_exception!(nuage, NuageError, PyException, "An error met while getting frames.");
+
+impl From<crate::error::NuageError> for PyErr {
+    fn from(error: crate::error::NuageError) -> PyErr {
+        NuageError::new_err(error.to_string())
+    }
+}
+
+/// A time given as an aware datetime, a naive one in UTC or a unix
+/// timestamp.
+fn to_datetime(timestamp: &Bound<'_, PyAny>) -> PyResult<DateTime<Utc>> {
+    if let Ok(seconds) = timestamp.extract::<i64>() {
+        return DateTime::from_timestamp(seconds, 0).ok_or_else(|| NuageError::new_err("invalid timestamp"));
+    }
+    match timestamp.extract::<DateTime<Utc>>() {
+        Ok(timestamp) => Ok(timestamp),
+        Err(_) => Ok(timestamp.extract::<NaiveDateTime>()?.and_utc()),
+    }
+}
+
+/// `image` as (width, height, RGBA bytes).
+fn rgba(py: Python<'_>, image: image::RgbImage) -> (u32, u32, Py<PyBytes>) {
+    let rgba = image::DynamicImage::ImageRgb8(image).to_rgba8();
+    (rgba.width(), rgba.height(), PyBytes::new(py, rgba.as_raw()).unbind())
+}
+
+/// The times of the frames the viewer would show now, most recent first.
+#[pyfunction]
+fn timeline(py: Python<'_>) -> PyResult<Vec<DateTime<Utc>>> {
+    Ok(py.allow_threads(|| ffi::provider()?.timepoints(Utc::now()))?)
+}
+
+/// Fetch (or read from the cache) the most recent available frame and return
+/// its time.
+#[pyfunction]
+fn fetch_latest(py: Python<'_>) -> PyResult<DateTime<Utc>> {
+    py.allow_threads(ffi::fetch_latest)?.ok_or_else(|| NuageError::new_err("no frame available"))
+}
+
+/// The frame at `timestamp` as (width, height, RGBA bytes).
+#[pyfunction]
+fn frame_bytes(py: Python<'_>, timestamp: &Bound<'_, PyAny>) -> PyResult<(u32, u32, Py<PyBytes>)> {
+    let timestamp = to_datetime(timestamp)?;
+    let image = py.allow_threads(|| ffi::provider()?.frame(timestamp))?;
+    Ok(rgba(py, image))
+}
+
+/// The frame at `timestamp` as a numpy array of shape (height, width, 4).
+#[pyfunction]
+fn frame<'py>(py: Python<'py>, timestamp: &Bound<'py, PyAny>) -> PyResult<Bound<'py, PyAny>> {
+    let (width, height, pixels) = frame_bytes(py, timestamp)?;
+    let numpy = py.import("numpy")?;
+    numpy
+        .call_method1("frombuffer", (pixels, numpy.getattr("uint8")?))?
+        .call_method1("reshape", (height, width, 4))
+}
+
+/// The times of the newest and oldest tiles of a picture, its width and
+/// height and its RGBA bytes.
+type Picture = (DateTime<Utc>, DateTime<Utc>, u32, u32, Py<PyBytes>);
+
+/// The freshest picture of the frames, the latest whole frame with the tiles
+/// published since laid over it, as (newest, oldest, width, height, RGBA
+/// bytes), newest and oldest being the times of its tiles.
+#[pyfunction]
+fn best_bytes(py: Python<'_>) -> PyResult<Picture> {
+    let mosaic = py.allow_threads(|| mosaic::best_available(&ffi::provider()?, Utc::now()))?;
+    let (width, height, pixels) = rgba(py, mosaic.annotated());
+    Ok((mosaic.newest(), mosaic.oldest(), width, height, pixels))
+}
+
+#[pymodule]
+fn nuage(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add("NuageError", module.py().get_type::<NuageError>())?;
+    module.add_function(wrap_pyfunction!(timeline, module)?)?;
+    module.add_function(wrap_pyfunction!(fetch_latest, module)?)?;
+    module.add_function(wrap_pyfunction!(frame_bytes, module)?)?;
+    module.add_function(wrap_pyfunction!(frame, module)?)?;
+    module.add_function(wrap_pyfunction!(best_bytes, module)?)?;
+    Ok(())
+}
@@ -0,0 +1,104 @@
+//! How alike the frames of two providers are, to choose the provider used by
+//! default for a region.
+
+use image::{GrayImage, RgbImage};
+
+use crate::analysis::luminance;
+use crate::provider::Tiles;
+
+/// The similarity of two frames taken at the same time.
+#[derive(Clone, Copy, Debug)]
+pub struct FrameMetrics {
+    /// Peak signal-to-noise ratio, in dB, infinite for identical frames
+    pub psnr: f64,
+    /// Mean structural similarity (-1 to 1, 1 for identical frames)
+    pub ssim: f64,
+}
+
+impl FrameMetrics {
+    /// The metrics of the luminance of two frames of the same size.
+    pub fn between(a: &GrayImage, b: &GrayImage) -> Self {
+        FrameMetrics { psnr: psnr(a, b), ssim: ssim(a, b) }
+    }
+}
+
+/// Peak signal-to-noise ratio of two images of the same size, in dB.
+pub fn psnr(a: &GrayImage, b: &GrayImage) -> f64 {
+    let squared_error: f64 = a
+        .as_raw()
+        .iter()
+        .zip(b.as_raw())
+        .map(|(a, b)| (*a as f64 - *b as f64).powi(2))
+        .sum();
+    let mse = squared_error / a.as_raw().len().max(1) as f64;
+    if mse == 0. {
+        return f64::INFINITY;
+    }
+    10. * (255f64.powi(2) / mse).log10()
+}
+
+/// Side of the windows the structural similarity is computed on, in pixels.
+const SSIM_WINDOW: u32 = 8;
+
+/// Structural similarity of two images of the same size, averaged over
+/// windows of 8x8 pixels.
+pub fn ssim(a: &GrayImage, b: &GrayImage) -> f64 {
+    const C1: f64 = (0.01 * 255.) * (0.01 * 255.);
+    const C2: f64 = (0.03 * 255.) * (0.03 * 255.);
+    let (width, height) = a.dimensions();
+    let mut sum = 0.;
+    let mut windows = 0;
+    for y in (0..height.saturating_sub(SSIM_WINDOW - 1)).step_by(SSIM_WINDOW as usize) {
+        for x in (0..width.saturating_sub(SSIM_WINDOW - 1)).step_by(SSIM_WINDOW as usize) {
+            let pixels: Vec<(f64, f64)> = (0..SSIM_WINDOW * SSIM_WINDOW)
+                .map(|i| (x + i % SSIM_WINDOW, y + i / SSIM_WINDOW))
+                .map(|(x, y)| (a.get_pixel(x, y).0[0] as f64, b.get_pixel(x, y).0[0] as f64))
+                .collect();
+            let n = pixels.len() as f64;
+            let mean_a = pixels.iter().map(|(a, _)| a).sum::<f64>() / n;
+            let mean_b = pixels.iter().map(|(_, b)| b).sum::<f64>() / n;
+            let variance_a = pixels.iter().map(|(a, _)| (a - mean_a).powi(2)).sum::<f64>() / n;
+            let variance_b = pixels.iter().map(|(_, b)| (b - mean_b).powi(2)).sum::<f64>() / n;
+            let covariance = pixels.iter().map(|(a, b)| (a - mean_a) * (b - mean_b)).sum::<f64>() / n;
+            sum += ((2. * mean_a * mean_b + C1) * (2. * covariance + C2))
+                / ((mean_a.powi(2) + mean_b.powi(2) + C1) * (variance_a + variance_b + C2));
+            windows += 1;
+        }
+    }
+    if windows == 0 { 1. } else { sum / windows as f64 }
+}
+
+/// The luminance of the area covered by both frames, each covering the tile
+/// window of its region, the second one resized to the first. `None` when the
+/// regions do not overlap or are not at the same zoom level.
+pub fn common_area(a: &RgbImage, region_a: (Tiles, u16), b: &RgbImage, region_b: (Tiles, u16)) -> Option<(GrayImage, GrayImage)> {
+    let ((tiles_a, zoom_a), (tiles_b, zoom_b)) = (region_a, region_b);
+    if zoom_a != zoom_b {
+        return None;
+    }
+    let common = (
+        (tiles_a.0.0.max(tiles_b.0.0), tiles_a.0.1.max(tiles_b.0.1)),
+        (tiles_a.1.0.min(tiles_b.1.0), tiles_a.1.1.min(tiles_b.1.1)),
+    );
+    if common.0.0 > common.1.0 || common.0.1 > common.1.1 {
+        return None;
+    }
+    let a = crop_to_tiles(a, tiles_a, common);
+    let b = crop_to_tiles(b, tiles_b, common);
+    let b = match a.dimensions() == b.dimensions() {
+        true => b,
+        false => image::imageops::resize(&b, a.width(), a.height(), image::imageops::FilterType::Triangle),
+    };
+    Some((luminance(&a), luminance(&b)))
+}
+
+/// The part of `image`, covering `tiles`, covering `window`.
+fn crop_to_tiles(image: &RgbImage, tiles: Tiles, window: Tiles) -> RgbImage {
+    let span = ((tiles.1.0 - tiles.0.0 + 1) as f32, (tiles.1.1 - tiles.0.1 + 1) as f32);
+    let scale = (image.width() as f32 / span.0, image.height() as f32 / span.1);
+    let x = ((window.0.0 - tiles.0.0) as f32 * scale.0) as u32;
+    let y = ((window.0.1 - tiles.0.1) as f32 * scale.1) as u32;
+    let width = ((window.1.0 - window.0.0 + 1) as f32 * scale.0) as u32;
+    let height = ((window.1.1 - window.0.1 + 1) as f32 * scale.1) as u32;
+    image::imageops::crop_imm(image, x, y, width.max(1), height.max(1)).to_image()
+}
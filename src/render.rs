@@ -0,0 +1,450 @@
+use ab_glyph::{Font, FontRef, PxScale, ScaleFont};
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Local, Utc};
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+use crate::palettes::{self, Stops};
+use crate::processing::{self, ProcessingStep};
+use crate::projection::convert_gps_to_pixels;
+use crate::provider::Tiles;
+use crate::timeline::SatImage;
+use crate::timezone;
+use crate::viewer::PARIS;
+
+/// The icon drawn on the point of interest, its tip at the bottom center.
+pub const PINPOINT_ICON: &[u8] = include_bytes!("../pinpoint-icon.png");
+
+/// The font of the labels.
+pub const VCR_FONT: &[u8] = include_bytes!("../VCR_OSD_MONO_1.001.ttf");
+
+/// Brightness, contrast and gamma corrections of the displayed frames.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(default)]
+pub struct Adjustments {
+    /// Added to the values, from -1 (black) to 1 (white)
+    pub brightness: f32,
+    /// Factor of the distance to mid-gray, 1 unchanged
+    pub contrast: f32,
+    /// Above 1 brightens the dark tones, 1 unchanged
+    pub gamma: f32,
+}
+
+impl Default for Adjustments {
+    fn default() -> Self {
+        Adjustments {
+            brightness: 0.,
+            contrast: 1.,
+            gamma: 1.,
+        }
+    }
+}
+
+impl Adjustments {
+    pub fn is_identity(&self) -> bool {
+        *self == Adjustments::default()
+    }
+
+    /// The adjusted image, through a lookup table of the 256 values.
+    pub fn apply(&self, image: &image::RgbImage) -> image::RgbImage {
+        let table: Vec<u8> = (0..=255)
+            .map(|value| {
+                let value = ((value as f32 / 255. - 0.5) * self.contrast + 0.5 + self.brightness).clamp(0., 1.);
+                (value.powf(1. / self.gamma.max(0.01)) * 255.).round() as u8
+            })
+            .collect();
+        let mut adjusted = image.clone();
+        for channel in adjusted.iter_mut() {
+            *channel = table[*channel as usize];
+        }
+        adjusted
+    }
+}
+
+/// Colors given to the luminance of the frames.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub enum Palette {
+    /// The colors of the provider
+    #[default]
+    Original,
+    Grayscale,
+    /// Gray for the warm low clouds, then blue, green, yellow, red and white
+    /// as the cloud tops get colder (brighter)
+    Infrared,
+    Viridis,
+    /// A palette file, see [`crate::palettes`]
+    File(String),
+}
+
+impl Palette {
+    pub const ALL: [Palette; 4] = [Palette::Original, Palette::Grayscale, Palette::Infrared, Palette::Viridis];
+
+    /// The name shown in the status line.
+    pub fn name(&self) -> &str {
+        match self {
+            Palette::Original => "Original",
+            Palette::Grayscale => "Grayscale",
+            Palette::Infrared => "Infrared",
+            Palette::Viridis => "Viridis",
+            Palette::File(name) => name,
+        }
+    }
+
+    /// The next palette, the palette files after the others, back to the
+    /// first after the last.
+    pub fn next(&self) -> Palette {
+        let all: Vec<Palette> = Palette::ALL.into_iter().chain(palettes::names().into_iter().map(Palette::File)).collect();
+        let index = all.iter().position(|palette| palette == self).unwrap_or(0);
+        all[(index + 1) % all.len()].clone()
+    }
+
+    /// The color of each luminance, interpolated between (luminance, color)
+    /// stops. `None` for the original colors, or a palette file that is gone.
+    fn stops(&self) -> Option<Stops> {
+        let stops: &'static [(u8, [u8; 3])] = match self {
+            Palette::Original => return None,
+            Palette::File(name) => return palettes::stops(name),
+            Palette::Grayscale => &[(0, [0, 0, 0]), (255, [255, 255, 255])],
+            Palette::Infrared => &[
+                (0, [0, 0, 0]),
+                (150, [170, 170, 170]),
+                (151, [0, 60, 200]),
+                (180, [0, 200, 80]),
+                (205, [240, 230, 0]),
+                (230, [230, 0, 0]),
+                (245, [80, 0, 0]),
+                (255, [255, 255, 255]),
+            ],
+            Palette::Viridis => &[
+                (0, [68, 1, 84]),
+                (64, [59, 82, 139]),
+                (128, [33, 145, 140]),
+                (192, [94, 201, 98]),
+                (255, [253, 231, 37]),
+            ],
+        };
+        Some(stops.to_vec())
+    }
+
+    /// The frame colored by its luminance, unchanged with the original
+    /// colors.
+    pub fn apply(&self, image: &image::RgbImage) -> image::RgbImage {
+        match self.stops() {
+            Some(stops) => color_by_luminance(image, &stops),
+            None => image.clone(),
+        }
+    }
+}
+
+/// The frame colored by its luminance, interpolated between (luminance,
+/// color) stops in increasing luminance. `stops` must not be empty.
+pub fn color_by_luminance(image: &image::RgbImage, stops: &[(u8, [u8; 3])]) -> image::RgbImage {
+    let table: Vec<[u8; 3]> = (0..=255u8)
+        .map(|luminance| {
+            let upper = stops.iter().position(|(stop, _)| *stop >= luminance).unwrap_or(stops.len() - 1);
+            let (high, high_color) = stops[upper];
+            let Some((low, low_color)) = upper.checked_sub(1).map(|lower| stops[lower]) else {
+                return high_color;
+            };
+            let t = (luminance.saturating_sub(low)) as f32 / (high.saturating_sub(low)).max(1) as f32;
+            std::array::from_fn(|channel| {
+                let (low, high) = (low_color[channel] as f32, high_color[channel] as f32);
+                (low + (high - low) * t.min(1.)).round() as u8
+            })
+        })
+        .collect();
+    let gray = image::imageops::grayscale(image);
+    image::RgbImage::from_fn(image.width(), image.height(), |x, y| image::Rgb(table[gray.get_pixel(x, y).0[0] as usize]))
+}
+
+/// What is shown instead of a frame, to make the changes since the previous
+/// one stand out.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub enum Difference {
+    /// The frame itself
+    #[default]
+    Off,
+    /// How much the luminance changed, black where it did not
+    Absolute,
+    /// The frame in gray, red where it got brighter (growing clouds), blue
+    /// where it got darker
+    Highlight,
+}
+
+/// Luminance changes smaller than this are not highlighted.
+const HIGHLIGHT_THRESHOLD: i16 = 12;
+
+impl Difference {
+    pub const ALL: [Difference; 3] = [Difference::Off, Difference::Absolute, Difference::Highlight];
+
+    /// The next mode, back to the first after the last.
+    pub fn next(self) -> Difference {
+        let index = Difference::ALL.iter().position(|difference| *difference == self).unwrap_or(0);
+        Difference::ALL[(index + 1) % Difference::ALL.len()]
+    }
+
+    /// The changes from `previous` to `current`, of the same size, or
+    /// `current` when off.
+    pub fn apply(self, previous: &image::RgbImage, current: &image::RgbImage) -> image::RgbImage {
+        let (before, after) = (image::imageops::grayscale(previous), image::imageops::grayscale(current));
+        let change = |x, y| after.get_pixel(x, y).0[0] as i16 - before.get_pixel(x, y).0[0] as i16;
+        match self {
+            Difference::Off => current.clone(),
+            Difference::Absolute => image::RgbImage::from_fn(current.width(), current.height(), |x, y| {
+                // Amplified, changes between frames are small
+                let value = (change(x, y).unsigned_abs() * 4).min(255) as u8;
+                image::Rgb([value; 3])
+            }),
+            Difference::Highlight => image::RgbImage::from_fn(current.width(), current.height(), |x, y| {
+                let gray = (after.get_pixel(x, y).0[0] as f32 * 0.6) as u8;
+                let change = change(x, y);
+                if change.abs() < HIGHLIGHT_THRESHOLD {
+                    return image::Rgb([gray; 3]);
+                }
+                let strength = (change.unsigned_abs() as f32 * 4. / 255.).min(1.);
+                let color = if change > 0 { [255., 40., 40.] } else { [40., 120., 255.] };
+                image::Rgb(std::array::from_fn(|channel| (gray as f32 + (color[channel] - gray as f32) * strength) as u8))
+            }),
+        }
+    }
+}
+
+/// The frame as displayed: processed, adjusted then colored.
+pub fn display_image<'a>(
+    image: &'a image::RgbImage,
+    processing: &[ProcessingStep],
+    adjustments: &Adjustments,
+    palette: &Palette,
+) -> std::borrow::Cow<'a, image::RgbImage> {
+    let mut image = std::borrow::Cow::Borrowed(image);
+    if !processing.is_empty() {
+        image = std::borrow::Cow::Owned(processing::apply(processing, &image));
+    }
+    if !adjustments.is_identity() {
+        image = std::borrow::Cow::Owned(adjustments.apply(&image));
+    }
+    if *palette != Palette::Original {
+        image = std::borrow::Cow::Owned(palette.apply(&image));
+    }
+    image
+}
+
+/// The time zone the times are shown in.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub enum TimeZone {
+    #[default]
+    Local,
+    Utc,
+}
+
+impl TimeZone {
+    pub fn next(self) -> TimeZone {
+        match self {
+            TimeZone::Local => TimeZone::Utc,
+            TimeZone::Utc => TimeZone::Local,
+        }
+    }
+
+    /// `format` applied to `timestamp` in the zone, followed by the
+    /// abbreviation of the zone.
+    pub fn format(self, timestamp: DateTime<Utc>, format: &str) -> String {
+        match self {
+            TimeZone::Local => {
+                let local_timestamp: DateTime<Local> = DateTime::from(timestamp);
+                format!("{} {}", local_timestamp.format(format), timezone::local_abbreviation(timestamp))
+            }
+            TimeZone::Utc => format!("{} UTC", timestamp.format(format)),
+        }
+    }
+}
+
+/// The formats of the times of the frames, strftime-like: `%d-%m-%Y %H:%M`,
+/// `%a %I:%M %p`, `%Y-%m-%dT%H:%M`...
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(default)]
+pub struct TimestampFormat {
+    /// In the labels, followed by the time zone
+    pub label: String,
+    /// In the names of the files saved
+    pub file: String,
+}
+
+impl Default for TimestampFormat {
+    fn default() -> Self {
+        TimestampFormat {
+            label: "%d-%m-%Y %H:%M".into(),
+            file: "%Y%m%d_%H%M".into(),
+        }
+    }
+}
+
+impl TimestampFormat {
+    /// Fail on the first format chrono cannot apply, which would panic
+    /// when formatting.
+    pub fn check(&self) -> Result<(), String> {
+        for format in [&self.label, &self.file] {
+            if StrftimeItems::new(format).any(|item| item == Item::Error) {
+                return Err(format!("invalid timestamp format {:?}", format));
+            }
+        }
+        Ok(())
+    }
+
+    /// `timestamp` for a file name, path separators and colons replaced.
+    pub fn file_part<Tz: chrono::TimeZone>(&self, timestamp: DateTime<Tz>) -> String
+    where
+        Tz::Offset: std::fmt::Display,
+    {
+        timestamp.format(&self.file).to_string().replace(['/', '\\', ':'], "-")
+    }
+}
+
+/// The time of a frame as displayed in the labels, in `format` and followed
+/// by its time zone.
+pub fn timestamp_label(timestamp: DateTime<Utc>, zone: TimeZone, format: &str) -> String {
+    zone.format(timestamp, format)
+}
+
+/// Draw `text` in white with the label font, its bottom-left corner at
+/// `(x, y)`. A dark shadow keeps it readable on bright clouds.
+pub fn draw_label(canvas: &mut image::RgbImage, text: &str, (x, y): (f32, f32), size: f32) {
+    let font = FontRef::try_from_slice(VCR_FONT).expect("Could not load font");
+    let scaled_font = font.as_scaled(PxScale::from(size));
+    for (offset, color) in [(2., [0, 0, 0]), (0., [255, 255, 255])] {
+        let mut caret = x + offset;
+        for c in text.chars() {
+            let glyph_id = scaled_font.glyph_id(c);
+            let glyph = glyph_id.with_scale_and_position(size, ab_glyph::point(caret, y + offset - scaled_font.descent()));
+            caret += scaled_font.h_advance(glyph_id);
+            let Some(outlined) = font.outline_glyph(glyph) else {
+                continue;
+            };
+            let bounds = outlined.px_bounds();
+            outlined.draw(|gx, gy, coverage| {
+                let px = bounds.min.x as i64 + gx as i64;
+                let py = bounds.min.y as i64 + gy as i64;
+                if px < 0 || py < 0 || px >= canvas.width() as i64 || py >= canvas.height() as i64 {
+                    return;
+                }
+                let pixel = canvas.get_pixel_mut(px as u32, py as u32);
+                for (channel, value) in pixel.0.iter_mut().zip(color) {
+                    *channel = (*channel as f32 * (1. - coverage) + value as f32 * coverage).round() as u8;
+                }
+            });
+        }
+    }
+}
+
+/// Fill a disc of `radius` pixels centered on `(x, y)` with `color`.
+pub fn draw_disc(canvas: &mut image::RgbImage, (x, y): (f32, f32), radius: f32, color: [u8; 3]) {
+    let radius = radius.max(0.5);
+    let (left, right) = ((x - radius).floor().max(0.) as i64, (x + radius).ceil() as i64);
+    let (top, bottom) = ((y - radius).floor().max(0.) as i64, (y + radius).ceil() as i64);
+    for py in top..=bottom.min(canvas.height() as i64 - 1) {
+        for px in left..=right.min(canvas.width() as i64 - 1) {
+            let (dx, dy) = (px as f32 + 0.5 - x, py as f32 + 0.5 - y);
+            if dx * dx + dy * dy <= radius * radius {
+                canvas.put_pixel(px as u32, py as u32, image::Rgb(color));
+            }
+        }
+    }
+}
+
+/// Draw a line `width` pixels wide from `from` to `to` with `color`, as discs
+/// stamped along it.
+pub fn draw_line(canvas: &mut image::RgbImage, from: (f32, f32), to: (f32, f32), width: f32, color: [u8; 3]) {
+    let length = (to.0 - from.0).hypot(to.1 - from.1);
+    // Lines far off the frame are not walked pixel by pixel
+    let size = canvas.width().max(canvas.height()) as f32;
+    if length > 16. * size {
+        return;
+    }
+    let steps = length.ceil().max(1.) as u32;
+    for step in 0..=steps {
+        let t = step as f32 / steps as f32;
+        draw_disc(canvas, (from.0 + (to.0 - from.0) * t, from.1 + (to.1 - from.1) * t), width / 2., color);
+    }
+}
+
+/// The frame with the point of interest burned in, as shown by the viewer.
+pub fn composite(sat_image: &SatImage, tiles: Tiles) -> image::RgbImage {
+    let mut canvas = image::DynamicImage::ImageRgb8(sat_image.image.clone()).to_rgba8();
+    let pinpoint = image::load_from_memory(PINPOINT_ICON)
+        .expect("Could not load pinpoint")
+        .to_rgba8();
+    let image_rect = egui::Rect::from_min_size(
+        egui::pos2(0., 0.),
+        egui::vec2(canvas.width() as f32, canvas.height() as f32),
+    );
+    let point_of_interest = convert_gps_to_pixels(tiles, &image_rect, PARIS);
+    image::imageops::overlay(
+        &mut canvas,
+        &pinpoint,
+        (point_of_interest.0 - pinpoint.width() as f32 / 2.) as i64,
+        (point_of_interest.1 - pinpoint.height() as f32) as i64,
+    );
+    image::DynamicImage::ImageRgba8(canvas).to_rgb8()
+}
+
+/// The frame with the point of interest and `label`, its time, burned in.
+pub fn composite_with_label(sat_image: &SatImage, tiles: Tiles, label: &str) -> image::RgbImage {
+    let mut canvas = composite(sat_image, tiles);
+    let bottom = canvas.height() as f32 - 10.;
+    draw_label(&mut canvas, label, (10., bottom), 24.);
+    canvas
+}
+
+/// The frame for an e-ink panel of `width` × `height` pixels: fitted and
+/// centered on white, its contrast stretched between the 2nd and 98th
+/// percentiles of luminance, dithered to black and white (Floyd-Steinberg)
+/// and `label`, its time, written in its bottom-left corner.
+pub fn eink_image(sat_image: &SatImage, tiles: Tiles, (width, height): (u32, u32), label: &str) -> image::GrayImage {
+    let frame = image::DynamicImage::ImageRgb8(composite(sat_image, tiles))
+        .resize(width, height, image::imageops::FilterType::Triangle)
+        .to_luma8();
+    let mut sorted: Vec<u8> = frame.pixels().map(|pixel| pixel.0[0]).collect();
+    sorted.sort_unstable();
+    let percentile = |p: usize| sorted.get(sorted.len() * p / 100).copied().unwrap_or(0) as f32;
+    let (low, high) = (percentile(2), percentile(98).max(percentile(2) + 1.));
+    // Luminance from 0 to 1, white around the frame
+    let mut levels = vec![1f32; (width * height) as usize];
+    let (left, top) = ((width - frame.width()) / 2, (height - frame.height()) / 2);
+    for (x, y, pixel) in frame.enumerate_pixels() {
+        levels[((top + y) * width + left + x) as usize] = ((pixel.0[0] as f32 - low) / (high - low)).clamp(0., 1.);
+    }
+    let mut canvas = image::RgbImage::new(width, height);
+    for y in 0..height {
+        for x in 0..width {
+            let i = (y * width + x) as usize;
+            let value = if levels[i] < 0.5 { 0. } else { 1. };
+            let error = levels[i] - value;
+            let mut spread = |dx: i32, dy: u32, weight: f32| {
+                let (nx, ny) = (x as i32 + dx, y + dy);
+                if nx >= 0 && (nx as u32) < width && ny < height {
+                    levels[(ny * width + nx as u32) as usize] += error * weight;
+                }
+            };
+            spread(1, 0, 7. / 16.);
+            spread(-1, 1, 3. / 16.);
+            spread(0, 1, 5. / 16.);
+            spread(1, 1, 1. / 16.);
+            canvas.put_pixel(x, y, image::Rgb([(value * 255.) as u8; 3]));
+        }
+    }
+    // Written after dithering and snapped to black and white, to stay crisp
+    let size = (height as f32 / 16.).max(12.);
+    let corner = (left as f32 + size / 2., (top + frame.height()) as f32 - size / 2.);
+    draw_label(&mut canvas, label, corner, size);
+    let mut gray = image::DynamicImage::ImageRgb8(canvas).to_luma8();
+    for pixel in gray.pixels_mut() {
+        pixel.0[0] = if pixel.0[0] < 128 { 0 } else { 255 };
+    }
+    gray
+}
+
+/// Encode a frame as a JPEG.
+pub fn encode_jpeg(image: &image::RgbImage) -> image::ImageResult<Vec<u8>> {
+    let mut jpeg = vec![];
+    image::codecs::jpeg::JpegEncoder::new_with_quality(&mut jpeg, 85).encode_image(image)?;
+    Ok(jpeg)
+}
@@ -0,0 +1,68 @@
+//! Display presets, switched at fixed times of the day for unattended
+//! displays.
+
+use chrono::NaiveTime;
+use serde::{Deserialize, Serialize};
+
+use crate::hud::HudConfig;
+use crate::provider::{TILES, Tiles, ZOOM};
+
+/// What is shown: the region, the layers and the labels.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct SceneConfig {
+    pub name: String,
+    /// The tile window of the frames and its zoom level
+    pub tiles: Tiles,
+    pub zoom: u16,
+    /// The overlays shown, by file name without extension, all of them if
+    /// not given
+    pub overlays: Option<Vec<String>>,
+    /// Whether the aircraft and vessels, the radiosonde stations and the
+    /// forecasts are shown
+    pub live: bool,
+    pub stations: bool,
+    pub nowcast: bool,
+    /// The labels, those of the configuration if not given
+    pub hud: Option<HudConfig>,
+}
+
+impl Default for SceneConfig {
+    fn default() -> Self {
+        SceneConfig {
+            name: String::new(),
+            tiles: TILES,
+            zoom: ZOOM,
+            overlays: None,
+            live: true,
+            stations: false,
+            nowcast: false,
+            hud: None,
+        }
+    }
+}
+
+/// Show `scene` every day from `at` (local time, HH:MM) until the next entry
+/// of the schedule.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct ScheduleEntry {
+    pub at: String,
+    pub scene: String,
+}
+
+/// The scene of `schedule` shown at `now`: the last one started, the last of
+/// the day before early in the morning. Entries with an invalid time are
+/// ignored.
+pub fn scheduled(schedule: &[ScheduleEntry], now: NaiveTime) -> Option<&str> {
+    let mut entries: Vec<(NaiveTime, &str)> = schedule
+        .iter()
+        .filter_map(|entry| Some((NaiveTime::parse_from_str(&entry.at, "%H:%M").ok()?, entry.scene.as_str())))
+        .collect();
+    entries.sort_by_key(|(at, _)| *at);
+    entries
+        .iter()
+        .rev()
+        .find(|(at, _)| *at <= now)
+        .or(entries.last())
+        .map(|(_, scene)| *scene)
+}
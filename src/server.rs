@@ -0,0 +1,299 @@
+//! A minimal HTTP server exposing the frames of a timeline:
+//! - `/latest.jpg`: the most recent frame, with the point of interest,
+//! - `/frames/<YYYYMMDDHHMM>.jpg`: the frame at a UTC time,
+//! - `/index.json`: the available frames,
+//! - `/status.json`: the health of the providers, when failing over,
+//! - `/cache/index.json` and `/cache/<name>`: the frames of the cache, as
+//!   stored, and their checksums as downloaded, for `nuage cache sync`,
+//! - `/stream`: the animation as an MJPEG stream.
+//!
+//! Frames are served without the point of interest with a `?raw` query.
+
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::Arc;
+
+use crate::cache::{self, Cache};
+use crate::error::{NuageError, Result};
+use crate::failover::Failover;
+use crate::geo::json_string;
+use crate::provider::Tiles;
+use crate::render;
+use crate::timeline::Timeline;
+
+struct Response {
+    status: &'static str,
+    content_type: &'static str,
+    body: Vec<u8>,
+}
+
+impl Response {
+    fn ok(content_type: &'static str, body: Vec<u8>) -> Self {
+        Response { status: "200 OK", content_type, body }
+    }
+
+    fn error(status: &'static str) -> Self {
+        Response {
+            status,
+            content_type: "text/plain",
+            body: status.as_bytes().to_vec(),
+        }
+    }
+}
+
+/// What the server serves: the frames, their region and, when failing over,
+/// the health of the providers.
+#[derive(Clone)]
+pub struct Served {
+    pub timeline: Timeline,
+    pub region: (Tiles, u16),
+    pub failover: Option<Arc<Failover>>,
+    /// The cache shared with other machines, if any
+    pub cache: Option<Cache>,
+}
+
+/// The JSON index of the available frames, the most recent first, and their
+/// region.
+fn index_json(served: &Served) -> String {
+    let timeline = &served.timeline;
+    let sat_images = timeline.sat_images.lock().unwrap();
+    let frames: Vec<String> = sat_images
+        .iter()
+        .map(|sat_image| {
+            format!(
+                "{{\"timestamp\":\"{}\",\"url\":\"/frames/{}.jpg\"}}",
+                sat_image.timestamp.format("%Y-%m-%dT%H:%M:%SZ"),
+                sat_image.timestamp.format("%Y%m%d%H%M"),
+            )
+        })
+        .collect();
+    let ((x1, y1), (x2, y2)) = served.region.0;
+    format!(
+        "{{\"downloading\":{},\"tiles\":[{},{},{},{}],\"zoom\":{},\"frames\":[{}]}}",
+        timeline.is_downloading(),
+        x1,
+        y1,
+        x2,
+        y2,
+        served.region.1,
+        frames.join(","),
+    )
+}
+
+/// The JSON health of the providers and the changes of provider.
+fn status_json(failover: &Failover) -> String {
+    let providers: Vec<String> = failover
+        .status()
+        .iter()
+        .map(|status| {
+            format!(
+                "{{\"name\":{},\"error_rate\":{:.2},\"active\":{}}}",
+                json_string(&status.name),
+                status.error_rate,
+                status.active,
+            )
+        })
+        .collect();
+    let transitions: Vec<String> = failover
+        .transitions()
+        .iter()
+        .map(|transition| {
+            format!(
+                "{{\"at\":\"{}\",\"from\":{},\"to\":{},\"reason\":{}}}",
+                transition.at.format("%Y-%m-%dT%H:%M:%SZ"),
+                json_string(&transition.from),
+                json_string(&transition.to),
+                json_string(&transition.reason),
+            )
+        })
+        .collect();
+    format!("{{\"providers\":[{}],\"transitions\":[{}]}}", providers.join(","), transitions.join(","))
+}
+
+/// A cached frame file, as stored. Only frame names are accepted, not to serve
+/// anything else from the disk.
+fn cached_frame(cache: Option<&Cache>, name: &str) -> Response {
+    let Some(cache) = cache.filter(|_| cache::is_frame_name(name)) else {
+        return Response::error("404 Not Found");
+    };
+    match cache.read_file(name) {
+        Ok(bytes) => Response::ok("image/jpeg", bytes),
+        Err(_) => Response::error("404 Not Found"),
+    }
+}
+
+fn route(path: &str, raw: bool, served: &Served) -> Response {
+    let sat_images = served.timeline.sat_images.lock().unwrap();
+    let frame = match path {
+        "/" | "/index.json" => {
+            drop(sat_images);
+            return Response::ok("application/json", index_json(served).into_bytes());
+        }
+        "/cache/index.json" => {
+            drop(sat_images);
+            return match served.cache.as_ref().map(Cache::frame_names) {
+                Some(Ok(names)) => {
+                    let checksums = served.cache.as_ref().and_then(|cache| cache.checksums().ok()).unwrap_or_default();
+                    let checksums: Vec<String> = names
+                        .iter()
+                        .filter_map(|name| checksums.get(name).map(|checksum| format!("{}:{}", json_string(name), json_string(checksum))))
+                        .collect();
+                    let names: Vec<String> = names.iter().map(|name| json_string(name)).collect();
+                    let index = format!("{{\"frames\":[{}],\"checksums\":{{{}}}}}", names.join(","), checksums.join(","));
+                    Response::ok("application/json", index.into_bytes())
+                }
+                Some(Err(e)) => {
+                    log::warn!("{}", e);
+                    Response::error("500 Internal Server Error")
+                }
+                None => Response::error("404 Not Found"),
+            };
+        }
+        _ if path.starts_with("/cache/") => {
+            drop(sat_images);
+            return cached_frame(served.cache.as_ref(), &path["/cache/".len()..]);
+        }
+        "/status.json" => {
+            drop(sat_images);
+            return match &served.failover {
+                Some(failover) => Response::ok("application/json", status_json(failover).into_bytes()),
+                None => Response::error("404 Not Found"),
+            };
+        }
+        "/latest.jpg" => sat_images.latest(),
+        _ => match path.strip_prefix("/frames/").and_then(|name| name.strip_suffix(".jpg")) {
+            Some(name) => sat_images
+                .iter()
+                .find(|sat_image| sat_image.timestamp.format("%Y%m%d%H%M").to_string() == name),
+            None => return Response::error("404 Not Found"),
+        },
+    };
+    let Some(frame) = frame.cloned() else {
+        return Response::error("404 Not Found");
+    };
+    drop(sat_images);
+    let image = if raw { frame.image.clone() } else { render::composite(&frame, served.region.0) };
+    match render::encode_jpeg(&image) {
+        Ok(jpeg) => Response::ok("image/jpeg", jpeg),
+        Err(e) => {
+            log::warn!("could not encode {}: {}", frame.timestamp, e);
+            Response::error("500 Internal Server Error")
+        }
+    }
+}
+
+/// Frames per second of the MJPEG stream, the pace of the viewer animation.
+const STREAM_FPS: u64 = 5;
+const STREAM_BOUNDARY: &str = "nuageframe";
+
+/// Push the frames of `timeline` from the least recent to the most recent, in
+/// a loop, until the client disconnects. Frames arriving meanwhile join the
+/// loop.
+fn stream_mjpeg(mut stream: TcpStream, timeline: &Timeline, tiles: Tiles) -> std::io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: multipart/x-mixed-replace; boundary={}\r\nCache-Control: no-cache\r\nConnection: close\r\n\r\n",
+        STREAM_BOUNDARY,
+    )?;
+    // Encoded frames, so each is only composited once per client
+    let mut jpegs: HashMap<DateTime<Utc>, Vec<u8>> = HashMap::new();
+    let mut position = 0;
+    loop {
+        let frame = {
+            let sat_images = timeline.sat_images.lock().unwrap();
+            if sat_images.is_empty() {
+                None
+            } else {
+                position %= sat_images.len();
+                // Forget the frames rolled out of the loop on each pass
+                if position == 0 {
+                    jpegs.retain(|timestamp, _| sat_images.contains(*timestamp));
+                }
+                sat_images.get(sat_images.len() - 1 - position).cloned()
+            }
+        };
+        let Some(frame) = frame else {
+            std::thread::sleep(std::time::Duration::from_secs(1));
+            continue;
+        };
+        let period = std::time::Duration::from_millis(1000 / STREAM_FPS);
+        position += 1;
+        let jpeg = match jpegs.entry(frame.timestamp) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => match render::encode_jpeg(&render::composite(&frame, tiles)) {
+                Ok(jpeg) => entry.insert(jpeg),
+                Err(e) => {
+                    log::warn!("could not encode {}: {}", frame.timestamp, e);
+                    std::thread::sleep(period);
+                    continue;
+                }
+            },
+        };
+        write!(
+            stream,
+            "--{}\r\nContent-Type: image/jpeg\r\nContent-Length: {}\r\n\r\n",
+            STREAM_BOUNDARY,
+            jpeg.len(),
+        )?;
+        stream.write_all(jpeg)?;
+        stream.write_all(b"\r\n")?;
+        std::thread::sleep(period);
+    }
+}
+
+fn handle(stream: TcpStream, served: &Served) -> std::io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // Skip the headers
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2 {
+        header.clear();
+    }
+    let mut parts = request_line.split_whitespace();
+    let response = match (parts.next(), parts.next()) {
+        (Some("GET"), Some(path)) if path.split('?').next() == Some("/stream") => {
+            // The client going away is the normal end of a stream
+            let _ = stream_mjpeg(stream, &served.timeline, served.region.0);
+            return Ok(());
+        }
+        (Some("GET"), Some(path)) => {
+            let (path, query) = path.split_once('?').unwrap_or((path, ""));
+            route(path, query.split('&').any(|parameter| parameter == "raw"), served)
+        }
+        _ => Response::error("405 Method Not Allowed"),
+    };
+    let mut stream = stream;
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-cache\r\nConnection: close\r\n\r\n",
+        response.status,
+        response.content_type,
+        response.body.len(),
+    )?;
+    stream.write_all(&response.body)
+}
+
+/// Serve `served` on `addr` (e.g. `0.0.0.0:8080`), blocking forever. Each
+/// connection is handled in its own thread.
+pub fn serve(addr: impl ToSocketAddrs, served: Served) -> Result<()> {
+    let listener = TcpListener::bind(addr).map_err(|e| NuageError::Config(format!("could not listen: {}", e)))?;
+    if let Ok(addr) = listener.local_addr() {
+        log::info!("serving on http://{}/latest.jpg", addr);
+    }
+    for stream in listener.incoming() {
+        let Ok(stream) = stream else {
+            continue;
+        };
+        let served = served.clone();
+        std::thread::spawn(move || {
+            if let Err(e) = handle(stream, &served) {
+                log::warn!("http: {}", e);
+            }
+        });
+    }
+    Ok(())
+}
@@ -0,0 +1,126 @@
+//! Radiosonde launch sites and the instability indices of their latest
+//! sounding, from the University of Wyoming sounding archive.
+
+use chrono::{DateTime, Duration, Timelike, Utc};
+
+use crate::error::{NuageError, Result};
+use crate::geo::LonLat;
+
+/// A radiosonde launch site.
+#[derive(Clone, Copy, Debug)]
+pub struct Station {
+    /// WMO station number
+    pub wmo: &'static str,
+    pub name: &'static str,
+    pub position: LonLat,
+}
+
+const fn station(wmo: &'static str, name: &'static str, lon: f64, lat: f64) -> Station {
+    Station { wmo, name, position: (lon, lat) }
+}
+
+/// The main launch sites of western europe.
+pub const STATIONS: &[Station] = &[
+    station("03005", "Lerwick", -1.18, 60.14),
+    station("03808", "Camborne", -5.33, 50.22),
+    station("03882", "Herstmonceux", 0.32, 50.90),
+    station("06260", "De Bilt", 5.18, 52.10),
+    station("06447", "Uccle", 4.35, 50.80),
+    station("06610", "Payerne", 6.95, 46.81),
+    station("07110", "Brest", -4.41, 48.45),
+    station("07145", "Trappes", 2.01, 48.77),
+    station("07481", "Lyon", 5.08, 45.73),
+    station("07510", "Bordeaux", -0.69, 44.83),
+    station("07645", "Nimes", 4.41, 43.86),
+    station("07761", "Ajaccio", 8.80, 41.92),
+    station("08190", "Barcelona", 2.12, 41.38),
+    station("08221", "Madrid", -3.58, 40.50),
+    station("10393", "Lindenberg", 14.12, 52.21),
+    station("10410", "Essen", 6.97, 51.40),
+    station("10739", "Stuttgart", 9.20, 48.83),
+    station("10868", "Munich", 11.55, 48.25),
+    station("11520", "Prague", 14.45, 50.01),
+    station("16080", "Milan", 9.28, 45.43),
+    station("16245", "Pratica di Mare", 12.43, 41.65),
+];
+
+/// Sounding-derived indices, `None` when not reported.
+#[derive(Clone, Debug, Default)]
+pub struct Indices {
+    /// Launch time as reported, e.g. `250101/1200`
+    pub observation_time: String,
+    /// Convective available potential energy, in J/kg
+    pub cape: Option<f64>,
+    /// Lifted index, in °C (negative is unstable)
+    pub lifted_index: Option<f64>,
+    /// Showalter index, in °C
+    pub showalter: Option<f64>,
+    /// Precipitable water, in mm
+    pub precipitable_water: Option<f64>,
+}
+
+/// Parse the "Station information and sounding indices" section of a
+/// Wyoming text sounding.
+pub fn parse_indices(text: &str) -> Result<Indices> {
+    let mut indices = Indices::default();
+    let mut found = false;
+    for line in text.lines() {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        let number = value.parse::<f64>().ok();
+        match key.trim() {
+            "Station observation time" => {
+                indices.observation_time = value.to_string();
+                found = true;
+            }
+            "Convective Available Potential Energy" => indices.cape = number,
+            "Lifted index" => indices.lifted_index = number,
+            "Showalter index" => indices.showalter = number,
+            "Precipitable water [mm] for entire sounding" => indices.precipitable_water = number,
+            _ => (),
+        }
+    }
+    if !found {
+        return Err(NuageError::Config("no sounding indices in the response".into()));
+    }
+    Ok(indices)
+}
+
+/// The launch times (00Z and 12Z) before `now`, the most recent first. Data
+/// takes a couple of hours to be published.
+fn launch_times(now: DateTime<Utc>) -> impl Iterator<Item = DateTime<Utc>> {
+    let published = now - Duration::hours(2);
+    let latest = published
+        .with_hour(published.hour() / 12 * 12)
+        .and_then(|t| t.with_minute(0))
+        .and_then(|t| t.with_second(0))
+        .and_then(|t| t.with_nanosecond(0))
+        .unwrap_or(published);
+    (0..4).map(move |i| latest - Duration::hours(12 * i))
+}
+
+/// The indices of the latest sounding of `station` available at `now`.
+/// Blocking.
+pub fn fetch_latest(station: &Station, now: DateTime<Utc>) -> Result<Indices> {
+    let mut last_error = NuageError::Config(format!("no recent sounding for {}", station.name));
+    for launch in launch_times(now) {
+        let url = format!(
+            "https://weather.uwyo.edu/cgi-bin/sounding?region=europe&TYPE=TEXT%3ALIST&YEAR={}&MONTH={}&FROM={}&TO={}&STNM={}",
+            launch.format("%Y"),
+            launch.format("%m"),
+            launch.format("%d%H"),
+            launch.format("%d%H"),
+            station.wmo,
+        );
+        log::info!("fetching {}", url);
+        let text = ureq::get(url).call()?.body_mut().read_to_string()?;
+        match parse_indices(&text) {
+            Ok(indices) => return Ok(indices),
+            // Not launched or not published yet, try the previous one
+            Err(e) => last_error = e,
+        }
+    }
+    Err(last_error)
+}
@@ -0,0 +1,209 @@
+//! Frames of a tile server of one's own or of any slippy-tile weather layer,
+//! described by a URL template: the time of the frame and the tile are
+//! filled in the placeholders, and the tiles of the window are stitched
+//! together, a few missing tiles being drawn blank.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use crate::error::{NuageError, Result};
+use crate::provider::{DELAY, INTERVAL, Provenance, Provider, TILES, Tiles, ZOOM, fit_to_screen};
+use crate::timeline::{HISTORY_HOURS, previous_time};
+
+/// Color of the tiles missing from a frame.
+const MISSING_COLOR: [u8; 3] = [40, 40, 40];
+
+/// A tile server and the frames it publishes.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct TileServerConfig {
+    /// Short name used in logs and in the provenance of the frames
+    pub name: String,
+    /// The URL of a tile, with `{year}`, `{month}`, `{day}`, `{hour}`,
+    /// `{minute}` or `{timestamp}` (YYYYMMDDHHMM) for the time of the frame
+    /// and `{z}`, `{x}` and `{y}` (or `{-y}`, counted from the south as in
+    /// TMS) for the tile. Without `{x}` and `{y}`, the URL is that of the
+    /// whole frame.
+    pub url: String,
+    /// The servers `{s}` stands for, taken in turn, `a`, `b` and `c` by
+    /// default
+    pub subdomains: Vec<String>,
+    /// The tile window shown, (top-left (x, y), bottom-right (x, y))
+    pub tiles: Tiles,
+    pub zoom: u16,
+    /// Minutes between two frames
+    pub interval: u32,
+    /// Minutes after which the frames are published
+    pub delay: u32,
+    /// The share of the tiles of a frame that may be missing, drawn blank,
+    /// before the whole frame is
+    pub missing_tiles: f32,
+}
+
+impl Default for TileServerConfig {
+    fn default() -> Self {
+        TileServerConfig {
+            name: "tiles".into(),
+            url: String::new(),
+            subdomains: vec!["a".into(), "b".into(), "c".into()],
+            tiles: TILES,
+            zoom: ZOOM,
+            interval: INTERVAL,
+            delay: DELAY,
+            missing_tiles: 0.25,
+        }
+    }
+}
+
+impl TileServerConfig {
+    /// `url` with the placeholders filled for the frame at `timestamp` and
+    /// tile `(x, y)`.
+    pub fn fill(&self, timestamp: DateTime<Utc>, (x, y): (u16, u16)) -> String {
+        let subdomain = match self.subdomains.len() {
+            0 => String::new(),
+            count => self.subdomains[(x as usize + y as usize) % count].clone(),
+        };
+        [
+            ("{timestamp}", timestamp.format("%Y%m%d%H%M").to_string()),
+            ("{year}", timestamp.format("%Y").to_string()),
+            ("{month}", timestamp.format("%m").to_string()),
+            ("{day}", timestamp.format("%d").to_string()),
+            ("{hour}", timestamp.format("%H").to_string()),
+            ("{minute}", timestamp.format("%M").to_string()),
+            ("{z}", self.zoom.to_string()),
+            ("{x}", x.to_string()),
+            ("{y}", y.to_string()),
+            ("{-y}", ((1u32 << self.zoom) - 1 - y as u32).to_string()),
+            ("{s}", subdomain),
+        ]
+        .iter()
+        .fold(self.url.clone(), |url, (placeholder, value)| url.replace(placeholder, value))
+    }
+
+    /// Whether the URL is that of a tile rather than of the whole frame.
+    fn is_tiled(&self) -> bool {
+        self.url.contains("{x}") && (self.url.contains("{y}") || self.url.contains("{-y}"))
+    }
+}
+
+/// The frames of a tile server, not cached.
+#[derive(Clone, Debug)]
+pub struct TileServer {
+    pub config: TileServerConfig,
+    /// Hours of frames shown, `HISTORY_HOURS` by default
+    pub hours: u32,
+    /// The tiles missing from each frame made
+    missing: Arc<Mutex<HashMap<DateTime<Utc>, usize>>>,
+}
+
+impl TileServer {
+    pub fn new(config: TileServerConfig) -> Self {
+        TileServer { config, hours: HISTORY_HOURS, missing: Arc::new(Mutex::new(HashMap::new())) }
+    }
+
+    /// Download and decode the image at `url`, telling who asks as tile
+    /// servers like OpenStreetMap's want. Images the server does not have are
+    /// missing.
+    fn download(&self, url: &str) -> Result<image::RgbImage> {
+        log::info!("fetching {}", url);
+        let mut res = match ureq::get(url).header("User-Agent", concat!("nuage/", env!("CARGO_PKG_VERSION"))).call() {
+            Err(ureq::Error::StatusCode(404 | 410)) => return Err(NuageError::Missing(url.to_string())),
+            res => res?,
+        };
+        let image_bytes = res.body_mut().with_config().limit(20 * 1024 * 1024).read_to_vec()?;
+        Ok(image::load_from_memory(&image_bytes)?.to_rgb8())
+    }
+
+    /// Download the tile at `url`, once more if the network failed.
+    fn download_tile(&self, url: &str) -> Result<image::RgbImage> {
+        match self.download(url) {
+            Err(NuageError::Network(_)) => self.download(url),
+            result => result,
+        }
+    }
+}
+
+impl Provider for TileServer {
+    fn name(&self) -> &str {
+        &self.config.name
+    }
+
+    fn timepoints(&self, now: DateTime<Utc>) -> Result<Vec<DateTime<Utc>>> {
+        Ok(previous_time(now, self.hours, self.config.delay, self.config.interval))
+    }
+
+    /// The tiles of the window at `timestamp` laid side by side, each the
+    /// size of the first one found. Up to `missing_tiles` of them may be
+    /// missing or fail, drawn blank; the frame fails beyond.
+    fn frame(&self, timestamp: DateTime<Utc>) -> Result<image::RgbImage> {
+        let ((x1, y1), (x2, y2)) = self.config.tiles;
+        if !self.config.is_tiled() {
+            let image = self.download(&self.config.fill(timestamp, (x1, y1)))?;
+            return Ok(fit_to_screen(image.into()).to_rgb8());
+        }
+        let (columns, rows) = ((x2 - x1 + 1) as u32, (y2 - y1 + 1) as u32);
+        let mut frame: Option<image::RgbImage> = None;
+        let mut missing = 0;
+        let mut last_error = None;
+        for y in y1..=y2 {
+            for x in x1..=x2 {
+                let tile = match self.download_tile(&self.config.fill(timestamp, (x, y))) {
+                    Ok(tile) => tile,
+                    Err(e) => {
+                        missing += 1;
+                        last_error = Some(e);
+                        if missing as f32 > self.config.missing_tiles * (columns * rows) as f32 {
+                            return Err(last_error.unwrap());
+                        }
+                        continue;
+                    }
+                };
+                let frame = frame.get_or_insert_with(|| {
+                    image::RgbImage::from_pixel(columns * tile.width(), rows * tile.height(), image::Rgb(MISSING_COLOR))
+                });
+                let (width, height) = (frame.width() / columns, frame.height() / rows);
+                let tile = match tile.dimensions() {
+                    dimensions if dimensions == (width, height) => tile,
+                    _ => image::imageops::resize(&tile, width, height, image::imageops::FilterType::Triangle),
+                };
+                let (left, top) = ((x - x1) as u32 * width, (y - y1) as u32 * height);
+                image::imageops::replace(frame, &tile, left as i64, top as i64);
+            }
+        }
+        let frame = frame.ok_or_else(|| {
+            last_error.unwrap_or_else(|| NuageError::Config(format!("{}: empty tile window", self.config.name)))
+        })?;
+        if missing > 0 {
+            log::info!("{} of {} tiles missing at {}", missing, columns * rows, timestamp);
+        }
+        self.missing.lock().unwrap().insert(timestamp, missing);
+        Ok(fit_to_screen(frame.into()).to_rgb8())
+    }
+
+    fn region(&self) -> (Tiles, u16) {
+        (self.config.tiles, self.config.zoom)
+    }
+
+    fn interval(&self) -> u32 {
+        self.config.interval
+    }
+
+    /// The URL of the north-west tile, and the tiles missing.
+    fn provenance(&self, timestamp: DateTime<Utc>) -> Provenance {
+        let ((x1, y1), (x2, y2)) = self.config.tiles;
+        let processing = match self.missing.lock().unwrap().get(&timestamp) {
+            Some(&missing) if missing > 0 => {
+                vec![format!("{} of {} tiles missing", missing, (x2 - x1 + 1) as u32 * (y2 - y1 + 1) as u32)]
+            }
+            _ => vec![],
+        };
+        Provenance {
+            provider: self.name().to_string(),
+            url: Some(self.config.fill(timestamp, self.config.tiles.0)),
+            processing,
+            ..Provenance::default()
+        }
+    }
+}
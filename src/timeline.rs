@@ -0,0 +1,536 @@
+use chrono::prelude::*;
+use chrono::{DateTime, Duration, Utc};
+use eframe::egui;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::mpsc::{Receiver, RecvTimeoutError, Sender};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::analysis;
+use crate::error::NuageError;
+use crate::provider::{Provenance, Provider};
+
+/// A decoded frame and the time it was taken.
+#[derive(Clone)]
+pub struct SatImage {
+    pub image: image::RgbImage,
+    pub timestamp: DateTime<Utc>,
+    /// Extrapolated from the observed frames rather than observed
+    pub forecast: bool,
+}
+
+impl SatImage {
+    /// An observed frame.
+    pub fn new(image: image::RgbImage, timestamp: DateTime<Utc>) -> Self {
+        SatImage { image, timestamp, forecast: false }
+    }
+}
+
+/// Hours of frames shown by default.
+pub const HISTORY_HOURS: u32 = 2;
+
+/// The pause between two frames downloaded for a timeline in the
+/// background, for the one shown to be downloaded first.
+pub const BACKGROUND_PAUSE: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// The most frames loaded at once, not to run out of memory: longer
+/// histories are thinned out.
+pub const MAX_FRAMES: u32 = 96;
+
+/// The minutes between the frames of `hours` of history for frames taken
+/// every `interval` minutes: `interval` until it would take more than
+/// `MAX_FRAMES`, or `power::MAX_FRAMES` in low-power mode.
+pub fn history_step(hours: u32, interval: u32) -> u32 {
+    let interval = interval.max(1);
+    let frames = (hours.max(1) * 60).div_ceil(interval);
+    let max_frames = if crate::power::is_low_power() { crate::power::MAX_FRAMES } else { MAX_FRAMES };
+    interval * frames.div_ceil(max_frames)
+}
+
+/// Whether frames can be taken every `interval` minutes: the same times
+/// every day.
+pub fn is_valid_interval(interval: u32) -> bool {
+    interval > 0 && 24 * 60 % interval == 0
+}
+
+/// `timestamp` rounded down to the whole minutes that are a multiple of
+/// `interval` since midnight.
+fn round_down(timestamp: DateTime<Utc>, interval: u32) -> DateTime<Utc> {
+    let timestamp = timestamp.with_second(0).unwrap().with_nanosecond(0).unwrap();
+    let minutes = timestamp.hour() * 60 + timestamp.minute();
+    timestamp - Duration::minutes((minutes % interval.max(1)) as i64)
+}
+
+/// The time points of the last `hours` of frames available at `now`, from
+/// the most recent to the least, for frames taken every `interval` minutes
+/// and shown every `history_step(hours, interval)` minutes. Frames are only
+/// published `delay` minutes after they are taken.
+pub fn previous_time(now: DateTime<Utc>, hours: u32, delay: u32, interval: u32) -> Vec<DateTime<Utc>> {
+    let published = round_down(now - Duration::minutes(delay as i64), interval);
+    let mut result = vec![];
+    for x in (0..hours.max(1) as i64 * 60).step_by(history_step(hours, interval) as usize) {
+        result.push(published - Duration::minutes(x));
+    }
+    result
+}
+
+/// The time points every `interval` minutes between `from` and `to`
+/// (included), from the most recent to the least. `to` is rounded down to
+/// the interval.
+pub fn time_range(from: DateTime<Utc>, to: DateTime<Utc>, interval: u32) -> Vec<DateTime<Utc>> {
+    let mut result = vec![];
+    let mut timepoint = round_down(to, interval);
+    while timepoint >= from {
+        result.push(timepoint);
+        timepoint -= Duration::minutes(interval.max(1) as i64);
+    }
+    result
+}
+
+/// Frames ordered from the most recent to the least: index 0 is the most
+/// recent.
+#[derive(Default)]
+pub struct FrameSequence {
+    frames: Vec<SatImage>,
+}
+
+impl FrameSequence {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Insert a frame at its place in the sequence. A frame with the same
+    /// timestamp as an existing one replaces it.
+    pub fn insert(&mut self, sat_image: SatImage) {
+        match self
+            .frames
+            .binary_search_by(|frame| sat_image.timestamp.cmp(&frame.timestamp))
+        {
+            Ok(index) => self.frames[index] = sat_image,
+            Err(index) => self.frames.insert(index, sat_image),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> Option<&SatImage> {
+        self.frames.get(index)
+    }
+
+    /// The most recent observed frame.
+    pub fn latest(&self) -> Option<&SatImage> {
+        self.observed().next()
+    }
+
+    /// The observed frames, forecasts excluded, the most recent first.
+    pub fn observed(&self) -> impl Iterator<Item = &SatImage> {
+        self.frames.iter().filter(|frame| !frame.forecast)
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, SatImage> {
+        self.frames.iter()
+    }
+
+    /// Whether there is a frame at `timestamp`.
+    pub fn contains(&self, timestamp: DateTime<Utc>) -> bool {
+        self.frames
+            .binary_search_by(|frame| timestamp.cmp(&frame.timestamp))
+            .is_ok()
+    }
+
+    /// Keep only the frames for which `keep` returns true.
+    pub fn retain(&mut self, keep: impl FnMut(&SatImage) -> bool) {
+        self.frames.retain(keep);
+    }
+
+    /// The bytes taken by the decoded frames.
+    pub fn memory(&self) -> usize {
+        self.frames.iter().map(|frame| frame.image.as_raw().len()).sum()
+    }
+}
+
+impl std::ops::Index<usize> for FrameSequence {
+    type Output = SatImage;
+
+    fn index(&self, index: usize) -> &SatImage {
+        &self.frames[index]
+    }
+}
+
+/// How long to wait for a file appearing in a watched folder to be written,
+/// as it triggers several events.
+const WATCH_SETTLE: std::time::Duration = std::time::Duration::from_millis(500);
+
+/// Send a message on `changes` each time a file is added, changed or removed
+/// in `folder`, as long as the watcher is kept.
+fn watch(folder: &Path, changes: Sender<()>) -> Option<::notify::RecommendedWatcher> {
+    use ::notify::{EventKind, RecursiveMode, Watcher};
+    let watcher = ::notify::recommended_watcher(move |event: ::notify::Result<::notify::Event>| {
+        if let Ok(event) = event
+            && matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_))
+        {
+            // The timeline is gone when the receiver is
+            let _ = changes.send(());
+        }
+    });
+    match watcher.and_then(|mut watcher| watcher.watch(folder, RecursiveMode::NonRecursive).map(|()| watcher)) {
+        Ok(watcher) => Some(watcher),
+        Err(e) => {
+            log::warn!("could not watch {}: {}", folder.display(), e);
+            None
+        }
+    }
+}
+
+/// How far the frames being loaded are.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Progress {
+    /// The frames to load
+    pub total: usize,
+    /// The frames loaded or failed so far
+    pub done: usize,
+    started: Option<std::time::Instant>,
+}
+
+impl Progress {
+    /// The time left to load the remaining frames at the pace of those
+    /// loaded so far, unknown before the first one.
+    pub fn remaining(&self) -> Option<std::time::Duration> {
+        let started = self.started?;
+        if self.done == 0 || self.done >= self.total {
+            return None;
+        }
+        Some(started.elapsed().mul_f64((self.total - self.done) as f64 / self.done as f64))
+    }
+}
+
+/// The errors met while loading: how many, and the most recent one only,
+/// not to grow without end in long running processes.
+#[derive(Debug, Default)]
+pub struct LoadErrors {
+    pub count: usize,
+    pub last: Option<NuageError>,
+}
+
+impl LoadErrors {
+    pub fn push(&mut self, error: NuageError) {
+        self.count += 1;
+        self.last = Some(error);
+    }
+}
+
+/// The frames of the animation, filled in the background as they are
+/// downloaded.
+#[derive(Clone)]
+pub struct Timeline {
+    pub sat_images: Arc<Mutex<FrameSequence>>,
+    pub downloading: Arc<Mutex<bool>>,
+    /// The errors met while loading.
+    pub errors: Arc<Mutex<LoadErrors>>,
+    /// How far the current load is.
+    pub progress: Arc<Mutex<Progress>>,
+    /// Where the frames loaded come from, by timestamp.
+    pub provenance: Arc<Mutex<HashMap<DateTime<Utc>, Provenance>>>,
+    /// Whether the frames are not shown, downloaded after those of the
+    /// timelines that are.
+    pub background: Arc<AtomicBool>,
+}
+
+impl Timeline {
+    /// Start loading the frames from `provider` in a separate thread. `ctx` is
+    /// asked to repaint each time a frame arrives. The frames appearing in the
+    /// folder of a local provider are loaded as they come.
+    pub fn load(provider: Arc<dyn Provider>, ctx: &egui::Context) -> Self {
+        Self::spawn(provider, Some(ctx.clone()), None)
+    }
+
+    /// Load the frames from `provider` in a separate thread, and check for new
+    /// frames every `period`, forgetting the frames no longer listed by the
+    /// provider. For long running processes without UI.
+    pub fn load_and_refresh(provider: Arc<dyn Provider>, period: std::time::Duration) -> Self {
+        Self::spawn(provider, None, Some(period))
+    }
+
+    /// Load the frames from `provider` in a separate thread, and check for new
+    /// frames every `period` for as long as the timeline is used, asking
+    /// `ctx` to repaint each time a frame arrives. Frames that could not be
+    /// loaded are tried again each time. For unattended displays.
+    pub fn load_forever(provider: Arc<dyn Provider>, ctx: &egui::Context, period: std::time::Duration) -> Self {
+        Self::spawn(provider, Some(ctx.clone()), Some(period))
+    }
+
+    fn spawn(provider: Arc<dyn Provider>, ctx: Option<egui::Context>, refresh: Option<std::time::Duration>) -> Self {
+        let timeline = Timeline {
+            sat_images: Arc::new(Mutex::new(FrameSequence::new())),
+            downloading: Arc::new(Mutex::new(true)),
+            errors: Arc::new(Mutex::new(LoadErrors::default())),
+            progress: Arc::new(Mutex::new(Progress::default())),
+            provenance: Arc::new(Mutex::new(HashMap::new())),
+            background: Arc::new(AtomicBool::new(false)),
+        };
+        let loader = timeline.clone();
+        let request_repaint = move || {
+            if let Some(ctx) = &ctx {
+                ctx.request_repaint();
+            }
+        };
+        std::thread::spawn(move || {
+            let (sender, changes) = std::sync::mpsc::channel();
+            let watcher = match (refresh, provider.watch_folder()) {
+                (None, Some(folder)) => watch(folder, sender),
+                _ => None,
+            };
+            loop {
+                *loader.downloading.lock().unwrap() = true;
+                loader.load_missing(provider.as_ref(), &request_repaint);
+                *loader.downloading.lock().unwrap() = false;
+                request_repaint();
+                match refresh {
+                    Some(refresh) => {
+                        std::thread::sleep(crate::metered::poll_period(refresh));
+                        // Only the loading thread is left
+                        if Arc::strong_count(&loader.sat_images) == 1 {
+                            break;
+                        }
+                    }
+                    None if watcher.is_some() && loader.wait_for_change(&changes) => (),
+                    None => break,
+                }
+            }
+        });
+        timeline
+    }
+
+    /// Wait for a change in a watched folder and for it to settle. False once
+    /// the timeline is no longer used.
+    fn wait_for_change(&self, changes: &Receiver<()>) -> bool {
+        loop {
+            match changes.recv_timeout(std::time::Duration::from_secs(1)) {
+                Ok(()) => break,
+                // Only the loading thread is left
+                Err(RecvTimeoutError::Timeout) if Arc::strong_count(&self.sat_images) == 1 => return false,
+                Err(RecvTimeoutError::Timeout) => (),
+                Err(RecvTimeoutError::Disconnected) => return false,
+            }
+        }
+        std::thread::sleep(WATCH_SETTLE);
+        changes.try_iter().for_each(drop);
+        true
+    }
+
+    /// Download the frames after those of the timelines shown, or again as
+    /// they are, with `background`.
+    pub fn set_background(&self, background: bool) {
+        self.background.store(background, Ordering::Relaxed);
+    }
+
+    /// Get the frames listed by the provider that are not loaded yet.
+    fn load_missing(&self, provider: &dyn Provider, request_repaint: &impl Fn()) {
+        // Build the time points use to create the image url
+        let timepoints = match provider.timepoints(Utc::now()) {
+            Ok(timepoints) => timepoints,
+            Err(e) => {
+                log::warn!("{}: could not list frames: {}", provider.name(), e);
+                self.errors.lock().unwrap().push(e);
+                return;
+            }
+        };
+        // Forecasts are made again from the new frames
+        self.sat_images
+            .lock()
+            .unwrap()
+            .retain(|sat_image| !sat_image.forecast && timepoints.contains(&sat_image.timestamp));
+        self.provenance.lock().unwrap().retain(|timestamp, _| timepoints.contains(timestamp));
+        let missing: Vec<DateTime<Utc>> = {
+            let sat_images = self.sat_images.lock().unwrap();
+            timepoints.into_iter().filter(|timepoint| !sat_images.contains(*timepoint)).collect()
+        };
+        *self.progress.lock().unwrap() = Progress {
+            total: missing.len(),
+            done: 0,
+            started: Some(std::time::Instant::now()),
+        };
+        for (index, timepoint) in missing.into_iter().enumerate() {
+            if index > 0 && self.background.load(Ordering::Relaxed) {
+                std::thread::sleep(BACKGROUND_PAUSE);
+            } else if index > 0 && crate::power::is_low_power() {
+                std::thread::sleep(crate::power::DOWNLOAD_PAUSE);
+            }
+            match provider.frame(timepoint) {
+                Ok(image) => {
+                    let provenance = provider.provenance(timepoint);
+                    self.provenance.lock().unwrap().insert(timepoint, provenance);
+                    self.sat_images.lock().unwrap().insert(SatImage::new(crate::power::shrink(image), timepoint));
+                }
+                Err(e) => {
+                    log::warn!("{}: could not get frame {}: {}", provider.name(), timepoint, e);
+                    self.errors.lock().unwrap().push(e);
+                }
+            }
+            self.progress.lock().unwrap().done += 1;
+            request_repaint();
+        }
+    }
+
+    /// Replace the forecasts by frames extrapolated `NOWCAST_MINUTES` after
+    /// the latest one, following the motion between the last three observed
+    /// frames. Blocking.
+    pub fn nowcast(&self) {
+        let recent: Vec<SatImage> = self.sat_images.lock().unwrap().observed().take(3).cloned().collect();
+        let Some(latest) = recent.first() else {
+            return;
+        };
+        let vectors: Vec<analysis::MotionVector> = recent
+            .windows(2)
+            .flat_map(|pair| {
+                let minutes = (pair[0].timestamp - pair[1].timestamp).num_minutes().max(1) as f32;
+                analysis::motion_vectors(&pair[1].image, &pair[0].image, minutes)
+            })
+            .collect();
+        let field = analysis::MotionField::new(&vectors, latest.image.dimensions());
+        let forecasts: Vec<SatImage> = analysis::NOWCAST_MINUTES
+            .iter()
+            .map(|&minutes| SatImage {
+                image: analysis::extrapolate(&latest.image, &field, minutes as f32),
+                timestamp: latest.timestamp + Duration::minutes(minutes as i64),
+                forecast: true,
+            })
+            .collect();
+        let mut sat_images = self.sat_images.lock().unwrap();
+        sat_images.retain(|sat_image| !sat_image.forecast);
+        for forecast in forecasts {
+            // Never hide an observed frame
+            if !sat_images.contains(forecast.timestamp) {
+                sat_images.insert(forecast);
+            }
+        }
+    }
+
+    /// Remove the forecasts.
+    pub fn clear_forecasts(&self) {
+        self.sat_images.lock().unwrap().retain(|sat_image| !sat_image.forecast);
+    }
+
+    /// Whether frames are still being loaded.
+    pub fn is_downloading(&self) -> bool {
+        *self.downloading.lock().unwrap()
+    }
+
+    /// How far the current load is.
+    pub fn progress(&self) -> Progress {
+        *self.progress.lock().unwrap()
+    }
+
+    /// Where the frame at `timestamp` comes from, if it was loaded.
+    pub fn provenance(&self, timestamp: DateTime<Utc>) -> Option<Provenance> {
+        self.provenance.lock().unwrap().get(&timestamp).cloned()
+    }
+
+    /// The share of the frames loaded that came from the cache, `None` when
+    /// the provider does not tell.
+    pub fn cache_hit_rate(&self) -> Option<f32> {
+        let provenance = self.provenance.lock().unwrap();
+        let hits: Vec<bool> = provenance.values().filter_map(|provenance| provenance.cache_hit).collect();
+        (!hits.is_empty()).then(|| hits.iter().filter(|hit| **hit).count() as f32 / hits.len() as f32)
+    }
+
+    /// The message of the last error met while loading, if any.
+    pub fn last_error(&self) -> Option<String> {
+        self.errors.lock().unwrap().last.as_ref().map(|e| e.to_string())
+    }
+
+    /// The number of errors met while loading.
+    pub fn error_count(&self) -> usize {
+        self.errors.lock().unwrap().count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(hour: u32, minute: u32, second: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 1, 1, hour, minute, second).unwrap()
+    }
+
+    fn frame(timestamp: DateTime<Utc>) -> SatImage {
+        SatImage::new(image::RgbImage::new(1, 1), timestamp)
+    }
+
+    #[test]
+    fn round_down_to_the_interval() {
+        assert_eq!(round_down(at(15, 37, 42), 5), at(15, 35, 0));
+        assert_eq!(round_down(at(15, 37, 42), 15), at(15, 30, 0));
+        assert_eq!(round_down(at(15, 35, 0), 5), at(15, 35, 0));
+        // Multiples of the interval since midnight, not since the hour
+        assert_eq!(round_down(at(1, 10, 0), 40), at(0, 40, 0));
+        assert_eq!(round_down(at(15, 37, 42), 0), at(15, 37, 0));
+    }
+
+    #[test]
+    fn previous_time_of_published_frames() {
+        let timepoints = previous_time(at(15, 37, 42), 1, 10, 5);
+        assert_eq!(timepoints.len(), 12);
+        assert_eq!(timepoints.first(), Some(&at(15, 25, 0)));
+        assert_eq!(timepoints.last(), Some(&at(14, 30, 0)));
+        assert!(timepoints.windows(2).all(|pair| pair[0] - pair[1] == Duration::minutes(5)));
+    }
+
+    #[test]
+    fn previous_time_thinned_out() {
+        let timepoints = previous_time(at(15, 37, 42), 24, 0, 5);
+        assert_eq!(timepoints.len(), 96);
+        assert_eq!(timepoints.first(), Some(&at(15, 35, 0)));
+        assert!(timepoints.windows(2).all(|pair| pair[0] - pair[1] == Duration::minutes(15)));
+    }
+
+    #[test]
+    fn history_step_thins_long_histories() {
+        assert_eq!(history_step(2, 5), 5);
+        assert_eq!(history_step(8, 5), 5);
+        assert_eq!(history_step(9, 5), 10);
+        assert_eq!(history_step(24, 5), 15);
+        assert_eq!(history_step(24, 10), 20);
+        assert_eq!(history_step(0, 0), 1);
+    }
+
+    #[test]
+    fn valid_intervals() {
+        for interval in [1, 5, 10, 15, 30, 60, 1440] {
+            assert!(is_valid_interval(interval), "{}", interval);
+        }
+        for interval in [0, 7, 25, 1441] {
+            assert!(!is_valid_interval(interval), "{}", interval);
+        }
+    }
+
+    #[test]
+    fn frames_ordered_from_the_most_recent() {
+        let mut sequence = FrameSequence::new();
+        for timestamp in [at(10, 5, 0), at(10, 15, 0), at(10, 0, 0), at(10, 10, 0)] {
+            sequence.insert(frame(timestamp));
+        }
+        let timestamps: Vec<DateTime<Utc>> = sequence.iter().map(|frame| frame.timestamp).collect();
+        assert_eq!(timestamps, [at(10, 15, 0), at(10, 10, 0), at(10, 5, 0), at(10, 0, 0)]);
+        assert!(sequence.contains(at(10, 5, 0)));
+        assert!(!sequence.contains(at(10, 20, 0)));
+    }
+
+    #[test]
+    fn frame_at_the_same_time_replaced() {
+        let mut sequence = FrameSequence::new();
+        sequence.insert(SatImage { forecast: true, ..frame(at(10, 0, 0)) });
+        sequence.insert(frame(at(10, 5, 0)));
+        sequence.insert(frame(at(10, 0, 0)));
+        assert_eq!(sequence.len(), 2);
+        assert!(!sequence[1].forecast);
+        assert_eq!(sequence.latest().map(|frame| frame.timestamp), Some(at(10, 5, 0)));
+    }
+}
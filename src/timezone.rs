@@ -0,0 +1,86 @@
+//! The abbreviation of the local time zone (CET, CEST...), which chrono does
+//! not give, read from the TZif file of the system: that of `$TZ`, or
+//! `/etc/localtime`.
+
+use chrono::{DateTime, Local, Offset, TimeZone, Utc};
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+/// The local time types, (offset from UTC in seconds, abbreviation), the
+/// most recently used first.
+fn local_types() -> &'static [(i32, String)] {
+    static TYPES: OnceLock<Vec<(i32, String)>> = OnceLock::new();
+    TYPES.get_or_init(|| {
+        let path = match std::env::var("TZ").ok().filter(|tz| !tz.is_empty()) {
+            Some(tz) => {
+                let tz = tz.trim_start_matches(':');
+                if tz.starts_with('/') {
+                    PathBuf::from(tz)
+                } else {
+                    PathBuf::from("/usr/share/zoneinfo").join(tz)
+                }
+            }
+            None => PathBuf::from("/etc/localtime"),
+        };
+        std::fs::read(path).ok().and_then(|bytes| parse_tzif(&bytes)).unwrap_or_default()
+    })
+}
+
+/// The time types of a TZif file (RFC 8536), the most recently used first.
+pub fn parse_tzif(bytes: &[u8]) -> Option<Vec<(i32, String)>> {
+    let read_counts = |header: &[u8]| -> Option<[usize; 6]> {
+        if header.get(..4)? != b"TZif" {
+            return None;
+        }
+        let mut counts = [0; 6];
+        for (i, count) in counts.iter_mut().enumerate() {
+            *count = u32::from_be_bytes(header.get(20 + 4 * i..24 + 4 * i)?.try_into().ok()?) as usize;
+        }
+        Some(counts)
+    };
+    let block_size = |[isutcnt, isstdcnt, leapcnt, timecnt, typecnt, charcnt]: [usize; 6], time_size: usize| {
+        timecnt * time_size + timecnt + typecnt * 6 + charcnt + leapcnt * (time_size + 4) + isstdcnt + isutcnt
+    };
+    let (mut counts, mut block, mut time_size) = (read_counts(bytes)?, bytes.get(44..)?, 4);
+    // From version 2, a block with 64-bit times follows the first one
+    if *bytes.get(4)? >= b'2' {
+        let second = block.get(block_size(counts, 4)..)?;
+        (counts, block, time_size) = (read_counts(second)?, second.get(44..)?, 8);
+    }
+    let [_, _, _, timecnt, typecnt, charcnt] = counts;
+    let indices = block.get(timecnt * time_size..timecnt * time_size + timecnt)?;
+    let infos = block.get(timecnt * time_size + timecnt..)?;
+    let designations = infos.get(typecnt * 6..typecnt * 6 + charcnt)?;
+    let types = (0..typecnt)
+        .map(|i| {
+            let info = infos.get(i * 6..i * 6 + 6)?;
+            let offset = i32::from_be_bytes(info[..4].try_into().ok()?);
+            let name = designations.get(info[5] as usize..)?.split(|byte| *byte == 0).next()?;
+            Some((offset, String::from_utf8_lossy(name).into_owned()))
+        })
+        .collect::<Option<Vec<_>>>()?;
+    let mut ordered: Vec<(i32, String)> = vec![];
+    for index in indices.iter().rev().map(|index| *index as usize).chain(0..typecnt) {
+        let Some(time_type) = types.get(index) else {
+            continue;
+        };
+        if !ordered.contains(time_type) {
+            ordered.push(time_type.clone());
+        }
+    }
+    Some(ordered)
+}
+
+/// The abbreviation of the local time zone at `timestamp`, its offset from
+/// UTC when unknown (e.g. `UTC+2`).
+pub fn local_abbreviation(timestamp: DateTime<Utc>) -> String {
+    let offset = Local.offset_from_utc_datetime(&timestamp.naive_utc()).fix().local_minus_utc();
+    if let Some((_, name)) = local_types().iter().find(|(type_offset, _)| *type_offset == offset) {
+        return name.clone();
+    }
+    match (offset / 3600, offset.abs() % 3600 / 60) {
+        (0, 0) => "UTC".to_string(),
+        (hours, 0) => format!("UTC{:+}", hours),
+        (hours, minutes) => format!("UTC{}{}:{:02}", if offset < 0 { '-' } else { '+' }, hours.abs(), minutes),
+    }
+}
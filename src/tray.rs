@@ -0,0 +1,145 @@
+//! An icon in the system tray telling at a glance whether rain is likely near
+//! home, with the details in its tooltip. Clicking it opens the window, which
+//! only hides when closed, and its menu quits. A StatusNotifierItem over
+//! D-Bus, as KDE, GNOME (with the AppIndicator extension) and most Linux
+//! panels show; there is no tray on other platforms yet.
+
+use eframe::egui;
+use std::sync::mpsc::{Receiver, Sender};
+
+use crate::error::{NuageError, Result};
+
+/// The side of the icons, in pixels.
+const ICON_SIZE: u32 = 32;
+
+/// A round icon, blue when rain is likely near home, yellow otherwise, for
+/// the tray and the window.
+pub fn status_icon(rain: bool) -> egui::IconData {
+    let color = if rain { [64, 128, 255, 255] } else { [255, 200, 0, 255] };
+    let center = (ICON_SIZE as f32 - 1.) / 2.;
+    let rgba = (0..ICON_SIZE * ICON_SIZE)
+        .flat_map(|i| {
+            let (x, y) = ((i % ICON_SIZE) as f32 - center, (i / ICON_SIZE) as f32 - center);
+            if x * x + y * y <= center * center { color } else { [0; 4] }
+        })
+        .collect();
+    egui::IconData { rgba, width: ICON_SIZE, height: ICON_SIZE }
+}
+
+/// What was asked from the tray.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TrayEvent {
+    /// Show the window
+    Open,
+    Quit,
+}
+
+/// The status shown by the tray icon.
+#[derive(Clone, Debug, Default)]
+struct Status {
+    text: String,
+    rain: bool,
+}
+
+/// The tray icon, removed when dropped.
+pub struct StatusTray {
+    #[cfg(target_os = "linux")]
+    handle: ksni::blocking::Handle<Item>,
+    events: Receiver<TrayEvent>,
+}
+
+impl StatusTray {
+    /// Show the icon in the tray, `ctx` being asked to repaint when it is
+    /// clicked for the events to be handled.
+    #[cfg(target_os = "linux")]
+    pub fn start(ctx: &egui::Context) -> Result<Self> {
+        use ksni::blocking::TrayMethods;
+        let (sender, events) = std::sync::mpsc::channel();
+        let item = Item { status: Status { text: "Loading the frames".into(), rain: false }, sender, ctx: ctx.clone() };
+        let handle = item.spawn().map_err(|e| NuageError::Config(format!("could not show the tray icon: {}", e)))?;
+        Ok(StatusTray { handle, events })
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn start(_ctx: &egui::Context) -> Result<Self> {
+        Err(NuageError::Config("there is no tray icon on this platform".into()))
+    }
+
+    /// Show `text` in the tooltip, and whether rain is likely near home in
+    /// the icon.
+    pub fn set_status(&self, text: &str, rain: bool) {
+        #[cfg(target_os = "linux")]
+        self.handle.update(|item| item.status = Status { text: text.to_string(), rain });
+        #[cfg(not(target_os = "linux"))]
+        let _ = (text, rain);
+    }
+
+    /// What was asked from the tray since the last call.
+    pub fn events(&self) -> Vec<TrayEvent> {
+        self.events.try_iter().collect()
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl Drop for StatusTray {
+    fn drop(&mut self) {
+        self.handle.shutdown().wait();
+    }
+}
+
+/// The StatusNotifierItem, living in the thread of the D-Bus service.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+struct Item {
+    status: Status,
+    sender: Sender<TrayEvent>,
+    ctx: egui::Context,
+}
+
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+impl Item {
+    fn send(&self, event: TrayEvent) {
+        // The window is gone when the receiver is
+        let _ = self.sender.send(event);
+        self.ctx.request_repaint();
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl ksni::Tray for Item {
+    fn id(&self) -> String {
+        "nuage".into()
+    }
+
+    fn title(&self) -> String {
+        "Nuage".into()
+    }
+
+    fn icon_pixmap(&self) -> Vec<ksni::Icon> {
+        let icon = status_icon(self.status.rain);
+        // ARGB rather than RGBA
+        let data = icon.rgba.chunks_exact(4).flat_map(|pixel| [pixel[3], pixel[0], pixel[1], pixel[2]]).collect();
+        vec![ksni::Icon { width: icon.width as i32, height: icon.height as i32, data }]
+    }
+
+    fn tool_tip(&self) -> ksni::ToolTip {
+        ksni::ToolTip { title: "Nuage".into(), description: self.status.text.clone(), ..Default::default() }
+    }
+
+    fn activate(&mut self, _x: i32, _y: i32) {
+        self.send(TrayEvent::Open);
+    }
+
+    fn menu(&self) -> Vec<ksni::MenuItem<Self>> {
+        use ksni::menu::StandardItem;
+        let item = |label: &str, event: TrayEvent| {
+            let activate = Box::new(move |item: &mut Self| item.send(event));
+            StandardItem { label: label.into(), activate, ..Default::default() }.into()
+        };
+        vec![
+            StandardItem { label: self.status.text.clone(), enabled: false, ..Default::default() }.into(),
+            ksni::MenuItem::Separator,
+            item("Open", TrayEvent::Open),
+            item("Quit", TrayEvent::Quit),
+        ]
+    }
+}
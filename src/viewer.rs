@@ -0,0 +1,2267 @@
+use chrono::{DateTime, Utc};
+use eframe::egui;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+use crate::analysis::{self, MotionField};
+use crate::annotation::{Annotation, Tool};
+use crate::basemap::BasemapLayer;
+use crate::geo::{Geometry, LonLat};
+use crate::gpx::Track;
+use crate::hud::{self, HudConfig, HudElement, HudLine};
+use crate::keymap::{Action, Keymap};
+use crate::accessibility::{Announcements, Announcer};
+use crate::lightning::LightningFeed;
+use crate::live::{LiveKind, LiveSource};
+use crate::overlay::{self, Overlay};
+use crate::processing::ProcessingStep;
+use crate::projection::{Georeference, bearing_degrees, convert_gps_to_pixels, distance_km, tile_to_lonlat};
+use crate::provider::{INTERVAL, Provenance, Provider, TILES, Tiles, ZOOM};
+use crate::render::{self, Adjustments, Difference, Palette, TimeZone, TimestampFormat};
+use crate::sounding::{self, Indices, Station};
+use crate::timeline::{FrameSequence, Progress, SatImage, Timeline};
+use crate::wind::WindLayer;
+
+/// Paris, the default point of interest.
+pub const PARIS: (f32, f32) = (48.8575, 2.3514);
+
+/// A helper function to load the image from bytes and create an egui texture.
+pub fn load_image_from_memory(image_bytes: &[u8], name: &str, ctx: &egui::Context) -> Result<egui::TextureHandle, String> {
+    // 1. Decode the image using the `image` crate.
+    let image = image::load_from_memory_with_format(image_bytes, image::ImageFormat::Png)
+        .map_err(|e| format!("Failed to decode PNG: {}", e))?;
+
+    // 2. Convert the image to a format `egui` can use.
+    let size = [image.width() as usize, image.height() as usize];
+    let image_buffer = image.to_rgba8();
+    let pixels = image_buffer.as_flat_samples();
+    let color_image = egui::ColorImage::from_rgba_unmultiplied(size, pixels.as_slice());
+
+    // 3. Load the image into an `egui` texture.
+    Ok(ctx.load_texture(name, color_image, Default::default()))
+}
+
+/// Register the VCR font used by the viewer labels. Must be called once on the
+/// context before showing a [`Viewer`].
+pub fn install_fonts(ctx: &egui::Context) {
+    // Add a custom font
+    let font_bytes = render::VCR_FONT;
+    // Load fonts
+    let mut fonts = egui::FontDefinitions::default();
+    // Install my own font
+    fonts.font_data.insert(
+        "vcr".to_owned(),
+        egui::FontData::from_static(font_bytes).into(),
+    );
+    // Put my font first (highest priority):
+    fonts
+        .families
+        .entry(egui::FontFamily::Name("vcr".into()))
+        .or_default()
+        .insert(0, "vcr".to_owned());
+    // Tell egui to use the new `FontDefinitions`.
+    ctx.set_fonts(fonts);
+}
+
+fn vcr_font() -> egui::FontId {
+    egui::FontId::new(24.0, egui::FontFamily::Name("vcr".into()))
+}
+
+/// The frame and label color of the extrapolated frames.
+const FORECAST_COLOR: egui::Color32 = egui::Color32::from_rgb(0, 220, 255);
+/// The colors of the position along the track and of the radiosonde stations.
+const TRACK_COLOR: egui::Color32 = egui::Color32::from_rgb(255, 64, 64);
+const STATION_COLOR: egui::Color32 = egui::Color32::from_rgb(255, 128, 0);
+/// The length of the wind barbs and the spacing of their feathers, in
+/// points.
+const WIND_STAFF: f32 = 24.;
+const WIND_STEP: f32 = 4.;
+/// The color of the markers added from the context menu.
+const MARKER_COLOR: egui::Color32 = egui::Color32::from_rgb(255, 64, 255);
+/// The color of the region being defined.
+const REGION_COLOR: egui::Color32 = egui::Color32::from_rgb(0, 255, 128);
+/// The color of the distance measured.
+const MEASURE_COLOR: egui::Color32 = egui::Color32::from_rgb(255, 255, 0);
+/// The colors of the tiles in the tile debug view, by where their frame came
+/// from: the cache, the network, or a provider without cache.
+const TILE_CACHE_COLOR: egui::Color32 = egui::Color32::from_rgb(64, 255, 64);
+const TILE_NETWORK_COLOR: egui::Color32 = egui::Color32::from_rgb(255, 160, 0);
+const TILE_UNCACHED_COLOR: egui::Color32 = egui::Color32::from_rgb(200, 200, 200);
+/// The color of the performance HUD.
+const PERF_COLOR: egui::Color32 = egui::Color32::from_rgb(255, 255, 128);
+/// The color of the loading errors.
+const ERROR_COLOR: egui::Color32 = egui::Color32::from_rgb(255, 96, 96);
+/// The color of the warning that the frame shown is not the latest.
+const BEHIND_COLOR: egui::Color32 = egui::Color32::from_rgb(255, 200, 0);
+
+/// Show a loading error above `pos`, in a smaller font as messages can be long.
+fn paint_error(ui: &egui::Ui, pos: egui::Pos2, message: &str) {
+    ui.painter().text(
+        pos,
+        egui::Align2::LEFT_BOTTOM,
+        message,
+        egui::FontId::proportional(14.0),
+        ERROR_COLOR,
+    );
+}
+
+/// The download label, with how many frames are loaded and roughly how long
+/// the others will take: `DOWNLOADING... 6/24, ~40 s remaining`.
+fn downloading_label(progress: Progress) -> String {
+    if progress.total == 0 {
+        return "DOWNLOADING...".to_string();
+    }
+    let mut label = format!("DOWNLOADING... {}/{}", progress.done, progress.total);
+    if let Some(remaining) = progress.remaining() {
+        // Rounded up, by 5 seconds under a minute and a half
+        match remaining.as_secs() + 1 {
+            seconds if seconds < 90 => label += &format!(", ~{} s remaining", seconds.div_ceil(5) * 5),
+            seconds => label += &format!(", ~{} min remaining", seconds.div_ceil(60)),
+        }
+    }
+    label
+}
+
+/// A frame as a texture, uploaded under `name` as displayed after
+/// `processing`, with `adjustments` and `palette`.
+fn frame_texture(
+    ctx: &egui::Context,
+    name: &str,
+    sat_image: &SatImage,
+    processing: &[ProcessingStep],
+    adjustments: &Adjustments,
+    palette: &Palette,
+) -> egui::TextureHandle {
+    let image = render::display_image(&sat_image.image, processing, adjustments, palette);
+    let dimensions = image.dimensions();
+    let color_image = egui::ColorImage::from_rgb([dimensions.0 as usize, dimensions.1 as usize], image.as_raw());
+    ctx.load_texture(name, color_image, Default::default())
+}
+
+/// The value, position and time of the pixel of `sat_image` under the
+/// pointer, in a tooltip, when it is over the frame drawn in `image_rect`.
+fn show_inspector(ui: &egui::Ui, response: &egui::Response, sat_image: &SatImage, georeference: &Georeference, image_rect: egui::Rect) {
+    let Some(pointer) = response.hover_pos().filter(|pointer| image_rect.contains(*pointer)) else {
+        return;
+    };
+    let (width, height) = sat_image.image.dimensions();
+    let x = ((pointer.x - image_rect.min.x) / image_rect.width() * width as f32) as u32;
+    let y = ((pointer.y - image_rect.min.y) / image_rect.height() * height as f32) as u32;
+    let (x, y) = (x.min(width - 1), y.min(height - 1));
+    let [r, g, b] = sat_image.image.get_pixel(x, y).0;
+    let luminance = (0.299 * r as f32 + 0.587 * g as f32 + 0.114 * b as f32).round();
+    let (lon, lat) = georeference.pixel_to_lonlat(x as f64 + 0.5, y as f64 + 0.5);
+    egui::show_tooltip_at_pointer(ui.ctx(), ui.layer_id(), response.id.with("inspector"), |ui| {
+        ui.label(format!("Pixel ({}, {}): RGB {} {} {}, luminance {}", x, y, r, g, b, luminance));
+        ui.label(format!("{:.4}°N {:.4}°E", lat, lon));
+        ui.label(format!("{} UTC", sat_image.timestamp.format("%Y-%m-%d %H:%M")));
+    });
+}
+
+/// Where the frame shown comes from, in a tooltip: `provenance` as loaded,
+/// and what the viewer does to it on top (`shown_as`).
+fn show_provenance(ui: &egui::Ui, response: &egui::Response, provenance: Option<Provenance>, shown_as: &[&str], time_zone: TimeZone) {
+    egui::show_tooltip_at_pointer(ui.ctx(), ui.layer_id(), response.id.with("provenance"), |ui| {
+        match &provenance {
+            Some(provenance) => {
+                ui.label(format!("Provider: {}", provenance.provider));
+                if let Some(url) = &provenance.url {
+                    ui.label(format!("From: {}", url));
+                }
+                if let Some(fetched) = provenance.fetched {
+                    ui.label(format!("Fetched: {}", time_zone.format(fetched, "%Y-%m-%d %H:%M:%S")));
+                }
+                match provenance.cache_hit {
+                    Some(true) => ui.label("Cache: hit"),
+                    Some(false) => ui.label("Cache: miss, downloaded"),
+                    None => ui.label("Cache: not cached"),
+                };
+            }
+            None => {
+                ui.label("Not loaded from a provider");
+            }
+        }
+        let processing: Vec<&str> = provenance
+            .iter()
+            .flat_map(|provenance| provenance.processing.iter().map(String::as_str))
+            .chain(shown_as.iter().copied())
+            .collect();
+        match processing[..] {
+            [] => ui.label("Processing: none"),
+            _ => ui.label(format!("Processing: {}", processing.join(", "))),
+        };
+    });
+}
+
+/// The motion between consecutive frames, by the time of the older and of
+/// the newer frame.
+type MotionFields = HashMap<(DateTime<Utc>, DateTime<Utc>), Arc<MotionField>>;
+
+/// A mesh drawing `texture` in `image_rect`, tinted by `color`.
+fn frame_mesh(texture: egui::TextureId, image_rect: egui::Rect, color: egui::Color32) -> egui::Mesh {
+    let mut mesh = egui::Mesh::with_texture(texture);
+    mesh.add_rect_with_uv(image_rect, egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0)), color);
+    mesh
+}
+
+/// The weight of the last measure in the times of the performance HUD,
+/// smoothed not to flicker.
+const PERF_SMOOTHING: f32 = 0.1;
+
+/// What the performance HUD shows of the drawing, in seconds, smoothed.
+#[derive(Clone, Copy, Debug, Default)]
+struct PerfStats {
+    // Between two repaints
+    frame_time: f32,
+    // Processing the frames shown and uploading them as textures
+    texture_time: f32,
+}
+
+impl PerfStats {
+    fn record(&mut self, frame_time: f32, texture_time: f32) {
+        let smooth = |average: f32, measure: f32| if average == 0. { measure } else { average + PERF_SMOOTHING * (measure - average) };
+        self.frame_time = smooth(self.frame_time, frame_time);
+        self.texture_time = smooth(self.texture_time, texture_time);
+    }
+}
+
+/// The side of the part of the frame shown in compact mode, as a fraction of
+/// the height of the frame.
+const COMPACT_CROP: f32 = 0.3;
+
+/// The fraction of a frame duration, at its end, during which the next frame
+/// fades in when crossfading.
+const CROSSFADE: f32 = 0.3;
+
+/// The longest loop of the animation, in seconds. Longer histories are
+/// played faster.
+const MAX_CYCLE: f64 = 10.;
+
+/// Minutes of weather played per second of animation.
+const MINUTES_PER_SECOND: f64 = 25.;
+
+/// The slowest and the fastest the animation is played, as a factor of its
+/// usual rate.
+const SPEED_RANGE: (f64, f64) = (1. / 8., 8.);
+
+/// How long the playback rate is shown after it changes, in seconds.
+const SPEED_SHOWN: f64 = 1.5;
+
+/// Number of cells of the grid deforming the frames, horizontally and
+/// vertically.
+const WARP_GRID: (u32, u32) = (32, 24);
+
+/// A mesh drawing `texture` (of `dimensions` pixels) in `image_rect`, each
+/// pixel moved along `displacement` (in pixels) and tinted by `color`.
+fn warped_mesh(
+    texture: egui::TextureId,
+    image_rect: egui::Rect,
+    dimensions: (u32, u32),
+    displacement: impl Fn(f32, f32) -> (f32, f32),
+    color: egui::Color32,
+) -> egui::Mesh {
+    let mut mesh = egui::Mesh::with_texture(texture);
+    let (columns, rows) = WARP_GRID;
+    let scale = image_rect.width() / dimensions.0 as f32;
+    for row in 0..=rows {
+        for column in 0..=columns {
+            let uv = egui::pos2(column as f32 / columns as f32, row as f32 / rows as f32);
+            let (x, y) = (uv.x * dimensions.0 as f32, uv.y * dimensions.1 as f32);
+            let (dx, dy) = displacement(x, y);
+            let pos = image_rect.min + egui::vec2(x + dx, y + dy) * scale;
+            mesh.vertices.push(egui::epaint::Vertex { pos, uv, color });
+        }
+    }
+    for row in 0..rows {
+        for column in 0..columns {
+            let top_left = row * (columns + 1) + column;
+            let bottom_left = top_left + columns + 1;
+            mesh.add_triangle(top_left, top_left + 1, bottom_left);
+            mesh.add_triangle(top_left + 1, bottom_left + 1, bottom_left);
+        }
+    }
+    mesh
+}
+
+/// How long before the latest frame the frame compared to it is taken by
+/// default, in minutes.
+const COMPARISON_MINUTES: i64 = 60;
+
+/// The height of the thumbnails of the filmstrip, in pixels.
+const THUMBNAIL_HEIGHT: u32 = 64;
+
+/// The scrolling, in points, stepping a frame when scrubbing with the wheel,
+/// about a notch.
+const WHEEL_STEP: f32 = 40.;
+
+/// The width of the minimap of the frames compared, in points, and the color
+/// of the part of them shown.
+const MINIMAP_WIDTH: f32 = 160.;
+const MINIMAP_VIEW_COLOR: egui::Color32 = egui::Color32::from_rgb(255, 220, 0);
+
+/// Where the minimap of a frame of `image_size` is drawn in `half`: in its
+/// bottom-right corner.
+fn minimap_rect(half: egui::Rect, image_size: egui::Vec2) -> egui::Rect {
+    let size = egui::vec2(MINIMAP_WIDTH, MINIMAP_WIDTH * image_size.y / image_size.x);
+    egui::Rect::from_min_size(half.right_bottom() - size - egui::vec2(10., 10.), size)
+}
+
+/// Two frames shown side by side, zoomed and panned together.
+struct Comparison {
+    // The times of the frames on the left and on the right, chosen once the
+    // frames are loaded
+    left: Option<DateTime<Utc>>,
+    right: Option<DateTime<Utc>>,
+    // Magnification of the frames, 1 fitting them in their half, and offset
+    // of their center from the center of their half, in points
+    zoom: f32,
+    pan: egui::Vec2,
+}
+
+/// The latest sounding of a station, shown next to it.
+struct SoundingPopup {
+    station: Station,
+    // Filled by the download thread
+    indices: Arc<Mutex<Option<Result<Indices, String>>>>,
+}
+
+/// The animated satellite view as an egui widget.
+///
+/// ```no_run
+/// use eframe::egui;
+/// use std::sync::{Arc, Mutex};
+///
+/// struct Dashboard {
+///     viewer: nuage::Viewer,
+/// }
+///
+/// impl eframe::App for Dashboard {
+///     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+///         egui::CentralPanel::default().show(ctx, |ui| {
+///             self.viewer.show(ui);
+///         });
+///     }
+/// }
+///
+/// let _ = eframe::run_native("dashboard", Default::default(), Box::new(|cc| {
+///     nuage::install_fonts(&cc.egui_ctx);
+///     let cache = nuage::Cache::default_location()?;
+///     let viewer = nuage::Viewer::new(&cc.egui_ctx, Arc::new(nuage::Infoplaza::new(cache)));
+///     Ok(Box::new(Dashboard { viewer }))
+/// }));
+/// ```
+///
+/// The viewer takes all the space available in the `Ui` while keeping the
+/// frames aspect ratio. Left/Right step through the frames and Space pauses
+/// the animation.
+pub struct Viewer {
+    timeline: Timeline,
+    image_index: usize,
+    auto_play: bool,
+    pinpoint_icon: egui::TextureHandle,
+    // The tile window of the frames and its zoom level
+    region: (Tiles, u16),
+    // The keys stepping through the frames and pausing
+    keymap: Keymap,
+    // Minutes between two frames
+    interval: u32,
+    overlays: Vec<Overlay>,
+    // A GPX track whose position is shown at the time of the frame
+    track: Option<Track>,
+    // Whether the animation is limited to the time of the track
+    track_sync: bool,
+    live_sources: Vec<LiveSource>,
+    live_visible: bool,
+    lightning: Option<LightningFeed>,
+    // The wind barbs shown, if any
+    wind: Option<WindLayer>,
+    // The map drawn under the frames, if any, and its texture once
+    // downloaded
+    basemap: Option<BasemapLayer>,
+    basemap_texture: Option<egui::TextureHandle>,
+    // Whether the radiosonde launch sites are shown
+    stations_visible: bool,
+    // Whether the boundaries of the tiles are drawn, for debugging
+    tile_debug: bool,
+    // Whether the performance HUD is shown, and what it measured
+    perf_visible: bool,
+    perf: PerfStats,
+    sounding: Option<SoundingPopup>,
+    // The corners clicked while defining a region, if defining one
+    region_corners: Option<Vec<LonLat>>,
+    // The ends of the distance measured, if measuring one
+    measure_points: Option<Vec<LonLat>>,
+    // The annotations drawn by hand, the tool drawing them if any, the
+    // shape being dragged, from where to where, and the text being typed
+    annotations: Vec<Annotation>,
+    annotation_tool: Option<Tool>,
+    annotation_drag: Option<(LonLat, LonLat)>,
+    annotation_text: Option<(LonLat, String)>,
+    // The frame to show once loaded
+    pending_frame: Option<DateTime<Utc>>,
+    // The thumbnails of the filmstrip, by time of their frame, and the
+    // frame outlined in it
+    thumbnails: HashMap<DateTime<Utc>, egui::TextureHandle>,
+    filmstrip_index: Option<usize>,
+    // The scrolling not yet turned into frames when scrubbing
+    wheel: f32,
+    // The markers added from the context menu, and the point the menu was
+    // opened on
+    markers: Vec<LonLat>,
+    context_point: Option<LonLat>,
+    // Whether forecasts are appended to the loop, and the time of the frame
+    // they were made from
+    nowcast: bool,
+    nowcast_from: Option<DateTime<Utc>>,
+    hud: HudConfig,
+    // The time zone and the format of the times shown
+    time_zone: TimeZone,
+    timestamp_format: TimestampFormat,
+    // Whether in-between frames are shown while playing, and the motion
+    // between consecutive frames (older, newer) they follow, computed in the
+    // background
+    interpolate: bool,
+    motion_fields: Arc<Mutex<MotionFields>>,
+    computing_motion: Arc<Mutex<bool>>,
+    // Whether the next frame fades in when there are no in-between frames
+    crossfade: bool,
+    // Whether the animation pauses while the window is not focused, and
+    // whether it was paused for that
+    pause_unfocused: bool,
+    paused_unfocused: bool,
+    // Seconds without input after which the animation plays again when
+    // paused, and the time of the last input
+    resume_after: Option<u32>,
+    last_input: f64,
+    // Playing from the latest frame back to the oldest
+    reverse: bool,
+    // The playback rate, as a factor of the usual one, when it last changed
+    // and the time the loop is counted from, not to jump when it does
+    speed: f64,
+    speed_changed: Option<f64>,
+    cycle_start: f64,
+    processing: Vec<ProcessingStep>,
+    adjustments: Adjustments,
+    palette: Palette,
+    // What is shown instead of the frames to bring out their changes
+    difference: Difference,
+    comparison: Option<Comparison>,
+    announcements: Announcements,
+    announcer: Announcer,
+}
+
+impl Viewer {
+    /// Create a viewer and start loading its frames from `provider`.
+    pub fn new(ctx: &egui::Context, provider: Arc<dyn Provider>) -> Self {
+        let timeline = Timeline::load(provider.clone(), ctx);
+        Self::with_provider_timeline(ctx, provider.as_ref(), timeline)
+    }
+
+    /// Create a viewer loading its frames from `provider`, then checking for
+    /// new ones every `period` for as long as it is shown.
+    pub fn refreshing(ctx: &egui::Context, provider: Arc<dyn Provider>, period: std::time::Duration) -> Self {
+        let timeline = Timeline::load_forever(provider.clone(), ctx, period);
+        Self::with_provider_timeline(ctx, provider.as_ref(), timeline)
+    }
+
+    /// Create a viewer playing the frames of `provider` loaded by `timeline`.
+    fn with_provider_timeline(ctx: &egui::Context, provider: &dyn Provider, timeline: Timeline) -> Self {
+        let mut viewer = Self::with_timeline(ctx, timeline);
+        viewer.region = provider.region();
+        viewer.set_interval(provider.interval());
+        viewer
+    }
+
+    /// Create a viewer playing the frames of an existing timeline.
+    pub fn with_timeline(ctx: &egui::Context, timeline: Timeline) -> Self {
+        Self {
+            timeline,
+            image_index: 0,
+            auto_play: true,
+            pinpoint_icon: load_image_from_memory(
+                render::PINPOINT_ICON,
+                "pinpoint_icon", ctx).expect("Could not load pinpoint"),
+            region: (TILES, ZOOM),
+            keymap: Keymap::default(),
+            interval: INTERVAL,
+            overlays: vec![],
+            track: None,
+            track_sync: false,
+            live_sources: vec![],
+            live_visible: true,
+            lightning: None,
+            wind: None,
+            basemap: None,
+            basemap_texture: None,
+            stations_visible: false,
+            tile_debug: false,
+            perf_visible: false,
+            perf: PerfStats::default(),
+            sounding: None,
+            region_corners: None,
+            measure_points: None,
+            annotations: vec![],
+            annotation_tool: None,
+            annotation_drag: None,
+            annotation_text: None,
+            pending_frame: None,
+            thumbnails: HashMap::new(),
+            filmstrip_index: None,
+            wheel: 0.,
+            markers: vec![],
+            context_point: None,
+            nowcast: false,
+            nowcast_from: None,
+            hud: HudConfig::default(),
+            interpolate: true,
+            motion_fields: Arc::new(Mutex::new(HashMap::new())),
+            computing_motion: Arc::new(Mutex::new(false)),
+            crossfade: false,
+            pause_unfocused: false,
+            paused_unfocused: false,
+            resume_after: None,
+            last_input: 0.,
+            reverse: false,
+            speed: 1.,
+            speed_changed: None,
+            cycle_start: 0.,
+            processing: vec![],
+            adjustments: Adjustments::default(),
+            palette: Palette::Original,
+            difference: Difference::Off,
+            comparison: None,
+            announcements: Announcements::default(),
+            time_zone: TimeZone::default(),
+            timestamp_format: TimestampFormat::default(),
+            announcer: Announcer::default(),
+        }
+    }
+
+    /// Set the tile window covered by the frames, used to place overlays.
+    pub fn set_region(&mut self, tiles: Tiles, zoom: u16) {
+        self.region = (tiles, zoom);
+    }
+
+    /// Set the keyboard shortcuts, of which the viewer handles stepping
+    /// through the frames and pausing.
+    pub fn set_keymap(&mut self, keymap: Keymap) {
+        self.keymap = keymap;
+    }
+
+    pub fn keymap(&self) -> &Keymap {
+        &self.keymap
+    }
+
+    /// Set the minutes between two frames, which sets the pace of the
+    /// animation.
+    pub fn set_interval(&mut self, interval: u32) {
+        self.interval = interval.max(1);
+    }
+
+    /// Draw an overlay over the frames.
+    pub fn add_overlay(&mut self, overlay: Overlay) {
+        self.overlays.push(overlay);
+    }
+
+    pub fn overlays_mut(&mut self) -> &mut Vec<Overlay> {
+        &mut self.overlays
+    }
+
+    /// Show the current position of the aircraft or vessels of `source`.
+    pub fn add_live_source(&mut self, source: LiveSource) {
+        self.live_sources.push(source);
+    }
+
+    pub fn live_sources(&self) -> &[LiveSource] {
+        &self.live_sources
+    }
+
+    /// Show or hide the aircraft and vessels.
+    pub fn set_live_visible(&mut self, visible: bool) {
+        self.live_visible = visible;
+    }
+
+    pub fn live_visible(&self) -> bool {
+        self.live_visible
+    }
+
+    /// Show the lightning strikes of `feed` on the frames they fell before,
+    /// or none.
+    pub fn set_lightning(&mut self, feed: Option<LightningFeed>) {
+        self.lightning = feed;
+    }
+
+    pub fn lightning(&self) -> Option<&LightningFeed> {
+        self.lightning.as_ref()
+    }
+
+    /// Draw the strikes of the minutes before the frame as crosses, fading
+    /// out with their age.
+    fn paint_lightning(&self, ui: &egui::Ui, sat_image: &SatImage, georeference: &Georeference, image_rect: egui::Rect) {
+        let Some(feed) = &self.lightning else {
+            return;
+        };
+        let painter = ui.painter().with_clip_rect(image_rect);
+        let (r, g, b) = feed.config.color;
+        for (strike, age) in feed.strikes_before(sat_image.timestamp) {
+            let center = overlay::screen_position(georeference, image_rect, strike.position);
+            let color = egui::Color32::from_rgba_unmultiplied(r, g, b, ((1. - age) * 255.) as u8);
+            let stroke = egui::Stroke::new(2., color);
+            painter.line_segment([center - egui::vec2(4., 4.), center + egui::vec2(4., 4.)], stroke);
+            painter.line_segment([center - egui::vec2(4., -4.), center + egui::vec2(4., -4.)], stroke);
+        }
+    }
+
+    /// Show the wind of `layer` as barbs, or none.
+    pub fn set_wind(&mut self, layer: Option<WindLayer>) {
+        self.wind = layer;
+    }
+
+    pub fn wind(&self) -> Option<&WindLayer> {
+        self.wind.as_ref()
+    }
+
+    /// Draw the frames over a map, see [`crate::basemap`].
+    pub fn set_basemap(&mut self, layer: Option<BasemapLayer>) {
+        self.basemap = layer;
+        self.basemap_texture = None;
+    }
+
+    pub fn basemap(&self) -> Option<&BasemapLayer> {
+        self.basemap.as_ref()
+    }
+
+    /// Set the opacity of the frames over the map, from 0 to 1.
+    pub fn set_basemap_opacity(&mut self, opacity: f32) {
+        if let Some(layer) = &mut self.basemap {
+            layer.config.opacity = opacity.clamp(0., 1.);
+        }
+    }
+
+    /// Upload the map once downloaded.
+    fn update_basemap_texture(&mut self, ctx: &egui::Context) {
+        if self.basemap_texture.is_some() {
+            return;
+        }
+        let Some(layer) = &self.basemap else {
+            return;
+        };
+        if let Some(image) = layer.image.lock().unwrap().as_ref() {
+            let color_image = egui::ColorImage::from_rgb([image.width() as usize, image.height() as usize], image.as_raw());
+            self.basemap_texture = Some(ctx.load_texture("basemap", color_image, Default::default()));
+        }
+    }
+
+    /// Draw the wind at the time of the frame as barbs: the staff points to
+    /// where the wind comes from, a half feather is 5 knots, a feather 10 and
+    /// a pennant 50. Calm is a circle.
+    fn paint_wind(&self, ui: &egui::Ui, sat_image: &SatImage, georeference: &Georeference, image_rect: egui::Rect) {
+        let Some(layer) = &self.wind else {
+            return;
+        };
+        let Some(winds) = layer.field.lock().unwrap().as_ref().map(|field| field.at(sat_image.timestamp)) else {
+            return;
+        };
+        let painter = ui.painter().with_clip_rect(image_rect);
+        let (r, g, b) = layer.config.color;
+        let color = egui::Color32::from_rgb(r, g, b);
+        let stroke = egui::Stroke::new(1.5, color);
+        for (position, speed, direction) in winds {
+            let center = overlay::screen_position(georeference, image_rect, position);
+            let speed = (speed / 5.).round() as u32 * 5;
+            if speed == 0 {
+                painter.circle_stroke(center, 4., stroke);
+                continue;
+            }
+            let angle = (direction as f32).to_radians();
+            let staff = egui::vec2(angle.sin(), -angle.cos());
+            let side = egui::vec2(-staff.y, staff.x);
+            let end = center + staff * WIND_STAFF;
+            painter.line_segment([center, end], stroke);
+            let (pennants, feathers, half) = (speed / 50, speed % 50 / 10, speed % 10 / 5);
+            // From the end of the staff towards the center, a lone half
+            // feather a step in
+            let mut along = if pennants + feathers == 0 { WIND_STEP } else { 0. };
+            for _ in 0..pennants {
+                let base = end - staff * along;
+                let points = vec![base, base + side * 10., base - staff * WIND_STEP * 1.5];
+                painter.add(egui::Shape::convex_polygon(points, color, egui::Stroke::NONE));
+                along += WIND_STEP * 2.;
+            }
+            for _ in 0..feathers {
+                let base = end - staff * along;
+                painter.line_segment([base, base + side * 10. + staff * 3.], stroke);
+                along += WIND_STEP;
+            }
+            if half == 1 {
+                let base = end - staff * along;
+                painter.line_segment([base, base + side * 5. + staff * 1.5], stroke);
+            }
+        }
+    }
+
+    /// Draw the live targets, as arrows when their heading is known.
+    fn paint_live_targets(&self, ui: &egui::Ui, georeference: &Georeference, image_rect: egui::Rect) {
+        if !self.live_visible {
+            return;
+        }
+        let painter = ui.painter().with_clip_rect(image_rect);
+        for source in &self.live_sources {
+            let (r, g, b) = source.config.color;
+            let color = egui::Color32::from_rgb(r, g, b);
+            for target in source.targets.lock().unwrap().iter() {
+                let center = overlay::screen_position(georeference, image_rect, target.position);
+                match target.heading {
+                    Some(heading) => {
+                        let angle = (heading as f32).to_radians();
+                        let forward = egui::vec2(angle.sin(), -angle.cos());
+                        let side = egui::vec2(-forward.y, forward.x);
+                        let points = vec![center + forward * 9., center - forward * 6. + side * 5., center - forward * 6. - side * 5.];
+                        painter.add(egui::Shape::convex_polygon(points, color, egui::Stroke::new(1., egui::Color32::BLACK)));
+                    }
+                    None => {
+                        painter.circle(center, 5., color, egui::Stroke::new(1., egui::Color32::BLACK));
+                    }
+                }
+                painter.text(
+                    center + egui::vec2(10., 0.),
+                    egui::Align2::LEFT_CENTER,
+                    &target.name,
+                    egui::FontId::proportional(12.0),
+                    color,
+                );
+            }
+        }
+    }
+
+    /// Label the points of the overlays with their name and how cloudy it is
+    /// around them on `sat_image`, in percent.
+    fn paint_cloudiness(&self, ui: &egui::Ui, sat_image: &SatImage, georeference: &Georeference, image_rect: egui::Rect) {
+        let painter = ui.painter().with_clip_rect(image_rect);
+        for overlay in self.overlays.iter().filter(|overlay| overlay.visible) {
+            for feature in &overlay.features {
+                let Geometry::Point(point) = feature.geometry else {
+                    continue;
+                };
+                let Some(cover) = analysis::cover_around(
+                    &sat_image.image,
+                    georeference,
+                    point,
+                    analysis::CLOUDINESS_RADIUS_KM,
+                    analysis::CLOUD_THRESHOLD,
+                ) else {
+                    continue;
+                };
+                let name = feature.property("name").unwrap_or_else(|| overlay.name.clone());
+                painter.text(
+                    overlay::screen_position(georeference, image_rect, point) + egui::vec2(overlay.width * 2. + 4., 0.),
+                    egui::Align2::LEFT_CENTER,
+                    format!("{} {:.0}%", name, cover * 100.),
+                    egui::FontId::proportional(12.0),
+                    overlay.color,
+                );
+            }
+        }
+    }
+
+    /// Play in-between frames, following the motion of the clouds, rather
+    /// than stepping from one frame to the next.
+    pub fn set_interpolate(&mut self, interpolate: bool) {
+        self.interpolate = interpolate;
+    }
+
+    pub fn is_interpolating(&self) -> bool {
+        self.interpolate
+    }
+
+    /// Fade the next frame in at the end of each frame while playing, when
+    /// in-between frames are not shown.
+    pub fn set_crossfade(&mut self, crossfade: bool) {
+        self.crossfade = crossfade;
+    }
+
+    pub fn is_crossfading(&self) -> bool {
+        self.crossfade
+    }
+
+    /// Pause the animation while the window is not focused, and play it
+    /// again when it is.
+    pub fn set_pause_unfocused(&mut self, pause_unfocused: bool) {
+        self.pause_unfocused = pause_unfocused;
+    }
+
+    pub fn pauses_unfocused(&self) -> bool {
+        self.pause_unfocused
+    }
+
+    /// Play the animation again after `seconds` without a key pressed or
+    /// the mouse used, when paused or stepped through, never if `None`.
+    pub fn set_resume_after(&mut self, seconds: Option<u32>) {
+        self.resume_after = seconds;
+    }
+
+    pub fn resume_after(&self) -> Option<u32> {
+        self.resume_after
+    }
+
+    /// Show the times in `zone`, with its abbreviation.
+    pub fn set_time_zone(&mut self, zone: TimeZone) {
+        self.time_zone = zone;
+    }
+
+    pub fn time_zone(&self) -> TimeZone {
+        self.time_zone
+    }
+
+    /// Show the times of the frames in `format`, which must have been
+    /// checked.
+    pub fn set_timestamp_format(&mut self, format: TimestampFormat) {
+        self.timestamp_format = format;
+    }
+
+    pub fn timestamp_format(&self) -> &TimestampFormat {
+        &self.timestamp_format
+    }
+
+    /// The time of a frame as shown in the labels.
+    pub fn timestamp_label(&self, timestamp: DateTime<Utc>) -> String {
+        render::timestamp_label(timestamp, self.time_zone, &self.timestamp_format.label)
+    }
+
+    /// Process the frames shown through `steps`, before the adjustments and
+    /// the palette.
+    pub fn set_processing(&mut self, steps: Vec<ProcessingStep>) {
+        self.processing = steps;
+    }
+
+    pub fn processing(&self) -> &[ProcessingStep] {
+        &self.processing
+    }
+
+    /// Correct the brightness, contrast and gamma of the frames shown.
+    pub fn set_adjustments(&mut self, adjustments: Adjustments) {
+        self.adjustments = adjustments;
+    }
+
+    pub fn adjustments(&self) -> Adjustments {
+        self.adjustments
+    }
+
+    /// Color the frames by their luminance.
+    pub fn set_palette(&mut self, palette: Palette) {
+        self.palette = palette;
+    }
+
+    pub fn palette(&self) -> &Palette {
+        &self.palette
+    }
+
+    /// Show the changes of each frame since the previous one instead of the
+    /// frames. The oldest frame, without a previous one, is shown as is.
+    pub fn set_difference(&mut self, difference: Difference) {
+        self.difference = difference;
+    }
+
+    pub fn difference(&self) -> Difference {
+        self.difference
+    }
+
+    /// Show two frames side by side instead of the animation, an hour ago on
+    /// the left and the latest on the right at first. Both are zoomed with
+    /// the mouse wheel and panned by dragging together, double-click resets.
+    pub fn set_comparing(&mut self, comparing: bool) {
+        self.comparison = comparing.then_some(Comparison {
+            left: None,
+            right: None,
+            zoom: 1.,
+            pan: egui::Vec2::ZERO,
+        });
+    }
+
+    pub fn is_comparing(&self) -> bool {
+        self.comparison.is_some()
+    }
+
+    /// Show the two frames compared, each with a list to pick another one.
+    /// Zoomed in, a minimap of the whole frame on the right shows the part
+    /// of it shown, and clicking it centers the frames on the point clicked.
+    fn show_comparison(&mut self, ui: &mut egui::Ui, available_rect: egui::Rect) -> egui::Response {
+        let ctx = ui.ctx().clone();
+        let timeline = self.timeline.clone();
+        let sat_images = timeline.sat_images.lock().unwrap();
+        let timestamp_label = |timestamp| render::timestamp_label(timestamp, self.time_zone, &self.timestamp_format.label);
+        let Some(comparison) = &mut self.comparison else {
+            return ui.allocate_rect(available_rect, egui::Sense::hover());
+        };
+        let timestamps: Vec<DateTime<Utc>> = sat_images.iter().map(|sat_image| sat_image.timestamp).collect();
+        // Frames dropped from the sequence are replaced by the default ones
+        if comparison.right.is_none_or(|right| !timestamps.contains(&right)) {
+            comparison.right = sat_images.latest().map(|latest| latest.timestamp);
+        }
+        if comparison.left.is_none_or(|left| !timestamps.contains(&left)) {
+            let target = comparison.right.map(|right| right - chrono::Duration::minutes(COMPARISON_MINUTES));
+            comparison.left = target.and_then(|target| {
+                sat_images.observed().map(|sat_image| sat_image.timestamp).min_by_key(|timestamp| (*timestamp - target).abs())
+            });
+        }
+
+        let response = ui.allocate_rect(available_rect, egui::Sense::click_and_drag());
+        let gap = 4.;
+        let half_size = egui::vec2((available_rect.width() - gap) / 2., available_rect.height());
+        let halves = [
+            egui::Rect::from_min_size(available_rect.min, half_size),
+            egui::Rect::from_min_size(available_rect.min + egui::vec2(half_size.x + gap, 0.), half_size),
+        ];
+        let right_size = comparison
+            .right
+            .and_then(|right| sat_images.iter().find(|sat_image| sat_image.timestamp == right))
+            .map(|sat_image| egui::vec2(sat_image.image.width() as f32, sat_image.image.height() as f32));
+        let minimap = right_size.filter(|_| comparison.zoom > 1.).map(|size| (minimap_rect(halves[1], size), size));
+        let on_minimap = minimap.and_then(|(rect, size)| {
+            let pointer = response.interact_pointer_pos().filter(|pointer| rect.contains(*pointer))?;
+            Some((pointer, rect, size))
+        });
+        // Zoom around the pointer, in whichever half it is
+        if let Some(pointer) = response.hover_pos() {
+            let scroll = ctx.input(|i| i.smooth_scroll_delta.y);
+            let half = halves.iter().find(|half| half.contains(pointer)).unwrap_or(&halves[0]);
+            if scroll != 0. {
+                let zoom = (comparison.zoom * (scroll / 200.).exp()).clamp(1., 20.);
+                let from_center = pointer - half.center();
+                comparison.pan = from_center - (from_center - comparison.pan) * zoom / comparison.zoom;
+                comparison.zoom = zoom;
+            }
+        }
+        if let Some((pointer, rect, size)) = on_minimap {
+            // The point clicked in the middle of the right half
+            let fit = (halves[1].width() / size.x).min(halves[1].height() / size.y);
+            let uv = (pointer - rect.min) / rect.size();
+            comparison.pan = (egui::vec2(0.5, 0.5) - uv) * size * fit * comparison.zoom;
+        } else if response.dragged() {
+            comparison.pan += response.drag_delta();
+        }
+        if response.double_clicked() {
+            comparison.zoom = 1.;
+            comparison.pan = egui::Vec2::ZERO;
+        }
+
+        let mut right = None;
+        let sides = [(halves[0], &mut comparison.left, "compare-left"), (halves[1], &mut comparison.right, "compare-right")];
+        for (half, timestamp, name) in sides {
+            let found = timestamp.and_then(|timestamp| sat_images.iter().find(|sat_image| sat_image.timestamp == timestamp));
+            let Some(sat_image) = found else {
+                continue;
+            };
+            let texture_handle = frame_texture(&ctx, name, sat_image, &self.processing, &self.adjustments, &self.palette);
+            let image_size = texture_handle.size_vec2();
+            let fit = (half.width() / image_size.x).min(half.height() / image_size.y);
+            let image_rect = egui::Rect::from_center_size(half.center() + comparison.pan, image_size * fit * comparison.zoom);
+            let painter = ui.painter().with_clip_rect(half);
+            painter.add(egui::Shape::mesh(frame_mesh(texture_handle.id(), image_rect, egui::Color32::WHITE)));
+            if half == halves[1] {
+                right = Some((texture_handle.id(), image_rect));
+            }
+            let georeference = Georeference::new(self.region.0, self.region.1, sat_image.image.dimensions());
+            for overlay in &self.overlays {
+                overlay.paint(&painter, &georeference, image_rect);
+            }
+            let color = if sat_image.forecast { FORECAST_COLOR } else { egui::Color32::WHITE };
+            let label = HudLine { text: timestamp_label(sat_image.timestamp), font: vcr_font(), color };
+            hud::paint_corner(&painter, half, egui::Align2::LEFT_BOTTOM, self.hud.margin, &[label]);
+
+            egui::Area::new(egui::Id::new(name))
+                .fixed_pos(half.right_top() + egui::vec2(-190., 10.))
+                .show(&ctx, |ui| {
+                    egui::ComboBox::from_id_salt(name)
+                        .width(180.)
+                        .selected_text(timestamp_label(sat_image.timestamp))
+                        .show_ui(ui, |ui| {
+                            for sat_image in sat_images.iter() {
+                                let text = timestamp_label(sat_image.timestamp);
+                                ui.selectable_value(timestamp, Some(sat_image.timestamp), text);
+                            }
+                        });
+                });
+        }
+        ui.painter().rect_filled(
+            egui::Rect::from_min_size(halves[0].right_top(), egui::vec2(gap, available_rect.height())),
+            0.,
+            egui::Color32::BLACK,
+        );
+        if let (Some((rect, _)), Some((texture_id, image_rect))) = (minimap, right) {
+            let painter = ui.painter();
+            painter.add(egui::Shape::mesh(frame_mesh(texture_id, rect, egui::Color32::WHITE)));
+            painter.rect_stroke(rect, 0., egui::Stroke::new(1., egui::Color32::BLACK), egui::StrokeKind::Outside);
+            // The right half, in the frame
+            let to_minimap = |pos: egui::Pos2| rect.min + (pos - image_rect.min) / image_rect.size() * rect.size();
+            let view = egui::Rect::from_min_max(to_minimap(halves[1].min), to_minimap(halves[1].max)).intersect(rect);
+            painter.rect_stroke(view, 0., egui::Stroke::new(1.5, MINIMAP_VIEW_COLOR), egui::StrokeKind::Inside);
+        }
+        response
+    }
+
+    /// Compute in the background the motion between the consecutive frames
+    /// that do not have it yet, forgetting the pairs no longer played.
+    fn update_motion_fields(&self, ctx: &egui::Context) {
+        if !self.interpolate || self.timeline.is_downloading() || *self.computing_motion.lock().unwrap() {
+            return;
+        }
+        let sat_images = self.timeline.sat_images.lock().unwrap();
+        // Only the frames of the pairs missing are copied, not the whole
+        // sequence at every repaint
+        let pairs = || sat_images.iter().zip(sat_images.iter().skip(1)).map(|(newer, older)| (older, newer));
+        let keys: HashSet<(DateTime<Utc>, DateTime<Utc>)> = pairs().map(|(older, newer)| (older.timestamp, newer.timestamp)).collect();
+        let mut motion_fields = self.motion_fields.lock().unwrap();
+        motion_fields.retain(|key, _| keys.contains(key));
+        let missing: Vec<(SatImage, SatImage)> = pairs()
+            .filter(|(older, newer)| !motion_fields.contains_key(&(older.timestamp, newer.timestamp)))
+            .map(|(older, newer)| (older.clone(), newer.clone()))
+            .collect();
+        drop(motion_fields);
+        drop(sat_images);
+        if missing.is_empty() {
+            return;
+        }
+        *self.computing_motion.lock().unwrap() = true;
+        let (motion_fields, computing_motion, ctx) = (self.motion_fields.clone(), self.computing_motion.clone(), ctx.clone());
+        std::thread::spawn(move || {
+            for (older, newer) in missing {
+                let minutes = (newer.timestamp - older.timestamp).num_minutes().max(1) as f32;
+                let field = MotionField::between(&older.image, &newer.image, minutes);
+                motion_fields.lock().unwrap().insert((older.timestamp, newer.timestamp), Arc::new(field));
+            }
+            *computing_motion.lock().unwrap() = false;
+            ctx.request_repaint();
+        });
+    }
+
+    /// Choose the labels shown in each corner.
+    pub fn set_hud(&mut self, hud: HudConfig) {
+        self.hud = hud;
+    }
+
+    pub fn hud(&self) -> &HudConfig {
+        &self.hud
+    }
+
+    /// The lines of a HUD element for the frame at `image_index`.
+    fn hud_lines(&self, element: HudElement, sat_images: &FrameSequence, errors: usize) -> Vec<HudLine> {
+        let vcr_line = |text: String, color: egui::Color32| HudLine { text, font: vcr_font(), color };
+        let sat_image = &sat_images[self.image_index];
+        match element {
+            HudElement::Timestamp => {
+                let mut label = format!(
+                    "{:0>2}/{:0>2} {}",
+                    // as image are order from most recent to least recent,
+                    // we display here a more natural index
+                    sat_images.len() - self.image_index,
+                    sat_images.len(),
+                    self.timestamp_label(sat_image.timestamp),
+                );
+                if self.difference != Difference::Off && self.image_index + 1 < sat_images.len() {
+                    label.push_str(" CHANGES");
+                }
+                // Forecasts must not be mistaken for observations
+                if sat_image.forecast {
+                    let minutes = sat_images
+                        .latest()
+                        .map_or(0, |latest| (sat_image.timestamp - latest.timestamp).num_minutes());
+                    label.push_str(&format!(" FORECAST +{}MIN", minutes));
+                    vec![vcr_line(label, FORECAST_COLOR)]
+                } else {
+                    vec![vcr_line(label, egui::Color32::WHITE)] // Make it visible on a dark image
+                }
+            }
+            HudElement::Clock => vec![vcr_line(self.time_zone.format(chrono::Utc::now(), "%H:%M"), egui::Color32::WHITE)],
+            HudElement::DataAge => sat_images
+                .latest()
+                .map(|latest| {
+                    let minutes = (chrono::Utc::now() - latest.timestamp).num_minutes();
+                    vcr_line(format!("AGE {}MIN", minutes), egui::Color32::WHITE)
+                })
+                .into_iter()
+                .collect(),
+            HudElement::Stats => {
+                let observed = sat_images.observed().count();
+                vec![vcr_line(format!("{} FRAMES {} ERRORS", observed, errors), egui::Color32::WHITE)]
+            }
+            HudElement::Legend => {
+                // Each layer named in its color
+                let legend_line = |text: &str, color: egui::Color32| HudLine {
+                    text: text.to_string(),
+                    font: egui::FontId::proportional(14.0),
+                    color,
+                };
+                let mut lines = vec![];
+                if self.nowcast {
+                    lines.push(legend_line("Forecast", FORECAST_COLOR));
+                }
+                if self.track.is_some() {
+                    lines.push(legend_line("Track position", TRACK_COLOR));
+                }
+                if self.stations_visible {
+                    lines.push(legend_line("Radiosonde stations", STATION_COLOR));
+                }
+                for overlay in self.overlays.iter().filter(|overlay| overlay.visible) {
+                    lines.push(legend_line(&overlay.name, overlay.color));
+                }
+                for source in self.live_sources.iter().filter(|_| self.live_visible) {
+                    let (r, g, b) = source.config.color;
+                    let name = match source.config.kind {
+                        LiveKind::Adsb => "Aircraft",
+                        LiveKind::Ais => "Vessels",
+                    };
+                    lines.push(legend_line(name, egui::Color32::from_rgb(r, g, b)));
+                }
+                lines
+            }
+            // Measured on the frame drawn, in `show`
+            HudElement::ScaleBar => vec![],
+        }
+    }
+
+    /// Append frames extrapolated from the motion of the clouds to the loop,
+    /// made again as new frames arrive.
+    pub fn set_nowcast(&mut self, nowcast: bool) {
+        self.nowcast = nowcast;
+        self.nowcast_from = None;
+        if !nowcast {
+            self.timeline.clear_forecasts();
+        }
+    }
+
+    pub fn is_nowcasting(&self) -> bool {
+        self.nowcast
+    }
+
+    /// Make the forecasts in the background once the frames are loaded, if
+    /// not made from the latest frame yet.
+    fn update_nowcast(&mut self) {
+        if !self.nowcast || self.timeline.is_downloading() {
+            return;
+        }
+        let latest = self.timeline.sat_images.lock().unwrap().latest().map(|latest| latest.timestamp);
+        if latest.is_none() || latest == self.nowcast_from {
+            return;
+        }
+        self.nowcast_from = latest;
+        let timeline = self.timeline.clone();
+        std::thread::spawn(move || timeline.nowcast());
+    }
+
+    /// Show the radiosonde launch sites. Clicking one shows the indices of its
+    /// latest sounding.
+    pub fn set_stations_visible(&mut self, visible: bool) {
+        self.stations_visible = visible;
+        if !visible {
+            self.sounding = None;
+        }
+    }
+
+    pub fn stations_visible(&self) -> bool {
+        self.stations_visible
+    }
+
+    /// Draw the boundaries of the tiles over the frames with their zoom
+    /// level and indices, tinted by where the frame came from.
+    pub fn set_tile_debug(&mut self, tile_debug: bool) {
+        self.tile_debug = tile_debug;
+    }
+
+    pub fn tile_debug(&self) -> bool {
+        self.tile_debug
+    }
+
+    /// Show the time taken to draw and to upload the frames, the frames
+    /// waiting to be loaded, the memory they take and how many came from the
+    /// cache, under the top-right corner of the HUD.
+    pub fn set_perf_visible(&mut self, visible: bool) {
+        self.perf_visible = visible;
+    }
+
+    pub fn perf_visible(&self) -> bool {
+        self.perf_visible
+    }
+
+    /// The lines of the performance HUD.
+    fn perf_lines(&self, sat_images: &FrameSequence) -> Vec<HudLine> {
+        let perf_line = |text: String| HudLine { text, font: egui::FontId::monospace(14.), color: PERF_COLOR };
+        let progress = self.timeline.progress();
+        let fps = if self.perf.frame_time > 0. { 1. / self.perf.frame_time } else { 0. };
+        let cache = match self.timeline.cache_hit_rate() {
+            Some(rate) => format!("{:.0}%", rate * 100.),
+            None => "-".to_string(),
+        };
+        vec![
+            perf_line(format!("FRAME {:.1} MS ({:.0} FPS)", self.perf.frame_time * 1000., fps)),
+            perf_line(format!("TEXTURE {:.1} MS", self.perf.texture_time * 1000.)),
+            perf_line(format!("QUEUE {}", progress.total.saturating_sub(progress.done))),
+            perf_line(format!("MEMORY {:.0} MB", sat_images.memory() as f64 / (1024. * 1024.))),
+            perf_line(format!("CACHE {}", cache)),
+        ]
+    }
+
+    /// Draw the tiles of the window, placed through the projection so that a
+    /// stitching or projection error shows as a grid off the seams, labeled
+    /// `z/x/y` and tinted by where the frame came from. The tiles of a frame
+    /// are downloaded together, so they all share the frame's source.
+    fn paint_tile_grid(&self, ui: &egui::Ui, sat_image: &SatImage, georeference: &Georeference, image_rect: egui::Rect) {
+        if !self.tile_debug {
+            return;
+        }
+        let painter = ui.painter().with_clip_rect(image_rect);
+        let (((x1, y1), (x2, y2)), zoom) = self.region;
+        let color = match self.timeline.provenance(sat_image.timestamp).and_then(|provenance| provenance.cache_hit) {
+            Some(true) => TILE_CACHE_COLOR,
+            Some(false) => TILE_NETWORK_COLOR,
+            None => TILE_UNCACHED_COLOR,
+        };
+        let corner = |x: u16, y: u16| overlay::screen_position(georeference, image_rect, tile_to_lonlat(x as f64, y as f64, zoom));
+        for y in y1..=y2 {
+            for x in x1..=x2 {
+                let tile_rect = egui::Rect::from_min_max(corner(x, y), corner(x + 1, y + 1));
+                painter.rect_filled(tile_rect, 0., color.gamma_multiply(0.15));
+                painter.rect_stroke(tile_rect, 0., egui::Stroke::new(1., color), egui::StrokeKind::Inside);
+                painter.text(
+                    tile_rect.left_top() + egui::vec2(4., 4.),
+                    egui::Align2::LEFT_TOP,
+                    format!("{}/{}/{}", zoom, x, y),
+                    egui::FontId::monospace(12.),
+                    color,
+                );
+            }
+        }
+    }
+
+    /// Let clicks on the frames pick the two corners of a region.
+    pub fn set_defining_region(&mut self, defining: bool) {
+        self.region_corners = defining.then(Vec::new);
+        if defining {
+            self.measure_points = None;
+            self.set_annotation_tool(None);
+        }
+    }
+
+    pub fn is_defining_region(&self) -> bool {
+        self.region_corners.is_some()
+    }
+
+    /// The two corners picked, (longitude, latitude), once both are.
+    pub fn region_corners(&self) -> Option<(LonLat, LonLat)> {
+        match self.region_corners.as_deref() {
+            Some([a, b]) => Some((*a, *b)),
+            _ => None,
+        }
+    }
+
+    /// Add the corner clicked, starting over after two, and draw the
+    /// rectangle picked, up to the pointer while the second corner is not.
+    fn pick_region(&mut self, ui: &egui::Ui, response: &egui::Response, georeference: &Georeference, image_rect: egui::Rect) {
+        let Some(corners) = &mut self.region_corners else {
+            return;
+        };
+        if response.clicked()
+            && let Some(pointer) = response.interact_pointer_pos().filter(|pointer| image_rect.contains(*pointer))
+        {
+            if corners.len() == 2 {
+                corners.clear();
+            }
+            corners.push(overlay::lonlat_at(georeference, image_rect, pointer));
+        }
+        let painter = ui.painter().with_clip_rect(image_rect);
+        let stroke = egui::Stroke::new(2., REGION_COLOR);
+        let mut points: Vec<egui::Pos2> = corners
+            .iter()
+            .map(|corner| overlay::screen_position(georeference, image_rect, *corner))
+            .collect();
+        if points.len() == 1
+            && let Some(pointer) = response.hover_pos()
+        {
+            points.push(pointer);
+        }
+        for point in &points {
+            painter.circle_filled(*point, 4., REGION_COLOR);
+        }
+        if let [a, b] = points[..] {
+            painter.rect_stroke(egui::Rect::from_two_pos(a, b), 0., stroke, egui::StrokeKind::Middle);
+        }
+    }
+
+    /// Let clicks on the frames pick the two ends of a distance, measured
+    /// along the great circle with the bearing from the first to the second.
+    pub fn set_measuring(&mut self, measuring: bool) {
+        self.measure_points = measuring.then(Vec::new);
+        if measuring {
+            self.region_corners = None;
+            self.set_annotation_tool(None);
+        }
+    }
+
+    pub fn is_measuring(&self) -> bool {
+        self.measure_points.is_some()
+    }
+
+    /// Add the end clicked, starting over after two, and draw the distance
+    /// and bearing between them, up to the pointer while the second end is
+    /// not picked.
+    fn measure(&mut self, ui: &egui::Ui, response: &egui::Response, georeference: &Georeference, image_rect: egui::Rect) {
+        let Some(points) = &mut self.measure_points else {
+            return;
+        };
+        if response.clicked()
+            && let Some(pointer) = response.interact_pointer_pos().filter(|pointer| image_rect.contains(*pointer))
+        {
+            if points.len() == 2 {
+                points.clear();
+            }
+            points.push(overlay::lonlat_at(georeference, image_rect, pointer));
+        }
+        let mut ends = points.clone();
+        if ends.len() == 1
+            && let Some(pointer) = response.hover_pos().filter(|pointer| image_rect.contains(*pointer))
+        {
+            ends.push(overlay::lonlat_at(georeference, image_rect, pointer));
+        }
+        let painter = ui.painter().with_clip_rect(image_rect);
+        let screen: Vec<egui::Pos2> = ends.iter().map(|end| overlay::screen_position(georeference, image_rect, *end)).collect();
+        for point in &screen {
+            painter.circle_filled(*point, 4., MEASURE_COLOR);
+        }
+        if let (&[from, to], &[a, b]) = (&ends[..], &screen[..]) {
+            painter.line_segment([a, b], egui::Stroke::new(2., MEASURE_COLOR));
+            let label = format!("{:.0} km, {:.0}°", distance_km(from, to), bearing_degrees(from, to));
+            let galley = painter.layout_no_wrap(label, egui::FontId::proportional(16.0), MEASURE_COLOR);
+            // Readable over bright clouds
+            let label_rect = egui::Align2::LEFT_BOTTOM.anchor_size(b + egui::vec2(10., -10.), galley.size());
+            painter.rect_filled(label_rect.expand(3.), 3., egui::Color32::from_black_alpha(160));
+            painter.galley(label_rect.min, galley, MEASURE_COLOR);
+        }
+    }
+
+    /// Let dragging or clicking on the frames draw annotations with `tool`,
+    /// or stop drawing with `None`.
+    pub fn set_annotation_tool(&mut self, tool: Option<Tool>) {
+        self.annotation_tool = tool;
+        self.annotation_drag = None;
+        self.annotation_text = None;
+        if tool.is_some() {
+            self.region_corners = None;
+            self.measure_points = None;
+        }
+    }
+
+    pub fn annotation_tool(&self) -> Option<Tool> {
+        self.annotation_tool
+    }
+
+    /// The annotations drawn over the frames.
+    pub fn set_annotations(&mut self, annotations: Vec<Annotation>) {
+        self.annotations = annotations;
+    }
+
+    pub fn annotations(&self) -> &[Annotation] {
+        &self.annotations
+    }
+
+    /// Draw with the annotation tool: arrows and circles are dragged, text is
+    /// typed where clicked.
+    fn annotate(&mut self, ui: &egui::Ui, response: &egui::Response, georeference: &Georeference, image_rect: egui::Rect) {
+        let Some(tool) = self.annotation_tool else {
+            return;
+        };
+        let lonlat_at = |pointer| overlay::lonlat_at(georeference, image_rect, pointer);
+        let pointer = response.interact_pointer_pos().filter(|pointer| image_rect.contains(*pointer));
+        match tool {
+            Tool::Arrow | Tool::Circle => {
+                if response.drag_started()
+                    && let Some(pointer) = pointer
+                {
+                    self.annotation_drag = Some((lonlat_at(pointer), lonlat_at(pointer)));
+                }
+                if let (Some((_, end)), Some(pointer)) = (&mut self.annotation_drag, response.interact_pointer_pos()) {
+                    *end = lonlat_at(pointer);
+                }
+                let shape = |start, end| match tool {
+                    Tool::Circle => Annotation::Circle(start, end),
+                    _ => Annotation::Arrow(start, end),
+                };
+                if response.drag_stopped()
+                    && let Some((start, end)) = self.annotation_drag.take()
+                {
+                    // Not a mere click
+                    if start != end {
+                        self.annotations.push(shape(start, end));
+                    }
+                } else if let Some((start, end)) = self.annotation_drag {
+                    shape(start, end).paint(ui.painter(), georeference, image_rect);
+                }
+            }
+            Tool::Text => {
+                let started = response.clicked() && pointer.is_some();
+                if let Some(pointer) = pointer.filter(|_| started) {
+                    self.annotation_text = Some((lonlat_at(pointer), String::new()));
+                }
+                let Some((at, text)) = &mut self.annotation_text else {
+                    return;
+                };
+                let mut typed = None;
+                egui::Area::new(egui::Id::new("annotation-text"))
+                    .fixed_pos(overlay::screen_position(georeference, image_rect, *at) - egui::vec2(0., 24.))
+                    .show(ui.ctx(), |ui| {
+                        let edit = ui.add(egui::TextEdit::singleline(text).hint_text("Text, Enter to add it").desired_width(200.));
+                        if started {
+                            edit.request_focus();
+                        }
+                        // Dropped when clicking elsewhere
+                        if edit.lost_focus() {
+                            typed = Some(ui.input(|i| i.key_pressed(egui::Key::Enter)));
+                        }
+                    });
+                if let Some(entered) = typed {
+                    let (at, text) = self.annotation_text.take().unwrap();
+                    if entered && !text.trim().is_empty() {
+                        self.annotations.push(Annotation::Text(at, text.trim().to_string()));
+                    }
+                }
+            }
+        }
+    }
+
+    /// The points marked from the context menu, as (longitude, latitude).
+    pub fn set_markers(&mut self, markers: Vec<LonLat>) {
+        self.markers = markers;
+    }
+
+    pub fn markers(&self) -> &[LonLat] {
+        &self.markers
+    }
+
+    /// Draw the markers with the cloud cover around them.
+    fn paint_markers(&self, ui: &egui::Ui, sat_image: &SatImage, georeference: &Georeference, image_rect: egui::Rect) {
+        let painter = ui.painter().with_clip_rect(image_rect);
+        for marker in &self.markers {
+            let center = overlay::screen_position(georeference, image_rect, *marker);
+            painter.circle(center, 5., MARKER_COLOR, egui::Stroke::new(1.5, egui::Color32::WHITE));
+            let (radius, threshold) = (analysis::CLOUDINESS_RADIUS_KM, analysis::CLOUD_THRESHOLD);
+            let cover = analysis::cover_around(&sat_image.image, georeference, *marker, radius, threshold);
+            if let Some(cover) = cover {
+                painter.text(
+                    center + egui::vec2(9., 0.),
+                    egui::Align2::LEFT_CENTER,
+                    format!("{:.0}%", cover * 100.),
+                    egui::FontId::proportional(12.0),
+                    MARKER_COLOR,
+                );
+            }
+        }
+    }
+
+    /// Open a menu on right-clicks on the frames, copying the latitude and
+    /// longitude of the point clicked or adding a marker there.
+    fn show_context_menu(&mut self, response: &egui::Response, georeference: &Georeference, image_rect: egui::Rect) {
+        if response.secondary_clicked()
+            && let Some(pointer) = response.interact_pointer_pos()
+        {
+            self.context_point = image_rect.contains(pointer).then(|| overlay::lonlat_at(georeference, image_rect, pointer));
+        }
+        let Some((lon, lat)) = self.context_point else {
+            return;
+        };
+        response.context_menu(|ui| {
+            let coordinates = format!("{:.5}, {:.5}", lat, lon);
+            ui.label(&coordinates);
+            if ui.button("Copy lat/lon").clicked() {
+                ui.ctx().copy_text(coordinates);
+                ui.close_menu();
+            }
+            if ui.button("Add marker here").clicked() {
+                self.markers.push((lon, lat));
+                ui.close_menu();
+            }
+            if !self.markers.is_empty() && ui.button("Remove the markers").clicked() {
+                self.markers.clear();
+                ui.close_menu();
+            }
+            if !self.annotations.is_empty() && ui.button("Remove the last annotation").clicked() {
+                self.annotations.pop();
+                ui.close_menu();
+            }
+            if !self.annotations.is_empty() && ui.button("Remove the annotations").clicked() {
+                self.annotations.clear();
+                ui.close_menu();
+            }
+        });
+    }
+
+    /// Draw the launch sites, open the sounding of the station clicked and
+    /// show it.
+    fn show_stations(&mut self, ui: &egui::Ui, response: &egui::Response, georeference: &Georeference, image_rect: egui::Rect) {
+        let painter = ui.painter().with_clip_rect(image_rect);
+        let color = STATION_COLOR;
+        let positions: Vec<(Station, egui::Pos2)> = sounding::STATIONS
+            .iter()
+            .map(|station| (*station, overlay::screen_position(georeference, image_rect, station.position)))
+            .collect();
+        for (_, position) in &positions {
+            painter.rect_filled(egui::Rect::from_center_size(*position, egui::vec2(8., 8.)), 0., color);
+        }
+        if response.clicked() {
+            let clicked = response.interact_pointer_pos().and_then(|pointer| {
+                positions.iter().find(|(_, position)| position.distance(pointer) < 8.).map(|(station, _)| *station)
+            });
+            self.sounding = clicked.map(|station| {
+                let indices = Arc::new(Mutex::new(None));
+                let result = indices.clone();
+                let ctx = ui.ctx().clone();
+                std::thread::spawn(move || {
+                    let indices = sounding::fetch_latest(&station, chrono::Utc::now()).map_err(|e| e.to_string());
+                    *result.lock().unwrap() = Some(indices);
+                    ctx.request_repaint();
+                });
+                SoundingPopup { station, indices }
+            });
+        }
+        let Some(popup) = &self.sounding else {
+            return;
+        };
+        let position = overlay::screen_position(georeference, image_rect, popup.station.position);
+        egui::Area::new(egui::Id::new("sounding_popup"))
+            .fixed_pos(position + egui::vec2(10., 10.))
+            .show(ui.ctx(), |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.strong(format!("{} ({})", popup.station.name, popup.station.wmo));
+                    let format = |value: Option<f64>, unit: &str| value.map_or("-".to_string(), |value| format!("{:.1} {}", value, unit));
+                    match &*popup.indices.lock().unwrap() {
+                        None => {
+                            ui.label("Downloading...");
+                        }
+                        Some(Err(e)) => {
+                            ui.label(e);
+                        }
+                        Some(Ok(indices)) => {
+                            ui.label(format!("Launch: {}", indices.observation_time));
+                            ui.label(format!("CAPE: {}", format(indices.cape, "J/kg")));
+                            ui.label(format!("Lifted index: {}", format(indices.lifted_index, "°C")));
+                            ui.label(format!("Showalter index: {}", format(indices.showalter, "°C")));
+                            ui.label(format!("Precipitable water: {}", format(indices.precipitable_water, "mm")));
+                        }
+                    }
+                });
+            });
+    }
+
+    /// Show the position along `track` at the time of the frame. When
+    /// `sync` is set and the track has timestamps, only the frames taken
+    /// during the track are played.
+    pub fn set_track(&mut self, track: Option<Track>, sync: bool) {
+        self.track = track;
+        self.track_sync = sync;
+    }
+
+    pub fn track(&self) -> Option<&Track> {
+        self.track.as_ref()
+    }
+
+    pub fn is_track_synced(&self) -> bool {
+        self.track_sync
+    }
+
+    pub fn set_track_sync(&mut self, sync: bool) {
+        self.track_sync = sync;
+    }
+
+    /// The first and last index of the frames played, all of them unless
+    /// synchronized with a track. Half a frame interval of margin is kept
+    /// around the track.
+    fn playback_range(&self, sat_images: &FrameSequence) -> (usize, usize) {
+        let all = (0, sat_images.len() - 1);
+        let Some((from, to)) = self.track.as_ref().filter(|_| self.track_sync).and_then(Track::time_range) else {
+            return all;
+        };
+        let margin = chrono::Duration::seconds(self.interval as i64 * 30);
+        let in_track = |sat_image: &SatImage| sat_image.timestamp >= from - margin && sat_image.timestamp <= to + margin;
+        let first = sat_images.iter().position(in_track);
+        let last = sat_images.iter().rposition(in_track);
+        match (first, last) {
+            (Some(first), Some(last)) => (first, last),
+            _ => all,
+        }
+    }
+
+    /// The frames played by the viewer.
+    pub fn timeline(&self) -> &Timeline {
+        &self.timeline
+    }
+
+    /// A copy of the frame currently displayed, if any.
+    pub fn current_frame(&self) -> Option<SatImage> {
+        self.timeline.sat_images.lock().unwrap().get(self.image_index).cloned()
+    }
+
+    /// When the frame shown is announced to screen readers.
+    pub fn set_announcements(&mut self, announcements: Announcements) {
+        self.announcements = announcements;
+    }
+
+    pub fn announcements(&self) -> Announcements {
+        self.announcements
+    }
+
+    /// Pause on the frame taken at `timestamp`, as soon as it is loaded. It
+    /// is forgotten if the frames are all loaded without it.
+    pub fn show_frame(&mut self, timestamp: DateTime<Utc>) {
+        self.pending_frame = Some(timestamp);
+    }
+
+    /// Show a thumbnail of every frame loaded, the oldest on the left, and
+    /// outline the frame shown, scrolling to it; clicking a thumbnail pauses
+    /// on its frame. The thumbnails are made once, when their frame arrives.
+    pub fn show_filmstrip(&mut self, ui: &mut egui::Ui) {
+        let timeline = self.timeline.clone();
+        let sat_images = timeline.sat_images.lock().unwrap();
+        self.thumbnails.retain(|timestamp, _| sat_images.contains(*timestamp));
+        // Nothing to show or step through while the first frames download
+        if sat_images.is_empty() {
+            return;
+        }
+        let mut clicked = None;
+        // The wheel steps through the frames rather than scrolling the strip,
+        // which follows the frame shown
+        if ui.rect_contains_pointer(ui.max_rect()) {
+            let delta = ui.input_mut(|i| {
+                let delta = i.raw_scroll_delta.x + i.raw_scroll_delta.y;
+                (i.raw_scroll_delta, i.smooth_scroll_delta) = (egui::Vec2::ZERO, egui::Vec2::ZERO);
+                delta
+            });
+            if delta != 0. {
+                let range = self.playback_range(&sat_images);
+                self.auto_play = false;
+                Viewer::scrub(&mut self.image_index, &mut self.wheel, delta, range);
+            }
+        }
+        egui::ScrollArea::horizontal().show(ui, |ui| {
+            ui.horizontal(|ui| {
+                for (index, sat_image) in sat_images.iter().enumerate().rev() {
+                    let label = self.timestamp_label(sat_image.timestamp);
+                    let texture = self.thumbnails.entry(sat_image.timestamp).or_insert_with(|| {
+                        let (width, height) = sat_image.image.dimensions();
+                        let thumbnail_width = (width * THUMBNAIL_HEIGHT / height).max(1);
+                        let thumbnail = image::imageops::thumbnail(&sat_image.image, thumbnail_width, THUMBNAIL_HEIGHT);
+                        let size = [thumbnail.width() as usize, thumbnail.height() as usize];
+                        let name = format!("thumbnail-{}", sat_image.timestamp.timestamp());
+                        ui.ctx().load_texture(name, egui::ColorImage::from_rgb(size, thumbnail.as_raw()), Default::default())
+                    });
+                    let response = ui.add(egui::Image::new(&*texture).sense(egui::Sense::click())).on_hover_text(label);
+                    if response.clicked() {
+                        clicked = Some(index);
+                    }
+                    if sat_image.forecast {
+                        ui.painter().rect_stroke(response.rect, 0., egui::Stroke::new(2., FORECAST_COLOR), egui::StrokeKind::Inside);
+                    }
+                    if index == self.image_index {
+                        ui.painter().rect_stroke(response.rect, 0., egui::Stroke::new(3., egui::Color32::WHITE), egui::StrokeKind::Outside);
+                        if self.filmstrip_index != Some(index) {
+                            response.scroll_to_me(None);
+                            self.filmstrip_index = Some(index);
+                        }
+                    }
+                }
+            });
+        });
+        if let Some(index) = clicked {
+            self.image_index = index;
+            self.auto_play = false;
+        }
+    }
+
+    /// Step through the frames played, from `first` to `last`, by the
+    /// `delta` points the wheel scrolled, `wheel` keeping what is left of a
+    /// step: towards the oldest scrolling up, towards the latest scrolling
+    /// down.
+    fn scrub(image_index: &mut usize, wheel: &mut f32, delta: f32, (first, last): (usize, usize)) {
+        *wheel += delta;
+        let steps = (*wheel / WHEEL_STEP).trunc();
+        *wheel -= steps * WHEEL_STEP;
+        *image_index = (*image_index as isize + steps as isize).clamp(first as isize, last as isize) as usize;
+    }
+
+    /// Whether the animation is playing.
+    pub fn is_playing(&self) -> bool {
+        self.auto_play
+    }
+
+    pub fn set_playing(&mut self, auto_play: bool) {
+        self.auto_play = auto_play;
+    }
+
+    fn increase_image_index(image_index: &mut usize, nb_images: usize) {
+        if *image_index == nb_images - 1 {
+            *image_index = 0;
+        } else {
+            *image_index += 1;
+        }
+    }
+
+    fn decrease_image_index(image_index: &mut usize, nb_images: usize) {
+        if *image_index == 0 {
+            *image_index = nb_images - 1;
+        } else {
+            *image_index -= 1;
+        }
+    }
+
+    /// The index of the frame nearest `minutes` after the one at `index`,
+    /// before it if negative, in `timestamps` from the most recent: the
+    /// frames may be further apart than the interval in long histories.
+    /// Stops on the last frame that way before going round to the other end.
+    fn step_in_time(timestamps: &[DateTime<Utc>], index: usize, minutes: i64) -> usize {
+        let target = timestamps[index] + chrono::Duration::minutes(minutes);
+        let distance = |i: &usize| (timestamps[*i] - target).abs();
+        // On a tie, the frame furthest from `index`
+        let nearest = match minutes > 0 {
+            true => (0..index).min_by_key(distance),
+            false => (index + 1..timestamps.len()).rev().min_by_key(distance),
+        };
+        nearest.unwrap_or(match minutes > 0 {
+            true => timestamps.len() - 1,
+            false => 0,
+        })
+    }
+
+    /// Show the latest frame only, the square part of it around `home` with
+    /// home marked and the time of the frame, as a widget in a small window.
+    pub fn show_compact(&self, ui: &mut egui::Ui, home: LonLat) -> egui::Response {
+        let ctx = ui.ctx().clone();
+        let available_rect = ui.available_rect_before_wrap();
+        let response = ui.allocate_rect(available_rect, egui::Sense::hover());
+        let font = egui::FontId::new(14.0, egui::FontFamily::Name("vcr".into()));
+        let sat_images = self.timeline.sat_images.lock().unwrap();
+        let Some(latest) = sat_images.latest() else {
+            let label = HudLine { text: "DOWNLOADING...".to_string(), font, color: egui::Color32::WHITE };
+            hud::paint_corner(ui.painter(), available_rect, egui::Align2::LEFT_BOTTOM, 6., &[label]);
+            return response;
+        };
+        let texture_handle = frame_texture(&ctx, "compact-frame", latest, &self.processing, &self.adjustments, &self.palette);
+        let georeference = Georeference::new(self.region.0, self.region.1, latest.image.dimensions());
+        let (home_x, home_y) = georeference.lonlat_to_pixel(home.0, home.1);
+        // Home in the middle, unless it is near an edge of the frame
+        let (width, height) = (latest.image.width() as f32, latest.image.height() as f32);
+        let side = (height * COMPACT_CROP).min(width);
+        let left = (home_x as f32 - side / 2.).clamp(0., width - side);
+        let top = (home_y as f32 - side / 2.).clamp(0., height - side);
+        let uv = egui::Rect::from_min_size(egui::pos2(left / width, top / height), egui::vec2(side / width, side / height));
+        let side = available_rect.width().min(available_rect.height());
+        let image_rect = egui::Rect::from_center_size(available_rect.center(), egui::Vec2::splat(side));
+        ui.painter().image(texture_handle.id(), image_rect, uv, egui::Color32::WHITE);
+        let marker = image_rect.min + egui::vec2((home_x as f32 - left) / side, (home_y as f32 - top) / side) * image_rect.width();
+        if image_rect.contains(marker) {
+            ui.painter().circle(marker, 4., TRACK_COLOR, egui::Stroke::new(1.5, egui::Color32::WHITE));
+        }
+        let label = HudLine { text: self.timestamp_label(latest.timestamp), font, color: egui::Color32::WHITE };
+        hud::paint_corner(ui.painter(), image_rect, egui::Align2::LEFT_BOTTOM, 6., &[label]);
+        response
+    }
+
+    /// Show the current frame in all the space available.
+    pub fn show(&mut self, ui: &mut egui::Ui) -> egui::Response {
+        let ctx = ui.ctx().clone();
+        let time = ctx.input(|i| i.time);
+        let available_rect = ui.available_rect_before_wrap();
+
+        // Blinking download label
+        const BLINK_HZ: f64 = 2.0;
+        let cycle_duration = 1.0 / BLINK_HZ;
+        let time_in_cycle = time % cycle_duration;
+        let downloading_is_visible = time_in_cycle < (cycle_duration / 2.0);
+        let downloading = self.timeline.is_downloading();
+        let last_error = self.timeline.last_error();
+        self.update_nowcast();
+        self.update_motion_fields(&ctx);
+        self.update_basemap_texture(&ctx);
+
+        // Nothing is drawn for a minimized window
+        let (focused, minimized) = ctx.input(|i| (i.focused, i.viewport().minimized == Some(true)));
+        if self.pause_unfocused {
+            if !focused && self.auto_play {
+                self.auto_play = false;
+                self.paused_unfocused = true;
+            } else if focused && self.paused_unfocused {
+                self.auto_play = true;
+                self.paused_unfocused = false;
+            }
+        }
+        // Played again after a while without input, for a wall display not
+        // to stay on an old frame because someone touched the keyboard
+        if ctx.input(|i| !i.events.is_empty() || i.pointer.any_down()) {
+            self.last_input = time;
+        }
+        if let Some(resume_after) = self.resume_after
+            && !self.auto_play
+            && !self.paused_unfocused
+        {
+            let idle = time - self.last_input;
+            if idle >= resume_after as f64 {
+                self.auto_play = true;
+            } else {
+                ctx.request_repaint_after_secs((resume_after as f64 - idle) as f32);
+            }
+        }
+
+        let sat_images = self.timeline.sat_images.lock().unwrap();
+        // Check we have images
+        if sat_images.is_empty() {
+            // Wait for images
+            let response = ui.allocate_rect(available_rect, egui::Sense::hover());
+            if let Some(last_error) = &last_error {
+                paint_error(ui, available_rect.left_bottom() + egui::vec2(10.0, -40.0), last_error);
+            }
+            if downloading {
+                if downloading_is_visible {
+                    ui.painter().text(
+                        available_rect.left_bottom() + egui::vec2(10.0, -10.0),
+                        egui::Align2::LEFT_BOTTOM,
+                        downloading_label(self.timeline.progress()),
+                        vcr_font(),
+                        egui::Color32::WHITE,
+                    );
+                }
+                ctx.request_repaint();
+            }
+            return response;
+        }
+        if self.comparison.is_some() {
+            drop(sat_images);
+            return self.show_comparison(ui, available_rect);
+        }
+        if let Some(timestamp) = self.pending_frame {
+            match sat_images.iter().position(|sat_image| sat_image.timestamp == timestamp) {
+                Some(index) => {
+                    self.image_index = index;
+                    self.auto_play = false;
+                    self.pending_frame = None;
+                }
+                None if !downloading => self.pending_frame = None,
+                None => (),
+            }
+        }
+        // New frames may have been pushed since the last update
+        let (first, last) = self.playback_range(&sat_images);
+        let nb_played = last - first + 1;
+        self.image_index = self.image_index.clamp(first, last);
+
+        // How far the animation is between the frame shown and the next one,
+        // and how long a frame is shown
+        let mut progress = 0.;
+        let mut frame_duration = None;
+        // Let's say 25 minutes of weather per second, an image every 1/5th of
+        // a second at 5 minutes, faster for long histories not to wait too
+        // long for the loop to come back, and as the playback rate says
+        let loop_duration = |speed: f64| (nb_played as f64 * self.interval as f64 / MINUTES_PER_SECOND).min(MAX_CYCLE) / speed;
+        // Twice as fast on + or ], twice as slow on - or [, going on from
+        // the frame shown
+        for (action, factor) in [(Action::Faster, 2.), (Action::Slower, 0.5)] {
+            if self.keymap.pressed(&ctx, action) {
+                let speed = (self.speed * factor).clamp(SPEED_RANGE.0, SPEED_RANGE.1);
+                let (before, after) = (loop_duration(self.speed), loop_duration(speed));
+                let share = (time - self.cycle_start).rem_euclid(before) / before;
+                self.cycle_start = time - share * after;
+                self.speed = speed;
+                self.speed_changed = Some(time);
+            }
+        }
+        if self.auto_play {
+            let cycle_duration = loop_duration(self.speed);
+            let time_in_cycle = (time - self.cycle_start).rem_euclid(cycle_duration);
+            let position = time_in_cycle * nb_played as f64 / cycle_duration;
+            self.image_index = match self.reverse {
+                true => first + position as usize,
+                false => last - position as usize,
+            };
+            progress = position.fract() as f32;
+            frame_duration = Some(cycle_duration / nb_played as f64);
+        }
+        // Images are order from the most recent to the least.
+        // Index 0 is the most recent.
+        // Navigate the image with left and right, by a frame, 15 minutes with
+        // Shift or an hour with Ctrl.
+        if self.keymap.pressed(&ctx, Action::Next) {
+            self.auto_play = false;
+            let mut played_index = self.image_index - first;
+            Viewer::decrease_image_index(&mut played_index, nb_played);
+            self.image_index = first + played_index;
+        }
+        if self.keymap.pressed(&ctx, Action::Previous) {
+            self.auto_play = false;
+            let mut played_index = self.image_index - first;
+            Viewer::increase_image_index(&mut played_index, nb_played);
+            self.image_index = first + played_index;
+        }
+        for (action, minutes) in [
+            (Action::NextQuarter, 15),
+            (Action::NextHour, 60),
+            (Action::PreviousQuarter, -15),
+            (Action::PreviousHour, -60),
+        ] {
+            if self.keymap.pressed(&ctx, action) {
+                self.auto_play = false;
+                let played = sat_images.iter().skip(first).take(nb_played);
+                let timestamps: Vec<DateTime<Utc>> = played.map(|sat_image| sat_image.timestamp).collect();
+                self.image_index = first + Viewer::step_in_time(&timestamps, self.image_index - first, minutes);
+            }
+        }
+        // Jump to the oldest frame on Home and to the latest observed one on
+        // End
+        if self.keymap.pressed(&ctx, Action::Oldest) {
+            self.auto_play = false;
+            self.image_index = last;
+        }
+        if self.keymap.pressed(&ctx, Action::Latest) {
+            self.auto_play = false;
+            let latest = sat_images.iter().position(|sat_image| !sat_image.forecast).unwrap_or(first);
+            self.image_index = latest.clamp(first, last);
+        }
+        // Scrub through the frames with the wheel and Shift over them, the
+        // wheel alone being left to the panels
+        let scroll = ctx.input(|i| i.modifiers.shift_only().then_some(i.raw_scroll_delta.x + i.raw_scroll_delta.y));
+        if let Some(delta) = scroll.filter(|delta| *delta != 0. && ui.rect_contains_pointer(available_rect)) {
+            self.auto_play = false;
+            Viewer::scrub(&mut self.image_index, &mut self.wheel, delta, (first, last));
+        }
+        // Pause / Unpaause on space
+        if self.keymap.pressed(&ctx, Action::PlayPause) {
+            self.auto_play = !self.auto_play;
+        }
+        // Play backwards, to trace where a system came from
+        if self.keymap.pressed(&ctx, Action::Reverse) {
+            self.reverse = !self.reverse;
+            self.auto_play = true;
+        }
+
+        let sat_image = &sat_images[self.image_index];
+        let position = (last - self.image_index + 1, nb_played);
+        self.announcer.update(self.announcements, sat_image.timestamp, sat_image.forecast, position, self.auto_play);
+        self.announcer.publish(&ctx);
+        let dimensions = sat_image.image.dimensions();
+        // The changes since the previous frame replace the frame
+        let changes = sat_images
+            .get(self.image_index + 1)
+            .filter(|previous| self.difference != Difference::Off && previous.image.dimensions() == dimensions)
+            .map(|previous| SatImage {
+                image: self.difference.apply(&previous.image, &sat_image.image),
+                timestamp: sat_image.timestamp,
+                forecast: sat_image.forecast,
+            });
+        // Made again at each repaint, timed for the performance HUD
+        let texture_time = std::cell::Cell::new(std::time::Duration::ZERO);
+        let timed_texture = |name: &str, sat_image: &SatImage| {
+            let start = std::time::Instant::now();
+            let texture = frame_texture(&ctx, name, sat_image, &self.processing, &self.adjustments, &self.palette);
+            texture_time.set(texture_time.get() + start.elapsed());
+            texture
+        };
+        let texture_handle = timed_texture("my-jpeg-image", changes.as_ref().unwrap_or(sat_image));
+
+        // We center the image ourselves here so we keep its exact position
+        let image_size = texture_handle.size_vec2();
+        let aspect_ratio = image_size.x / image_size.y;
+        let mut target_size = available_rect.size();
+        if target_size.x / target_size.y > aspect_ratio {
+            // The container is wider than the image.
+            target_size.x = target_size.y * aspect_ratio;
+        } else {
+            // The container is taller than the image.
+            target_size.y = target_size.x / aspect_ratio;
+        }
+        let image_rect = egui::Rect::from_center_size(available_rect.center(), target_size);
+        // Allocate the whole available space so the labels below have a stable position.
+        let sense = if self.annotation_tool.is_some() { egui::Sense::click_and_drag() } else { egui::Sense::click() };
+        let response = ui.allocate_rect(available_rect, sense);
+        // The next frame and the motion towards it, when playing in-between
+        // frames. The loop restarts without transition. Motion fields are
+        // only computed forwards, so playing backwards only crossfades.
+        let next = match self.reverse {
+            true => (self.auto_play && self.image_index < last && changes.is_none()).then(|| &sat_images[self.image_index + 1]),
+            false => (self.auto_play && self.image_index > first && changes.is_none()).then(|| &sat_images[self.image_index - 1]),
+        };
+        let field = next
+            .filter(|_| self.interpolate)
+            .and_then(|next| self.motion_fields.lock().unwrap().get(&(sat_image.timestamp, next.timestamp)).cloned());
+        // Repainted at each step of the transitions, else when the frame
+        // changes
+        if let Some(frame_duration) = frame_duration
+            && !minimized
+        {
+            let progress = progress as f64;
+            let transition_step = || match crate::power::is_low_power() {
+                true => ctx.request_repaint_after_secs(1. / crate::power::MAX_FPS),
+                false => ctx.request_repaint(),
+            };
+            match next {
+                Some(_) if field.is_some() => transition_step(),
+                Some(_) if self.crossfade && progress > 1. - CROSSFADE as f64 => transition_step(),
+                Some(_) if self.crossfade => ctx.request_repaint_after_secs(((1. - CROSSFADE as f64 - progress) * frame_duration) as f32),
+                _ => ctx.request_repaint_after_secs(((1. - progress) * frame_duration) as f32),
+            }
+        }
+        // What is done to the frame on screen, for its provenance
+        let mut shown_as = vec![];
+        if sat_image.forecast {
+            shown_as.push("extrapolated from the latest frames");
+        }
+        if changes.is_some() {
+            shown_as.push("changes since the previous frame");
+        }
+        match (next, &field) {
+            (Some(_), Some(_)) => shown_as.push("interpolated towards the next frame"),
+            (Some(_), None) if self.crossfade => shown_as.push("crossfaded into the next frame"),
+            _ => (),
+        }
+        // The frames are see-through over the map
+        let opacity = match (&self.basemap, &self.basemap_texture) {
+            (Some(layer), Some(_)) => layer.config.opacity,
+            _ => 1.,
+        };
+        let tint = |alpha: f32| egui::Color32::from_white_alpha((alpha * opacity * 255.) as u8);
+        if ui.is_rect_visible(image_rect) {
+            if let Some(texture) = &self.basemap_texture {
+                ui.painter().add(egui::Shape::mesh(frame_mesh(texture.id(), image_rect, egui::Color32::WHITE)));
+            }
+            match (next, field) {
+                // Both frames moved towards the in-between time, the next
+                // one fading in
+                (Some(next), Some(field)) => {
+                    let minutes = (next.timestamp - sat_image.timestamp).num_minutes() as f32;
+                    let forward = minutes * progress;
+                    let backward = minutes * (1. - progress);
+                    let current_mesh = warped_mesh(
+                        texture_handle.id(),
+                        image_rect,
+                        dimensions,
+                        |x, y| {
+                            let (dx, dy) = field.at(x, y);
+                            (dx * forward, dy * forward)
+                        },
+                        tint(1.),
+                    );
+                    let next_texture = timed_texture("next-frame", next);
+                    let next_mesh = warped_mesh(
+                        next_texture.id(),
+                        image_rect,
+                        dimensions,
+                        |x, y| {
+                            let (dx, dy) = field.at(x, y);
+                            (-dx * backward, -dy * backward)
+                        },
+                        tint(progress),
+                    );
+                    ui.painter().add(egui::Shape::mesh(current_mesh));
+                    ui.painter().add(egui::Shape::mesh(next_mesh));
+                }
+                // The next frame fades in at the end of the frame
+                (Some(next), None) if self.crossfade && progress > 1. - CROSSFADE => {
+                    let fade = (progress - (1. - CROSSFADE)) / CROSSFADE;
+                    let next_texture = timed_texture("next-frame", next);
+                    ui.painter().add(egui::Shape::mesh(frame_mesh(texture_handle.id(), image_rect, tint(1.))));
+                    ui.painter().add(egui::Shape::mesh(frame_mesh(next_texture.id(), image_rect, tint(fade))));
+                }
+                _ => {
+                    ui.painter().add(egui::Shape::mesh(frame_mesh(texture_handle.id(), image_rect, tint(1.))));
+                }
+            }
+        }
+        let georeference = Georeference::new(self.region.0, self.region.1, dimensions);
+        for overlay in &self.overlays {
+            overlay.paint(ui.painter(), &georeference, image_rect);
+        }
+        self.paint_cloudiness(ui, sat_image, &georeference, image_rect);
+        self.paint_live_targets(ui, &georeference, image_rect);
+        self.paint_lightning(ui, sat_image, &georeference, image_rect);
+        self.paint_wind(ui, sat_image, &georeference, image_rect);
+        self.paint_tile_grid(ui, sat_image, &georeference, image_rect);
+        self.paint_markers(ui, sat_image, &georeference, image_rect);
+        for annotation in &self.annotations {
+            annotation.paint(ui.painter(), &georeference, image_rect);
+        }
+
+        if let Some(position) = self.track.as_ref().and_then(|track| track.position_at(sat_image.timestamp)) {
+            let center = overlay::screen_position(&georeference, image_rect, position);
+            ui.painter().with_clip_rect(image_rect).circle(
+                center,
+                6.,
+                TRACK_COLOR,
+                egui::Stroke::new(2., egui::Color32::WHITE),
+            );
+        }
+
+        if sat_image.forecast {
+            ui.painter().rect_stroke(image_rect, 0., egui::Stroke::new(4., FORECAST_COLOR), egui::StrokeKind::Inside);
+        }
+        self.perf.record(ctx.input(|i| i.unstable_dt), texture_time.get().as_secs_f32());
+        let errors = self.timeline.error_count();
+        let corners = [
+            (&self.hud.top_left, egui::Align2::LEFT_TOP),
+            (&self.hud.top_right, egui::Align2::RIGHT_TOP),
+            (&self.hud.bottom_left, egui::Align2::LEFT_BOTTOM),
+            (&self.hud.bottom_right, egui::Align2::RIGHT_BOTTOM),
+        ];
+        // The kilometers across a pixel in the middle of the frame, and the
+        // longest round length fitting in the scale bar
+        let (width, height) = (dimensions.0 as f64, dimensions.1 as f64);
+        let pixel_km = distance_km(
+            georeference.pixel_to_lonlat(width / 2., height / 2.),
+            georeference.pixel_to_lonlat(width / 2. + 1., height / 2.),
+        );
+        let points_per_km = image_rect.width() as f64 / width / pixel_km;
+        let scale_km = hud::scale_bar_km(hud::SCALE_BAR_MAX as f64 / points_per_km);
+        let mut timestamp_rect = None;
+        for (elements, align) in corners {
+            let mut lines: Vec<HudLine> = vec![];
+            let mut timestamp_line = None;
+            let mut scale_line = None;
+            for element in elements {
+                if *element == HudElement::Timestamp {
+                    timestamp_line = Some(lines.len());
+                }
+                if *element == HudElement::ScaleBar {
+                    scale_line = Some(lines.len());
+                    lines.push(HudLine { text: format!("{} KM", scale_km), font: vcr_font(), color: egui::Color32::WHITE });
+                }
+                lines.extend(self.hud_lines(*element, &sat_images, errors));
+            }
+            // Loading problems are always shown, above the bottom-left labels
+            if align == egui::Align2::LEFT_BOTTOM {
+                if downloading && downloading_is_visible {
+                    let text = downloading_label(self.timeline.progress());
+                    lines.push(HudLine { text, font: vcr_font(), color: egui::Color32::WHITE });
+                }
+                if let Some(last_error) = &last_error {
+                    lines.push(HudLine {
+                        text: last_error.clone(),
+                        font: egui::FontId::proportional(14.0),
+                        color: ERROR_COLOR,
+                    });
+                }
+            }
+            if align == egui::Align2::RIGHT_TOP && self.perf_visible {
+                lines.extend(self.perf_lines(&sat_images));
+            }
+            let painted = hud::paint_corner(ui.painter(), available_rect, align, self.hud.margin, &lines);
+            if let Some(rect) = timestamp_line.and_then(|index| painted.get(index)) {
+                timestamp_rect = Some(*rect);
+            }
+            if let Some(rect) = scale_line.and_then(|index| painted.get(index)) {
+                hud::paint_scale_bar(ui.painter(), *rect, align, (scale_km * points_per_km) as f32);
+            }
+        }
+        // Paused on an older frame than the latest one, not to be taken for
+        // the current weather
+        let top = available_rect.center_top() + egui::vec2(0., self.hud.margin);
+        if let Some(latest) = sat_images.latest().filter(|latest| !self.auto_play && latest.timestamp > sat_image.timestamp) {
+            let minutes = (latest.timestamp - sat_image.timestamp).num_minutes();
+            let text = format!("{}MIN BEFORE THE LATEST", minutes);
+            ui.painter().text(top, egui::Align2::CENTER_TOP, text, vcr_font(), BEHIND_COLOR);
+        } else if self.auto_play && self.reverse {
+            ui.painter().text(top, egui::Align2::CENTER_TOP, "REVERSE", vcr_font(), BEHIND_COLOR);
+        }
+        // The playback rate for a moment after it changes
+        if let Some(changed) = self.speed_changed {
+            if time - changed < SPEED_SHOWN {
+                let text = match self.speed >= 1. {
+                    true => format!("{}x", self.speed),
+                    false => format!("1/{}x", 1. / self.speed),
+                };
+                let font = egui::FontId::new(48.0, egui::FontFamily::Name("vcr".into()));
+                let painter = ui.painter();
+                let galley = painter.layout_no_wrap(text, font, egui::Color32::WHITE);
+                let rect = egui::Align2::CENTER_CENTER.anchor_size(image_rect.center(), galley.size());
+                painter.rect_filled(rect.expand(8.), 6., egui::Color32::from_black_alpha(160));
+                painter.galley(rect.min, galley, egui::Color32::WHITE);
+                ctx.request_repaint_after_secs((SPEED_SHOWN - (time - changed)) as f32);
+            } else {
+                self.speed_changed = None;
+            }
+        }
+        if let Some(rect) = timestamp_rect
+            && response.hover_pos().is_some_and(|pointer| rect.contains(pointer))
+        {
+            let provenance = self.timeline.provenance(sat_image.timestamp);
+            show_provenance(ui, &response, provenance, &shown_as, self.time_zone);
+        }
+        // The clock and the age of the data change while paused
+        if self.hud.shows(HudElement::Clock) || self.hud.shows(HudElement::DataAge) || self.perf_visible {
+            ctx.request_repaint_after(std::time::Duration::from_secs(1));
+        }
+        if downloading {
+            ctx.request_repaint_after_secs((cycle_duration / 2.) as f32);
+        }
+
+        // Pinpoint icon
+        let point_of_interest = convert_gps_to_pixels(self.region.0, &image_rect, PARIS);
+        let icon_size = self.pinpoint_icon.size_vec2();
+        // The tip of the pin is at the bottom center of the icon
+        let icon_rect = egui::Rect::from_min_size(
+            egui::pos2(point_of_interest.0 - icon_size.x / 2., point_of_interest.1 - icon_size.y),
+            icon_size,
+        );
+        ui.painter().image(
+            self.pinpoint_icon.id(),
+            icon_rect,
+            egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0)),
+            egui::Color32::WHITE,
+        );
+
+        if ctx.input(|i| i.modifiers.alt) {
+            show_inspector(ui, &response, sat_image, &georeference, image_rect);
+        }
+
+        drop(sat_images);
+        if self.stations_visible {
+            self.show_stations(ui, &response, &georeference, image_rect);
+        }
+        if self.region_corners.is_some() {
+            self.pick_region(ui, &response, &georeference, image_rect);
+        }
+        if self.measure_points.is_some() {
+            self.measure(ui, &response, &georeference, image_rect);
+        }
+        self.annotate(ui, &response, &georeference, image_rect);
+        self.show_context_menu(&response, &georeference, image_rect);
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    /// Timestamps from the most recent, `minutes` apart.
+    fn timestamps(count: usize, minutes: i64) -> Vec<DateTime<Utc>> {
+        let latest = Utc.with_ymd_and_hms(2025, 1, 1, 12, 0, 0).unwrap();
+        (0..count).map(|i| latest - chrono::Duration::minutes(i as i64 * minutes)).collect()
+    }
+
+    #[test]
+    fn step_in_time_of_thinned_frames() {
+        // 24 h of frames thinned to 15 min
+        let thinned = timestamps(96, 15);
+        assert_eq!(Viewer::step_in_time(&thinned, 10, 15), 9);
+        assert_eq!(Viewer::step_in_time(&thinned, 10, 60), 6);
+        assert_eq!(Viewer::step_in_time(&thinned, 10, -15), 11);
+        assert_eq!(Viewer::step_in_time(&thinned, 10, -60), 14);
+        // Frames further apart than the step still move by one
+        let sparse = timestamps(10, 30);
+        assert_eq!(Viewer::step_in_time(&sparse, 5, 15), 4);
+        assert_eq!(Viewer::step_in_time(&sparse, 5, -15), 6);
+    }
+
+    #[test]
+    fn step_in_time_at_the_ends() {
+        let frames = timestamps(12, 5);
+        // Stops on the end first, then goes round
+        assert_eq!(Viewer::step_in_time(&frames, 2, 60), 0);
+        assert_eq!(Viewer::step_in_time(&frames, 0, 60), 11);
+        assert_eq!(Viewer::step_in_time(&frames, 9, -60), 11);
+        assert_eq!(Viewer::step_in_time(&frames, 11, -60), 0);
+    }
+}
@@ -0,0 +1,146 @@
+//! Setting the desktop background, for `nuage --wallpaper`.
+
+use std::path::Path;
+use std::process::{Child, Command};
+
+use crate::error::{NuageError, Result};
+
+/// The program used to set the desktop background.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Backend {
+    /// `feh --bg-max`, for X11 window managers
+    Feh,
+    /// `swaybg`, for wlroots Wayland compositors. It runs until killed.
+    Swaybg,
+    /// `gsettings`, for GNOME
+    Gnome,
+    /// `SystemParametersInfoW`
+    Windows,
+}
+
+impl Backend {
+    /// The backend matching the running desktop.
+    pub fn detect() -> Backend {
+        if cfg!(windows) {
+            return Backend::Windows;
+        }
+        let desktop = std::env::var("XDG_CURRENT_DESKTOP").unwrap_or_default().to_lowercase();
+        if desktop.contains("gnome") || desktop.contains("unity") {
+            Backend::Gnome
+        } else if std::env::var("WAYLAND_DISPLAY").is_ok() {
+            Backend::Swaybg
+        } else {
+            Backend::Feh
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Backend> {
+        match name {
+            "feh" => Some(Backend::Feh),
+            "swaybg" => Some(Backend::Swaybg),
+            "gnome" => Some(Backend::Gnome),
+            "windows" => Some(Backend::Windows),
+            _ => None,
+        }
+    }
+}
+
+fn run(command: &mut Command) -> Result<()> {
+    let status = command
+        .status()
+        .map_err(|e| NuageError::Config(format!("could not run {:?}: {}", command.get_program(), e)))?;
+    if !status.success() {
+        return Err(NuageError::Config(format!("{:?} failed: {}", command.get_program(), status)));
+    }
+    Ok(())
+}
+
+#[cfg(windows)]
+fn set_windows_wallpaper(path: &Path) -> Result<()> {
+    use std::os::windows::ffi::OsStrExt;
+
+    const SPI_SETDESKWALLPAPER: u32 = 0x0014;
+    const SPIF_UPDATEINIFILE: u32 = 0x01;
+    const SPIF_SENDCHANGE: u32 = 0x02;
+    #[link(name = "user32")]
+    unsafe extern "system" {
+        fn SystemParametersInfoW(action: u32, param: u32, pv: *mut std::ffi::c_void, win_ini: u32) -> i32;
+    }
+    let mut wide: Vec<u16> = path.as_os_str().encode_wide().chain(Some(0)).collect();
+    // SAFETY: `wide` is a nul-terminated UTF-16 path living across the call.
+    let ok = unsafe {
+        SystemParametersInfoW(
+            SPI_SETDESKWALLPAPER,
+            0,
+            wide.as_mut_ptr().cast(),
+            SPIF_UPDATEINIFILE | SPIF_SENDCHANGE,
+        )
+    };
+    if ok == 0 {
+        return Err(NuageError::Config(format!("could not set the wallpaper: {}", std::io::Error::last_os_error())));
+    }
+    Ok(())
+}
+
+#[cfg(not(windows))]
+fn set_windows_wallpaper(_path: &Path) -> Result<()> {
+    Err(NuageError::Config("the windows backend only works on Windows".into()))
+}
+
+/// Sets images as the desktop background.
+pub struct Wallpaper {
+    backend: Backend,
+    // The running swaybg, replaced on each update
+    swaybg: Option<Child>,
+}
+
+impl Wallpaper {
+    pub fn new(backend: Backend) -> Self {
+        Wallpaper { backend, swaybg: None }
+    }
+
+    pub fn backend(&self) -> Backend {
+        self.backend
+    }
+
+    /// Set the image at `path` as the desktop background. Desktops may not
+    /// reload an image set twice under the same path.
+    pub fn set(&mut self, path: &Path) -> Result<()> {
+        let path = std::path::absolute(path).map_err(NuageError::cache_io(path))?;
+        match self.backend {
+            Backend::Feh => run(Command::new("feh").arg("--bg-max").arg(&path)),
+            Backend::Swaybg => {
+                let swaybg = Command::new("swaybg")
+                    .arg("--image")
+                    .arg(&path)
+                    .args(["--mode", "fit"])
+                    .spawn()
+                    .map_err(|e| NuageError::Config(format!("could not run swaybg: {}", e)))?;
+                // Kill the previous one once the new one is started to avoid
+                // flashing the default background
+                if let Some(mut previous) = self.swaybg.replace(swaybg) {
+                    let _ = previous.kill();
+                    let _ = previous.wait();
+                }
+                Ok(())
+            }
+            Backend::Gnome => {
+                let uri = format!("file://{}", path.display());
+                for key in ["picture-uri", "picture-uri-dark"] {
+                    run(Command::new("gsettings").args(["set", "org.gnome.desktop.background", key, &uri]))?;
+                }
+                run(Command::new("gsettings").args(["set", "org.gnome.desktop.background", "picture-options", "scaled"]))
+            }
+            Backend::Windows => set_windows_wallpaper(&path),
+        }
+    }
+}
+
+impl Drop for Wallpaper {
+    fn drop(&mut self) {
+        if let Some(mut swaybg) = self.swaybg.take() {
+            let _ = swaybg.kill();
+            let _ = swaybg.wait();
+        }
+    }
+}
@@ -0,0 +1,131 @@
+//! A WebSocket client, enough to subscribe to a feed and read its text
+//! messages, over TLS for `wss://` URLs. The protocol is `tungstenite`'s, the
+//! connection ours to go through the same TLS setup as the HTTP requests.
+
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::sync::Arc;
+use tungstenite::Message;
+use tungstenite::protocol::WebSocketConfig;
+
+use crate::error::{NuageError, Result};
+
+/// How long the server may stay silent before the connection is taken for
+/// dead.
+const READ_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// The largest message and frame accepted, far more than a strike takes.
+const MAX_MESSAGE: usize = 1 << 20;
+
+fn websocket_error(e: impl std::fmt::Display) -> NuageError {
+    NuageError::WebSocket(e.to_string())
+}
+
+enum Stream {
+    Plain(TcpStream),
+    Tls(Box<rustls::StreamOwned<rustls::ClientConnection, TcpStream>>),
+}
+
+impl Read for Stream {
+    fn read(&mut self, buffer: &mut [u8]) -> std::io::Result<usize> {
+        match self {
+            Stream::Plain(stream) => stream.read(buffer),
+            Stream::Tls(stream) => stream.read(buffer),
+        }
+    }
+}
+
+impl Write for Stream {
+    fn write(&mut self, buffer: &[u8]) -> std::io::Result<usize> {
+        match self {
+            Stream::Plain(stream) => stream.write(buffer),
+            Stream::Tls(stream) => stream.write(buffer),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            Stream::Plain(stream) => stream.flush(),
+            Stream::Tls(stream) => stream.flush(),
+        }
+    }
+}
+
+/// A connection to a WebSocket server.
+pub struct WebSocket {
+    socket: tungstenite::WebSocket<Stream>,
+}
+
+impl WebSocket {
+    /// Connect to `url`, e.g. `wss://example.org/feed`.
+    pub fn connect(url: &str) -> Result<Self> {
+        let (tls, rest) = match url.split_once("://") {
+            Some(("wss", rest)) => (true, rest),
+            Some(("ws", rest)) => (false, rest),
+            _ => return Err(NuageError::WebSocket(format!("{}: expected a ws:// or wss:// URL", url))),
+        };
+        let authority = rest.split('/').next().unwrap_or(rest);
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, port)) => (host, port.parse().map_err(|_| NuageError::WebSocket(format!("{}: invalid port", url)))?),
+            None => (authority, if tls { 443 } else { 80 }),
+        };
+        let tcp = TcpStream::connect((host, port)).map_err(websocket_error)?;
+        tcp.set_read_timeout(Some(READ_TIMEOUT)).map_err(websocket_error)?;
+        let stream = if tls {
+            let roots = rustls::RootCertStore { roots: webpki_roots::TLS_SERVER_ROOTS.into() };
+            let config = rustls::ClientConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+                .with_safe_default_protocol_versions()
+                .map_err(websocket_error)?
+                .with_root_certificates(roots)
+                .with_no_client_auth();
+            let name = rustls::pki_types::ServerName::try_from(host.to_string()).map_err(websocket_error)?;
+            let connection = rustls::ClientConnection::new(Arc::new(config), name).map_err(websocket_error)?;
+            Stream::Tls(Box::new(rustls::StreamOwned::new(connection, tcp)))
+        } else {
+            Stream::Plain(tcp)
+        };
+        let config = WebSocketConfig::default().max_message_size(Some(MAX_MESSAGE)).max_frame_size(Some(MAX_MESSAGE));
+        let (socket, _) = tungstenite::client::client_with_config(url, stream, Some(config)).map_err(websocket_error)?;
+        Ok(WebSocket { socket })
+    }
+
+    pub fn send_text(&mut self, text: &str) -> Result<()> {
+        self.socket.send(Message::text(text)).map_err(websocket_error)
+    }
+
+    /// The next text or binary message, as text. Blocking, the pings
+    /// answered meanwhile.
+    pub fn read_text(&mut self) -> Result<String> {
+        loop {
+            match self.socket.read().map_err(websocket_error)? {
+                Message::Text(text) => return Ok(text.to_string()),
+                Message::Binary(bytes) => return Ok(String::from_utf8_lossy(&bytes).into_owned()),
+                Message::Close(_) => return Err(NuageError::WebSocket("closed by the server".into())),
+                _ => (),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn messages_and_their_size() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("ws://{}/feed", listener.local_addr().unwrap());
+        let server = std::thread::spawn(move || {
+            let mut server = tungstenite::accept(listener.accept().unwrap().0).unwrap();
+            assert_eq!(server.read().unwrap(), Message::text("{\"a\":111}"));
+            server.send(Message::text("strike")).unwrap();
+            // The client may hang up before all of it is sent
+            server.send(Message::binary(vec![b'x'; MAX_MESSAGE + 1])).ok();
+        });
+        let mut socket = WebSocket::connect(&url).unwrap();
+        socket.send_text("{\"a\":111}").unwrap();
+        assert_eq!(socket.read_text().unwrap(), "strike");
+        assert!(socket.read_text().is_err());
+        server.join().unwrap();
+    }
+}
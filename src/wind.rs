@@ -0,0 +1,202 @@
+//! The wind at 10 m over the region, on a coarse grid of points asked to the
+//! Open-Meteo API (https://open-meteo.com) in a single request.
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+
+use crate::error::{NuageError, Result};
+use crate::geo::LonLat;
+use crate::json::{self, Json};
+use crate::projection::Bounds;
+
+/// How often the wind is downloaded again.
+const REFRESH: std::time::Duration = std::time::Duration::from_secs(3600);
+
+/// The wind layer and how it is drawn.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct WindConfig {
+    /// Points of the grid across and down the region
+    pub grid: (u32, u32),
+    /// Barb color, (red, green, blue)
+    pub color: (u8, u8, u8),
+    /// Show the wind at startup
+    pub visible: bool,
+}
+
+impl Default for WindConfig {
+    fn default() -> Self {
+        WindConfig {
+            grid: (8, 6),
+            color: (255, 255, 255),
+            visible: false,
+        }
+    }
+}
+
+/// The hourly wind at the points of a grid.
+#[derive(Clone, Debug, Default)]
+pub struct WindField {
+    pub points: Vec<LonLat>,
+    pub times: Vec<DateTime<Utc>>,
+    /// (eastward, northward) components in knots, by point then by time
+    pub vectors: Vec<Vec<Option<(f64, f64)>>>,
+}
+
+impl WindField {
+    /// The wind at each point at `time`, interpolated between the hours
+    /// around it: (speed in knots, direction it blows from in degrees).
+    /// Empty when `time` is not covered.
+    pub fn at(&self, time: DateTime<Utc>) -> Vec<(LonLat, f64, f64)> {
+        let Some(after) = self.times.iter().position(|hour| *hour >= time) else {
+            return vec![];
+        };
+        let before = match after {
+            _ if self.times[after] == time => after,
+            0 => return vec![],
+            _ => after - 1,
+        };
+        let span = (self.times[after] - self.times[before]).num_seconds().max(1) as f64;
+        let weight = (time - self.times[before]).num_seconds() as f64 / span;
+        self.points
+            .iter()
+            .zip(&self.vectors)
+            .filter_map(|(point, vectors)| {
+                let ((u1, v1), (u2, v2)) = ((*vectors.get(before)?)?, (*vectors.get(after)?)?);
+                let (u, v) = (u1 + (u2 - u1) * weight, v1 + (v2 - v1) * weight);
+                let direction = (-u).atan2(-v).to_degrees().rem_euclid(360.);
+                Some((*point, u.hypot(v), direction))
+            })
+            .collect()
+    }
+}
+
+/// `columns` × `rows` points evenly spread inside `bounds`, row by row from
+/// the north-west.
+pub fn grid(bounds: &Bounds, (columns, rows): (u32, u32)) -> Vec<LonLat> {
+    let (columns, rows) = (columns.max(1), rows.max(1));
+    let mut points = vec![];
+    for row in 0..rows {
+        for column in 0..columns {
+            let lon = bounds.west + (bounds.east - bounds.west) * (column as f64 + 0.5) / columns as f64;
+            let lat = bounds.north - (bounds.north - bounds.south) * (row as f64 + 0.5) / rows as f64;
+            points.push((lon, lat));
+        }
+    }
+    points
+}
+
+/// Parse an Open-Meteo response for `points`, asked in UTC with the wind in
+/// knots: a list of locations, or a single one for a single point.
+pub fn parse(text: &str, points: &[LonLat]) -> Result<WindField> {
+    let response = json::parse(text).map_err(|e| NuageError::Config(format!("wind: {}", e)))?;
+    if let Some(reason) = response.get("reason").and_then(Json::as_str) {
+        return Err(NuageError::Config(format!("wind: {}", reason)));
+    }
+    let locations = match response.as_array() {
+        Some(locations) => locations,
+        None => std::slice::from_ref(&response),
+    };
+    if locations.len() != points.len() {
+        return Err(NuageError::Config(format!("wind: {} locations for {} points", locations.len(), points.len())));
+    }
+    let mut field = WindField { points: points.to_vec(), ..Default::default() };
+    for location in locations {
+        let hourly = location
+            .get("hourly")
+            .ok_or_else(|| NuageError::Config("wind: no hourly values".into()))?;
+        let values = |key: &str| -> Vec<Option<f64>> {
+            hourly
+                .get(key)
+                .and_then(Json::as_array)
+                .map(|values| values.iter().map(Json::as_f64).collect())
+                .unwrap_or_default()
+        };
+        if field.times.is_empty() {
+            field.times = hourly
+                .get("time")
+                .and_then(Json::as_array)
+                .ok_or_else(|| NuageError::Config("wind: no times".into()))?
+                .iter()
+                .filter_map(Json::as_str)
+                .filter_map(|time| NaiveDateTime::parse_from_str(time, "%Y-%m-%dT%H:%M").ok())
+                .map(|time| time.and_utc())
+                .collect();
+        }
+        let vectors = values("wind_speed_10m")
+            .into_iter()
+            .zip(values("wind_direction_10m"))
+            .map(|(speed, direction)| {
+                // The direction is the one the wind blows from
+                let (speed, direction) = (speed?, direction?.to_radians());
+                Some((-speed * direction.sin(), -speed * direction.cos()))
+            })
+            .collect();
+        field.vectors.push(vectors);
+    }
+    Ok(field)
+}
+
+/// The wind at `points` from yesterday to tomorrow. Blocking.
+pub fn fetch(points: &[LonLat]) -> Result<WindField> {
+    let join = |coordinate: fn(&LonLat) -> f64| {
+        let coordinates: Vec<String> = points.iter().map(|point| format!("{:.3}", coordinate(point))).collect();
+        coordinates.join(",")
+    };
+    let url = format!(
+        concat!(
+            "https://api.open-meteo.com/v1/forecast?latitude={}&longitude={}&hourly=wind_speed_10m,wind_direction_10m",
+            "&wind_speed_unit=kn&timezone=UTC&past_days=1&forecast_days=1",
+        ),
+        join(|point| point.1),
+        join(|point| point.0),
+    );
+    log::info!("fetching {}", url);
+    let text = ureq::get(url).call()?.body_mut().read_to_string()?;
+    parse(&text, points)
+}
+
+/// The wind over a region, downloaded in a separate thread and again every
+/// hour while the layer is kept.
+#[derive(Clone)]
+pub struct WindLayer {
+    pub config: WindConfig,
+    pub field: Arc<Mutex<Option<WindField>>>,
+    pub last_error: Arc<Mutex<Option<String>>>,
+}
+
+impl WindLayer {
+    /// Start downloading the wind on the grid of `config` over `bounds`.
+    /// `ctx`, if any, is asked to repaint when it arrives.
+    pub fn start(config: WindConfig, bounds: Bounds, ctx: Option<egui::Context>) -> Self {
+        let layer = WindLayer {
+            config,
+            field: Arc::new(Mutex::new(None)),
+            last_error: Arc::new(Mutex::new(None)),
+        };
+        let downloader = layer.clone();
+        std::thread::spawn(move || {
+            let points = grid(&bounds, downloader.config.grid);
+            // Stop once the layer is dropped
+            while Arc::strong_count(&downloader.field) > 1 {
+                match fetch(&points) {
+                    Ok(field) => {
+                        *downloader.field.lock().unwrap() = Some(field);
+                        *downloader.last_error.lock().unwrap() = None;
+                    }
+                    Err(e) => {
+                        log::warn!("wind: {}", e);
+                        *downloader.last_error.lock().unwrap() = Some(e.to_string());
+                    }
+                }
+                if let Some(ctx) = &ctx {
+                    ctx.request_repaint();
+                }
+                std::thread::sleep(crate::metered::poll_period(REFRESH));
+            }
+        });
+        layer
+    }
+}
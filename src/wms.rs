@@ -0,0 +1,383 @@
+//! Frames of an OGC Web Map Service with a time dimension, as many national
+//! weather services publish their satellite and radar layers: the times of
+//! the layer are read from the capabilities of the service, and each frame is
+//! a map of the tile window asked at one of them.
+
+use chrono::{DateTime, Duration, Utc};
+use quick_xml::events::{BytesStart, Event};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+
+use crate::error::{NuageError, Result};
+use crate::projection::{lonlat_to_mercator, tile_to_lonlat};
+use crate::provider::{INTERVAL, Provenance, Provider, TILE_SIZE, TILES, Tiles, ZOOM, screen_size};
+use crate::timeline::HISTORY_HOURS;
+
+/// A layer of a WMS and how its maps are asked for.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct WmsConfig {
+    /// The address of the service, without the request parameters
+    pub url: String,
+    /// The name of the layer, as in the capabilities
+    pub layer: String,
+    /// The style of the layer, that of the service by default
+    pub style: String,
+    /// The image format asked for
+    pub format: String,
+    /// The tile window shown, (top-left (x, y), bottom-right (x, y))
+    pub tiles: Tiles,
+    pub zoom: u16,
+}
+
+impl Default for WmsConfig {
+    fn default() -> Self {
+        WmsConfig {
+            url: String::new(),
+            layer: String::new(),
+            style: String::new(),
+            format: "image/png".into(),
+            tiles: TILES,
+            zoom: ZOOM,
+        }
+    }
+}
+
+fn invalid(message: impl std::fmt::Display) -> NuageError {
+    NuageError::Config(format!("invalid WMS capabilities: {}", message))
+}
+
+/// An ISO 8601 duration as found in time dimensions, e.g. `PT5M`, `PT1H` or
+/// `P1D`. Months and years, of no fixed length, are not supported, nor are
+/// periods under a second.
+pub fn parse_period(period: &str) -> Option<Duration> {
+    let period = period.trim().strip_prefix('P')?;
+    let (days, time) = period.split_once('T').unwrap_or((period, ""));
+    let mut total = Duration::zero();
+    for (part, units) in [(days, &[('W', 7 * 86400), ('D', 86400)][..]), (time, &[('H', 3600), ('M', 60), ('S', 1)][..])] {
+        let mut number = String::new();
+        for c in part.chars() {
+            match units.iter().find(|(unit, _)| *unit == c) {
+                Some((_, seconds)) => {
+                    total += Duration::milliseconds((number.parse::<f64>().ok()? * *seconds as f64 * 1000.) as i64)
+                }
+                None if c.is_ascii_digit() || c == '.' => {
+                    number.push(c);
+                    continue;
+                }
+                None => return None,
+            }
+            number.clear();
+        }
+        if !number.is_empty() {
+            return None;
+        }
+    }
+    (total >= Duration::seconds(1)).then_some(total)
+}
+
+/// A time of a time dimension: RFC 3339, in UTC without the seconds or the
+/// minutes (`2025-01-01T12:30Z`, `2025-01-01T12Z`), a date, or `now` for
+/// `PRESENT` and `current`.
+fn parse_time(time: &str, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    let time = time.trim();
+    if time.eq_ignore_ascii_case("present") || time.eq_ignore_ascii_case("current") {
+        return Some(now);
+    }
+    if let Ok(time) = DateTime::parse_from_rfc3339(time) {
+        return Some(time.with_timezone(&Utc));
+    }
+    let utc = time.strip_suffix('Z').unwrap_or(time);
+    [utc.to_string(), format!("{}:00", utc), format!("{}:00:00", utc)]
+        .iter()
+        .find_map(|time| chrono::NaiveDateTime::parse_from_str(time, "%Y-%m-%dT%H:%M:%S%.f").ok())
+        .or_else(|| chrono::NaiveDate::parse_from_str(utc, "%Y-%m-%d").ok()?.and_hms_opt(0, 0, 0))
+        .map(|time| time.and_utc())
+}
+
+/// The times of a time dimension up to `now`, from the most recent to the
+/// least, and the period between them if given. The dimension lists times
+/// and `start/end/period` intervals separated by commas; the times of the
+/// intervals are only those of the `hours` before the most recent one.
+/// Values not understood are an error rather than times missing.
+pub fn dimension_times(extent: &str, now: DateTime<Utc>, hours: u32) -> Result<(Vec<DateTime<Utc>>, Option<Duration>)> {
+    let mut times = vec![];
+    let mut period = None;
+    for value in extent.split(',').filter(|value| !value.trim().is_empty()) {
+        match value.split('/').collect::<Vec<_>>()[..] {
+            [start, end, step] => {
+                let (Some(start), Some(end)) = (parse_time(start, now), parse_time(end, now)) else {
+                    return Err(invalid(format!("interval {}", value.trim())));
+                };
+                let Some(step) = parse_period(step) else {
+                    return Err(invalid(format!("period of {}, at least a second is needed", value.trim())));
+                };
+                let steps = (end.min(now) - start).num_seconds() / step.num_seconds();
+                let oldest = end.min(now) - Duration::hours(hours as i64);
+                let mut time = start + Duration::seconds(steps * step.num_seconds());
+                while time >= start && time >= oldest {
+                    times.push(time);
+                    time -= step;
+                }
+                period = Some(step);
+            }
+            [time] => match parse_time(time, now) {
+                Some(time) => times.extend(Some(time).filter(|time| *time <= now)),
+                None => return Err(invalid(format!("time {}", time.trim()))),
+            },
+            _ => return Err(invalid(format!("time dimension value {}", value.trim()))),
+        }
+    }
+    times.sort_by_key(|time| std::cmp::Reverse(*time));
+    times.dedup();
+    if let Some(newest) = times.first() {
+        let oldest = *newest - Duration::hours(hours as i64);
+        times.retain(|time| *time > oldest);
+    }
+    Ok((times, period))
+}
+
+/// Whether `element` is a `Dimension` or an `Extent` (WMS 1.1.1) of the
+/// time.
+fn is_time_dimension(element: &BytesStart) -> bool {
+    matches!(element.local_name().as_ref(), b"Dimension" | b"Extent")
+        && element
+            .try_get_attribute("name")
+            .ok()
+            .flatten()
+            .is_some_and(|name| name.value.eq_ignore_ascii_case(b"time"))
+}
+
+/// The time dimension of `layer` in a capabilities document, inherited from
+/// the enclosing layers if it has none. `None` when the layer is not found,
+/// an empty string when it has no time dimension.
+pub fn layer_time_extent(capabilities: &str, layer: &str) -> Result<Option<String>> {
+    let mut reader = quick_xml::Reader::from_str(capabilities);
+    // The name and time dimension of each enclosing layer
+    let mut layers: Vec<(Option<String>, String)> = vec![];
+    let mut path: Vec<Vec<u8>> = vec![];
+    let mut in_time = false;
+    loop {
+        match reader.read_event().map_err(invalid)? {
+            Event::Start(start) => {
+                if start.local_name().as_ref() == b"Layer" {
+                    let inherited = layers.last().map(|(_, time)| time.clone()).unwrap_or_default();
+                    layers.push((None, inherited));
+                }
+                in_time = is_time_dimension(&start);
+                if in_time && let Some((_, time)) = layers.last_mut() {
+                    time.clear();
+                }
+                path.push(start.local_name().as_ref().to_vec());
+            }
+            Event::Text(text) => {
+                let text = text.unescape().map_err(invalid)?;
+                let parent = path.len().checked_sub(2).map(|index| path[index].as_slice());
+                match (path.last().map(Vec::as_slice), parent, layers.last_mut()) {
+                    (Some(b"Name"), Some(b"Layer"), Some((name, _))) => *name = Some(text.trim().to_string()),
+                    (_, _, Some((_, time))) if in_time => time.push_str(text.trim()),
+                    _ => (),
+                }
+            }
+            Event::End(end) => {
+                if end.local_name().as_ref() == b"Layer"
+                    && let Some((name, time)) = layers.pop()
+                    && name.as_deref() == Some(layer)
+                {
+                    return Ok(Some(time));
+                }
+                in_time = false;
+                path.pop();
+            }
+            Event::Eof => return Ok(None),
+            _ => (),
+        }
+    }
+}
+
+/// The maps of a layer of a WMS, at the times it has, not cached.
+#[derive(Clone, Debug)]
+pub struct Wms {
+    pub config: WmsConfig,
+    /// Hours of frames shown, `HISTORY_HOURS` by default
+    pub hours: u32,
+    /// Minutes between two frames, as last read from the capabilities
+    interval: Arc<Mutex<u32>>,
+}
+
+impl Wms {
+    pub fn new(config: WmsConfig) -> Self {
+        Wms { config, hours: HISTORY_HOURS, interval: Arc::new(Mutex::new(INTERVAL)) }
+    }
+
+    /// `url` with `parameters` added to its query.
+    fn request(&self, parameters: &[(&str, String)]) -> String {
+        let separator = match self.config.url.contains('?') {
+            true if self.config.url.ends_with(['?', '&']) => "",
+            true => "&",
+            false => "?",
+        };
+        let query: Vec<String> = parameters.iter().map(|(key, value)| format!("{}={}", key, value)).collect();
+        format!("{}{}{}", self.config.url, separator, query.join("&"))
+    }
+
+    /// The GetMap request of the window at `timestamp`, in Web Mercator.
+    fn map_url(&self, timestamp: DateTime<Utc>) -> String {
+        let ((x1, y1), (x2, y2)) = self.config.tiles;
+        let (west, north) = lonlat_to_mercator_corner(x1 as f64, y1 as f64, self.config.zoom);
+        let (east, south) = lonlat_to_mercator_corner(x2 as f64 + 1., y2 as f64 + 1., self.config.zoom);
+        let (width, height) = screen_size(((x2 - x1 + 1) as u32 * TILE_SIZE, (y2 - y1 + 1) as u32 * TILE_SIZE));
+        self.request(&[
+            ("SERVICE", "WMS".into()),
+            ("VERSION", "1.3.0".into()),
+            ("REQUEST", "GetMap".into()),
+            ("LAYERS", self.config.layer.clone()),
+            ("STYLES", self.config.style.clone()),
+            ("CRS", "EPSG:3857".into()),
+            ("BBOX", format!("{:.2},{:.2},{:.2},{:.2}", west, south, east, north)),
+            ("WIDTH", width.to_string()),
+            ("HEIGHT", height.to_string()),
+            ("FORMAT", self.config.format.replace('/', "%2F")),
+            ("TIME", timestamp.format("%Y-%m-%dT%H:%M:%SZ").to_string()),
+        ])
+    }
+}
+
+/// Web Mercator coordinates of the top-left corner of the slippy tile (x, y).
+fn lonlat_to_mercator_corner(x: f64, y: f64, zoom: u16) -> (f64, f64) {
+    let (lon, lat) = tile_to_lonlat(x, y, zoom);
+    lonlat_to_mercator(lon, lat)
+}
+
+impl Provider for Wms {
+    fn name(&self) -> &str {
+        &self.config.layer
+    }
+
+    /// The times of the layer in the capabilities, the availability delay
+    /// not being needed as only the published ones are listed.
+    fn timepoints(&self, now: DateTime<Utc>) -> Result<Vec<DateTime<Utc>>> {
+        let url = self.request(&[("SERVICE", "WMS".into()), ("REQUEST", "GetCapabilities".into())]);
+        log::info!("fetching {}", url);
+        let text = ureq::get(&url).call()?.body_mut().with_config().limit(20 * 1024 * 1024).read_to_string()?;
+        let extent = layer_time_extent(&text, &self.config.layer)?
+            .ok_or_else(|| NuageError::Config(format!("{}: no layer {}", self.config.url, self.config.layer)))?;
+        if extent.is_empty() {
+            return Err(NuageError::Config(format!("{}: layer {} has no time dimension", self.config.url, self.config.layer)));
+        }
+        let (times, period) = dimension_times(&extent, now, self.hours)?;
+        let period = period.or_else(|| times.get(..2).map(|newest| newest[0] - newest[1]));
+        if let Some(period) = period {
+            *self.interval.lock().unwrap() = period.num_minutes().max(1) as u32;
+        }
+        Ok(times)
+    }
+
+    /// The map of the window at `timestamp`. Services answer errors with
+    /// an XML document rather than an image.
+    fn frame(&self, timestamp: DateTime<Utc>) -> Result<image::RgbImage> {
+        let url = self.map_url(timestamp);
+        log::info!("fetching {}", url);
+        let mut res = match ureq::get(&url).call() {
+            Err(ureq::Error::StatusCode(404 | 410)) => return Err(NuageError::Missing(url)),
+            res => res?,
+        };
+        let image_bytes = res.body_mut().with_config().limit(20 * 1024 * 1024).read_to_vec()?;
+        if image_bytes.trim_ascii_start().starts_with(b"<") {
+            let message = String::from_utf8_lossy(&image_bytes);
+            return Err(NuageError::Config(format!("{}: {}", self.config.url, message.trim())));
+        }
+        Ok(image::load_from_memory(&image_bytes)?.to_rgb8())
+    }
+
+    fn region(&self) -> (Tiles, u16) {
+        (self.config.tiles, self.config.zoom)
+    }
+
+    fn interval(&self) -> u32 {
+        *self.interval.lock().unwrap()
+    }
+
+    fn provenance(&self, timestamp: DateTime<Utc>) -> Provenance {
+        Provenance {
+            provider: format!("WMS {}", self.config.url),
+            url: Some(self.map_url(timestamp)),
+            ..Provenance::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 1, 1, hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn periods() {
+        assert_eq!(parse_period("PT5M"), Some(Duration::minutes(5)));
+        assert_eq!(parse_period("PT1H30M"), Some(Duration::minutes(90)));
+        assert_eq!(parse_period("P1DT12H"), Some(Duration::hours(36)));
+        assert_eq!(parse_period("P1W"), Some(Duration::days(7)));
+        assert_eq!(parse_period("PT1.5S"), Some(Duration::milliseconds(1500)));
+        for period in ["PT0S", "PT0.5S", "P1M", "PT5", "5M", ""] {
+            assert_eq!(parse_period(period), None, "{}", period);
+        }
+    }
+
+    #[test]
+    fn times() {
+        let now = at(12, 0);
+        assert_eq!(parse_time("2025-01-01T10:30:00Z", now), Some(at(10, 30)));
+        assert_eq!(parse_time("2025-01-01T11:30:00+01:00", now), Some(at(10, 30)));
+        assert_eq!(parse_time("2025-01-01T10:30Z", now), Some(at(10, 30)));
+        assert_eq!(parse_time("2025-01-01T10Z", now), Some(at(10, 0)));
+        assert_eq!(parse_time("2025-01-01", now), Some(at(0, 0)));
+        assert_eq!(parse_time("PRESENT", now), Some(now));
+        assert_eq!(parse_time("current", now), Some(now));
+        assert_eq!(parse_time("yesterday", now), None);
+    }
+
+    #[test]
+    fn dimension_lists_and_intervals() {
+        let now = at(12, 0);
+        // A list, the times after now left out
+        let (times, period) = dimension_times("2025-01-01T11:00Z,2025-01-01T11:30:00Z, 2025-01-01T12:30Z", now, 3).unwrap();
+        assert_eq!((times, period), (vec![at(11, 30), at(11, 0)], None));
+        // An interval, up to now and for the hours asked
+        let (times, period) = dimension_times("2025-01-01T00:00:00Z/2025-01-01T13:00:00Z/PT15M", now, 1).unwrap();
+        assert_eq!(times, [at(12, 0), at(11, 45), at(11, 30), at(11, 15)]);
+        assert_eq!(period, Some(Duration::minutes(15)));
+        // Up to the present
+        let (times, _) = dimension_times("2025-01-01T11:00Z/PRESENT/PT20M", at(11, 50), 3).unwrap();
+        assert_eq!(times, [at(11, 40), at(11, 20), at(11, 0)]);
+        // A list and an interval
+        let (times, _) = dimension_times("2025-01-01T09:00Z,2025-01-01T10:00Z/2025-01-01T11:00Z/PT30M", now, 3).unwrap();
+        assert_eq!(times, [at(11, 0), at(10, 30), at(10, 0), at(9, 0)]);
+        for extent in ["2025-01-01T11:00Z/PRESENT/PT0.5S", "2025-01-01T11:00Z/PRESENT/P1M", "yesterday", "a/b"] {
+            assert!(dimension_times(extent, now, 3).is_err(), "{}", extent);
+        }
+    }
+
+    #[test]
+    fn layer_time_extents() {
+        let capabilities = r#"<WMS_Capabilities><Capability>
+            <Layer><Name>root</Name><Dimension name="time">2025-01-01T00:00Z/PRESENT/PT1H</Dimension>
+                <Layer><Name>inherited</Name></Layer>
+                <Layer><Name>own</Name><Dimension name="TIME">2025-01-01T10:00Z,2025-01-01T11:00Z</Dimension></Layer>
+                <Layer><Name>elevation</Name><Dimension name="elevation">0,1000</Dimension></Layer>
+            </Layer>
+            <Layer><Name>none</Name><Extent name="other">x</Extent></Layer>
+        </Capability></WMS_Capabilities>"#;
+        let extent = |layer| layer_time_extent(capabilities, layer).unwrap();
+        assert_eq!(extent("root").as_deref(), Some("2025-01-01T00:00Z/PRESENT/PT1H"));
+        assert_eq!(extent("inherited").as_deref(), Some("2025-01-01T00:00Z/PRESENT/PT1H"));
+        assert_eq!(extent("own").as_deref(), Some("2025-01-01T10:00Z,2025-01-01T11:00Z"));
+        assert_eq!(extent("elevation").as_deref(), Some("2025-01-01T00:00Z/PRESENT/PT1H"));
+        assert_eq!(extent("none").as_deref(), Some(""));
+        assert_eq!(extent("missing"), None);
+    }
+}
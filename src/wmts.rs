@@ -0,0 +1,447 @@
+//! Frames of an OGC Web Map Tile Service, as standards-based satellite and
+//! radar endpoints like EUMETSAT View publish them: the layer, its times and
+//! its tile matrices are read from the capabilities of the service, and each
+//! frame is the tiles of the matrix closest to the zoom level of the window,
+//! stitched together and cropped to it.
+
+use chrono::{DateTime, Utc};
+use quick_xml::events::{BytesStart, Event};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+
+use crate::error::{NuageError, Result};
+use crate::projection::{EARTH_RADIUS, lonlat_to_mercator, tile_to_lonlat};
+use crate::provider::{INTERVAL, Provenance, Provider, TILE_SIZE, TILES, Tiles, ZOOM, screen_size};
+use crate::timeline::HISTORY_HOURS;
+use crate::wms::dimension_times;
+
+/// Size of a pixel in the scale denominators of tile matrices, in meters.
+const PIXEL_SIZE: f64 = 0.00028;
+
+/// A layer of a WMTS and how its tiles are asked for.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct WmtsConfig {
+    /// The address of the capabilities of the service
+    pub url: String,
+    /// The identifier of the layer, as in the capabilities
+    pub layer: String,
+    /// The tile matrix set, the first in Web Mercator linked to the layer by
+    /// default
+    pub tile_matrix_set: Option<String>,
+    /// The style of the layer, its default one by default
+    pub style: Option<String>,
+    /// The tile window shown, (top-left (x, y), bottom-right (x, y))
+    pub tiles: Tiles,
+    pub zoom: u16,
+}
+
+impl Default for WmtsConfig {
+    fn default() -> Self {
+        WmtsConfig { url: String::new(), layer: String::new(), tile_matrix_set: None, style: None, tiles: TILES, zoom: ZOOM }
+    }
+}
+
+fn invalid(message: impl std::fmt::Display) -> NuageError {
+    NuageError::Config(format!("invalid WMTS capabilities: {}", message))
+}
+
+/// An element of an XML document, without its namespace.
+#[derive(Clone, Debug, Default)]
+struct Element {
+    name: String,
+    attributes: Vec<(String, String)>,
+    text: String,
+    children: Vec<Element>,
+}
+
+impl Element {
+    fn new(start: &BytesStart) -> Result<Element> {
+        let mut attributes = vec![];
+        for attribute in start.attributes() {
+            let attribute = attribute.map_err(invalid)?;
+            let value = attribute.unescape_value().map_err(invalid)?;
+            attributes.push((String::from_utf8_lossy(attribute.key.local_name().as_ref()).into_owned(), value.into_owned()));
+        }
+        Ok(Element { name: String::from_utf8_lossy(start.local_name().as_ref()).into_owned(), attributes, ..Element::default() })
+    }
+
+    fn parse(text: &str) -> Result<Element> {
+        let mut reader = quick_xml::Reader::from_str(text);
+        let mut open = vec![Element::default()];
+        loop {
+            match reader.read_event().map_err(invalid)? {
+                Event::Start(start) => open.push(Element::new(&start)?),
+                Event::Empty(start) => open.last_mut().unwrap().children.push(Element::new(&start)?),
+                Event::Text(text) => open.last_mut().unwrap().text.push_str(text.unescape().map_err(invalid)?.trim()),
+                Event::End(_) => {
+                    let element = open.pop().unwrap();
+                    open.last_mut().ok_or_else(|| invalid("unbalanced elements"))?.children.push(element);
+                }
+                Event::Eof => break,
+                _ => (),
+            }
+        }
+        open.pop().filter(|_| open.is_empty()).ok_or_else(|| invalid("unclosed elements"))
+    }
+
+    fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes.iter().find(|(key, _)| key == name).map(|(_, value)| value.as_str())
+    }
+
+    fn children<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Element> {
+        self.children.iter().filter(move |child| child.name == name)
+    }
+
+    fn child(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find(|child| child.name == name)
+    }
+
+    /// The text of the first child named `name`.
+    fn child_text(&self, name: &str) -> Option<&str> {
+        self.child(name).map(|child| child.text.as_str())
+    }
+
+    /// The descendants named `name`, depth first.
+    fn descendants<'a>(&'a self, name: &'a str) -> Vec<&'a Element> {
+        let mut found = vec![];
+        for child in &self.children {
+            if child.name == name {
+                found.push(child);
+            }
+            found.extend(child.descendants(name));
+        }
+        found
+    }
+}
+
+/// A zoom level of a tile matrix set.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TileMatrix {
+    pub identifier: String,
+    /// Meters per pixel
+    pub resolution: f64,
+    /// Web Mercator coordinates of the top-left corner of the matrix
+    pub top_left: (f64, f64),
+    pub tile_size: (u32, u32),
+    /// Columns and rows of tiles
+    pub size: (u32, u32),
+}
+
+impl TileMatrix {
+    /// The matrix of a `TileMatrix` element, `None` if incomplete. Empty
+    /// tiles or matrices are an error, nothing could be drawn from them.
+    fn parse(element: &Element) -> Result<Option<TileMatrix>> {
+        let parse = || {
+            let number = |name: &str| element.child_text(name)?.trim().parse::<f64>().ok();
+            let mut corner = element.child_text("TopLeftCorner")?.split_whitespace().map(|n| n.parse::<f64>().ok());
+            Some(TileMatrix {
+                identifier: element.child_text("Identifier")?.to_string(),
+                resolution: number("ScaleDenominator")? * PIXEL_SIZE,
+                top_left: (corner.next()??, corner.next()??),
+                tile_size: (number("TileWidth")? as u32, number("TileHeight")? as u32),
+                size: (number("MatrixWidth")? as u32, number("MatrixHeight")? as u32),
+            })
+        };
+        let Some(matrix) = parse() else {
+            return Ok(None);
+        };
+        let (tile_size, size) = (matrix.tile_size, matrix.size);
+        if tile_size.0 == 0 || tile_size.1 == 0 || size.0 == 0 || size.1 == 0 || matrix.resolution.is_nan() || matrix.resolution <= 0. {
+            return Err(NuageError::Config(format!(
+                "tile matrix {}: tiles of {}x{} pixels, {}x{} of them at {} m a pixel, nothing can be drawn",
+                matrix.identifier, tile_size.0, tile_size.1, size.0, size.1, matrix.resolution
+            )));
+        }
+        Ok(Some(matrix))
+    }
+}
+
+/// What is needed from the capabilities to ask for the tiles of a layer.
+#[derive(Clone, Debug)]
+pub struct Capabilities {
+    /// The `ResourceURL` template of the tiles, `None` for key-value pair
+    /// requests
+    pub template: Option<String>,
+    pub format: String,
+    pub style: String,
+    pub tile_matrix_set: String,
+    pub matrices: Vec<TileMatrix>,
+    /// The identifier of the time dimension, and its values
+    pub time: Option<(String, String)>,
+}
+
+/// Whether `crs` is Web Mercator, in any of its spellings.
+fn is_web_mercator(crs: &str) -> bool {
+    ["3857", "900913", "3785"].iter().any(|code| crs.ends_with(&format!(":{}", code)))
+}
+
+/// The capabilities of `layer` in a capabilities document. Only tile matrix
+/// sets in Web Mercator are supported.
+pub fn parse_capabilities(text: &str, config: &WmtsConfig) -> Result<Capabilities> {
+    let document = Element::parse(text)?;
+    let layer = document
+        .descendants("Layer")
+        .into_iter()
+        .find(|layer| layer.child_text("Identifier") == Some(config.layer.as_str()))
+        .ok_or_else(|| NuageError::Config(format!("{}: no layer {}", config.url, config.layer)))?;
+    let sets = document.descendants("TileMatrixSet");
+    let set = layer
+        .children("TileMatrixSetLink")
+        .filter_map(|link| link.child_text("TileMatrixSet"))
+        .filter(|name| config.tile_matrix_set.as_deref().is_none_or(|wanted| wanted == *name))
+        .find_map(|name| {
+            sets.iter().find(|set| {
+                set.child_text("Identifier") == Some(name) && set.child_text("SupportedCRS").is_some_and(is_web_mercator)
+            })
+        })
+        .ok_or_else(|| NuageError::Config(format!("{}: no tile matrix set in Web Mercator for {}", config.url, config.layer)))?;
+    let template = layer
+        .children("ResourceURL")
+        .find(|url| url.attribute("resourceType") == Some("tile"))
+        .and_then(|url| Some((url.attribute("template")?, url.attribute("format")?)));
+    let style = config.style.clone().or_else(|| {
+        let mut styles = layer.children("Style");
+        let default = layer.children("Style").find(|style| style.attribute("isDefault") == Some("true"));
+        default.or_else(|| styles.next())?.child_text("Identifier").map(str::to_string)
+    });
+    let time = layer.children("Dimension").find(|dimension| {
+        dimension.child_text("Identifier").is_some_and(|identifier| identifier.eq_ignore_ascii_case("time"))
+    });
+    Ok(Capabilities {
+        template: template.map(|(template, _)| template.to_string()),
+        format: template
+            .map(|(_, format)| format)
+            .or_else(|| layer.child_text("Format"))
+            .unwrap_or("image/png")
+            .to_string(),
+        style: style.unwrap_or_default(),
+        tile_matrix_set: set.child_text("Identifier").unwrap_or_default().to_string(),
+        matrices: set.children("TileMatrix").filter_map(|matrix| TileMatrix::parse(matrix).transpose()).collect::<Result<_>>()?,
+        time: time.map(|time| {
+            let values: Vec<&str> = time.children("Value").map(|value| value.text.as_str()).collect();
+            (time.child_text("Identifier").unwrap_or_default().to_string(), values.join(","))
+        }),
+    })
+}
+
+/// The maps of a layer of a WMTS, at the times it has, not cached.
+#[derive(Clone, Debug)]
+pub struct Wmts {
+    pub config: WmtsConfig,
+    /// Hours of frames shown, `HISTORY_HOURS` by default
+    pub hours: u32,
+    /// The capabilities of the layer, as last read
+    capabilities: Arc<Mutex<Option<Capabilities>>>,
+    /// Minutes between two frames, as last read from the capabilities
+    interval: Arc<Mutex<u32>>,
+}
+
+impl Wmts {
+    pub fn new(config: WmtsConfig) -> Self {
+        Wmts {
+            config,
+            hours: HISTORY_HOURS,
+            capabilities: Arc::new(Mutex::new(None)),
+            interval: Arc::new(Mutex::new(INTERVAL)),
+        }
+    }
+
+    /// Read the capabilities of the layer again.
+    fn fetch_capabilities(&self) -> Result<Capabilities> {
+        log::info!("fetching {}", self.config.url);
+        let text = ureq::get(&self.config.url).call()?.body_mut().with_config().limit(20 * 1024 * 1024).read_to_string()?;
+        let capabilities = parse_capabilities(&text, &self.config)?;
+        *self.capabilities.lock().unwrap() = Some(capabilities.clone());
+        Ok(capabilities)
+    }
+
+    fn capabilities(&self) -> Result<Capabilities> {
+        match self.capabilities.lock().unwrap().clone() {
+            Some(capabilities) => Ok(capabilities),
+            None => self.fetch_capabilities(),
+        }
+    }
+
+    /// The URL of the tile at `(column, row)` of `matrix` at `timestamp`.
+    fn tile_url(&self, capabilities: &Capabilities, matrix: &TileMatrix, (column, row): (u32, u32), timestamp: DateTime<Utc>) -> String {
+        let time = timestamp.format("%Y-%m-%dT%H:%M:%SZ").to_string();
+        match &capabilities.template {
+            Some(template) => {
+                let mut url = template
+                    .replace("{TileMatrixSet}", &capabilities.tile_matrix_set)
+                    .replace("{TileMatrix}", &matrix.identifier)
+                    .replace("{TileRow}", &row.to_string())
+                    .replace("{TileCol}", &column.to_string())
+                    .replace("{Style}", &capabilities.style);
+                if let Some((identifier, _)) = &capabilities.time {
+                    url = url.replace(&format!("{{{}}}", identifier), &time);
+                }
+                url
+            }
+            None => {
+                let base = self.config.url.split('?').next().unwrap_or_default();
+                let mut url = format!(
+                    concat!(
+                        "{}?SERVICE=WMTS&REQUEST=GetTile&VERSION=1.0.0&LAYER={}&STYLE={}&TILEMATRIXSET={}",
+                        "&TILEMATRIX={}&TILEROW={}&TILECOL={}&FORMAT={}",
+                    ),
+                    base,
+                    self.config.layer,
+                    capabilities.style,
+                    capabilities.tile_matrix_set,
+                    matrix.identifier,
+                    row,
+                    column,
+                    capabilities.format.replace('/', "%2F")
+                );
+                if let Some((identifier, _)) = &capabilities.time {
+                    url.push_str(&format!("&{}={}", identifier.to_uppercase(), time));
+                }
+                url
+            }
+        }
+    }
+
+    /// The matrix whose resolution is the closest to that of the zoom level
+    /// of the window.
+    fn matrix<'a>(&self, capabilities: &'a Capabilities) -> Result<&'a TileMatrix> {
+        let resolution = 2. * std::f64::consts::PI * EARTH_RADIUS / (TILE_SIZE as f64 * (1u32 << self.config.zoom) as f64);
+        let distance = |matrix: &&TileMatrix| (matrix.resolution / resolution).ln().abs();
+        capabilities
+            .matrices
+            .iter()
+            .min_by(|a, b| distance(a).total_cmp(&distance(b)))
+            .ok_or_else(|| NuageError::Config(format!("{}: no tile matrix", self.config.url)))
+    }
+
+    fn download(&self, url: &str) -> Result<image::RgbImage> {
+        log::info!("fetching {}", url);
+        let mut res = match ureq::get(url).call() {
+            Err(ureq::Error::StatusCode(404 | 410)) => return Err(NuageError::Missing(url.to_string())),
+            res => res?,
+        };
+        let image_bytes = res.body_mut().with_config().limit(20 * 1024 * 1024).read_to_vec()?;
+        Ok(image::load_from_memory(&image_bytes)?.to_rgb8())
+    }
+}
+
+impl Provider for Wmts {
+    fn name(&self) -> &str {
+        &self.config.layer
+    }
+
+    /// The times of the layer in the capabilities, the availability delay
+    /// not being needed as only the published ones are listed.
+    fn timepoints(&self, now: DateTime<Utc>) -> Result<Vec<DateTime<Utc>>> {
+        let capabilities = self.fetch_capabilities()?;
+        let Some((_, values)) = capabilities.time else {
+            return Err(NuageError::Config(format!("{}: layer {} has no time dimension", self.config.url, self.config.layer)));
+        };
+        let (times, period) = dimension_times(&values, now, self.hours)?;
+        let period = period.or_else(|| times.get(..2).map(|newest| newest[0] - newest[1]));
+        if let Some(period) = period {
+            *self.interval.lock().unwrap() = period.num_minutes().max(1) as u32;
+        }
+        Ok(times)
+    }
+
+    /// The tiles covering the window at `timestamp`, cropped to it and
+    /// resized to the size of a frame of the window.
+    fn frame(&self, timestamp: DateTime<Utc>) -> Result<image::RgbImage> {
+        let capabilities = self.capabilities()?;
+        let matrix = self.matrix(&capabilities)?;
+        let ((x1, y1), (x2, y2)) = self.config.tiles;
+        let corner = |x: u16, y: u16| {
+            let (lon, lat) = tile_to_lonlat(x as f64, y as f64, self.config.zoom);
+            lonlat_to_mercator(lon, lat)
+        };
+        let ((west, north), (east, south)) = (corner(x1, y1), corner(x2 + 1, y2 + 1));
+        let (tile_width, tile_height) = matrix.tile_size;
+        let span = (tile_width as f64 * matrix.resolution, tile_height as f64 * matrix.resolution);
+        let column = |x: f64| ((x - matrix.top_left.0) / span.0).floor().clamp(0., matrix.size.0 as f64 - 1.) as u32;
+        let row = |y: f64| ((matrix.top_left.1 - y) / span.1).floor().clamp(0., matrix.size.1 as f64 - 1.) as u32;
+        let (columns, rows) = (column(west)..=column(east), row(north)..=row(south));
+        let mut canvas = image::RgbImage::new(columns.clone().count() as u32 * tile_width, rows.clone().count() as u32 * tile_height);
+        for (top, row) in rows.clone().enumerate() {
+            for (left, column) in columns.clone().enumerate() {
+                let tile = self.download(&self.tile_url(&capabilities, matrix, (column, row), timestamp))?;
+                let tile = image::imageops::resize(&tile, tile_width, tile_height, image::imageops::FilterType::Triangle);
+                image::imageops::replace(&mut canvas, &tile, (left as u32 * tile_width) as i64, (top as u32 * tile_height) as i64);
+            }
+        }
+        // The window within the tiles, in pixels
+        let origin = (
+            matrix.top_left.0 + *columns.start() as f64 * span.0,
+            matrix.top_left.1 - *rows.start() as f64 * span.1,
+        );
+        let left = ((west - origin.0) / matrix.resolution).max(0.) as u32;
+        let top = ((origin.1 - north) / matrix.resolution).max(0.) as u32;
+        let width = (((east - west) / matrix.resolution) as u32).clamp(1, canvas.width() - left.min(canvas.width() - 1));
+        let height = (((north - south) / matrix.resolution) as u32).clamp(1, canvas.height() - top.min(canvas.height() - 1));
+        let cropped = image::imageops::crop_imm(&canvas, left, top, width, height).to_image();
+        let (width, height) = screen_size(((x2 - x1 + 1) as u32 * TILE_SIZE, (y2 - y1 + 1) as u32 * TILE_SIZE));
+        Ok(image::imageops::resize(&cropped, width, height, crate::power::resize_filter()))
+    }
+
+    fn region(&self) -> (Tiles, u16) {
+        (self.config.tiles, self.config.zoom)
+    }
+
+    fn interval(&self) -> u32 {
+        *self.interval.lock().unwrap()
+    }
+
+    /// The URL of the north-west tile, once the capabilities are read.
+    fn provenance(&self, timestamp: DateTime<Utc>) -> Provenance {
+        let capabilities = self.capabilities.lock().unwrap().clone();
+        let url = capabilities.as_ref().and_then(|capabilities| {
+            let matrix = self.matrix(capabilities).ok()?;
+            let ((x, y), _) = self.config.tiles;
+            let (lon, lat) = tile_to_lonlat(x as f64, y as f64, self.config.zoom);
+            let (west, north) = lonlat_to_mercator(lon, lat);
+            let column = ((west - matrix.top_left.0) / (matrix.tile_size.0 as f64 * matrix.resolution)).max(0.) as u32;
+            let row = ((matrix.top_left.1 - north) / (matrix.tile_size.1 as f64 * matrix.resolution)).max(0.) as u32;
+            Some(self.tile_url(capabilities, matrix, (column, row), timestamp))
+        });
+        Provenance { provider: format!("WMTS {}", self.config.url), url, ..Provenance::default() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Capabilities of a layer whose only tile matrix is `matrix`.
+    fn capabilities(matrix: &str) -> String {
+        format!(
+            r#"<Capabilities><Contents>
+                <Layer><ows:Identifier>clouds</ows:Identifier>
+                    <TileMatrixSetLink><TileMatrixSet>mercator</TileMatrixSet></TileMatrixSetLink>
+                </Layer>
+                <TileMatrixSet><ows:Identifier>mercator</ows:Identifier><ows:SupportedCRS>urn:ogc:def:crs:EPSG::3857</ows:SupportedCRS>
+                    <TileMatrix><ows:Identifier>0</ows:Identifier>{}<TopLeftCorner>-20037508.34 20037508.34</TopLeftCorner></TileMatrix>
+                </TileMatrixSet>
+            </Contents></Capabilities>"#,
+            matrix
+        )
+    }
+
+    #[test]
+    fn empty_tile_matrices() {
+        let config = WmtsConfig { layer: "clouds".into(), ..WmtsConfig::default() };
+        let matrix = |tile: u32, size: u32| {
+            let tile = format!("<TileWidth>{0}</TileWidth><TileHeight>{0}</TileHeight>", tile);
+            let size = format!("<MatrixWidth>{}</MatrixWidth><MatrixHeight>1</MatrixHeight>", size);
+            capabilities(&format!("<ScaleDenominator>559082264.03</ScaleDenominator>{}{}", tile, size))
+        };
+        let parsed = parse_capabilities(&matrix(256, 1), &config).unwrap();
+        assert_eq!(parsed.matrices.len(), 1);
+        assert_eq!((parsed.matrices[0].tile_size, parsed.matrices[0].size), ((256, 256), (1, 1)));
+        assert!(parse_capabilities(&matrix(256, 0), &config).is_err());
+        assert!(parse_capabilities(&matrix(0, 1), &config).is_err());
+        // Incomplete matrices are left out
+        let incomplete = capabilities("<TileWidth>256</TileWidth>");
+        assert!(parse_capabilities(&incomplete, &config).unwrap().matrices.is_empty());
+    }
+}